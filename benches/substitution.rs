@@ -0,0 +1,49 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use oas_forge::scanner::substitute_smart_references_in;
+use std::collections::HashSet;
+
+// A route snippet as produced by a typical handler doc comment
+const ROUTE: &str = "\
+paths:
+  /users/{id}:
+    get:
+      summary: Fetch a user
+      description: Returns the $User with the given id
+      parameters:
+        - name: id
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        '200':
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: $User
+        '404':
+          description: Not found
+          content:
+            application/json:
+              schema:
+                $ref: $accounts::Error
+";
+
+fn substitution(c: &mut Criterion) {
+    let schemas: HashSet<String> = (0..200)
+        .map(|i| format!("Schema{}", i))
+        .chain(["User".to_string(), "accounts.Error".to_string()])
+        .collect();
+    let plain = ROUTE.replace("$User", "User").replace("$", "");
+
+    c.bench_function("substitute/references", |b| {
+        b.iter(|| substitute_smart_references_in(black_box(ROUTE), &schemas, "accounts"))
+    });
+    c.bench_function("substitute/no_dollar", |b| {
+        b.iter(|| substitute_smart_references_in(black_box(&plain), &schemas, "accounts"))
+    });
+}
+
+criterion_group!(benches, substitution);
+criterion_main!(benches);
@@ -0,0 +1,235 @@
+use crate::refs;
+use crate::validate::HTTP_METHODS;
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+
+/// Extension naming who an operation or schema is meant for.
+pub const AUDIENCE_KEY: &str = "x-audience";
+
+/// Audience of a document, or of an item via [`AUDIENCE_KEY`]. A document for one audience
+/// keeps the items for it and for wider ones: `internal` sees everything, `public` only
+/// public items.
+///
+/// Configured as `"internal"`, `"partner"` or `"public"` (the default for unlabeled items).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum Audience {
+    Internal,
+    Partner,
+    #[default]
+    Public,
+}
+
+impl std::str::FromStr for Audience {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "internal" => Ok(Audience::Internal),
+            "partner" => Ok(Audience::Partner),
+            "public" => Ok(Audience::Public),
+            other => Err(format!(
+                "unknown audience '{}', expected 'internal', 'partner' or 'public'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Audience {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Removes the operations and schemas whose [`AUDIENCE_KEY`] is more restrictive than
+/// `audience`; unlabeled operations count as `default`. Path items left without operations
+/// are removed, and so are the components only the removed operations referenced.
+/// Unlabeled schemas hidden by `default` are kept while something kept references them.
+pub fn filter_audience(doc: &mut Value, audience: Audience, default: Audience) {
+    let label = |item: &Value| -> Option<Audience> {
+        item.get(AUDIENCE_KEY)
+            .and_then(Value::as_str)
+            .and_then(|s| s.parse().ok())
+    };
+    let visible = |item: &Value| label(item).unwrap_or(default) >= audience;
+
+    let mut removed = Vec::new();
+    if let Some(Value::Mapping(paths)) = doc.get_mut("paths") {
+        for item in paths.values_mut() {
+            let Value::Mapping(item) = item else { continue };
+            let hidden: Vec<Value> = item
+                .iter()
+                .filter(|(method, operation)| is_operation(method) && !visible(operation))
+                .map(|(method, _)| method.clone())
+                .collect();
+            removed.extend(hidden.iter().filter_map(|method| item.shift_remove(method)));
+        }
+        paths.retain(|_, item| {
+            item.as_mapping()
+                .is_none_or(|item| item.keys().any(is_operation))
+        });
+    }
+
+    let mut candidates = BTreeSet::new();
+    if let Some(Value::Mapping(schemas)) =
+        doc.get_mut("components").and_then(|c| c.get_mut("schemas"))
+    {
+        schemas.retain(|_, schema| label(schema).is_none_or(|level| level >= audience));
+        if default < audience {
+            candidates.extend(
+                schemas
+                    .keys()
+                    .filter_map(Value::as_str)
+                    .map(|name| format!("#/components/schemas/{}", refs::escape(name))),
+            );
+        }
+    }
+
+    // Removed unless something kept still uses them
+    candidates.extend(
+        removed
+            .iter()
+            .flat_map(|operation| refs::reachable_refs(doc, operation)),
+    );
+    if candidates.is_empty() {
+        return;
+    }
+    let mut kept = doc.clone();
+    for target in &candidates {
+        remove_component(&mut kept, target);
+    }
+    let used = refs::reachable_refs(doc, &kept);
+    for target in candidates.difference(&used) {
+        remove_component(doc, target);
+    }
+    if let Some(Value::Mapping(components)) = doc.get_mut("components") {
+        components.retain(|_, entries| entries.as_mapping().is_none_or(|e| !e.is_empty()));
+    }
+}
+
+fn is_operation(key: &Value) -> bool {
+    key.as_str().is_some_and(|k| HTTP_METHODS.contains(&k))
+}
+
+// Removes `#/components/{kind}/{name}`; other targets are left alone
+fn remove_component(doc: &mut Value, target: &str) {
+    let Some(rest) = target.strip_prefix("#/components/") else {
+        return;
+    };
+    let Some((kind, name)) = rest.split_once('/') else {
+        return;
+    };
+    let name = name.replace("~1", "/").replace("~0", "~");
+    if let Some(Value::Mapping(entries)) = doc.get_mut("components").and_then(|c| c.get_mut(kind)) {
+        entries.shift_remove(name.as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r##"
+paths:
+  /status:
+    get:
+      responses:
+        '200': {$ref: '#/components/responses/Status'}
+  /partners:
+    get:
+      x-audience: partner
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: '#/components/schemas/Partner'}
+  /admin:
+    parameters:
+      - {name: verbose, in: query}
+    post:
+      x-audience: internal
+      requestBody:
+        content:
+          application/json:
+            schema: {$ref: '#/components/schemas/Command'}
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: '#/components/schemas/Status'}
+components:
+  responses:
+    Status:
+      description: ok
+      content:
+        application/json:
+          schema: {$ref: '#/components/schemas/Status'}
+  schemas:
+    Status: {type: object}
+    Partner:
+      type: object
+      properties:
+        contact: {$ref: '#/components/schemas/Contact'}
+    Contact: {type: object}
+    Command: {type: object}
+    Audit: {type: object, x-audience: internal}
+    Unused: {type: object}
+"##;
+
+    fn filtered(audience: Audience) -> Value {
+        let mut doc: Value = serde_yaml::from_str(DOC).unwrap();
+        filter_audience(&mut doc, audience, Audience::Public);
+        doc
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_mapping()
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_internal_keeps_everything() {
+        let doc = filtered(Audience::Internal);
+        assert_eq!(doc, serde_yaml::from_str::<Value>(DOC).unwrap());
+    }
+
+    #[test]
+    fn test_filter_partner() {
+        let doc = filtered(Audience::Partner);
+        assert_eq!(keys(&doc["paths"]), ["/status", "/partners"]);
+        // Command was only used by the internal operation; Status is still used
+        assert_eq!(
+            keys(&doc["components"]["schemas"]),
+            ["Status", "Partner", "Contact", "Unused"]
+        );
+    }
+
+    #[test]
+    fn test_filter_public() {
+        let doc = filtered(Audience::Public);
+        assert_eq!(keys(&doc["paths"]), ["/status"]);
+        assert_eq!(keys(&doc["components"]["schemas"]), ["Status", "Unused"]);
+        assert_eq!(keys(&doc["components"]["responses"]), ["Status"]);
+    }
+
+    #[test]
+    fn test_unlabeled_default() {
+        let mut doc: Value = serde_yaml::from_str(DOC).unwrap();
+        filter_audience(&mut doc, Audience::Partner, Audience::Internal);
+        assert_eq!(keys(&doc["paths"]), ["/partners"]);
+        assert_eq!(keys(&doc["components"]["schemas"]), ["Partner", "Contact"]);
+        assert!(doc["components"].get("responses").is_none());
+
+        assert_eq!("partner".parse(), Ok(Audience::Partner));
+        assert!("everyone".parse::<Audience>().is_err());
+    }
+}
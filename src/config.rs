@@ -1,27 +1,464 @@
-use clap::Parser;
+use crate::generics::NamingStrategy;
+use crate::visitor::{InputMetadata, SchemaNaming};
+use crate::{Format, OutputVersion};
+use clap::{Parser, Subcommand};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
+/// Settings layered by [`Config::load`], lowest precedence first: `Cargo.toml`
+/// `[package.metadata.oas-forge]`, `openapi.toml`, the `--config` file, CLI arguments.
+///
+/// Scalar settings are taken from the highest layer that sets them. The path lists `input`,
+/// `include` and `import_registry` accumulate across layers (duplicates dropped) unless a
+/// layer sets `no_config_inputs`, which discards the inputs and includes of the layers
+/// below it. `info`, `set`, `variables` and `lint` accumulate as well; other lists are replaced.
+///
+/// A `--profile` is applied on top of the configuration files, below CLI arguments.
 #[derive(Debug, Deserialize, Parser, Default, Clone)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 #[command(author, version, about, long_about = None)]
 pub struct Config {
     /// Input directories to scan for Rust files and OpenAPI fragments
     #[arg(short = 'i', long = "input")]
-    pub input: Option<Vec<PathBuf>>,
+    #[serde(alias = "inputs")]
+    pub input: Option<Vec<Input>>,
 
-    /// Specific files to include (e.g., .json, .yaml)
+    /// Specific files to include (e.g., .json, .yaml; `-` reads stdin), or http(s) URLs with the `remote` feature
     #[arg(long = "include")]
     pub include: Option<Vec<PathBuf>>,
 
-    /// Output file for the generated OpenAPI definition (defaults to openapi.yaml)
+    /// Timeout in seconds for fetching remote includes (default 30)
+    #[arg(long = "remote-timeout", value_name = "SECONDS")]
+    pub remote_timeout: Option<u64>,
+
+    /// Cache directory for remote includes (default target/oas-forge-cache)
+    #[arg(long = "remote-cache-dir")]
+    pub remote_cache_dir: Option<PathBuf>,
+
+    /// Use only cached copies of remote includes
+    #[arg(long = "offline", num_args = 0..=1, default_missing_value = "true")]
+    pub offline: Option<bool>,
+
+    /// Ignore inputs and includes from lower configuration layers, using only this layer's
+    #[arg(long = "no-config-inputs", num_args = 0..=1, default_missing_value = "true")]
+    pub no_config_inputs: Option<bool>,
+
+    /// Output file(s) for the generated OpenAPI definition (defaults to openapi.yaml)
     #[arg(short = 'o', long = "output")]
-    pub output: Option<PathBuf>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub output: Option<Vec<PathBuf>>,
+
+    /// Naming scheme for monomorphized schemas: "underscore" (default), "of", or a "{name}...{args}" template
+    #[arg(long = "generic-naming")]
+    pub generic_naming: Option<NamingStrategy>,
+
+    /// Treat missing fragments/blueprints and unresolved references as errors
+    #[arg(long = "strict", num_args = 0..=1, default_missing_value = "true")]
+    pub strict: Option<bool>,
+
+    /// Naming scheme for reflected schemas: "plain" (default) or "module_prefixed" (e.g. `accounts.User`)
+    #[arg(long = "schema-naming")]
+    pub schema_naming: Option<SchemaNaming>,
+
+    /// How doc comments of reflected items become schema text: "joined" (default, one
+    /// description) or "paragraphs" (first paragraph as title, the rest as markdown description)
+    #[arg(long = "doc-style")]
+    pub doc_style: Option<crate::visitor::DocStyle>,
+
+    /// Sort `paths` and `components.schemas` and emit top-level keys in canonical order
+    #[arg(long = "sort-output", num_args = 0..=1, default_missing_value = "true")]
+    pub sort_output: Option<bool>,
+
+    /// Order of the operations in each path item: "merge" (default) or "method-order"
+    /// (get, post, put, patch, delete, then the rest)
+    #[arg(long = "sort-operations")]
+    pub sort_operations: Option<crate::merger::OperationOrder>,
+
+    /// Order of `paths`: "merge" (default), "tag" (grouped by the first operation tag) or "alpha"
+    #[arg(long = "group-paths-by")]
+    pub group_paths_by: Option<crate::merger::PathGrouping>,
+
+    /// Move inline parameters repeated unchanged on 3+ operations into `components.parameters`
+    #[arg(
+        long = "hoist-common-parameters",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub hoist_common_parameters: Option<bool>,
+
+    /// Debug aid: tag each path item and schema with `x-oas-forge-source: "file:line"`
+    #[arg(long = "annotate-sources", num_args = 0..=1, default_missing_value = "true")]
+    pub annotate_sources: Option<bool>,
+
+    /// Remove internal x-oas-forge keys, nulls and empty parameters/tags before writing (default true)
+    #[arg(long = "sanitize", num_args = 0..=1, default_missing_value = "true")]
+    pub sanitize: Option<bool>,
+
+    /// Keep null values when sanitizing (e.g. an intentional `example: null`)
+    #[arg(long = "keep-nulls", num_args = 0..=1, default_missing_value = "true")]
+    pub keep_nulls: Option<bool>,
+
+    /// When snippets define a schema differently: "error" (default) or "last" (last one wins)
+    #[arg(long = "on-conflict")]
+    pub on_conflict: Option<crate::merger::OnConflict>,
+
+    /// Only keep operations and schemas whose `x-audience` is at least as wide as this:
+    /// "internal" (everything), "partner" or "public"
+    #[arg(long = "audience")]
+    pub audience: Option<crate::audience::Audience>,
+
+    /// Audience of operations and schemas without `x-audience` (default "public")
+    #[arg(long = "default-audience")]
+    pub default_audience: Option<crate::audience::Audience>,
+
+    /// Skip Rust files that fail to parse with a warning instead of failing (not in strict mode)
+    #[arg(long = "skip-parse-errors", num_args = 0..=1, default_missing_value = "true")]
+    pub skip_parse_errors: Option<bool>,
+
+    /// Only parse Rust files under the inputs that contain an `@openapi` or route DSL marker;
+    /// types in other files are then not reflected as schemas
+    #[arg(long = "prefilter", num_args = 0..=1, default_missing_value = "true")]
+    pub prefilter: Option<bool>,
+
+    /// Fail when a local `$ref` in the merged document points nowhere (otherwise a warning)
+    #[arg(long = "strict-refs", num_args = 0..=1, default_missing_value = "true")]
+    pub strict_refs: Option<bool>,
+
+    /// Check the merged document against structural OpenAPI rules; errors fail the run
+    #[arg(long = "validate", num_args = 0..=1, default_missing_value = "true")]
+    pub validate: Option<bool>,
+
+    /// Also write one self-contained `<output>.<tag>.<ext>` document per operation tag
+    #[arg(long = "split-by-tag", num_args = 0..=1, default_missing_value = "true")]
+    pub split_by_tag: Option<bool>,
+
+    /// Output format: "yaml", "json" or "json-compact" (defaults to the output file extension)
+    #[arg(long = "format")]
+    pub format: Option<Format>,
+
+    /// Generate the root `openapi`/`info` block from Cargo.toml when no snippet provides one
+    #[arg(long = "synthesize-root", num_args = 0..=1, default_missing_value = "true")]
+    pub synthesize_root: Option<bool>,
+
+    /// Specification version to write: "3.0"/"3.1" (as merged) or "2.0" (Swagger, converted)
+    #[arg(long = "spec-version")]
+    pub spec_version: Option<OutputVersion>,
+
+    /// Write a JSON report (counts and warnings) of the run to this file
+    #[arg(long = "report")]
+    pub report: Option<PathBuf>,
+
+    /// How diagnostics are printed: "human" or "json" (one object per line on stdout)
+    #[arg(long = "message-format")]
+    pub message_format: Option<MessageFormat>,
+
+    /// Only print errors: no banner, status lines or progress
+    #[arg(short = 'q', long = "quiet", num_args = 0..=1, default_missing_value = "true")]
+    pub quiet: Option<bool>,
+
+    /// More log output: `-v` for debug, `-vv` for trace (overrides RUST_LOG)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Compare the generated document with the existing outputs instead of writing them
+    #[arg(long = "check", num_args = 0..=1, default_missing_value = "true")]
+    pub check: Option<bool>,
+
+    /// Only index definitions and write them to a registry file (`oas-forge.registry.json`)
+    #[arg(
+        long = "export-registry",
+        num_args = 0..=1,
+        default_missing_value = crate::index::REGISTRY_FILE
+    )]
+    pub export_registry: Option<PathBuf>,
+
+    /// Registry files exported by other crates, loaded before scanning
+    #[arg(long = "import-registry")]
+    pub import_registry: Option<Vec<PathBuf>>,
+
+    /// Fields of the root `info` object overriding scanned content (`[info]` table)
+    #[arg(skip)]
+    pub info: Option<serde_yaml::Mapping>,
+
+    /// Servers replacing scanned ones (`[[servers]]` tables with `url` and `description`)
+    #[arg(skip)]
+    pub servers: Option<Vec<Server>>,
+
+    /// Override a value of the generated document by dotted path, e.g. `info.version=1.2.3`
+    #[arg(long = "set", value_name = "PATH=VALUE")]
+    pub set: Option<Vec<Assignment>>,
+
+    /// Template variables, replacing `{{NAME}}` placeholders (`[variables]` table)
+    #[arg(skip)]
+    pub variables: Option<HashMap<String, String>>,
+
+    /// Lint rules to turn on or off by name (`[lint]` table), see [`crate::lint::RULES`]
+    #[arg(skip)]
+    pub lint: Option<HashMap<String, bool>>,
 
     /// Path to a configuration file (toml)
     #[arg(long = "config")]
     #[serde(skip)]
     pub config_file: Option<PathBuf>,
+
+    /// Profile whose settings override the configuration files, see [`Config::with_profile`]
+    #[arg(long = "profile")]
+    #[serde(skip)]
+    pub profile: Option<String>,
+
+    /// Named variants of this configuration (`[profile.<name>]` tables)
+    #[arg(skip)]
+    #[serde(rename = "profile")]
+    pub profiles: Option<HashMap<String, Config>>,
+
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+
+    /// Configuration files this configuration was read from
+    #[arg(skip)]
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
+}
+
+/// Subcommands run instead of generating.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Summarize the API changes between two OpenAPI documents
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Output format: "text" or "json"
+        #[arg(long = "format", default_value = "text")]
+        format: DiffFormat,
+    },
+    /// List the operations found by the scan: method, path, operationId and source
+    ListRoutes {
+        /// Output format: "text" or "json"
+        #[arg(long = "format", default_value = "text")]
+        format: DiffFormat,
+    },
+    /// List the schemas found by the scan with the file defining each
+    ListSchemas {
+        /// Output format: "text" or "json"
+        #[arg(long = "format", default_value = "text")]
+        format: DiffFormat,
+    },
+    /// Generate the document in memory and check it against the style rules of `[lint]`
+    Lint,
+    /// Write a starter `openapi.toml` and `src/openapi.rs` into the current directory
+    Init {
+        /// Overwrite files that already exist
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Print a shell completion script
+    Completions { shell: clap_complete::Shell },
+    /// Serve the generated document with Swagger UI (`/`) and Redoc (`/redoc`)
+    #[cfg(feature = "serve")]
+    Serve {
+        #[arg(long = "port", default_value_t = 8080)]
+        port: u16,
+    },
+}
+
+/// How the CLI prints diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    /// One JSON [`crate::diagnostics::Diagnostic`] per line on stdout
+    Json,
+}
+
+impl std::str::FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            other => Err(format!(
+                "unknown message format '{}', expected 'human' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for MessageFormat {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// How `diff`, `list-routes` and `list-schemas` print their result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(DiffFormat::Text),
+            "json" => Ok(DiffFormat::Json),
+            other => Err(format!(
+                "unknown format '{}', expected 'text' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+/// An entry of `[[servers]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Server {
+    pub url: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// An input directory: a plain path, or an `[[inputs]]` table with `path`, `tag` (or
+/// `tags`) and `x-` extension keys applied to every operation found in it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "InputEntry")]
+pub struct Input {
+    pub path: PathBuf,
+    pub metadata: InputMetadata,
+}
+
+impl From<PathBuf> for Input {
+    fn from(path: PathBuf) -> Self {
+        Input {
+            path,
+            metadata: InputMetadata::default(),
+        }
+    }
+}
+
+impl From<&str> for Input {
+    fn from(path: &str) -> Self {
+        PathBuf::from(path).into()
+    }
+}
+
+impl std::str::FromStr for Input {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum InputEntry {
+    Path(PathBuf),
+    Table(BTreeMap<String, serde_json::Value>),
+}
+
+impl TryFrom<InputEntry> for Input {
+    type Error = String;
+
+    fn try_from(entry: InputEntry) -> Result<Self, Self::Error> {
+        let mut table = match entry {
+            InputEntry::Path(path) => return Ok(path.into()),
+            InputEntry::Table(table) => table,
+        };
+        let path = match table.remove("path") {
+            Some(serde_json::Value::String(path)) => PathBuf::from(path),
+            _ => return Err("input table needs a `path` string".to_string()),
+        };
+        let mut metadata = InputMetadata::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("tag", serde_json::Value::String(tag)) => metadata.tags.push(tag),
+                ("tags", serde_json::Value::Array(tags)) => {
+                    for tag in tags {
+                        match tag {
+                            serde_json::Value::String(tag) => metadata.tags.push(tag),
+                            other => {
+                                return Err(format!("input tag must be a string, got {}", other));
+                            }
+                        }
+                    }
+                }
+                (key, value) if key.starts_with("x-") => {
+                    metadata.extensions.insert(key.to_string(), value);
+                }
+                (key, _) => {
+                    return Err(format!(
+                        "unexpected input key `{}` (expected path, tag, tags or x-*)",
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(Input { path, metadata })
+    }
+}
+
+/// A `--set path.to.key=value` override; the value is always a string.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Assignment {
+    pub path: Vec<String>,
+    pub value: String,
+}
+
+impl std::str::FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=VALUE, got '{}'", s))?;
+        let path: Vec<String> = path.trim().split('.').map(String::from).collect();
+        if path.iter().any(String::is_empty) {
+            return Err(format!("invalid override path in '{}'", s));
+        }
+        Ok(Assignment {
+            path,
+            value: value.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for Assignment {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+// `output = "openapi.yaml"` or `output = ["openapi.yaml", "openapi.json"]`
+fn one_or_many<'de, D>(deserializer: D) -> Result<Option<Vec<PathBuf>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+
+    Ok(
+        Option::<OneOrMany>::deserialize(deserializer)?.map(|paths| match paths {
+            OneOrMany::One(path) => vec![path],
+            OneOrMany::Many(paths) => paths,
+        }),
+    )
 }
 
 #[derive(Deserialize)]
@@ -46,62 +483,276 @@ impl Config {
     /// 2. --config file
     /// 3. openapi.toml
     /// 4. Cargo.toml [package.metadata.oas-forge]
-    pub fn load() -> Self {
+    ///
+    /// Missing `openapi.toml`/`Cargo.toml` are skipped; files that exist but do not parse
+    /// (unknown keys included) are errors, as is a missing `--config` file.
+    pub fn load() -> crate::error::Result<Self> {
         let cli_args = Config::parse();
 
         // Start with default empty config
         let mut final_config = Config::default();
 
         // 4. Try loading Cargo.toml
-        if let Ok(cargo_conf) = load_cargo_toml() {
+        if let Some(cargo_conf) = load_cargo_toml()? {
             final_config.merge(cargo_conf);
+            final_config.files.push(PathBuf::from("Cargo.toml"));
         }
 
         // 3. Try loading openapi.toml
-        if let Ok(toml_conf) = load_toml_file("openapi.toml") {
+        if let Some(toml_conf) = load_toml_file("openapi.toml")? {
             final_config.merge(toml_conf);
         }
 
-        // 2. Try loading explicit config file
+        // 2. Load explicit config file
         if let Some(path) = &cli_args.config_file {
-            if let Ok(file_conf) = load_toml_file(path) {
-                final_config.merge(file_conf);
-            }
+            final_config.merge(Config::from_file(path)?);
+        }
+
+        if let Some(name) = &cli_args.profile {
+            final_config = final_config.with_profile(name)?;
         }
 
         // 1. Merge CLI args (taking precedence)
         final_config.merge(cli_args);
 
-        final_config
+        Ok(final_config)
+    }
+
+    /// Reads a configuration file (toml) without layering CLI args or other sources.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> crate::error::Result<Self> {
+        let content = std::fs::read_to_string(&path)?;
+        let mut config: Config = parse_toml(path.as_ref(), &content)?;
+        config.files.push(path.as_ref().to_path_buf());
+        Ok(config)
+    }
+
+    /// Merges the `[profile.<name>]` table over this configuration, with the same
+    /// semantics as any other layer.
+    pub fn with_profile(mut self, name: &str) -> crate::error::Result<Self> {
+        let mut profiles = self.profiles.take().unwrap_or_default();
+        let Some(profile) = profiles.remove(name) else {
+            let mut available: Vec<String> = profiles.into_keys().collect();
+            available.sort();
+            return Err(crate::error::Error::UnknownProfile {
+                name: name.to_string(),
+                available,
+            });
+        };
+        self.merge(profile);
+        self.profile = Some(name.to_string());
+        Ok(self)
     }
 
     fn merge(&mut self, other: Config) {
+        if let Some(no_config_inputs) = other.no_config_inputs {
+            if no_config_inputs {
+                self.input = None;
+                self.include = None;
+            }
+            self.no_config_inputs = Some(no_config_inputs);
+        }
         if let Some(input) = other.input {
-            self.input = Some(input);
+            append_inputs(&mut self.input, input);
         }
         if let Some(include) = other.include {
-            self.include = Some(include);
+            append_unique(&mut self.include, include);
+        }
+        if let Some(remote_timeout) = other.remote_timeout {
+            self.remote_timeout = Some(remote_timeout);
+        }
+        if let Some(remote_cache_dir) = other.remote_cache_dir {
+            self.remote_cache_dir = Some(remote_cache_dir);
+        }
+        if let Some(offline) = other.offline {
+            self.offline = Some(offline);
         }
         if let Some(output) = other.output {
             self.output = Some(output);
         }
+        if let Some(naming) = other.generic_naming {
+            self.generic_naming = Some(naming);
+        }
+        if let Some(strict) = other.strict {
+            self.strict = Some(strict);
+        }
+        if let Some(schema_naming) = other.schema_naming {
+            self.schema_naming = Some(schema_naming);
+        }
+        if let Some(doc_style) = other.doc_style {
+            self.doc_style = Some(doc_style);
+        }
+        if let Some(sanitize) = other.sanitize {
+            self.sanitize = Some(sanitize);
+        }
+        if let Some(keep_nulls) = other.keep_nulls {
+            self.keep_nulls = Some(keep_nulls);
+        }
+        if let Some(on_conflict) = other.on_conflict {
+            self.on_conflict = Some(on_conflict);
+        }
+        if let Some(audience) = other.audience {
+            self.audience = Some(audience);
+        }
+        if let Some(default_audience) = other.default_audience {
+            self.default_audience = Some(default_audience);
+        }
+        if let Some(sort_output) = other.sort_output {
+            self.sort_output = Some(sort_output);
+        }
+        if let Some(sort_operations) = other.sort_operations {
+            self.sort_operations = Some(sort_operations);
+        }
+        if let Some(group_paths_by) = other.group_paths_by {
+            self.group_paths_by = Some(group_paths_by);
+        }
+        if let Some(hoist) = other.hoist_common_parameters {
+            self.hoist_common_parameters = Some(hoist);
+        }
+        if let Some(annotate_sources) = other.annotate_sources {
+            self.annotate_sources = Some(annotate_sources);
+        }
+        if let Some(skip_parse_errors) = other.skip_parse_errors {
+            self.skip_parse_errors = Some(skip_parse_errors);
+        }
+        if let Some(prefilter) = other.prefilter {
+            self.prefilter = Some(prefilter);
+        }
+        if let Some(strict_refs) = other.strict_refs {
+            self.strict_refs = Some(strict_refs);
+        }
+        if let Some(validate) = other.validate {
+            self.validate = Some(validate);
+        }
+        if let Some(split_by_tag) = other.split_by_tag {
+            self.split_by_tag = Some(split_by_tag);
+        }
+        if let Some(format) = other.format {
+            self.format = Some(format);
+        }
+        if let Some(synthesize_root) = other.synthesize_root {
+            self.synthesize_root = Some(synthesize_root);
+        }
+        if let Some(spec_version) = other.spec_version {
+            self.spec_version = Some(spec_version);
+        }
+        if let Some(report) = other.report {
+            self.report = Some(report);
+        }
+        if let Some(message_format) = other.message_format {
+            self.message_format = Some(message_format);
+        }
+        if let Some(quiet) = other.quiet {
+            self.quiet = Some(quiet);
+        }
+        if other.verbose > 0 {
+            self.verbose = other.verbose;
+        }
+        if let Some(check) = other.check {
+            self.check = Some(check);
+        }
+        if let Some(export_registry) = other.export_registry {
+            self.export_registry = Some(export_registry);
+        }
+        if let Some(import_registry) = other.import_registry {
+            append_unique(&mut self.import_registry, import_registry);
+        }
+        // `info` fields, assignments and variables accumulate across layers; later layers win
+        if let Some(info) = other.info {
+            self.info.get_or_insert_with(Default::default).extend(info);
+        }
+        if let Some(servers) = other.servers {
+            self.servers = Some(servers);
+        }
+        if let Some(set) = other.set {
+            self.set.get_or_insert_with(Vec::new).extend(set);
+        }
+        if let Some(variables) = other.variables {
+            self.variables
+                .get_or_insert_with(HashMap::new)
+                .extend(variables);
+        }
+        if let Some(lint) = other.lint {
+            self.lint.get_or_insert_with(HashMap::new).extend(lint);
+        }
+        if let Some(command) = other.command {
+            self.command = Some(command);
+        }
+        if let Some(profile) = other.profile {
+            self.profile = Some(profile);
+        }
+        // A profile defined in several files is layered like the files themselves
+        for (name, profile) in other.profiles.into_iter().flatten() {
+            let profiles = self.profiles.get_or_insert_with(HashMap::new);
+            match profiles.get_mut(&name) {
+                Some(existing) => existing.merge(profile),
+                None => {
+                    profiles.insert(name, profile);
+                }
+            }
+        }
+        self.files.extend(other.files);
+    }
+}
+
+// Like `append_unique`, by path; metadata given again for a directory replaces the earlier one
+fn append_inputs(target: &mut Option<Vec<Input>>, inputs: Vec<Input>) {
+    let target = target.get_or_insert_with(Vec::new);
+    for input in inputs {
+        match target
+            .iter_mut()
+            .find(|existing| existing.path == input.path)
+        {
+            Some(existing) if !input.metadata.is_empty() => existing.metadata = input.metadata,
+            Some(_) => {}
+            None => target.push(input),
+        }
     }
 }
 
-fn load_cargo_toml() -> Result<Config, Box<dyn std::error::Error>> {
-    let content = std::fs::read_to_string("Cargo.toml")?;
-    let config: CargoConfig = toml::from_str(&content)?;
-    Ok(config
-        .package
-        .and_then(|p| p.metadata)
-        .and_then(|m| m.oas_forge)
-        .unwrap_or_default())
+fn append_unique(target: &mut Option<Vec<PathBuf>>, paths: Vec<PathBuf>) {
+    let target = target.get_or_insert_with(Vec::new);
+    for path in paths {
+        if !target.contains(&path) {
+            target.push(path);
+        }
+    }
+}
+
+fn load_cargo_toml() -> crate::error::Result<Option<Config>> {
+    let Some(content) = read_if_exists("Cargo.toml")? else {
+        return Ok(None);
+    };
+    let config: CargoConfig = parse_toml(std::path::Path::new("Cargo.toml"), &content)?;
+    Ok(Some(
+        config
+            .package
+            .and_then(|p| p.metadata)
+            .and_then(|m| m.oas_forge)
+            .unwrap_or_default(),
+    ))
+}
+
+fn load_toml_file<P: AsRef<std::path::Path>>(path: P) -> crate::error::Result<Option<Config>> {
+    match read_if_exists(&path)? {
+        Some(_) => Config::from_file(path).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn read_if_exists<P: AsRef<std::path::Path>>(path: P) -> crate::error::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
 }
 
-fn load_toml_file<P: AsRef<std::path::Path>>(
-    path: P,
-) -> Result<Config, Box<dyn std::error::Error>> {
-    let content = std::fs::read_to_string(path)?;
-    let config: Config = toml::from_str(&content)?;
-    Ok(config)
+fn parse_toml<T: serde::de::DeserializeOwned>(
+    path: &std::path::Path,
+    content: &str,
+) -> crate::error::Result<T> {
+    toml::from_str(content).map_err(|source| crate::error::Error::Config {
+        file: path.to_path_buf(),
+        source: Box::new(source),
+    })
 }
@@ -0,0 +1,472 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Numbers identifying each kind of problem, shared by errors (`E0003`) and warnings (`W0003`).
+pub mod code {
+    pub const NO_ROOT: u16 = 1;
+    pub const MULTIPLE_ROOTS: u16 = 2;
+    pub const FRAGMENT_NOT_FOUND: u16 = 3;
+    pub const MISSING_FRAGMENT_ARG: u16 = 4;
+    pub const FRAGMENT_ARGS: u16 = 5;
+    pub const FRAGMENT_CYCLE: u16 = 6;
+    pub const FRAGMENT_DEPTH: u16 = 7;
+    pub const BLUEPRINT_NOT_FOUND: u16 = 8;
+    pub const BLUEPRINT_ARITY: u16 = 9;
+    pub const UNRESOLVED_REFERENCE: u16 = 10;
+    pub const DUPLICATE_DEFINITION: u16 = 11;
+    pub const UNKNOWN_VARIABLE: u16 = 12;
+    pub const NO_FILES_FOUND: u16 = 13;
+    pub const SNIPPET_YAML: u16 = 14;
+    pub const DANGLING_REF: u16 = 15;
+    pub const DOWNCONVERT: u16 = 16;
+    pub const INVALID_DOCUMENT: u16 = 17;
+    pub const IMPORT_COLLISION: u16 = 18;
+    pub const MISSING_OUTPUT: u16 = 19;
+    pub const RUST_SYNTAX: u16 = 20;
+    pub const ROUTE: u16 = 21;
+    pub const IO: u16 = 22;
+    pub const YAML: u16 = 23;
+    pub const JSON: u16 = 24;
+    pub const TOML: u16 = 25;
+    pub const SYNTHESIZED_ROOT: u16 = 26;
+    pub const OUTDATED_OUTPUT: u16 = 27;
+    pub const INPUT_NOT_FOUND: u16 = 28;
+    pub const CONFIG: u16 = 29;
+    pub const UNKNOWN_PROFILE: u16 = 30;
+    pub const REMOTE: u16 = 31;
+    pub const SCHEMA_CONFLICT: u16 = 32;
+    pub const LINT: u16 = 33;
+    pub const ALREADY_EXISTS: u16 = 34;
+    pub const BLUEPRINT_PARAM: u16 = 35;
+    pub const GENERIC_NAME_COLLISION: u16 = 36;
+}
+
+/// A problem found by an expansion pass (fragments, blueprints, smart refs).
+///
+/// Passes only record what went wrong; the scanner knows which snippet was being
+/// processed, attaches the file and line, and decides whether the issue is a
+/// warning or (in strict mode) an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    FragmentNotFound(String),
+    /// A fragment parameter with neither an argument nor a default
+    MissingFragmentArg {
+        fragment: String,
+        param: String,
+    },
+    /// Malformed call arguments (mixed named/positional, unknown parameter)
+    FragmentArgs {
+        fragment: String,
+        message: String,
+    },
+    /// Fragments that (transitively) include themselves, e.g. A -> B -> A
+    FragmentCycle(Vec<String>),
+    /// Fragment nesting deeper than the given limit
+    FragmentDepth(usize),
+    BlueprintNotFound(String),
+    /// Argument count differing from the parameter count; `offending` holds the parameters
+    /// without an argument or the extra arguments
+    BlueprintArity {
+        name: String,
+        usage: String,
+        expected: usize,
+        found: usize,
+        offending: Vec<String>,
+    },
+    /// A blueprint parameter whose `$Param` appears nowhere in the body (e.g. `$Tdata`)
+    UnusedBlueprintParam {
+        name: String,
+        param: String,
+        usage: String,
+    },
+    /// A `$Param` of the blueprint still in the concrete schema although it had an argument
+    UnsubstitutedBlueprintParam {
+        name: String,
+        param: String,
+        usage: String,
+    },
+    /// Two different instantiations mapped to the same concrete schema name, e.g.
+    /// `Pair<A_B, C>` and `Pair<A, B_C>` under the `underscore` naming
+    GenericNameCollision {
+        name: String,
+        first: String,
+        second: String,
+    },
+    UnresolvedReference(String),
+    /// `{{NAME}}` placeholder with no built-in or configured value
+    UnknownVariable(String),
+}
+
+impl Issue {
+    /// The issue's [`code`] number.
+    pub fn number(&self) -> u16 {
+        match self {
+            Issue::FragmentNotFound(_) => code::FRAGMENT_NOT_FOUND,
+            Issue::MissingFragmentArg { .. } => code::MISSING_FRAGMENT_ARG,
+            Issue::FragmentArgs { .. } => code::FRAGMENT_ARGS,
+            Issue::FragmentCycle(_) => code::FRAGMENT_CYCLE,
+            Issue::FragmentDepth(_) => code::FRAGMENT_DEPTH,
+            Issue::BlueprintNotFound(_) => code::BLUEPRINT_NOT_FOUND,
+            Issue::BlueprintArity { .. } => code::BLUEPRINT_ARITY,
+            Issue::UnusedBlueprintParam { .. } | Issue::UnsubstitutedBlueprintParam { .. } => {
+                code::BLUEPRINT_PARAM
+            }
+            Issue::GenericNameCollision { .. } => code::GENERIC_NAME_COLLISION,
+            Issue::UnresolvedReference(_) => code::UNRESOLVED_REFERENCE,
+            Issue::UnknownVariable(_) => code::UNKNOWN_VARIABLE,
+        }
+    }
+
+    /// Issues that fail the scan even outside strict mode.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Issue::FragmentCycle(_) | Issue::FragmentDepth(_) | Issue::GenericNameCollision { .. }
+        )
+    }
+
+    /// Converts the issue into an [`Error`] located at `file:line`.
+    pub fn into_error(self, file: PathBuf, line: usize) -> Error {
+        match self {
+            Issue::FragmentNotFound(name) => Error::FragmentNotFound { name, file, line },
+            Issue::MissingFragmentArg { fragment, param } => Error::MissingFragmentArg {
+                fragment,
+                param,
+                file,
+                line,
+            },
+            Issue::FragmentArgs { fragment, message } => Error::FragmentArgs {
+                fragment,
+                message,
+                file,
+                line,
+            },
+            Issue::FragmentCycle(path) => Error::FragmentCycle {
+                path: path.join(" -> "),
+                file,
+                line,
+            },
+            Issue::FragmentDepth(limit) => Error::FragmentDepth { limit, file, line },
+            Issue::BlueprintNotFound(name) => Error::BlueprintNotFound { name, file, line },
+            Issue::BlueprintArity {
+                name,
+                usage,
+                expected,
+                found,
+                offending,
+            } => Error::BlueprintArity {
+                name,
+                usage,
+                expected,
+                found,
+                offending: arity_detail(expected, found, &offending),
+                file,
+                line,
+            },
+            Issue::UnusedBlueprintParam { name, param, usage } => Error::UnusedBlueprintParam {
+                name,
+                param,
+                usage,
+                file,
+                line,
+            },
+            Issue::UnsubstitutedBlueprintParam { name, param, usage } => {
+                Error::UnsubstitutedBlueprintParam {
+                    name,
+                    param,
+                    usage,
+                    file,
+                    line,
+                }
+            }
+            Issue::GenericNameCollision {
+                name,
+                first,
+                second,
+            } => Error::GenericNameCollision {
+                name,
+                first,
+                second,
+                file,
+                line,
+            },
+            Issue::UnresolvedReference(name) => Error::UnresolvedReference { name, file, line },
+            Issue::UnknownVariable(name) => Error::UnknownVariable { name, file, line },
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::FragmentNotFound(name) => write!(f, "Fragment '{}' not found", name),
+            Issue::MissingFragmentArg { fragment, param } => write!(
+                f,
+                "Fragment '{}' parameter '{}' has no argument and no default",
+                fragment, param
+            ),
+            Issue::FragmentArgs { fragment, message } => {
+                write!(
+                    f,
+                    "Invalid arguments for fragment '{}': {}",
+                    fragment, message
+                )
+            }
+            Issue::FragmentCycle(path) => {
+                write!(f, "Fragment cycle: {}", path.join(" -> "))
+            }
+            Issue::FragmentDepth(limit) => {
+                write!(f, "Fragment nesting exceeds {} levels", limit)
+            }
+            Issue::BlueprintNotFound(name) => write!(f, "Blueprint '{}' not found", name),
+            Issue::BlueprintArity {
+                name,
+                usage,
+                expected,
+                found,
+                offending,
+            } => write!(
+                f,
+                "Blueprint '{}' expects {} args, got {} in ${}: {}",
+                name,
+                expected,
+                found,
+                usage,
+                arity_detail(*expected, *found, offending)
+            ),
+            Issue::UnusedBlueprintParam { name, param, usage } => write!(
+                f,
+                "Blueprint '{}' never uses parameter '{}' (instantiated as ${})",
+                name, param, usage
+            ),
+            Issue::UnsubstitutedBlueprintParam { name, param, usage } => write!(
+                f,
+                "Blueprint '{}' parameter '{}' is left unsubstituted in ${}",
+                name, param, usage
+            ),
+            Issue::GenericNameCollision {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "${} and ${} both generate the schema name '{}'",
+                first, second, name
+            ),
+            Issue::UnresolvedReference(name) => {
+                write!(f, "Unresolved smart reference '${}'", name)
+            }
+            Issue::UnknownVariable(name) => {
+                write!(f, "Unknown template variable '{{{{{}}}}}'", name)
+            }
+        }
+    }
+}
+
+// "missing 'U'" or "extra 'Tag', 'Order'"
+fn arity_detail(expected: usize, found: usize, offending: &[String]) -> String {
+    let kind = if found < expected { "missing" } else { "extra" };
+    let names: Vec<String> = offending.iter().map(|o| format!("'{}'", o)).collect();
+    format!("{} {}", kind, names.join(", "))
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Severity {
+    /// Summaries and other progress information
+    Info,
+    Warning,
+    /// Failures; documents are only reported this way by validation
+    Error,
+}
+
+impl Severity {
+    fn code_prefix(self) -> char {
+        match self {
+            Severity::Info => 'I',
+            Severity::Warning => 'W',
+            Severity::Error => 'E',
+        }
+    }
+}
+
+/// A problem found during a run, with where it happened.
+///
+/// Scan diagnostics point at a source file and line; checks on the merged document
+/// carry a JSON pointer into it instead (plus the source when annotations provide one).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// e.g. `W0003`, see [`code`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Lint rule that produced it, see [`crate::lint::RULES`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// 1-based, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// JSON pointer into the merged document, e.g. `/paths/~1users/get`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn warning(message: impl Into<String>, file: PathBuf, line: usize) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: None,
+            rule: None,
+            message: message.into(),
+            file: Some(file),
+            line: Some(line),
+            column: None,
+            pointer: None,
+        }
+    }
+
+    /// Information without a location, e.g. a run's summary.
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Info,
+            code: None,
+            rule: None,
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+            pointer: None,
+        }
+    }
+
+    /// A warning about the merged document at JSON `pointer`.
+    pub fn at_pointer(message: impl Into<String>, pointer: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code: None,
+            rule: None,
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+            pointer: Some(pointer.into()),
+        }
+    }
+
+    /// Like [`Diagnostic::at_pointer`], with [`Severity::Error`].
+    pub fn error_at_pointer(message: impl Into<String>, pointer: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            ..Self::at_pointer(message, pointer)
+        }
+    }
+
+    /// Sets the code from a [`code`] number, prefixed by the severity.
+    pub fn with_code(mut self, number: u16) -> Self {
+        self.code = Some(format!("{}{:04}", self.severity.code_prefix(), number));
+        self
+    }
+
+    pub fn with_rule(mut self, rule: impl Into<String>) -> Self {
+        self.rule = Some(rule.into());
+        self
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Errors as diagnostics, one per error of [`Error::Multiple`].
+    pub fn from_error(error: &Error) -> Vec<Diagnostic> {
+        if let Error::Multiple(errors) = error {
+            return errors.iter().flat_map(Diagnostic::from_error).collect();
+        }
+        let (file, line, column) = error.location();
+        let diagnostic = match error {
+            Error::DanglingRef(inner) | Error::Invalid(inner) => Diagnostic {
+                message: error.to_string(),
+                ..(**inner).clone()
+            },
+            _ => Diagnostic {
+                severity: Severity::Error,
+                code: None,
+                rule: None,
+                message: error.to_string(),
+                file,
+                line,
+                column,
+                pointer: None,
+            },
+        };
+        vec![Diagnostic {
+            severity: Severity::Error,
+            code: Some(error.code()),
+            ..diagnostic
+        }]
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: ", file.display(), line)?,
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        if let Some(pointer) = &self.pointer {
+            write!(f, "#{}: ", pointer)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(rule) = &self.rule {
+            write!(f, " [{}]", rule)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_json_roundtrip() {
+        assert_eq!(Error::NoRootFound.code(), "E0001");
+
+        let issue = Issue::FragmentNotFound("Missing".into());
+        let warning = Diagnostic::warning(issue.to_string(), "src/lib.rs".into(), 4)
+            .with_code(issue.number());
+        assert_eq!(warning.code(), Some("W0003"));
+
+        let line = serde_json::to_string(&warning).unwrap();
+        assert_eq!(
+            line,
+            r#"{"severity":"warning","code":"W0003","message":"Fragment 'Missing' not found","file":"src/lib.rs","line":4}"#
+        );
+        assert_eq!(serde_json::from_str::<Diagnostic>(&line).unwrap(), warning);
+    }
+
+    #[test]
+    fn test_from_error_flattens_and_locates() {
+        let error = Error::Multiple(vec![
+            Error::NoRootFound,
+            Issue::BlueprintNotFound("Page".into()).into_error("src/a.rs".into(), 7),
+        ]);
+        let diagnostics = Diagnostic::from_error(&error);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code(), Some("E0001"));
+        assert_eq!(diagnostics[0].file, None);
+        assert_eq!(diagnostics[1].code(), Some("E0008"));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].file, Some(PathBuf::from("src/a.rs")));
+        assert_eq!(diagnostics[1].line, Some(7));
+    }
+}
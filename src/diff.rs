@@ -0,0 +1,632 @@
+use crate::error::Result;
+use crate::refs::{escape, with_token};
+use crate::split::HTTP_METHODS;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::Path;
+
+/// How a location differs between two documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+    /// Same keys, different order (only reported for ordered comparisons)
+    Reordered,
+}
+
+/// A location (JSON pointer) where two documents differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub pointer: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+            ChangeKind::Reordered => "reordered",
+        };
+        write!(f, "{} #{}", kind, self.pointer)
+    }
+}
+
+/// Where `new` differs from `old`, as the innermost differing JSON pointers.
+///
+/// Mapping key order only matters when `ordered` is set (e.g. for sorted output).
+pub fn changes(old: &Value, new: &Value, ordered: bool) -> Vec<Change> {
+    let mut out = Vec::new();
+    walk(old, new, &mut String::new(), ordered, &mut out);
+    out
+}
+
+fn walk(old: &Value, new: &Value, pointer: &mut String, ordered: bool, out: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            for (key, old_value) in a {
+                with_token(pointer, &key_token(key), |p| match b.get(key) {
+                    Some(new_value) => walk(old_value, new_value, p, ordered, out),
+                    None => push(out, ChangeKind::Removed, p),
+                });
+            }
+            for key in b.keys().filter(|key| !a.contains_key(*key)) {
+                with_token(pointer, &key_token(key), |p| {
+                    push(out, ChangeKind::Added, p)
+                });
+            }
+            let same_keys = a.len() == b.len() && a.keys().all(|key| b.contains_key(key));
+            if ordered && same_keys && !a.keys().eq(b.keys()) {
+                push(out, ChangeKind::Reordered, pointer);
+            }
+        }
+        (Value::Sequence(a), Value::Sequence(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                with_token(pointer, &i.to_string(), |p| match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => walk(x, y, p, ordered, out),
+                    (Some(_), None) => push(out, ChangeKind::Removed, p),
+                    _ => push(out, ChangeKind::Added, p),
+                });
+            }
+        }
+        _ if old != new => push(out, ChangeKind::Changed, pointer),
+        _ => {}
+    }
+}
+
+fn push(out: &mut Vec<Change>, kind: ChangeKind, pointer: &str) {
+    out.push(Change {
+        kind,
+        pointer: pointer.to_string(),
+    });
+}
+
+fn key_token(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Reads a YAML or JSON document.
+pub fn read_document(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path)?;
+    // JSON is valid YAML, so one parser covers every format
+    Ok(serde_yaml::from_str(&content)?)
+}
+
+/// A change to the API described by a document, as reported by [`diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiChange {
+    /// Whether existing clients may stop working
+    pub breaking: bool,
+    pub message: String,
+    /// JSON pointer of the changed location (in the new document, or the old one if removed)
+    pub pointer: String,
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = if self.breaking {
+            "breaking"
+        } else {
+            "non-breaking"
+        };
+        write!(f, "{}: {} (#{})", marker, self.message, self.pointer)
+    }
+}
+
+/// What changed between two versions of an API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ApiDiff {
+    pub changes: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    pub fn breaking(&self) -> usize {
+        self.changes.iter().filter(|c| c.breaking).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ApiDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        write!(
+            f,
+            "{} changes, {} breaking",
+            self.changes.len(),
+            self.breaking()
+        )
+    }
+}
+
+/// Compares two OpenAPI documents: paths, operations, parameters, request and response
+/// schemas and `components.schemas`, classifying the common cases as breaking or not.
+pub fn diff(old: &Value, new: &Value) -> ApiDiff {
+    let mut differ = Differ::default();
+    differ.paths(old, new);
+    differ.components(old, new);
+    ApiDiff {
+        changes: differ.changes,
+    }
+}
+
+#[derive(Default)]
+struct Differ {
+    changes: Vec<ApiChange>,
+}
+
+impl Differ {
+    fn push(&mut self, breaking: bool, message: String, pointer: String) {
+        self.changes.push(ApiChange {
+            breaking,
+            message,
+            pointer,
+        });
+    }
+
+    fn paths(&mut self, old: &Value, new: &Value) {
+        let empty = Mapping::new();
+        let old_paths = old
+            .get("paths")
+            .and_then(Value::as_mapping)
+            .unwrap_or(&empty);
+        let new_paths = new
+            .get("paths")
+            .and_then(Value::as_mapping)
+            .unwrap_or(&empty);
+
+        for (path, old_item) in old_paths {
+            let Some(path) = path.as_str() else { continue };
+            let pointer = format!("/paths/{}", escape(path));
+            match new_paths.get(path) {
+                Some(new_item) => self.path_item(path, &pointer, old_item, new_item),
+                None => self.push(true, format!("path {} removed", path), pointer),
+            }
+        }
+        for path in new_paths.keys().filter_map(Value::as_str) {
+            if !old_paths.contains_key(path) {
+                let pointer = format!("/paths/{}", escape(path));
+                self.push(false, format!("path {} added", path), pointer);
+            }
+        }
+    }
+
+    fn path_item(&mut self, path: &str, pointer: &str, old: &Value, new: &Value) {
+        for method in HTTP_METHODS {
+            let name = format!("{} {}", method.to_uppercase(), path);
+            let pointer = format!("{}/{}", pointer, method);
+            match (old.get(method), new.get(method)) {
+                (Some(old_op), Some(new_op)) => self.operation(&name, &pointer, old_op, new_op),
+                (Some(_), None) => self.push(true, format!("operation {} removed", name), pointer),
+                (None, Some(_)) => self.push(false, format!("operation {} added", name), pointer),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn operation(&mut self, name: &str, pointer: &str, old: &Value, new: &Value) {
+        self.parameters(name, pointer, old, new);
+
+        let old_body = old.get("requestBody").and_then(|b| b.get("content"));
+        let new_body = new.get("requestBody").and_then(|b| b.get("content"));
+        if let (Some(old_body), Some(new_body)) = (old_body, new_body) {
+            let context = format!("{} request", name);
+            let pointer = format!("{}/requestBody/content", pointer);
+            self.content(&context, &pointer, old_body, new_body, Direction::Request);
+        }
+
+        let empty = Mapping::new();
+        let responses = |op: &Value| {
+            op.get("responses")
+                .and_then(Value::as_mapping)
+                .cloned()
+                .unwrap_or_else(|| empty.clone())
+        };
+        let (old_responses, new_responses) = (responses(old), responses(new));
+        for (code, old_response) in &old_responses {
+            let code = key_token(code);
+            let pointer = format!("{}/responses/{}", pointer, escape(&code));
+            match new_responses.get(code.as_str()) {
+                Some(new_response) => {
+                    if let (Some(old_content), Some(new_content)) =
+                        (old_response.get("content"), new_response.get("content"))
+                    {
+                        let context = format!("{} response {}", name, code);
+                        let pointer = format!("{}/content", pointer);
+                        self.content(
+                            &context,
+                            &pointer,
+                            old_content,
+                            new_content,
+                            Direction::Response,
+                        );
+                    }
+                }
+                None => self.push(true, format!("{} response {} removed", name, code), pointer),
+            }
+        }
+        for code in new_responses.keys().map(key_token) {
+            if !old_responses.contains_key(code.as_str()) {
+                let pointer = format!("{}/responses/{}", pointer, escape(&code));
+                self.push(false, format!("{} response {} added", name, code), pointer);
+            }
+        }
+    }
+
+    fn parameters(&mut self, name: &str, pointer: &str, old: &Value, new: &Value) {
+        let params = |op: &Value| -> Vec<(String, String, bool)> {
+            op.get("parameters")
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+                .filter_map(|p| {
+                    let param_name = p.get("name")?.as_str()?.to_string();
+                    let location = p.get("in")?.as_str()?.to_string();
+                    let required = p.get("required").and_then(Value::as_bool) == Some(true);
+                    Some((param_name, location, required))
+                })
+                .collect()
+        };
+        let (old_params, new_params) = (params(old), params(new));
+        let pointer = format!("{}/parameters", pointer);
+
+        for (param, location, _) in &old_params {
+            if !new_params
+                .iter()
+                .any(|(n, l, _)| n == param && l == location)
+            {
+                let message = format!("{} {} parameter '{}' removed", name, location, param);
+                self.push(true, message, pointer.clone());
+            }
+        }
+        for (param, location, required) in &new_params {
+            match old_params
+                .iter()
+                .find(|(n, l, _)| n == param && l == location)
+            {
+                None => {
+                    let kind = if *required { "required" } else { "optional" };
+                    let message =
+                        format!("{} {} {} parameter '{}' added", name, kind, location, param);
+                    self.push(*required, message, pointer.clone());
+                }
+                Some((_, _, false)) if *required => {
+                    let message = format!(
+                        "{} {} parameter '{}' became required",
+                        name, location, param
+                    );
+                    self.push(true, message, pointer.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn content(
+        &mut self,
+        context: &str,
+        pointer: &str,
+        old: &Value,
+        new: &Value,
+        direction: Direction,
+    ) {
+        let Some(old) = old.as_mapping() else { return };
+        for (media_type, old_media) in old {
+            let media = key_token(media_type);
+            let pointer = format!("{}/{}", pointer, escape(&media));
+            match new.get(media.as_str()) {
+                Some(new_media) => {
+                    if let (Some(old_schema), Some(new_schema)) =
+                        (old_media.get("schema"), new_media.get("schema"))
+                    {
+                        let context = format!("{} ({})", context, media);
+                        let pointer = format!("{}/schema", pointer);
+                        self.schema(&context, &pointer, old_schema, new_schema, direction);
+                    }
+                }
+                None => self.push(
+                    true,
+                    format!("{} media type {} removed", context, media),
+                    pointer,
+                ),
+            }
+        }
+    }
+
+    fn components(&mut self, old: &Value, new: &Value) {
+        let schemas = |doc: &Value| {
+            doc.get("components")
+                .and_then(|c| c.get("schemas"))
+                .and_then(Value::as_mapping)
+                .cloned()
+                .unwrap_or_default()
+        };
+        let (old_schemas, new_schemas) = (schemas(old), schemas(new));
+        for (name, old_schema) in &old_schemas {
+            let name = key_token(name);
+            let pointer = format!("/components/schemas/{}", escape(&name));
+            match new_schemas.get(name.as_str()) {
+                // Shared schemas may be used in requests and responses alike
+                Some(new_schema) => {
+                    let context = format!("schema {}", name);
+                    self.schema(&context, &pointer, old_schema, new_schema, Direction::Both);
+                }
+                None => self.push(true, format!("schema {} removed", name), pointer),
+            }
+        }
+        for name in new_schemas.keys().map(key_token) {
+            if !old_schemas.contains_key(name.as_str()) {
+                let pointer = format!("/components/schemas/{}", escape(&name));
+                self.push(false, format!("schema {} added", name), pointer);
+            }
+        }
+    }
+
+    fn schema(
+        &mut self,
+        context: &str,
+        pointer: &str,
+        old: &Value,
+        new: &Value,
+        direction: Direction,
+    ) {
+        let old_ref = old.get("$ref").and_then(Value::as_str);
+        let new_ref = new.get("$ref").and_then(Value::as_str);
+        if old_ref.is_some() || new_ref.is_some() {
+            // Referenced schemas are compared under components
+            if old_ref != new_ref {
+                let message = format!(
+                    "{} schema changed from {} to {}",
+                    context,
+                    old_ref.unwrap_or("an inline schema"),
+                    new_ref.unwrap_or("an inline schema")
+                );
+                self.push(true, message, pointer.to_string());
+            }
+            return;
+        }
+
+        if old.get("type") != new.get("type") {
+            let message = format!(
+                "{} type changed from {} to {}",
+                context,
+                type_name(old.get("type")),
+                type_name(new.get("type"))
+            );
+            self.push(true, message, format!("{}/type", pointer));
+        }
+
+        let old_required = strings(old.get("required"));
+        let new_required = strings(new.get("required"));
+        for field in new_required.iter().filter(|f| !old_required.contains(f)) {
+            let message = format!("{} field '{}' became required", context, field);
+            // Clients must now send it; responses only gain a guarantee
+            self.push(
+                direction != Direction::Response,
+                message,
+                format!("{}/required", pointer),
+            );
+        }
+
+        let empty = Mapping::new();
+        let old_props = properties(old).unwrap_or(&empty);
+        let new_props = properties(new).unwrap_or(&empty);
+        for (field, old_prop) in old_props {
+            let field = key_token(field);
+            let pointer = format!("{}/properties/{}", pointer, escape(&field));
+            match new_props.get(field.as_str()) {
+                Some(new_prop) => {
+                    let context = format!("{}.{}", context, field);
+                    self.schema(&context, &pointer, old_prop, new_prop, direction);
+                }
+                None => {
+                    let message = format!("{} field '{}' removed", context, field);
+                    self.push(direction != Direction::Request, message, pointer);
+                }
+            }
+        }
+        for field in new_props.keys().map(key_token) {
+            if !old_props.contains_key(field.as_str()) {
+                let pointer = format!("{}/properties/{}", pointer, escape(&field));
+                let message = format!("{} field '{}' added", context, field);
+                self.push(false, message, pointer);
+            }
+        }
+
+        if let (Some(old_enum), Some(new_enum)) = (
+            old.get("enum").and_then(Value::as_sequence),
+            new.get("enum").and_then(Value::as_sequence),
+        ) {
+            for value in old_enum.iter().filter(|v| !new_enum.contains(v)) {
+                let message = format!("{} enum value {} removed", context, scalar(value));
+                self.push(true, message, format!("{}/enum", pointer));
+            }
+            for value in new_enum.iter().filter(|v| !old_enum.contains(v)) {
+                let message = format!("{} enum value {} added", context, scalar(value));
+                // New values can surprise clients reading responses
+                self.push(
+                    direction != Direction::Request,
+                    message,
+                    format!("{}/enum", pointer),
+                );
+            }
+        }
+
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            let context = format!("{}[]", context);
+            let pointer = format!("{}/items", pointer);
+            self.schema(&context, &pointer, old_items, new_items, direction);
+        }
+    }
+}
+
+// Which side of the exchange a schema describes, deciding what counts as breaking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Request,
+    Response,
+    Both,
+}
+
+fn properties(schema: &Value) -> Option<&Mapping> {
+    schema.get("properties").and_then(Value::as_mapping)
+}
+
+fn strings(value: Option<&Value>) -> Vec<&str> {
+    value
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+fn type_name(value: Option<&Value>) -> String {
+    value.map_or_else(|| "unspecified".to_string(), scalar)
+}
+
+fn scalar(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_changes_ignore_key_order_unless_ordered() {
+        let old = yaml("paths: {/a: {get: {summary: A}}, /b: {}}\ntags: [x, y]");
+        let new = yaml("paths: {/b: {}, /a: {get: {summary: B}}, /c: {}}\ntags: [x]");
+
+        let found: Vec<String> = changes(&old, &new, false)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "changed #/paths/~1a/get/summary",
+                "added #/paths/~1c",
+                "removed #/tags/1",
+            ]
+        );
+
+        let old = yaml("a: 1\nb: 2");
+        let new = yaml("b: 2\na: 1");
+        assert!(changes(&old, &new, false).is_empty());
+        assert_eq!(
+            changes(&old, &new, true),
+            vec![Change {
+                kind: ChangeKind::Reordered,
+                pointer: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_paths_and_operations() {
+        let old = yaml("paths:\n  /users: {get: {}, delete: {}}\n  /old: {get: {}}");
+        let new = yaml("paths:\n  /users: {get: {}, post: {}}\n  /new: {get: {}}");
+
+        let found: Vec<(bool, String)> = diff(&old, &new)
+            .changes
+            .into_iter()
+            .map(|c| (c.breaking, c.message))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (false, "operation POST /users added".to_string()),
+                (true, "operation DELETE /users removed".to_string()),
+                (true, "path /old removed".to_string()),
+                (false, "path /new added".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_required_fields_and_enums() {
+        let old = yaml(
+            r#"
+paths:
+  /users:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              properties: {name: {type: string}}
+      responses:
+        '200':
+          content:
+            application/json:
+              schema: {type: object, required: [], properties: {id: {type: string}}}
+components:
+  schemas:
+    Status: {type: string, enum: [active, banned]}
+"#,
+        );
+        let new = yaml(
+            r#"
+paths:
+  /users:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              required: [email]
+              properties: {name: {type: string}, email: {type: string}}
+      responses:
+        '200':
+          content:
+            application/json:
+              schema: {type: object, required: [id], properties: {id: {type: string}}}
+components:
+  schemas:
+    Status: {type: string, enum: [active]}
+"#,
+        );
+
+        let diff = diff(&old, &new);
+        let breaking: Vec<&str> = diff
+            .changes
+            .iter()
+            .filter(|c| c.breaking)
+            .map(|c| c.message.as_str())
+            .collect();
+        assert_eq!(
+            breaking,
+            vec![
+                "POST /users request (application/json) field 'email' became required",
+                "schema Status enum value \"banned\" removed",
+            ]
+        );
+        assert_eq!(diff.breaking(), 2);
+        assert!(diff.changes.iter().any(|c| !c.breaking
+            && c.message
+                == "POST /users response 200 (application/json) field 'id' became required"));
+        assert_eq!(
+            diff.changes[0].pointer,
+            "/paths/~1users/post/requestBody/content/application~1json/schema/required"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::diagnostics::{Diagnostic, code};
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -15,9 +16,22 @@ pub enum Error {
     #[error("TOML parsing error: {0}")]
     Toml(#[from] toml::de::Error),
 
+    #[error("Invalid configuration in {file:?}: {source}")]
+    Config {
+        file: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+
     #[error("Syntactic parsing error in file {file:?}: {source}")]
     Parse { file: PathBuf, source: syn::Error },
 
+    #[error("Route error in {file:?}:{line}: {message}")]
+    Route {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+
     #[error(
         "Validation failed: No Root OpenAPI definition found. One definition must contain 'openapi' and 'info' fields."
     )]
@@ -28,16 +42,349 @@ pub enum Error {
     )]
     MultipleRootsFound,
 
-    #[error("Empty input: No files found in the specified directories.")]
-    NoFilesFound,
+    #[error("Fragment '{name}' not found (in {file:?}:{line})")]
+    FragmentNotFound {
+        name: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error(
+        "Fragment '{fragment}' parameter '{param}' has no argument and no default (in {file:?}:{line})"
+    )]
+    MissingFragmentArg {
+        fragment: String,
+        param: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Invalid arguments for fragment '{fragment}': {message} (in {file:?}:{line})")]
+    FragmentArgs {
+        fragment: String,
+        message: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Fragment cycle: {path} (in {file:?}:{line})")]
+    FragmentCycle {
+        path: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Fragment nesting exceeds {limit} levels (in {file:?}:{line})")]
+    FragmentDepth {
+        limit: usize,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Blueprint '{name}' not found (in {file:?}:{line})")]
+    BlueprintNotFound {
+        name: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error(
+        "Blueprint '{name}' expects {expected} args, got {found} in ${usage}: {offending} (in {file:?}:{line})"
+    )]
+    BlueprintArity {
+        name: String,
+        usage: String,
+        expected: usize,
+        found: usize,
+        offending: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error(
+        "Blueprint '{name}' never uses parameter '{param}' (instantiated as ${usage}, in {file:?}:{line})"
+    )]
+    UnusedBlueprintParam {
+        name: String,
+        param: String,
+        usage: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error(
+        "Blueprint '{name}' parameter '{param}' is left unsubstituted in ${usage} (in {file:?}:{line})"
+    )]
+    UnsubstitutedBlueprintParam {
+        name: String,
+        param: String,
+        usage: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("${first} and ${second} both generate the schema name '{name}' (in {file:?}:{line})")]
+    GenericNameCollision {
+        name: String,
+        first: String,
+        second: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Unresolved smart reference '${name}' (in {file:?}:{line})")]
+    UnresolvedReference {
+        name: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error(
+        "Duplicate {kind} '{name}': defined in {first_file:?}:{first_line} and again in {file:?}:{line}"
+    )]
+    DuplicateDefinition {
+        kind: &'static str,
+        name: String,
+        first_file: PathBuf,
+        first_line: usize,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Imported {kind} '{name}' is also defined locally")]
+    ImportCollision { kind: &'static str, name: String },
+
+    #[error("Unknown template variable '{{{{{name}}}}}' (in {file:?}:{line})")]
+    UnknownVariable {
+        name: String,
+        file: PathBuf,
+        line: usize,
+    },
+
+    #[error("Failed to fetch {url}: {message}")]
+    Remote {
+        url: String,
+        status: Option<u16>,
+        message: String,
+    },
+
+    #[error("Conflicting definitions of schema '{name}' in {first} and {second}")]
+    SchemaConflict {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("Unknown profile '{name}' (available: {})", format_names(available))]
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+
+    #[error("{0:?} does not exist; generate it before checking")]
+    MissingOutput(PathBuf),
+
+    #[error("Input directory {0:?} does not exist")]
+    InputNotFound(PathBuf),
+
+    #[error("{0:?} already exists (use --force to overwrite)")]
+    AlreadyExists(PathBuf),
+
+    #[error(
+        "Empty input: No files found in the specified directories.{}",
+        format_searched(.0)
+    )]
+    NoFilesFound(Vec<(PathBuf, RootStatus)>),
 
-    #[error("YAML error in {file}:{line}: {source}\nContext:\n{context}")]
+    #[error(
+        "YAML error in {}: {}\n{context}",
+        format_location(file, *line, *column),
+        yaml_message(source)
+    )]
     SourceMapped {
         file: PathBuf,
         line: usize,
+        /// 1-based, in the source line as written
+        column: Option<usize>,
         source: serde_yaml::Error,
+        /// The offending source lines, the failing one marked with a caret
         context: String,
     },
+
+    #[error("JSON error in {}:{}:{}: {source}", file.display(), source.line(), source.column())]
+    JsonSnippet {
+        file: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Dangling reference: {0}")]
+    DanglingRef(Box<Diagnostic>),
+
+    #[error("Cannot convert to Swagger 2.0: {0}")]
+    Downconvert(String),
+
+    #[error("Invalid document: {0}")]
+    Invalid(Box<Diagnostic>),
+
+    #[error("{}", format_multiple(.0))]
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    /// Stable identifier of the error kind, e.g. `E0001` for [`Error::NoRootFound`].
+    ///
+    /// [`Error::Multiple`] reports `E0000`.
+    pub fn code(&self) -> String {
+        let number = match self {
+            Error::Io(_) => code::IO,
+            Error::Yaml(_) => code::YAML,
+            Error::Json(_) => code::JSON,
+            Error::Toml(_) => code::TOML,
+            Error::Parse { .. } => code::RUST_SYNTAX,
+            Error::Route { .. } => code::ROUTE,
+            Error::NoRootFound => code::NO_ROOT,
+            Error::MultipleRootsFound => code::MULTIPLE_ROOTS,
+            Error::FragmentNotFound { .. } => code::FRAGMENT_NOT_FOUND,
+            Error::MissingFragmentArg { .. } => code::MISSING_FRAGMENT_ARG,
+            Error::FragmentArgs { .. } => code::FRAGMENT_ARGS,
+            Error::FragmentCycle { .. } => code::FRAGMENT_CYCLE,
+            Error::FragmentDepth { .. } => code::FRAGMENT_DEPTH,
+            Error::BlueprintNotFound { .. } => code::BLUEPRINT_NOT_FOUND,
+            Error::BlueprintArity { .. } => code::BLUEPRINT_ARITY,
+            Error::UnusedBlueprintParam { .. } | Error::UnsubstitutedBlueprintParam { .. } => {
+                code::BLUEPRINT_PARAM
+            }
+            Error::GenericNameCollision { .. } => code::GENERIC_NAME_COLLISION,
+            Error::UnresolvedReference { .. } => code::UNRESOLVED_REFERENCE,
+            Error::DuplicateDefinition { .. } => code::DUPLICATE_DEFINITION,
+            Error::ImportCollision { .. } => code::IMPORT_COLLISION,
+            Error::UnknownVariable { .. } => code::UNKNOWN_VARIABLE,
+            Error::MissingOutput(_) => code::MISSING_OUTPUT,
+            Error::InputNotFound(_) => code::INPUT_NOT_FOUND,
+            Error::AlreadyExists(_) => code::ALREADY_EXISTS,
+            Error::Config { .. } => code::CONFIG,
+            Error::UnknownProfile { .. } => code::UNKNOWN_PROFILE,
+            Error::Remote { .. } => code::REMOTE,
+            Error::SchemaConflict { .. } => code::SCHEMA_CONFLICT,
+            Error::NoFilesFound(_) => code::NO_FILES_FOUND,
+            Error::SourceMapped { .. } => code::SNIPPET_YAML,
+            Error::JsonSnippet { .. } => code::JSON,
+            Error::DanglingRef(_) => code::DANGLING_REF,
+            Error::Downconvert(_) => code::DOWNCONVERT,
+            Error::Invalid(_) => code::INVALID_DOCUMENT,
+            Error::Multiple(_) => 0,
+        };
+        format!("E{:04}", number)
+    }
+
+    // File, line and (1-based) column the error points at, where known
+    pub(crate) fn location(&self) -> (Option<PathBuf>, Option<usize>, Option<usize>) {
+        match self {
+            Error::Parse { file, source } => {
+                let start = source.span().start();
+                (Some(file.clone()), Some(start.line), Some(start.column + 1))
+            }
+            Error::Route { file, line, .. }
+            | Error::FragmentNotFound { file, line, .. }
+            | Error::MissingFragmentArg { file, line, .. }
+            | Error::FragmentArgs { file, line, .. }
+            | Error::FragmentCycle { file, line, .. }
+            | Error::FragmentDepth { file, line, .. }
+            | Error::BlueprintNotFound { file, line, .. }
+            | Error::BlueprintArity { file, line, .. }
+            | Error::UnusedBlueprintParam { file, line, .. }
+            | Error::UnsubstitutedBlueprintParam { file, line, .. }
+            | Error::GenericNameCollision { file, line, .. }
+            | Error::UnresolvedReference { file, line, .. }
+            | Error::DuplicateDefinition { file, line, .. }
+            | Error::UnknownVariable { file, line, .. } => (Some(file.clone()), Some(*line), None),
+            Error::SourceMapped {
+                file, line, column, ..
+            } => (Some(file.clone()), Some(*line), *column),
+            Error::JsonSnippet { file, source } => (
+                Some(file.clone()),
+                Some(source.line()),
+                Some(source.column()),
+            ),
+            Error::MissingOutput(file)
+            | Error::InputNotFound(file)
+            | Error::AlreadyExists(file)
+            | Error::Config { file, .. } => (Some(file.clone()), None, None),
+            _ => (None, None, None),
+        }
+    }
+
+    /// `Ok` for no errors, the error itself for one, [`Error::Multiple`] otherwise.
+    pub fn from_all(mut errors: Vec<Error>) -> Result<()> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(Error::Multiple(errors)),
+        }
+    }
+}
+
+fn format_location(file: &std::path::Path, line: usize, column: Option<usize>) -> String {
+    match column {
+        Some(column) => format!("{}:{}:{}", file.display(), line, column),
+        None => format!("{}:{}", file.display(), line),
+    }
+}
+
+// serde_yaml reports positions within the processed snippet; those are misleading next to
+// the mapped source location
+fn yaml_message(error: &serde_yaml::Error) -> String {
+    let message = error.to_string();
+    match error.location() {
+        Some(loc) => message.replace(
+            &format!(" at line {} column {}", loc.line(), loc.column()),
+            "",
+        ),
+        None => message,
+    }
+}
+
+fn format_multiple(errors: &[Error]) -> String {
+    let mut out = format!("{} errors:", errors.len());
+    for error in errors {
+        out.push_str(&format!("\n- {}", error));
+    }
+    out
+}
+
+/// Why a searched input contributed no files, see [`Error::NoFilesFound`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootStatus {
+    NotFound,
+    Empty,
+    /// Contains files, but none with a `.rs`, `.json`, `.yaml`, `.yml` or `.md` extension
+    NoMatchingFiles,
+}
+
+impl std::fmt::Display for RootStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RootStatus::NotFound => "not found",
+            RootStatus::Empty => "empty",
+            RootStatus::NoMatchingFiles => "no .rs, .json, .yaml, .yml or .md files",
+        })
+    }
+}
+
+fn format_names(names: &[String]) -> String {
+    if names.is_empty() {
+        "none defined".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn format_searched(searched: &[(PathBuf, RootStatus)]) -> String {
+    let mut out = String::new();
+    for (path, status) in searched {
+        out.push_str(&format!("\n- {}: {}", path.display(), status));
+    }
+    out
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,8 +1,107 @@
+use crate::diagnostics::Issue;
 use crate::index::Registry;
-use std::collections::HashSet;
+use crate::visitor;
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Naming scheme for concrete schemas produced by monomorphization.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum NamingStrategy {
+    /// `Page_User`, `Result_User_Error` (default). Not injective: `Wrapper<Inner, Item>` and
+    /// `Wrapper<Inner<Item>>` both give `Wrapper_Inner_Item`; such collisions are reported
+    /// as errors instead.
+    #[default]
+    Underscore,
+    /// `PageOfUser`, `ResultOfUserAndError`, `WrapperOfInnerOfItem`; a nested argument list
+    /// followed by further arguments is closed with `End` (`WrapperOfInnerOfAEndAndB`)
+    Of,
+    /// Custom template with `{name}` and `{args}`, e.g. `{name}For{args}`; args are joined and
+    /// closed like [`NamingStrategy::Of`] (`PairForUserAndError`)
+    Template(String),
+}
+
+impl NamingStrategy {
+    /// Builds the concrete schema name for `name<args...>`.
+    pub fn concrete_name(&self, name: &str, args: &[String]) -> String {
+        let args: Vec<(String, usize)> = args.iter().map(|a| (a.clone(), 0)).collect();
+        self.compose(name, &args).0
+    }
+
+    // Name for `name<args...>` where each argument comes with the number of its nested
+    // argument lists left open at its end. Those are only closed (`End`) when another
+    // argument follows, which keeps the names injective without suffixing every nested name.
+    // Returns the name and the lists it leaves open.
+    fn compose(&self, name: &str, args: &[(String, usize)]) -> (String, usize) {
+        if let NamingStrategy::Underscore = self {
+            let suffix = if args.is_empty() {
+                "Generic".to_string()
+            } else {
+                let names: Vec<&str> = args.iter().map(|(a, _)| a.as_str()).collect();
+                names.join("_")
+            };
+            return (format!("{}_{}", name, suffix), 0);
+        }
+        let Some(((_, open), _)) = args.split_last() else {
+            return match self {
+                NamingStrategy::Template(template) => {
+                    (template.replace("{name}", name).replace("{args}", ""), 0)
+                }
+                _ => (name.to_string(), 0),
+            };
+        };
+        let joined = args
+            .iter()
+            .enumerate()
+            .map(|(idx, (arg, open))| {
+                if idx + 1 < args.len() {
+                    format!("{}{}", arg, "End".repeat(*open))
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("And");
+        let composed = match self {
+            NamingStrategy::Template(template) => {
+                template.replace("{name}", name).replace("{args}", &joined)
+            }
+            _ => format!("{}Of{}", name, joined),
+        };
+        (composed, open + 1)
+    }
+}
+
+impl std::str::FromStr for NamingStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "underscore" => Ok(NamingStrategy::Underscore),
+            "of" => Ok(NamingStrategy::Of),
+            t if t.contains("{name}") => Ok(NamingStrategy::Template(t.to_string())),
+            other => Err(format!(
+                "Unknown naming strategy '{}': expected 'underscore', 'of', or a template containing {{name}}",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for NamingStrategy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
 
 pub struct Monomorphizer<'a> {
     registry: &'a mut Registry,
+    naming: NamingStrategy,
+    issues: Vec<Issue>,
+    // Argument lists left open at the end of each generated name (see `compose`)
+    open_lists: HashMap<String, usize>,
     _processed_generics: HashSet<String>,
 }
 
@@ -10,10 +109,24 @@ impl<'a> Monomorphizer<'a> {
     pub fn new(registry: &'a mut Registry) -> Self {
         Self {
             registry,
+            naming: NamingStrategy::default(),
+            issues: Vec::new(),
+            open_lists: HashMap::new(),
             _processed_generics: HashSet::new(),
         }
     }
 
+    /// Sets the naming scheme for generated concrete schemas.
+    pub fn with_naming(mut self, naming: NamingStrategy) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Returns (and clears) the issues recorded since the last call.
+    pub fn take_issues(&mut self) -> Vec<Issue> {
+        std::mem::take(&mut self.issues)
+    }
+
     /// Scans text for generic patterns like $Page<User> and generates concrete schemas.
     /// Returns the text with $Page<User> replaced by $Page_User (which will be resolved to ref later).
     pub fn process(&mut self, content: &str) -> String {
@@ -26,7 +139,8 @@ impl<'a> Monomorphizer<'a> {
         let mut i = 0;
 
         while i < chars.len() {
-            if chars[i] == '$' && i + 1 < chars.len() && chars[i + 1].is_alphabetic() {
+            let escaped = i > 0 && chars[i - 1] == '\\';
+            if chars[i] == '$' && !escaped && i + 1 < chars.len() && chars[i + 1].is_alphabetic() {
                 // Potential generic start
                 let start = i;
                 i += 1;
@@ -60,7 +174,7 @@ impl<'a> Monomorphizer<'a> {
                     result.push_str(&concrete_name);
                 } else {
                     // Just a regular $Name, push what we scanned
-                    result.push_str(&text[start..i]);
+                    result.extend(&chars[start..i]);
                 }
             } else {
                 result.push(chars[i]);
@@ -90,52 +204,127 @@ impl<'a> Monomorphizer<'a> {
             .collect();
 
         // 3. Generate Concrete Name
-        let suffix = if resolved_args.is_empty() {
-            "Generic".to_string()
-        } else {
-            resolved_args.join("_")
-        };
-        let concrete_name = format!("{}_{}", name, suffix);
+        let name_args: Vec<(String, usize)> =
+            resolved_args.iter().map(|a| self.arg_name(a)).collect();
+        let (concrete_name, open) = self.naming.compose(name, &name_args);
+        self.open_lists.insert(concrete_name.clone(), open);
+        let origin: String = format!("{}<{}>", name, resolved_args.join(","))
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
 
         if self.registry.concrete_schemas.contains_key(&concrete_name) {
+            match self.registry.concrete_origins.get(&concrete_name) {
+                Some(first) if *first != origin => {
+                    self.issues.push(Issue::GenericNameCollision {
+                        name: concrete_name.clone(),
+                        first: first.clone(),
+                        second: origin,
+                    });
+                }
+                _ => {}
+            }
             return concrete_name;
         }
 
         // 4. Instantiate Blueprint
         if let Some(blueprint) = self.registry.blueprints.get(name).cloned() {
             let mut content = blueprint.body.clone();
+            let usage = format!("{}<{}>", name, args_str.trim());
 
             // Check arg count
-            if resolved_args.len() != blueprint.params.len() {
-                log::error!(
-                    "Blueprint {} expects {} args, got {}. Using raw args.",
-                    name,
-                    blueprint.params.len(),
-                    resolved_args.len()
-                );
+            let (expected, found) = (blueprint.params.len(), resolved_args.len());
+            if found != expected {
+                let offending = if found < expected {
+                    blueprint.params[found..].to_vec()
+                } else {
+                    resolved_args[expected..].to_vec()
+                };
+                self.issues.push(Issue::BlueprintArity {
+                    name: name.to_string(),
+                    usage: usage.clone(),
+                    expected,
+                    found,
+                    offending,
+                });
+            }
+            let body_tokens = dollar_tokens(&content);
+            for param in &blueprint.params {
+                if !body_tokens.contains(param.as_str()) {
+                    self.issues.push(Issue::UnusedBlueprintParam {
+                        name: name.to_string(),
+                        param: param.clone(),
+                        usage: usage.clone(),
+                    });
+                }
             }
 
-            // Named Substitution: Replace $Param with $Arg
+            // Primitive args (String, u64, Vec<String>, ...) are inlined, not referenced
+            let mut inline_args = HashMap::new();
+            let mut substitutions = HashMap::new();
+
+            // Named Substitution: $Param -> $Arg
             for (idx, param) in blueprint.params.iter().enumerate() {
                 if let Some(arg) = resolved_args.get(idx) {
-                    // Pattern to replace: "$T" -> "$Arg"
-                    // We replace literal "$" + param name
-                    let target = format!("${}", param);
-                    let replacement = format!("${}", arg);
-                    content = content.replace(&target, &replacement);
+                    if let Some(schema) = visitor::primitive_schema(arg) {
+                        if let Ok(schema) = serde_yaml::to_value(schema) {
+                            inline_args.insert(format!("${}", param), schema);
+                            continue;
+                        }
+                    }
+                    substitutions.insert(param.as_str(), arg.as_str());
+                }
+            }
+            content = substitute_params(&content, &substitutions);
+
+            if !inline_args.is_empty() {
+                content = inline_primitive_args(&content, &inline_args);
+            }
+
+            // Parameters without an argument were reported above
+            let left = dollar_tokens(&content);
+            for param in blueprint.params.iter().take(found) {
+                if left.contains(param.as_str()) {
+                    self.issues.push(Issue::UnsubstitutedBlueprintParam {
+                        name: name.to_string(),
+                        param: param.clone(),
+                        usage: usage.clone(),
+                    });
                 }
             }
 
             self.registry
                 .concrete_schemas
                 .insert(concrete_name.clone(), content);
+            self.registry
+                .concrete_origins
+                .insert(concrete_name.clone(), origin);
         } else {
-            log::warn!("Blueprint {} not found", name);
+            self.issues.push(Issue::BlueprintNotFound(name.to_string()));
         }
 
         concrete_name
     }
 
+    // Name token for an argument; primitive generics like Vec<String> use the same scheme
+    fn arg_name(&self, arg: &str) -> (String, usize) {
+        match (arg.find('<'), arg.rfind('>')) {
+            (Some(start), Some(end)) if start < end => {
+                let inner: Vec<(String, usize)> = self
+                    .split_args(&arg[start + 1..end])
+                    .iter()
+                    .map(|a| self.arg_name(a.trim_start_matches('$')))
+                    .collect();
+                self.naming.compose(&sanitize_name(&arg[..start]), &inner)
+            }
+            _ => {
+                let name = sanitize_name(arg);
+                let open = self.open_lists.get(&name).copied().unwrap_or(0);
+                (name, open)
+            }
+        }
+    }
+
     fn split_args(&self, args_str: &str) -> Vec<String> {
         let mut args = Vec::new();
         let mut start = 0;
@@ -164,6 +353,97 @@ impl<'a> Monomorphizer<'a> {
     }
 }
 
+// Replaces whole `$Param` tokens in a single pass, so `$T` never matches
+// the prefix of `$TItem` or `$Total`.
+fn substitute_params(content: &str, substitutions: &HashMap<&str, &str>) -> String {
+    if substitutions.is_empty() {
+        return content.to_string();
+    }
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos + 1]);
+        let after = &rest[pos + 1..];
+        let end = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        let ident = &after[..end];
+        match substitutions.get(ident) {
+            Some(arg) => result.push_str(arg),
+            None => result.push_str(ident),
+        }
+        rest = &after[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Identifiers following a `$` in `content`
+fn dollar_tokens(content: &str) -> HashSet<&str> {
+    content
+        .match_indices('$')
+        .map(|(pos, _)| {
+            let after = &content[pos + 1..];
+            let end = after
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            &after[..end]
+        })
+        .collect()
+}
+
+// Turns a type argument into a schema-name-safe token: Vec<String> -> Vec_String
+fn sanitize_name(arg: &str) -> String {
+    arg.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+// Replaces `$ref: $T` (and bare `$T` values) with the inline schema of a primitive argument.
+fn inline_primitive_args(content: &str, inline_args: &HashMap<String, Value>) -> String {
+    fn walk(value: &mut Value, inline_args: &HashMap<String, Value>) {
+        match value {
+            Value::Mapping(map) => {
+                let inline = map
+                    .get("$ref")
+                    .and_then(|r| r.as_str())
+                    .and_then(|r| inline_args.get(r));
+                if let Some(Value::Mapping(schema)) = inline.cloned() {
+                    map.remove("$ref");
+                    for (k, v) in schema {
+                        if !map.contains_key(&k) {
+                            map.insert(k, v);
+                        }
+                    }
+                }
+                for (_, v) in map.iter_mut() {
+                    walk(v, inline_args);
+                }
+            }
+            Value::Sequence(seq) => {
+                for v in seq {
+                    walk(v, inline_args);
+                }
+            }
+            Value::String(s) => {
+                if let Some(schema) = inline_args.get(s.as_str()) {
+                    *value = schema.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match serde_yaml::from_str::<Value>(content) {
+        Ok(mut root) => {
+            walk(&mut root, inline_args);
+            serde_yaml::to_string(&root).unwrap_or_else(|_| content.to_string())
+        }
+        Err(_) => content.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +467,300 @@ mod tests {
         assert_eq!(concrete, "data: $ref: $User");
     }
 
+    #[test]
+    fn test_primitive_args_are_inlined() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint(
+            "Page".to_string(),
+            vec!["T".to_string()],
+            "type: object\nproperties:\n  items:\n    type: array\n    items:\n      $ref: $T"
+                .to_string(),
+        );
+
+        let mut mono = Monomorphizer::new(&mut registry);
+        assert_eq!(mono.process("$Page<String>"), "$Page_String");
+        assert_eq!(mono.process("$Page<Vec<String>>"), "$Page_Vec_String");
+
+        let concrete = registry.concrete_schemas.get("Page_String").unwrap();
+        assert!(concrete.contains("type: string"));
+        assert!(!concrete.contains("$ref"));
+
+        let nested = registry.concrete_schemas.get("Page_Vec_String").unwrap();
+        let value: Value = serde_yaml::from_str(nested).unwrap();
+        let items = &value["properties"]["items"]["items"];
+        assert_eq!(items["type"], Value::from("array"));
+        assert_eq!(items["items"]["type"], Value::from("string"));
+        assert!(!nested.contains("$ref"));
+    }
+
+    #[test]
+    fn test_param_substitution_is_token_aware() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint(
+            "Pair".to_string(),
+            vec!["T".to_string(), "TItem".to_string()],
+            "first: $T\nsecond: $TItem\ncount: $Total".to_string(),
+        );
+
+        let mut mono = Monomorphizer::new(&mut registry);
+        mono.process("$Pair<User, Order>");
+
+        let concrete = registry.concrete_schemas.get("Pair_User_Order").unwrap();
+        assert_eq!(concrete, "first: $User\nsecond: $Order\ncount: $Total");
+    }
+
+    fn naming_registry() -> Registry {
+        let mut registry = Registry::new();
+        for (name, params) in [
+            ("Wrapper", vec!["T"]),
+            ("Inner", vec!["U"]),
+            ("Pair", vec!["A", "B"]),
+        ] {
+            registry.insert_blueprint(
+                name.to_string(),
+                params.iter().map(|p| p.to_string()).collect(),
+                "x: 1".to_string(),
+            );
+        }
+        registry
+    }
+
+    #[test]
+    fn test_naming_strategies() {
+        let cases = [
+            (
+                NamingStrategy::Underscore,
+                "Wrapper_Inner_Item",
+                "Pair_User_Error",
+            ),
+            (
+                NamingStrategy::Of,
+                "WrapperOfInnerOfItem",
+                "PairOfUserAndError",
+            ),
+            (
+                "{name}For{args}".parse().unwrap(),
+                "WrapperForInnerForItem",
+                "PairForUserAndError",
+            ),
+        ];
+
+        for (naming, nested, two_args) in cases {
+            let mut registry = naming_registry();
+            let mut mono = Monomorphizer::new(&mut registry).with_naming(naming);
+            assert_eq!(
+                mono.process("$Wrapper<$Inner<Item>>"),
+                format!("${}", nested)
+            );
+            assert_eq!(mono.process("$Pair<User, Error>"), format!("${}", two_args));
+            assert!(registry.concrete_schemas.contains_key(nested));
+            assert!(registry.concrete_schemas.contains_key(two_args));
+        }
+    }
+
+    #[test]
+    fn test_naming_of_is_unambiguous() {
+        let mut registry = naming_registry();
+        let mut mono = Monomorphizer::new(&mut registry).with_naming(NamingStrategy::Of);
+        let nested = mono.process("$Wrapper<$Inner<Item>>");
+        let multi = mono.process("$Pair<Inner, Item>");
+        assert_ne!(nested, multi);
+        assert_eq!(
+            mono.process("$Wrapper<Vec<String>>"),
+            "$WrapperOfVecOfString"
+        );
+    }
+
+    #[test]
+    fn test_naming_nested_vs_multiple_args() {
+        let collisions = |issues: Vec<Issue>| -> Vec<Issue> {
+            issues
+                .into_iter()
+                .filter(|i| matches!(i, Issue::GenericNameCollision { .. }))
+                .collect()
+        };
+        // (two args, nested, nested followed by an arg, nested with two args)
+        let distinct = [
+            (
+                NamingStrategy::Of,
+                [
+                    "WrapperOfInnerAndItem",
+                    "WrapperOfInnerOfItem",
+                    "WrapperOfInnerOfAEndAndB",
+                    "WrapperOfInnerOfAAndB",
+                ],
+            ),
+            (
+                "{name}For{args}".parse().unwrap(),
+                [
+                    "WrapperForInnerAndItem",
+                    "WrapperForInnerForItem",
+                    "WrapperForInnerForAEndAndB",
+                    "WrapperForInnerForAAndB",
+                ],
+            ),
+        ];
+        let usages = [
+            "$Wrapper<Inner, Item>",
+            "$Wrapper<$Inner<Item>>",
+            "$Wrapper<$Inner<A>, B>",
+            "$Wrapper<$Inner<A, B>>",
+        ];
+        for (naming, expected) in distinct {
+            let mut registry = naming_registry();
+            let mut mono = Monomorphizer::new(&mut registry).with_naming(naming);
+            for (usage, name) in usages.iter().zip(expected) {
+                assert_eq!(mono.process(usage), format!("${}", name));
+            }
+            assert!(collisions(mono.take_issues()).is_empty());
+        }
+
+        // Deeper nesting closes one list per level
+        let mut registry = naming_registry();
+        let mut mono = Monomorphizer::new(&mut registry).with_naming(NamingStrategy::Of);
+        assert_eq!(
+            mono.process("$Pair<$Wrapper<$Inner<A, B>>, C>"),
+            "$PairOfWrapperOfInnerOfAAndBEndEndAndC"
+        );
+        assert_eq!(
+            mono.process("$Pair<$Wrapper<$Inner<A, B>, C>>"),
+            "$PairOfWrapperOfInnerOfAAndBEndAndC"
+        );
+        assert_eq!(
+            mono.process("$Wrapper<Vec<String>, B>"),
+            "$WrapperOfVecOfStringEndAndB"
+        );
+
+        // underscore is not injective; the collision check is its only safeguard
+        let mut registry = naming_registry();
+        let mut mono = Monomorphizer::new(&mut registry);
+        for pair in [
+            usages[..2].to_vec(),
+            usages[2..].to_vec(),
+            vec!["$Pair<A_B, C>", "$Pair<A, B_C>"],
+        ] {
+            assert_eq!(mono.process(pair[0]), mono.process(pair[1]));
+        }
+        let issues = collisions(mono.take_issues());
+        assert_eq!(
+            issues,
+            vec![
+                Issue::GenericNameCollision {
+                    name: "Wrapper_Inner_Item".to_string(),
+                    first: "Wrapper<Inner,Item>".to_string(),
+                    second: "Wrapper<Inner_Item>".to_string(),
+                },
+                Issue::GenericNameCollision {
+                    name: "Wrapper_Inner_A_B".to_string(),
+                    first: "Wrapper<Inner_A,B>".to_string(),
+                    second: "Wrapper<Inner_A_B>".to_string(),
+                },
+                Issue::GenericNameCollision {
+                    name: "Pair_A_B_C".to_string(),
+                    first: "Pair<A_B,C>".to_string(),
+                    second: "Pair<A,B_C>".to_string(),
+                },
+            ]
+        );
+        assert!(issues[0].is_fatal());
+        // Same instantiation, spelled differently: not a collision
+        mono.process("$Pair<A_B,C>");
+        assert!(collisions(mono.take_issues()).is_empty());
+    }
+
+    #[test]
+    fn test_naming_strategy_parse() {
+        assert_eq!("of".parse::<NamingStrategy>(), Ok(NamingStrategy::Of));
+        assert!("bogus".parse::<NamingStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_records_issues() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint("Page".to_string(), vec!["T".to_string()], "x: $T".into());
+
+        let mut mono = Monomorphizer::new(&mut registry);
+        mono.process("$Missing<User> $Page<A, B>");
+        assert_eq!(
+            mono.take_issues(),
+            vec![
+                Issue::BlueprintNotFound("Missing".to_string()),
+                Issue::BlueprintArity {
+                    name: "Page".to_string(),
+                    usage: "Page<A, B>".to_string(),
+                    expected: 1,
+                    found: 2,
+                    offending: vec!["B".to_string()],
+                }
+            ]
+        );
+        assert!(mono.take_issues().is_empty());
+    }
+
+    #[test]
+    fn test_blueprint_param_checks() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint(
+            "Page".to_string(),
+            vec!["T".to_string()],
+            "items: {$ref: $Tdata}".into(),
+        );
+        registry.insert_blueprint(
+            "Pair".to_string(),
+            vec!["L".to_string(), "R".to_string()],
+            "left: {$ref: $L}\nright: {$ref: $R}".into(),
+        );
+        registry.insert_blueprint(
+            "Tagged".to_string(),
+            vec!["T".to_string()],
+            "value: {$ref: $T}\ndescription: a tagged $T".into(),
+        );
+
+        let mut mono = Monomorphizer::new(&mut registry);
+        mono.process("$Page<User>");
+        let unused = Issue::UnusedBlueprintParam {
+            name: "Page".to_string(),
+            param: "T".to_string(),
+            usage: "Page<User>".to_string(),
+        };
+        assert_eq!(
+            unused.to_string(),
+            "Blueprint 'Page' never uses parameter 'T' (instantiated as $Page<User>)"
+        );
+        assert_eq!(mono.take_issues(), vec![unused]);
+
+        // Missing arguments are reported once, not again as unsubstituted
+        mono.process("$Pair<User>");
+        let issues = mono.take_issues();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].to_string(),
+            "Blueprint 'Pair' expects 2 args, got 1 in $Pair<User>: missing 'R'"
+        );
+
+        mono.process("$Pair<User, Order, Tag>");
+        let issues = mono.take_issues();
+        assert_eq!(
+            issues[0].to_string(),
+            "Blueprint 'Pair' expects 2 args, got 3 in $Pair<User, Order, Tag>: extra 'Tag'"
+        );
+        let error = issues[0].clone().into_error("src/lib.rs".into(), 3);
+        assert_eq!(error.code(), "E0009");
+
+        // A primitive argument is only inlined where it is a schema
+        mono.process("$Tagged<String>");
+        assert_eq!(
+            mono.take_issues(),
+            vec![Issue::UnsubstitutedBlueprintParam {
+                name: "Tagged".to_string(),
+                param: "T".to_string(),
+                usage: "Tagged<String>".to_string(),
+            }]
+        );
+        mono.process("$Tagged<User>");
+        assert!(mono.take_issues().is_empty());
+    }
+
     #[test]
     fn test_nested_generics() {
         let mut registry = Registry::new();
@@ -0,0 +1,207 @@
+use crate::refs;
+use crate::validate::HTTP_METHODS;
+use serde_yaml::{Mapping, Value};
+
+/// Operations an identical inline parameter must appear on to be hoisted.
+pub const MIN_OCCURRENCES: usize = 3;
+
+/// Extension naming the `components/parameters` entry of a hoisted parameter.
+pub const NAME_HINT_KEY: &str = "x-name";
+
+/// Moves inline parameter objects that appear unchanged on at least [`MIN_OCCURRENCES`]
+/// operations into `components/parameters`, replacing each occurrence with a `$ref`.
+///
+/// Parameters only count as the same when they are equal as a whole, so two that differ in
+/// their description stay apart. The entry is named after [`NAME_HINT_KEY`] if set, else
+/// after the parameter (`per_page` becomes `PerPage`). Returns the names of the new entries.
+pub fn hoist_common_parameters(doc: &mut Value) -> Vec<String> {
+    // Each distinct parameter with the number of operations using it, in document order
+    let mut counts: Vec<(Value, usize)> = Vec::new();
+    for parameters in operation_parameters(doc) {
+        let mut seen: Vec<&Value> = Vec::new();
+        for param in parameters.iter().filter(|p| is_inline(p)) {
+            if seen.contains(&param) {
+                continue;
+            }
+            seen.push(param);
+            match counts.iter_mut().find(|(p, _)| p == param) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((param.clone(), 1)),
+            }
+        }
+    }
+
+    let mut hoisted = Vec::new();
+    let mut replacements = Vec::new();
+    for (param, _) in counts
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_OCCURRENCES)
+    {
+        let mut component = param.clone();
+        let hint = match &mut component {
+            Value::Mapping(map) => map.remove(NAME_HINT_KEY),
+            _ => None,
+        };
+        let base = match hint.as_ref().and_then(Value::as_str) {
+            Some(hint) => hint.to_string(),
+            None => pascal_case(param.get("name").and_then(Value::as_str).unwrap_or("")),
+        };
+        let Some(components) = components_parameters(doc) else {
+            continue;
+        };
+        // Numbered on collisions, unless an equal entry already exists
+        let name = (1..)
+            .map(|n| match n {
+                1 => base.clone(),
+                n => format!("{}{}", base, n),
+            })
+            .find(|name| {
+                components
+                    .get(name.as_str())
+                    .is_none_or(|existing| *existing == component)
+            })
+            .unwrap();
+        if components.get(name.as_str()).is_none() {
+            components.insert(Value::from(name.clone()), component);
+            hoisted.push(name.clone());
+        }
+        let target = format!("#/components/parameters/{}", refs::escape(&name));
+        let mut reference = Mapping::new();
+        reference.insert(Value::from("$ref"), Value::from(target));
+        replacements.push((param, Value::Mapping(reference)));
+    }
+
+    if replacements.is_empty() {
+        return hoisted;
+    }
+    for parameters in operation_parameters_mut(doc) {
+        for param in parameters.iter_mut() {
+            if let Some((_, reference)) = replacements.iter().find(|(p, _)| p == param) {
+                *param = reference.clone();
+            }
+        }
+    }
+    hoisted
+}
+
+fn is_inline(param: &Value) -> bool {
+    param.as_mapping().is_some_and(|p| !p.contains_key("$ref"))
+}
+
+fn is_operation(key: &Value) -> bool {
+    key.as_str().is_some_and(|k| HTTP_METHODS.contains(&k))
+}
+
+fn operation_parameters(doc: &Value) -> impl Iterator<Item = &Vec<Value>> {
+    doc.get("paths")
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flat_map(|paths| paths.values())
+        .filter_map(Value::as_mapping)
+        .flat_map(|item| item.iter())
+        .filter(|(method, _)| is_operation(method))
+        .filter_map(|(_, operation)| operation.get("parameters")?.as_sequence())
+}
+
+fn operation_parameters_mut(doc: &mut Value) -> impl Iterator<Item = &mut Vec<Value>> {
+    doc.get_mut("paths")
+        .and_then(Value::as_mapping_mut)
+        .into_iter()
+        .flat_map(|paths| paths.values_mut())
+        .filter_map(Value::as_mapping_mut)
+        .flat_map(|item| item.iter_mut())
+        .filter(|(method, _)| is_operation(method))
+        .filter_map(|(_, operation)| operation.get_mut("parameters")?.as_sequence_mut())
+}
+
+// `components/parameters`, created as needed
+fn components_parameters(doc: &mut Value) -> Option<&mut Mapping> {
+    let root = doc.as_mapping_mut()?;
+    let components = root
+        .entry(Value::from("components"))
+        .or_insert_with(|| Value::Mapping(Mapping::new()))
+        .as_mapping_mut()?;
+    components
+        .entry(Value::from("parameters"))
+        .or_insert_with(|| Value::Mapping(Mapping::new()))
+        .as_mapping_mut()
+}
+
+// `per_page` -> `PerPage`, `X-Request-Id` -> `XRequestId`
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "{name: page, in: query, schema: {type: integer}}";
+
+    fn doc(operations: &[&str]) -> Value {
+        let paths: Vec<String> = operations
+            .iter()
+            .enumerate()
+            .map(|(i, params)| format!("  /r{}:\n    get:\n      parameters: [{}]", i, params))
+            .collect();
+        serde_yaml::from_str(&format!("paths:\n{}", paths.join("\n"))).unwrap()
+    }
+
+    fn parameters(doc: &Value, i: usize) -> &Value {
+        &doc["paths"][format!("/r{}", i).as_str()]["get"]["parameters"]
+    }
+
+    #[test]
+    fn test_hoists_repeated_parameters() {
+        let per_page = "{name: per_page, in: query, x-name: PageSize, schema: {type: integer}}";
+        let both = format!("{}, {}", PAGE, per_page);
+        let mut doc = doc(&[&both, &both, &both, PAGE]);
+        assert_eq!(hoist_common_parameters(&mut doc), ["Page", "PageSize"]);
+
+        let components = &doc["components"]["parameters"];
+        assert_eq!(
+            components["Page"],
+            serde_yaml::from_str::<Value>(PAGE).unwrap()
+        );
+        assert!(components["PageSize"].get(NAME_HINT_KEY).is_none());
+        for i in 0..4 {
+            assert_eq!(
+                parameters(&doc, i)[0]["$ref"],
+                "#/components/parameters/Page"
+            );
+        }
+        assert_eq!(
+            parameters(&doc, 2)[1]["$ref"],
+            "#/components/parameters/PageSize"
+        );
+    }
+
+    #[test]
+    fn test_near_duplicates_stay_inline() {
+        let described = "{name: page, in: query, description: Page, schema: {type: integer}}";
+        let mut doc = doc(&[PAGE, PAGE, described, described]);
+        assert!(hoist_common_parameters(&mut doc).is_empty());
+        assert!(doc.get("components").is_none());
+        assert_eq!(parameters(&doc, 0)[0]["name"], "page");
+    }
+
+    #[test]
+    fn test_name_collisions_are_numbered() {
+        let mut doc = doc(&[PAGE, PAGE, PAGE]);
+        doc["components"] =
+            serde_yaml::from_str("parameters: {Page: {name: p, in: path}}").unwrap();
+        assert_eq!(hoist_common_parameters(&mut doc), ["Page2"]);
+        assert_eq!(
+            parameters(&doc, 0)[0]["$ref"],
+            "#/components/parameters/Page2"
+        );
+    }
+}
@@ -1,28 +1,79 @@
-use std::collections::HashMap;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone)]
+/// Default file name for exported registries.
+pub const REGISTRY_FILE: &str = "oas-forge.registry.json";
+
+/// Where a registry entry was defined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl SourceLocation {
+    /// Location recorded for definitions registered through the [`crate::Generator`] builder.
+    pub fn programmatic() -> Self {
+        Self {
+            file: PathBuf::from("<generator>"),
+            line: 0,
+        }
+    }
+
+    pub fn is_programmatic(&self) -> bool {
+        *self == Self::programmatic()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blueprint {
     pub params: Vec<String>, // e.g. ["T", "U"] extracted from <T, U>
     pub body: String,
+    pub source: Option<SourceLocation>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fragment {
     pub params: Vec<String>,
+    /// Default values from `@openapi-fragment Name(param = "value")`, keyed by param
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
     pub body: String,
+    pub source: Option<SourceLocation>,
 }
 
 /// Stores definitions for fragments, blueprints, and concrete schemas.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Registry {
     /// @openapi-fragment Name(arg1, arg2)
-    pub fragments: HashMap<String, Fragment>,
+    pub fragments: BTreeMap<String, Fragment>,
     /// @openapi<T, U> -> key is Name ("Page")
-    pub blueprints: HashMap<String, Blueprint>,
+    pub blueprints: BTreeMap<String, Blueprint>,
     /// Standard @openapi on structs
-    pub schemas: HashMap<String, String>,
+    pub schemas: BTreeMap<String, String>,
+    /// Where each entry in `schemas` was defined (only for located inserts)
+    pub schema_sources: BTreeMap<String, SourceLocation>,
     /// Concrete schemas generated from generics (e.g. Page_User)
-    pub concrete_schemas: HashMap<String, String>,
+    pub concrete_schemas: BTreeMap<String, String>,
+    /// Instantiation each entry in `concrete_schemas` was generated from (`Pair<A_B,C>`)
+    #[serde(skip)]
+    pub concrete_origins: BTreeMap<String, String>,
+    /// Where each `@openapi-header`/`@openapi-example` was defined, keyed by section and
+    /// name (`headers/RateLimitRemaining`)
+    #[serde(skip)]
+    pub component_sources: BTreeMap<String, SourceLocation>,
+}
+
+/// A definition from an imported registry that is also defined locally; the local one is kept.
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub kind: &'static str,
+    pub name: String,
+    /// Where the local definition comes from, if known
+    pub local: Option<SourceLocation>,
 }
 
 impl Registry {
@@ -30,12 +81,79 @@ impl Registry {
         Self::default()
     }
 
+    /// Reads a registry written by [`Registry::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes the registry as JSON, for [`Registry::load`] in other crates.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds the definitions of `imported` not defined here yet.
+    ///
+    /// Returns the names of the schemas added and the definitions that collided.
+    pub fn import(&mut self, imported: Registry) -> (Vec<String>, Vec<Collision>) {
+        let mut collisions = Vec::new();
+        for (name, fragment) in imported.fragments {
+            match self.fragments.get(&name) {
+                Some(local) => collisions.push(Collision {
+                    kind: "fragment",
+                    local: local.source.clone(),
+                    name,
+                }),
+                None => {
+                    self.fragments.insert(name, fragment);
+                }
+            }
+        }
+        for (name, blueprint) in imported.blueprints {
+            match self.blueprints.get(&name) {
+                Some(local) => collisions.push(Collision {
+                    kind: "blueprint",
+                    local: local.source.clone(),
+                    name,
+                }),
+                None => {
+                    self.blueprints.insert(name, blueprint);
+                }
+            }
+        }
+        let mut schemas = Vec::new();
+        for (name, content) in imported.schemas {
+            if self.schemas.contains_key(&name) {
+                collisions.push(Collision {
+                    kind: "schema",
+                    local: self.schema_sources.get(&name).cloned(),
+                    name,
+                });
+                continue;
+            }
+            if let Some(source) = imported.schema_sources.get(&name) {
+                self.schema_sources.insert(name.clone(), source.clone());
+            }
+            self.schemas.insert(name.clone(), content);
+            schemas.push(name);
+        }
+        collisions.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+        schemas.sort();
+        (schemas, collisions)
+    }
+
     pub fn insert_fragment(&mut self, name: String, params: Vec<String>, content: String) {
         self.fragments.insert(
             name,
             Fragment {
                 params,
+                defaults: BTreeMap::new(),
                 body: content,
+                source: None,
             },
         );
     }
@@ -46,6 +164,7 @@ impl Registry {
             Blueprint {
                 params,
                 body: content,
+                source: None,
             },
         );
     }
@@ -53,4 +172,194 @@ impl Registry {
     pub fn insert_schema(&mut self, name: String, content: String) {
         self.schemas.insert(name, content);
     }
+
+    /// Like [`Registry::insert_fragment`], but fails if `name` was already defined elsewhere.
+    pub fn define_fragment(
+        &mut self,
+        name: String,
+        params: Vec<String>,
+        defaults: BTreeMap<String, String>,
+        content: String,
+        source: SourceLocation,
+    ) -> Result<()> {
+        let previous = self.fragments.get(&name).and_then(|f| f.source.as_ref());
+        check_duplicate("fragment", &name, previous, &source)?;
+        self.fragments.insert(
+            name,
+            Fragment {
+                params,
+                defaults,
+                body: content,
+                source: Some(source),
+            },
+        );
+        Ok(())
+    }
+
+    /// Like [`Registry::insert_blueprint`], but fails if `name` was already defined elsewhere.
+    pub fn define_blueprint(
+        &mut self,
+        name: String,
+        params: Vec<String>,
+        content: String,
+        source: SourceLocation,
+    ) -> Result<()> {
+        let previous = self.blueprints.get(&name).and_then(|b| b.source.as_ref());
+        check_duplicate("blueprint", &name, previous, &source)?;
+        self.blueprints.insert(
+            name,
+            Blueprint {
+                params,
+                body: content,
+                source: Some(source),
+            },
+        );
+        Ok(())
+    }
+
+    /// Like [`Registry::insert_schema`], but fails if `name` was already defined elsewhere.
+    pub fn define_schema(
+        &mut self,
+        name: String,
+        content: String,
+        source: SourceLocation,
+    ) -> Result<()> {
+        check_duplicate("schema", &name, self.schema_sources.get(&name), &source)?;
+        self.schema_sources.insert(name.clone(), source);
+        self.schemas.insert(name, content);
+        Ok(())
+    }
+
+    /// Records a component declared under `components/{section}`, failing if `name` was
+    /// already declared there elsewhere.
+    pub fn define_component(
+        &mut self,
+        section: &'static str,
+        name: &str,
+        source: SourceLocation,
+    ) -> Result<()> {
+        let key = format!("{}/{}", section, name);
+        let kind = section.strip_suffix('s').unwrap_or(section);
+        check_duplicate(kind, name, self.component_sources.get(&key), &source)?;
+        self.component_sources.insert(key, source);
+        Ok(())
+    }
+}
+
+// The same location seen twice (e.g. a file reached through both an input and an include) is not a conflict.
+fn check_duplicate(
+    kind: &'static str,
+    name: &str,
+    previous: Option<&SourceLocation>,
+    source: &SourceLocation,
+) -> Result<()> {
+    match previous {
+        Some(first) if first != source => Err(Error::DuplicateDefinition {
+            kind,
+            name: name.to_string(),
+            first_file: first.file.clone(),
+            first_line: first.line,
+            file: source.file.clone(),
+            line: source.line,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(file: &str, line: usize) -> SourceLocation {
+        SourceLocation {
+            file: PathBuf::from(file),
+            line,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_schema_reports_both_locations() {
+        let mut registry = Registry::new();
+        registry
+            .define_schema("User".into(), "a".into(), at("src/accounts.rs", 3))
+            .unwrap();
+        let err = registry
+            .define_schema("User".into(), "b".into(), at("src/billing.rs", 7))
+            .unwrap_err();
+        let Error::DuplicateDefinition {
+            kind,
+            name,
+            first_file,
+            first_line,
+            file,
+            line,
+        } = err
+        else {
+            panic!("expected duplicate definition error");
+        };
+        assert_eq!(kind, "schema");
+        assert_eq!(name, "User");
+        assert_eq!(
+            (first_file, first_line),
+            (PathBuf::from("src/accounts.rs"), 3)
+        );
+        assert_eq!((file, line), (PathBuf::from("src/billing.rs"), 7));
+        assert_eq!(registry.schemas["User"], "a");
+    }
+
+    #[test]
+    fn test_same_location_is_not_a_duplicate() {
+        let mut registry = Registry::new();
+        for _ in 0..2 {
+            registry
+                .define_fragment(
+                    "Err".into(),
+                    vec![],
+                    BTreeMap::new(),
+                    "x".into(),
+                    at("src/lib.rs", 1),
+                )
+                .unwrap();
+        }
+        assert!(
+            registry
+                .define_blueprint("Page".into(), vec!["T".into()], "x".into(), at("a.rs", 1))
+                .is_ok()
+        );
+        assert!(
+            registry
+                .define_blueprint("Page".into(), vec!["T".into()], "y".into(), at("b.rs", 1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_import_roundtrip_keeps_local_definitions() {
+        let mut platform = Registry::new();
+        platform
+            .define_schema("User".into(), "imported".into(), at("platform.rs", 3))
+            .unwrap();
+        platform
+            .define_schema("Org".into(), "org".into(), at("platform.rs", 9))
+            .unwrap();
+        platform.insert_fragment("Err".into(), vec!["code".into()], "x".into());
+        let json = serde_json::to_string(&platform).unwrap();
+        let imported: Registry = serde_json::from_str(&json).unwrap();
+
+        let mut local = Registry::new();
+        local
+            .define_schema("User".into(), "local".into(), at("src/lib.rs", 1))
+            .unwrap();
+        let (schemas, collisions) = local.import(imported);
+
+        assert_eq!(schemas, vec!["Org"]);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(
+            (collisions[0].kind, collisions[0].name.as_str()),
+            ("schema", "User")
+        );
+        assert_eq!(local.schemas["User"], "local");
+        assert_eq!(local.schema_sources["Org"], at("platform.rs", 9));
+        assert_eq!(local.fragments["Err"].params, vec!["code"]);
+    }
 }
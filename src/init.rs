@@ -0,0 +1,47 @@
+//! Starter files written by the `init` subcommand.
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+
+const CONFIG: &str = r#"# oas-forge configuration; see `openapi-extract --help` for every option
+input = ["src"]
+output = ["openapi.yaml"]
+"#;
+
+const ROOT: &str = r#"// Root of the OpenAPI document; `{{...}}` values are filled in from Cargo.toml.
+// oas-forge scans files, so this module does not need to be declared.
+
+//! @openapi
+//! openapi: 3.0.3
+//! info:
+//!   title: "{{CARGO_PKG_NAME}}"
+//!   version: "{{CARGO_PKG_VERSION}}"
+
+/// Reports whether the service is up
+/// @route GET /health
+/// @tag health
+/// @return 200: "The service is healthy"
+pub fn health() {}
+"#;
+
+/// Files written by [`scaffold`], relative to the project directory, with their content.
+pub const FILES: [(&str, &str); 2] = [("openapi.toml", CONFIG), ("src/openapi.rs", ROOT)];
+
+/// Writes the starter [`FILES`] into `dir` and returns their paths.
+///
+/// Nothing is written if one of them exists, unless `force` is set.
+pub fn scaffold(dir: &Path, force: bool) -> Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = FILES.iter().map(|(file, _)| dir.join(file)).collect();
+    if !force {
+        if let Some(existing) = paths.iter().find(|path| path.exists()) {
+            return Err(Error::AlreadyExists(existing.clone()));
+        }
+    }
+    for (path, (_, content)) in paths.iter().zip(FILES) {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::output::write_atomic(path, content.as_bytes())?;
+    }
+    Ok(paths)
+}
@@ -0,0 +1,173 @@
+use crate::scanner::{Scan, Snippet};
+use crate::validate::HTTP_METHODS;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::path::PathBuf;
+
+/// Routes and schemas found by a scan, before merging, with where each was defined.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Inventory {
+    /// Sorted by path, then method
+    pub routes: Vec<Route>,
+    /// Sorted by name; a schema contributed by several snippets is listed once per source
+    pub schemas: Vec<SchemaEntry>,
+}
+
+/// An operation of some snippet's `paths`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Route {
+    /// Upper case, e.g. `GET`
+    pub method: String,
+    pub path: String,
+    #[serde(rename = "operationId", skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+/// A `components.schemas` entry of some snippet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaEntry {
+    pub name: String,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl Inventory {
+    /// Collects the routes and schemas of the scanned snippets. Snippets that are not
+    /// valid YAML are skipped; merging reports them.
+    pub fn from_scan(scan: &Scan) -> Self {
+        let mut inventory = Inventory::default();
+        for snippet in &scan.snippets {
+            let Ok(doc) = serde_yaml::from_str::<Value>(&snippet.content) else {
+                continue;
+            };
+            let paths = doc.get("paths").and_then(Value::as_mapping);
+            for (path, item) in paths.into_iter().flatten() {
+                let Some(path) = path.as_str() else { continue };
+                for (method, operation) in item.as_mapping().into_iter().flatten() {
+                    let Some(method) = method.as_str().filter(|m| HTTP_METHODS.contains(m)) else {
+                        continue;
+                    };
+                    inventory.routes.push(Route {
+                        method: method.to_uppercase(),
+                        path: path.to_string(),
+                        operation_id: operation
+                            .get("operationId")
+                            .and_then(Value::as_str)
+                            .map(String::from),
+                        file: snippet.file_path.clone(),
+                        line: key_line(snippet, path),
+                    });
+                }
+            }
+
+            let schemas = doc
+                .get("components")
+                .and_then(|c| c.get("schemas"))
+                .and_then(Value::as_mapping);
+            for name in schemas.into_iter().flat_map(|s| s.keys()) {
+                let Some(name) = name.as_str() else { continue };
+                // The registry knows where named items were declared
+                let (file, line) = match scan.registry.schema_sources.get(name) {
+                    Some(source) if source.file == snippet.file_path => {
+                        (source.file.clone(), source.line)
+                    }
+                    _ => (snippet.file_path.clone(), key_line(snippet, name)),
+                };
+                let entry = SchemaEntry {
+                    name: name.to_string(),
+                    file,
+                    line,
+                };
+                if !inventory.schemas.contains(&entry) {
+                    inventory.schemas.push(entry);
+                }
+            }
+        }
+
+        let method_rank = |method: &str| {
+            HTTP_METHODS
+                .iter()
+                .position(|m| m.eq_ignore_ascii_case(method))
+        };
+        inventory.routes.sort_by(|a, b| {
+            (&a.path, method_rank(&a.method)).cmp(&(&b.path, method_rank(&b.method)))
+        });
+        inventory.schemas.sort_by(|a, b| a.name.cmp(&b.name));
+        inventory
+    }
+
+    /// `METHOD PATH operationId file:line` per route, in aligned columns.
+    pub fn routes_table(&self) -> String {
+        table(self.routes.iter().map(|route| {
+            [
+                route.method.clone(),
+                route.path.clone(),
+                route
+                    .operation_id
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string()),
+                location(&route.file, route.line),
+            ]
+        }))
+    }
+
+    /// `NAME file:line` per schema, in aligned columns.
+    pub fn schemas_table(&self) -> String {
+        table(
+            self.schemas
+                .iter()
+                .map(|schema| [schema.name.clone(), location(&schema.file, schema.line)]),
+        )
+    }
+}
+
+// Source line defining `key` in the snippet as written; snippets generated from Rust
+// items only know where the item starts
+fn key_line(snippet: &Snippet, key: &str) -> usize {
+    let generated =
+        snippet.raw().is_none() && snippet.file_path.extension().is_some_and(|ext| ext == "rs");
+    if generated {
+        return snippet.line_number;
+    }
+    let quotes: &[char] = &['"', '\''];
+    let (first_line, original) = snippet.original();
+    original
+        .lines()
+        .position(|line| {
+            let line = line.trim_start().trim_start_matches(quotes);
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start_matches(quotes).starts_with(':'))
+        })
+        .map_or(snippet.line_number, |i| first_line + i)
+}
+
+fn location(file: &std::path::Path, line: usize) -> String {
+    format!("{}:{}", file.display(), line)
+}
+
+// Left-aligned columns, the last one unpadded
+fn table<const N: usize>(rows: impl Iterator<Item = [String; N]>) -> String {
+    let rows: Vec<[String; N]> = rows.collect();
+    let mut widths = [0; N];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i + 1 == N {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:<width$}  ", cell, width = widths[i]));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
@@ -1,23 +1,99 @@
 #![allow(clippy::collapsible_if)]
+pub mod audience;
 pub mod config;
+pub mod diagnostics;
+pub mod diff;
 pub mod error;
 pub mod generics;
+pub mod hoist;
 pub mod index;
+pub mod init;
+pub mod inventory;
+pub mod lint;
 pub mod merger;
+pub mod output;
 pub mod preprocessor;
+pub mod refs;
+pub mod remote;
+pub mod report;
+mod route_dsl;
 pub mod scanner;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod split;
+pub mod template;
+pub mod validate;
 pub mod visitor;
 
 use config::Config;
-use error::Result;
+use diagnostics::Diagnostic;
+use error::{Error, Result};
+use generics::NamingStrategy;
+use report::Report;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use visitor::SchemaNaming;
+
+/// Source label of snippets added through [`Generator::include_reader`].
+pub const STDIN_LABEL: &str = "<stdin>";
+
+fn load_registries(paths: &[PathBuf]) -> Result<Vec<index::Registry>> {
+    paths
+        .iter()
+        .map(|path| index::Registry::load(path))
+        .collect()
+}
 
 /// Main entry point for generating OpenAPI definitions.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Generator {
     inputs: Vec<PathBuf>,
     includes: Vec<PathBuf>,
-    output_path: Option<PathBuf>,
+    outputs: Vec<PathBuf>,
+    naming: NamingStrategy,
+    strict: bool,
+    schema_naming: SchemaNaming,
+    doc_style: visitor::DocStyle,
+    variables: HashMap<String, String>,
+    sort_output: bool,
+    /// See [`merger::shape_paths`]
+    sort_operations: merger::OperationOrder,
+    group_paths_by: merger::PathGrouping,
+    /// See [`hoist::hoist_common_parameters`]
+    hoist_parameters: bool,
+    annotate_sources: bool,
+    skip_parse_errors: bool,
+    prefilter: bool,
+    strict_refs: bool,
+    validate: bool,
+    split_by_tag: bool,
+    report_path: Option<PathBuf>,
+    format: Option<Format>,
+    spec_version: OutputVersion,
+    synthesize_root: bool,
+    type_mappers: visitor::TypeMappers,
+    /// Definitions available to every scanned file
+    registry: index::Registry,
+    /// Registry files exported by other crates
+    imports: Vec<PathBuf>,
+    /// Tags and extensions for operations found under an input directory
+    input_metadata: HashMap<PathBuf, visitor::InputMetadata>,
+    remote: remote::RemoteOptions,
+    /// Snippets added through [`Generator::include_reader`] and [`Generator::snippet`]
+    snippets: Vec<scanner::Snippet>,
+    progress: Option<scanner::ProgressCallback>,
+    build_script: bool,
+    /// Leave the merged document as is, see [`merger::sanitize`]
+    skip_sanitize: bool,
+    keep_nulls: bool,
+    on_conflict: merger::OnConflict,
+    /// Filters the merged document, see [`audience::filter_audience`]
+    audience: Option<audience::Audience>,
+    default_audience: audience::Audience,
+    /// Configuration files read by [`Config::load`], reported in build script mode
+    config_files: Vec<PathBuf>,
+    /// Merged over the document last, see [`merger::apply_overrides`]
+    overrides: serde_yaml::Mapping,
 }
 
 impl Generator {
@@ -28,14 +104,108 @@ impl Generator {
 
     /// Configures the generator from a Config object.
     pub fn with_config(mut self, config: Config) -> Self {
-        if let Some(inputs) = config.input {
-            self.inputs.extend(inputs);
+        for input in config.input.into_iter().flatten() {
+            self = self.input_with_metadata(input.path, input.metadata);
         }
         if let Some(includes) = config.include {
             self.includes.extend(includes);
         }
-        if let Some(output) = config.output {
-            self.output_path = Some(output);
+        if let Some(outputs) = config.output {
+            self.outputs = outputs;
+        }
+        if let Some(naming) = config.generic_naming {
+            self.naming = naming;
+        }
+        if let Some(strict) = config.strict {
+            self.strict = strict;
+        }
+        if let Some(schema_naming) = config.schema_naming {
+            self.schema_naming = schema_naming;
+        }
+        if let Some(doc_style) = config.doc_style {
+            self.doc_style = doc_style;
+        }
+        if let Some(sort_output) = config.sort_output {
+            self.sort_output = sort_output;
+        }
+        if let Some(sort_operations) = config.sort_operations {
+            self.sort_operations = sort_operations;
+        }
+        if let Some(group_paths_by) = config.group_paths_by {
+            self.group_paths_by = group_paths_by;
+        }
+        if let Some(hoist) = config.hoist_common_parameters {
+            self.hoist_parameters = hoist;
+        }
+        if let Some(annotate_sources) = config.annotate_sources {
+            self.annotate_sources = annotate_sources;
+        }
+        if let Some(skip_parse_errors) = config.skip_parse_errors {
+            self.skip_parse_errors = skip_parse_errors;
+        }
+        if let Some(prefilter) = config.prefilter {
+            self.prefilter = prefilter;
+        }
+        if let Some(strict_refs) = config.strict_refs {
+            self.strict_refs = strict_refs;
+        }
+        if let Some(validate) = config.validate {
+            self.validate = validate;
+        }
+        if let Some(split_by_tag) = config.split_by_tag {
+            self.split_by_tag = split_by_tag;
+        }
+        if let Some(format) = config.format {
+            self.format = Some(format);
+        }
+        if let Some(spec_version) = config.spec_version {
+            self.spec_version = spec_version;
+        }
+        if let Some(synthesize_root) = config.synthesize_root {
+            self.synthesize_root = synthesize_root;
+        }
+        if let Some(report) = config.report {
+            self.report_path = Some(report);
+        }
+        if let Some(sanitize) = config.sanitize {
+            self.skip_sanitize = !sanitize;
+        }
+        if let Some(keep_nulls) = config.keep_nulls {
+            self.keep_nulls = keep_nulls;
+        }
+        if let Some(on_conflict) = config.on_conflict {
+            self.on_conflict = on_conflict;
+        }
+        if let Some(audience) = config.audience {
+            self.audience = Some(audience);
+        }
+        if let Some(default_audience) = config.default_audience {
+            self.default_audience = default_audience;
+        }
+        if let Some(timeout) = config.remote_timeout {
+            self.remote.timeout = std::time::Duration::from_secs(timeout);
+        }
+        if let Some(cache_dir) = config.remote_cache_dir {
+            self.remote.cache_dir = cache_dir;
+        }
+        if let Some(offline) = config.offline {
+            self.remote.offline = offline;
+        }
+        if let Some(imports) = config.import_registry {
+            self.imports.extend(imports);
+        }
+        self.config_files.extend(config.files);
+        if let Some(info) = config.info {
+            self = self.info(serde_yaml::Value::Mapping(info));
+        }
+        for server in config.servers.into_iter().flatten() {
+            self = self.server(server.url, server.description.unwrap_or_default());
+        }
+        for assignment in config.set.into_iter().flatten() {
+            self.set_path(&assignment.path, assignment.value.into());
+        }
+        if let Some(variables) = config.variables {
+            self.variables.extend(variables);
         }
         self
     }
@@ -46,62 +216,836 @@ impl Generator {
         self
     }
 
-    /// Adds a specific file to include.
+    /// Adds an input directory whose operations get the tags and extensions of `metadata`.
+    pub fn input_with_metadata<P: Into<PathBuf>>(
+        mut self,
+        path: P,
+        metadata: visitor::InputMetadata,
+    ) -> Self {
+        let path = path.into();
+        if !metadata.is_empty() {
+            self.input_metadata.insert(path.clone(), metadata);
+        }
+        self.inputs.push(path);
+        self
+    }
+
+    /// Adds a specific file to include; http(s) URLs are fetched (`remote` feature).
     pub fn include<P: Into<PathBuf>>(mut self, path: P) -> Self {
         self.includes.push(path.into());
         self
     }
 
-    /// Sets the output file path.
+    /// Adds one snippet read from `reader` (e.g. stdin), labelled `<stdin>` in errors.
+    pub fn include_reader(
+        mut self,
+        mut reader: impl std::io::Read,
+        format: Format,
+    ) -> Result<Self> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        let file_path = PathBuf::from(STDIN_LABEL);
+        if format != Format::Yaml {
+            // JSON is mostly YAML, but not entirely (tabs); normalize it
+            let value: serde_json::Value =
+                serde_json::from_str(&content).map_err(|source| Error::JsonSnippet {
+                    file: file_path.clone(),
+                    source,
+                })?;
+            content = serde_yaml::to_string(&value)?;
+        }
+        self.snippets.push(scanner::Snippet {
+            content,
+            file_path,
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        });
+        Ok(self)
+    }
+
+    /// Appends an in-memory YAML snippet. It goes through the same passes as scanned ones, so
+    /// it can use `@insert`, `$Refs` and blueprints declared in scanned code, and is merged
+    /// after all scanned snippets.
+    pub fn snippet(mut self, yaml: impl Into<String>) -> Self {
+        let location = index::SourceLocation::programmatic();
+        self.snippets.push(scanner::Snippet {
+            content: yaml.into(),
+            file_path: location.file,
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        });
+        self
+    }
+
+    /// Like [`Generator::snippet`], for an already built value.
+    pub fn snippet_value(self, value: serde_yaml::Value) -> Self {
+        let yaml = serde_yaml::to_string(&value).expect("YAML values serialize");
+        self.snippet(yaml)
+    }
+
+    /// Sets the timeout for fetching remote includes.
+    pub fn remote_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.remote.timeout = timeout;
+        self
+    }
+
+    /// Sets where fetched remote includes are cached (default `target/oas-forge-cache`).
+    pub fn remote_cache_dir<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.remote.cache_dir = path.into();
+        self
+    }
+
+    /// Uses only cached copies of remote includes.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.remote.offline = offline;
+        self
+    }
+
+    /// Calls `f` with `(n, total, file)` as each file is scanned, e.g. for a progress bar.
+    pub fn on_progress(
+        mut self,
+        f: impl Fn(usize, usize, &std::path::Path) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(scanner::ProgressCallback::new(f));
+        self
+    }
+
+    /// Adds an output file; each one gets the same document, in its own format.
     pub fn output<P: Into<PathBuf>>(mut self, path: P) -> Self {
-        self.output_path = Some(path.into());
+        self.outputs.push(path.into());
         self
     }
 
-    /// Executes the generation process.
-    pub fn generate(self) -> Result<()> {
-        let output = self.output_path.ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Output path is required")
-        })?;
+    /// Sets the naming scheme for schemas generated from blueprints (e.g. `Page_User` vs `PageOfUser`).
+    pub fn generic_naming(mut self, naming: NamingStrategy) -> Self {
+        self.naming = naming;
+        self
+    }
 
+    /// Enables strict mode: missing fragments/blueprints, blueprint arity mismatches
+    /// and unresolved `$Name` references become errors instead of warnings.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets how reflected Rust items are named, e.g. `User` or module-qualified `accounts.User`.
+    pub fn schema_naming(mut self, schema_naming: SchemaNaming) -> Self {
+        self.schema_naming = schema_naming;
+        self
+    }
+
+    /// Chooses how doc comments of reflected Rust items become `title` and `description`
+    /// (all lines joined into the description by default).
+    pub fn doc_style(mut self, doc_style: visitor::DocStyle) -> Self {
+        self.doc_style = doc_style;
+        self
+    }
+
+    /// Defines a `{{NAME}}` template variable (overrides built-ins like `CARGO_PKG_VERSION`).
+    pub fn variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sorts `paths` and `components.schemas` alphabetically and emits top-level keys
+    /// in canonical order (`openapi`, `info`, `servers`, `tags`, `security`, `paths`, `components`).
+    pub fn sort_output(mut self, sort_output: bool) -> Self {
+        self.sort_output = sort_output;
+        self
+    }
+
+    /// Orders the operations of each path item: get, post, put, patch, delete, then the rest.
+    pub fn sort_operations(mut self, order: merger::OperationOrder) -> Self {
+        self.sort_operations = order;
+        self
+    }
+
+    /// Orders `paths` by tag or alphabetically instead of merge order (applied after
+    /// [`Generator::sort_output`]).
+    pub fn group_paths_by(mut self, grouping: merger::PathGrouping) -> Self {
+        self.group_paths_by = grouping;
+        self
+    }
+
+    /// Moves inline parameters repeated unchanged on several operations into
+    /// `components.parameters`, referencing them instead.
+    pub fn hoist_common_parameters(mut self, hoist: bool) -> Self {
+        self.hoist_parameters = hoist;
+        self
+    }
+
+    /// Tags each path item and schema with `x-oas-forge-source: "file:line"` for debugging.
+    pub fn annotate_sources(mut self, annotate_sources: bool) -> Self {
+        self.annotate_sources = annotate_sources;
+        self
+    }
+
+    /// Cleans the merged document before writing, see [`merger::sanitize`] (on by default;
+    /// turn off to inspect what the merge produced).
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.skip_sanitize = !sanitize;
+        self
+    }
+
+    /// Keeps null values when sanitizing.
+    pub fn keep_nulls(mut self, keep_nulls: bool) -> Self {
+        self.keep_nulls = keep_nulls;
+        self
+    }
+
+    /// Chooses what happens when snippets define the same schema differently (an error by
+    /// default).
+    pub fn on_conflict(mut self, on_conflict: merger::OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Writes the document for one audience, dropping operations and schemas labeled with a
+    /// more restrictive `x-audience`.
+    pub fn audience(mut self, audience: audience::Audience) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// Sets the audience of operations and schemas without `x-audience` (public by default).
+    pub fn default_audience(mut self, default_audience: audience::Audience) -> Self {
+        self.default_audience = default_audience;
+        self
+    }
+
+    /// Skips Rust files that fail to parse (e.g. `include!`-ed partials) with a warning
+    /// instead of failing. Has no effect in strict mode.
+    pub fn skip_parse_errors(mut self, skip_parse_errors: bool) -> Self {
+        self.skip_parse_errors = skip_parse_errors;
+        self
+    }
+
+    /// Skips parsing Rust files under the inputs that contain none of the
+    /// [`visitor::MARKERS`], which speeds up scanning large trees. Types declared only in
+    /// such files are then not reflected as schemas; explicit includes are always parsed.
+    pub fn prefilter(mut self, prefilter: bool) -> Self {
+        self.prefilter = prefilter;
+        self
+    }
+
+    /// Fails generation when a local `$ref` in the merged document does not resolve,
+    /// instead of reporting a warning.
+    pub fn strict_refs(mut self, strict_refs: bool) -> Self {
+        self.strict_refs = strict_refs;
+        self
+    }
+
+    /// Checks the merged document against structural OpenAPI rules (see
+    /// [`validate::validate_document`]); any error fails generation.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Besides each output, writes one document per operation tag (`openapi.yaml` ->
+    /// `openapi.<tag>.yaml`) with only the referenced components; see [`split::split_by_tag`].
+    pub fn split_by_tag(mut self, split_by_tag: bool) -> Self {
+        self.split_by_tag = split_by_tag;
+        self
+    }
+
+    /// Writes the run's [`Report`] as JSON to `path` (by [`Generator::generate`] and
+    /// [`Generator::generate_file`]).
+    pub fn report<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.report_path = Some(path.into());
+        self
+    }
+
+    /// Sets the output format, overriding detection from the output file extension.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Sets the specification version to write; [`OutputVersion::Swagger2`] converts the
+    /// merged document, warning about anything 2.0 cannot represent.
+    pub fn spec_version(mut self, spec_version: OutputVersion) -> Self {
+        self.spec_version = spec_version;
+        self
+    }
+
+    /// When no `@openapi` block declares `openapi` and `info`, generates that root from
+    /// the scanned crate's Cargo.toml (name, version, description) instead of failing.
+    pub fn synthesize_root(mut self, synthesize_root: bool) -> Self {
+        self.synthesize_root = synthesize_root;
+        self
+    }
+
+    /// Adds a hook mapping Rust types to schemas, consulted before the built-in mapping
+    /// (e.g. for generic wrappers from other crates). Returning `None` falls through.
+    pub fn map_type_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&syn::Type) -> Option<serde_json::Value> + Send + Sync + 'static,
+    {
+        self.type_mappers.push(f);
+        self
+    }
+
+    /// Registers a fragment usable by `@insert`/`@extend` in scanned files, as if declared
+    /// with `@openapi-fragment Name(params)`.
+    pub fn fragment<I, S>(
+        mut self,
+        name: impl Into<String>,
+        params: I,
+        body: impl Into<String>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let params = params.into_iter().map(Into::into).collect();
+        self.registry
+            .define_fragment(
+                name.into(),
+                params,
+                BTreeMap::new(),
+                body.into(),
+                index::SourceLocation::programmatic(),
+            )
+            .expect("programmatic definitions share one location");
+        self
+    }
+
+    /// Registers a schema (its YAML body) under `components.schemas`, resolvable by `$Name`.
+    pub fn schema(mut self, name: impl Into<String>, body: impl Into<String>) -> Self {
+        let name = name.into();
+        let content = format!(
+            "components:\n  schemas:\n    {}:\n{}",
+            name,
+            scanner::indent(&body.into())
+        );
+        self.registry
+            .define_schema(name, content, index::SourceLocation::programmatic())
+            .expect("programmatic definitions share one location");
+        self
+    }
+
+    /// Starts from an existing registry, e.g. to share definitions between generators.
+    pub fn registry(mut self, registry: index::Registry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Sets `info.title`, overriding the scanned root.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.set_path(&["info", "title"], title.into().into());
+        self
+    }
+
+    /// Sets `info.version`, overriding the scanned root (e.g. with a CI build number).
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.set_path(&["info", "version"], version.into().into());
+        self
+    }
+
+    /// Adds a server; servers added here replace the scanned `servers` list.
+    pub fn server(mut self, url: impl Into<String>, description: impl Into<String>) -> Self {
+        let mut server = serde_yaml::Mapping::new();
+        server.insert("url".into(), url.into().into());
+        let description = description.into();
+        if !description.is_empty() {
+            server.insert("description".into(), description.into());
+        }
+        let servers = self
+            .overrides
+            .entry("servers".into())
+            .or_insert_with(|| serde_yaml::Value::Sequence(Vec::new()));
+        if let serde_yaml::Value::Sequence(servers) = servers {
+            servers.push(serde_yaml::Value::Mapping(server));
+        }
+        self
+    }
+
+    /// Merges fields into `info`, overriding the scanned root.
+    pub fn info(mut self, info: serde_yaml::Value) -> Self {
+        let mut overrides = serde_yaml::Value::Mapping(std::mem::take(&mut self.overrides));
+        let mut wrapped = serde_yaml::Mapping::new();
+        wrapped.insert("info".into(), info);
+        merger::apply_overrides(&mut overrides, serde_yaml::Value::Mapping(wrapped));
+        if let serde_yaml::Value::Mapping(overrides) = overrides {
+            self.overrides = overrides;
+        }
+        self
+    }
+
+    /// Sets a value of the generated document by dotted path, e.g. `set("info.version", "1.2.3")`.
+    pub fn set(mut self, path: &str, value: impl Into<String>) -> Self {
+        let path: Vec<&str> = path.split('.').filter(|p| !p.is_empty()).collect();
+        self.set_path(&path, value.into().into());
+        self
+    }
+
+    fn set_path<S: AsRef<str>>(&mut self, path: &[S], value: serde_yaml::Value) {
+        let Some((last, parents)) = path.split_last() else {
+            return;
+        };
+        let mut map = &mut self.overrides;
+        for key in parents {
+            let entry = map
+                .entry(key.as_ref().into())
+                .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+            if !entry.is_mapping() {
+                *entry = serde_yaml::Value::Mapping(Default::default());
+            }
+            map = entry.as_mapping_mut().expect("just made a mapping");
+        }
+        map.insert(last.as_ref().into(), value);
+    }
+
+    /// For running from `build.rs`: prints `cargo:rerun-if-changed` for every input, include,
+    /// config and scanned file, and the run's diagnostics as `cargo:warning`.
+    pub fn build_script_mode(mut self, build_script: bool) -> Self {
+        self.build_script = build_script;
+        self
+    }
+
+    /// Loads a registry exported by another crate (see [`Generator::export_registry`]) before
+    /// scanning; its fragments, blueprints and schemas become usable here.
+    pub fn import_registry<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.imports.push(path.into());
+        self
+    }
+
+    /// Only indexes the inputs and writes their definitions to `path`, for
+    /// [`Generator::import_registry`] in other crates. No document is generated.
+    pub fn export_registry(self, path: impl AsRef<std::path::Path>) -> Result<index::Registry> {
+        let options = scanner::ScanOptions {
+            strict: self.strict,
+            schema_naming: self.schema_naming,
+            doc_style: self.doc_style,
+            skip_parse_errors: self.skip_parse_errors,
+            prefilter: self.prefilter,
+            type_mappers: self.type_mappers,
+            registry: self.registry,
+            imports: load_registries(&self.imports)?,
+            index_only: true,
+            snippets: self.snippets,
+            ..Default::default()
+        };
+        let scan = scanner::scan(&self.inputs, &self.includes, &options)?;
+        scan.registry.save(path.as_ref())?;
+        log::info!("Written registry to {:?}", path.as_ref());
+        Ok(scan.registry)
+    }
+
+    /// Only scans the inputs and lists the routes and schemas found, with their sources.
+    pub fn inventory(mut self) -> Result<inventory::Inventory> {
+        let options = self.scan_options()?;
+        let scan = scanner::scan(&self.inputs, &self.includes, &options)?;
+        Ok(inventory::Inventory::from_scan(&scan))
+    }
+
+    // Moves the scan settings out of the generator
+    fn scan_options(&mut self) -> Result<scanner::ScanOptions> {
+        Ok(scanner::ScanOptions {
+            naming: std::mem::take(&mut self.naming),
+            strict: self.strict,
+            schema_naming: self.schema_naming,
+            doc_style: self.doc_style,
+            variables: std::mem::take(&mut self.variables),
+            skip_parse_errors: self.skip_parse_errors,
+            prefilter: self.prefilter,
+            type_mappers: std::mem::take(&mut self.type_mappers),
+            registry: std::mem::take(&mut self.registry),
+            imports: load_registries(&self.imports)?,
+            index_only: false,
+            input_metadata: std::mem::take(&mut self.input_metadata),
+            remote: std::mem::take(&mut self.remote),
+            snippets: std::mem::take(&mut self.snippets),
+            progress: self.progress.take(),
+        })
+    }
+
+    /// Runs scan and merge and returns the document instead of writing it.
+    ///
+    /// Unlike [`Generator::generate`], no output path is required.
+    pub fn generate_value(self) -> Result<serde_yaml::Value> {
+        self.generate_with_report().map(|(value, _)| value)
+    }
+
+    /// Like [`Generator::generate_value`], also returning statistics and warnings.
+    pub fn generate_with_report(mut self) -> Result<(serde_yaml::Value, Report)> {
         // 1. Scan and Extract
         log::info!(
             "Scanning directories: {:?} and includes: {:?}",
             self.inputs,
             self.includes
         );
-        let snippets = scanner::scan_directories(&self.inputs, &self.includes)?;
+        let default_root = self.synthesize_root.then(|| self.synthesized_root());
+        let options = self.scan_options()?;
+        let mut scan = scanner::Pipeline::extract(&self.inputs, &self.includes, &options)?
+            .preprocess(&options)
+            .monomorphize(&options)
+            .substitute(&options)
+            .finish()?;
+        if self.build_script {
+            // Directories too, so added files trigger a rerun
+            let mut files = Vec::new();
+            for file in self
+                .inputs
+                .iter()
+                .chain(&self.includes)
+                .chain(&self.config_files)
+                .chain(&self.imports)
+                .chain(&scan.files)
+            {
+                if !files.contains(file) && !remote::is_url(file) {
+                    files.push(file.clone());
+                }
+            }
+            output::write_cargo_instructions(&mut std::io::stdout(), &files, &[])?;
+        }
 
         // 2. Merge
-        log::info!("Merging {} snippets", snippets.len());
-        let merged_value = merger::merge_openapi(snippets)?;
+        log::info!("Merging {} snippets", scan.snippets.len());
+        let snippets = std::mem::take(&mut scan.snippets);
+        let merge_options = merger::MergeOptions {
+            annotate_sources: self.annotate_sources,
+            on_conflict: self.on_conflict,
+        };
+        let (mut merged_value, synthesized) = match default_root {
+            Some(root) => merger::merge_with_default_root(snippets, merge_options, root)?,
+            None => (
+                merger::merge_openapi_with_options(snippets, merge_options)?,
+                false,
+            ),
+        };
+        if !self.overrides.is_empty() {
+            let overrides = serde_yaml::Value::Mapping(self.overrides);
+            merger::apply_overrides(&mut merged_value, overrides);
+        }
+        if let Some(audience) = self.audience {
+            audience::filter_audience(&mut merged_value, audience, self.default_audience);
+        }
+        if !self.skip_sanitize {
+            let options = merger::SanitizeOptions {
+                keep_nulls: self.keep_nulls,
+                keep_sources: self.annotate_sources,
+            };
+            merger::sanitize(&mut merged_value, options);
+        }
+        if validate::SpecVersion::from_document(&merged_value) == Some(validate::SpecVersion::V3_1)
+        {
+            merger::nullable_to_null_type(&mut merged_value);
+            merger::exclusive_bounds_to_numbers(&mut merged_value);
+        }
+        if self.hoist_parameters {
+            let hoisted = hoist::hoist_common_parameters(&mut merged_value);
+            log::info!("Hoisted {} common parameters", hoisted.len());
+        }
+        if self.sort_output {
+            merger::sort_document(&mut merged_value);
+        }
+        merger::shape_paths(&mut merged_value, self.sort_operations, self.group_paths_by);
+        let mut report = Report::new(&merged_value, &scan);
+        if synthesized {
+            let diagnostic = Diagnostic::at_pointer(
+                "no root @openapi block found; generated 'openapi' and 'info' from Cargo.toml",
+                "/info",
+            )
+            .with_code(diagnostics::code::SYNTHESIZED_ROOT);
+            log::warn!("{}", diagnostic);
+            report.diagnostics.push(diagnostic);
+        }
+
+        // 3. Check that every local $ref points somewhere
+        let dangling = refs::dangling_refs(&merged_value);
+        if self.strict_refs {
+            Error::from_all(
+                dangling
+                    .into_iter()
+                    .map(|d| Error::DanglingRef(Box::new(d)))
+                    .collect(),
+            )?;
+        } else {
+            for diagnostic in dangling {
+                log::warn!("{}", diagnostic);
+                report.diagnostics.push(diagnostic);
+            }
+        }
+
+        // 4. Structural validation
+        if self.validate {
+            let version = validate::SpecVersion::from_document(&merged_value).unwrap_or_default();
+            let mut errors = Vec::new();
+            for diagnostic in validate::validate_document(&merged_value, version) {
+                if diagnostic.severity == diagnostics::Severity::Error {
+                    errors.push(Error::Invalid(Box::new(diagnostic)));
+                } else {
+                    log::warn!("{}", diagnostic);
+                    report.diagnostics.push(diagnostic);
+                }
+            }
+            Error::from_all(errors)?;
+        }
+
+        // 5. Convert for older consumers
+        if self.spec_version == OutputVersion::Swagger2 {
+            let mut dropped = Vec::new();
+            merged_value = merger::downconvert_v2_with(merged_value, &mut dropped)?;
+            for diagnostic in dropped {
+                log::warn!("{}", diagnostic);
+                report.diagnostics.push(diagnostic);
+            }
+        }
+
+        if self.build_script {
+            output::write_cargo_instructions(&mut std::io::stdout(), &[], &report.diagnostics)?;
+        }
+        Ok((merged_value, report))
+    }
+
+    /// Runs scan and merge and serializes the document in `format`.
+    ///
+    /// Unlike [`Generator::generate`], no output path is required.
+    pub fn generate_to_string(self, format: Format) -> Result<String> {
+        format.serialize(&self.generate_value()?)
+    }
+
+    /// Executes the generation process.
+    pub fn generate(self) -> Result<()> {
+        self.generate_file().map(|_| ())
+    }
+
+    /// Generates in memory and compares with the existing file at `path`.
+    ///
+    /// `Ok(false)` when the file is out of date; a missing file is an error.
+    pub fn check<P: Into<PathBuf>>(self, path: P) -> Result<bool> {
+        Ok(self.outdated(&[path.into()])?.is_empty())
+    }
+
+    /// Like [`Generator::check`] for every output, returning the outdated ones with the
+    /// locations where they differ.
+    pub fn check_outputs(mut self) -> Result<Vec<(PathBuf, Vec<diff::Change>)>> {
+        let outputs = std::mem::take(&mut self.outputs);
+        if outputs.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Output path is required",
+            )
+            .into());
+        }
+        self.outdated(&outputs)
+    }
 
-        // 3. Write Output
-        // Ensure parent directory exists
-        if let Some(parent) = output.parent() {
-            std::fs::create_dir_all(parent)?;
+    // Compared as values, so formatting does not matter; key order only does when sorting.
+    fn outdated(self, paths: &[PathBuf]) -> Result<Vec<(PathBuf, Vec<diff::Change>)>> {
+        let ordered = self.sort_output
+            || self.sort_operations != merger::OperationOrder::Merge
+            || self.group_paths_by != merger::PathGrouping::Merge;
+        let expected = self.generate_value()?;
+        let mut outdated = Vec::new();
+        for path in paths {
+            let content = match std::fs::read_to_string(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Error::MissingOutput(path.clone()));
+                }
+                content => content?,
+            };
+            // JSON is valid YAML, so one parser covers every format
+            let existing: serde_yaml::Value = serde_yaml::from_str(&content)?;
+            let changes = diff::changes(&existing, &expected, ordered);
+            if !changes.is_empty() {
+                outdated.push((path.clone(), changes));
+            }
         }
+        Ok(outdated)
+    }
 
-        let file = std::fs::File::create(&output)?;
-        let extension = output
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("yaml");
+    /// Like [`Generator::generate`], returning the run's [`Report`].
+    pub fn generate_file(mut self) -> Result<Report> {
+        if self.outputs.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Output path is required",
+            )
+            .into());
+        }
+        let outputs = std::mem::take(&mut self.outputs);
+        let format = self.format;
+        let split_by_tag = self.split_by_tag;
+        let report_path = self.report_path.take();
+        let (merged_value, mut report) = self.generate_with_report()?;
+        let splits = if split_by_tag {
+            split::split_by_tag(&merged_value)
+        } else {
+            Default::default()
+        };
 
-        match extension {
-            "json" => {
-                serde_json::to_writer_pretty(file, &merged_value)?;
+        // 6. Write Output (the document is merged once, then serialized per target)
+        for output in outputs {
+            let format = format.unwrap_or_else(|| Format::from_extension(&output));
+            let content = format.serialize(&merged_value)?;
+            let changed = output::write_atomic(&output, content.as_bytes())?;
+            if changed {
+                log::info!("Written output to {:?} ({})", output, report.summary());
             }
-            "yaml" | "yml" => {
-                serde_yaml::to_writer(file, &merged_value)?;
+            report.outputs.push(output::WrittenFile {
+                path: output.clone(),
+                changed,
+            });
+
+            for (tag, document) in &splits {
+                let path = tagged_path(&output, tag);
+                let changed = output::write_atomic(&path, format.serialize(document)?.as_bytes())?;
+                if changed {
+                    log::info!("Written tag '{}' to {:?}", tag, path);
+                }
+                report.outputs.push(output::WrittenFile { path, changed });
             }
+        }
+
+        if let Some(path) = report_path {
+            output::write_atomic(&path, serde_json::to_string_pretty(&report)?.as_bytes())?;
+            log::info!("Written report to {:?}", path);
+        }
+
+        Ok(report)
+    }
+
+    // Root for crates without one, from the scanned crate's package (or user overrides)
+    fn synthesized_root(&self) -> serde_yaml::Value {
+        let mut vars = template::builtin_variables(&self.inputs);
+        vars.extend(self.variables.clone());
+        let mut field = |name: &str| vars.remove(name).filter(|v| !v.is_empty());
+
+        let mut info = serde_yaml::Mapping::new();
+        let title = field("CARGO_PKG_NAME").unwrap_or_else(|| "API".to_string());
+        info.insert("title".into(), title.into());
+        let version = field("CARGO_PKG_VERSION").unwrap_or_else(|| "0.0.0".to_string());
+        info.insert("version".into(), version.into());
+        if let Some(description) = field("CARGO_PKG_DESCRIPTION") {
+            info.insert("description".into(), description.into());
+        }
+
+        let mut root = serde_yaml::Mapping::new();
+        root.insert("openapi".into(), "3.0.3".into());
+        root.insert("info".into(), serde_yaml::Value::Mapping(info));
+        serde_yaml::Value::Mapping(root)
+    }
+}
+
+// `out/openapi.yaml` + `users` -> `out/openapi.users.yaml`; unsafe file name characters become `_`
+fn tagged_path(output: &std::path::Path, tag: &str) -> PathBuf {
+    let tag: String = tag
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, tag, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, tag),
+    };
+    output.with_file_name(name)
+}
+
+/// Serialization of the generated document.
+///
+/// Configured as `"yaml"`, `"json"` (indented) or `"json-compact"` (minified).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum Format {
+    #[default]
+    Yaml,
+    /// Minified JSON
+    Json,
+    /// Indented JSON
+    JsonPretty,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "json" | "json-pretty" => Ok(Format::JsonPretty),
+            "json-compact" => Ok(Format::Json),
+            other => Err(format!(
+                "unknown format '{}', expected 'yaml', 'json' or 'json-compact'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Format {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Format {
+    // `.json` is JSON, anything else YAML
+    fn from_extension(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("json") => Format::JsonPretty,
+            Some("yaml" | "yml") => Format::Yaml,
             _ => {
-                serde_yaml::to_writer(file, &merged_value)?;
+                log::info!("No format configured for {:?}, writing YAML", path);
+                Format::Yaml
             }
         }
+    }
+
+    fn serialize(self, value: &serde_yaml::Value) -> Result<String> {
+        Ok(match self {
+            Format::Yaml => serde_yaml::to_string(value)?,
+            Format::Json => serde_json::to_string(value)?,
+            Format::JsonPretty => serde_json::to_string_pretty(value)?,
+        })
+    }
+}
+
+/// Specification version of the written document.
+///
+/// Configured as `"3.0"`/`"3.1"` (the merged document as declared) or `"2.0"`
+/// (converted with [`merger::downconvert_v2`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum OutputVersion {
+    #[default]
+    OpenApi3,
+    Swagger2,
+}
+
+impl std::str::FromStr for OutputVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "3" | "3.0" | "3.1" => Ok(OutputVersion::OpenApi3),
+            "2" | "2.0" => Ok(OutputVersion::Swagger2),
+            other => Err(format!(
+                "unknown spec version '{}', expected '3.0', '3.1' or '2.0'",
+                other
+            )),
+        }
+    }
+}
 
-        log::info!("Written output to {:?}", output);
+impl TryFrom<String> for OutputVersion {
+    type Error = String;
 
-        Ok(())
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
     }
 }
@@ -0,0 +1,618 @@
+use crate::diagnostics::{Diagnostic, code};
+use crate::refs;
+use crate::validate::HTTP_METHODS;
+use regex::Regex;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+static CAMEL_CASE_RE: OnceLock<Regex> = OnceLock::new();
+static PASCAL_CASE_RE: OnceLock<Regex> = OnceLock::new();
+
+/// Every rule [`lint_document`] knows, in the order they are checked.
+pub const RULES: [&str; 14] = [
+    "info-description",
+    "path-trailing-slash",
+    "path-template-conflict",
+    "path-trailing-slash-duplicate",
+    "operation-summary",
+    "operation-tags",
+    "operation-id",
+    "operation-id-unique",
+    "operation-id-camel-case",
+    "success-response",
+    "error-response-description",
+    "path-param-description",
+    "schema-name-pascal-case",
+    "property-camel-case",
+];
+
+/// Checks the merged document against style rules beyond structural validity.
+///
+/// `rules` maps rule names (see [`RULES`]) to whether they run; unlisted rules run.
+/// Every finding is an error carrying its rule name and a JSON pointer.
+pub fn lint_document(doc: &Value, rules: &HashMap<String, bool>) -> Vec<Diagnostic> {
+    for name in rules.keys().filter(|name| !RULES.contains(&name.as_str())) {
+        log::warn!("Unknown lint rule '{}'", name);
+    }
+    let mut linter = Linter {
+        doc,
+        rules,
+        operation_ids: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    linter.info();
+    linter.paths();
+    linter.schemas();
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    doc: &'a Value,
+    rules: &'a HashMap<String, bool>,
+    /// operationId -> pointer of the first operation using it
+    operation_ids: HashMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, rule: &str, pointer: impl Into<String>, message: impl Into<String>) {
+        if self.rules.get(rule).copied().unwrap_or(true) {
+            self.diagnostics.push(
+                Diagnostic::error_at_pointer(message, pointer)
+                    .with_code(code::LINT)
+                    .with_rule(rule),
+            );
+        }
+    }
+
+    // Follows a `$ref` to its target; `None` if it dangles
+    fn resolve(&self, value: &'a Value) -> Option<&'a Value> {
+        match value.get("$ref").and_then(Value::as_str) {
+            Some(target) => refs::resolve(self.doc, target),
+            None => Some(value),
+        }
+    }
+
+    fn info(&mut self) {
+        if !has_text(self.doc.get("info"), "description") {
+            self.report(
+                "info-description",
+                "/info/description",
+                "the API has no description",
+            );
+        }
+    }
+
+    fn paths(&mut self) {
+        let Some(paths) = self.doc.get("paths").and_then(Value::as_mapping) else {
+            return;
+        };
+        self.path_templates(paths);
+        for (path, item) in paths {
+            let Some(path) = path.as_str() else { continue };
+            let pointer = format!("/paths/{}", refs::escape(path));
+            if path.len() > 1 && path.ends_with('/') {
+                self.report(
+                    "path-trailing-slash",
+                    &pointer,
+                    format!("path '{}' ends with '/'", path),
+                );
+            }
+            let Some(item) = self.resolve(item) else {
+                continue;
+            };
+            self.path_params(item, &format!("{}/parameters", pointer));
+            for (method, operation) in item.as_mapping().into_iter().flatten() {
+                let Some(method) = method.as_str().filter(|m| HTTP_METHODS.contains(m)) else {
+                    continue;
+                };
+                let op_pointer = format!("{}/{}", pointer, method);
+                self.operation(operation, &op_pointer);
+                self.path_params(operation, &format!("{}/parameters", op_pointer));
+            }
+        }
+    }
+
+    // Paths that probably describe the same resource, reported on the later one
+    fn path_templates(&mut self, paths: &'a serde_yaml::Mapping) {
+        let mut templates: HashMap<String, &str> = HashMap::new();
+        let mut trimmed: HashMap<&str, &str> = HashMap::new();
+        for path in paths.keys().filter_map(Value::as_str) {
+            let pointer = format!("/paths/{}", refs::escape(path));
+            match templates.get(&anonymous_params(path)) {
+                Some(first) => {
+                    let message = format!(
+                        "path '{}' differs from '{}' only in parameter names",
+                        path, first
+                    );
+                    self.report("path-template-conflict", &pointer, message);
+                }
+                None => {
+                    templates.insert(anonymous_params(path), path);
+                }
+            }
+            let without_slash = match path.trim_end_matches('/') {
+                "" => "/",
+                trimmed => trimmed,
+            };
+            match trimmed.get(without_slash) {
+                Some(first) => {
+                    let message = format!(
+                        "path '{}' differs from '{}' only by a trailing slash",
+                        path, first
+                    );
+                    self.report("path-trailing-slash-duplicate", &pointer, message);
+                }
+                None => {
+                    trimmed.insert(without_slash, path);
+                }
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &'a Value, pointer: &str) {
+        if !has_text(Some(operation), "summary") {
+            self.report("operation-summary", pointer, "operation has no summary");
+        }
+        let tagged = operation
+            .get("tags")
+            .and_then(Value::as_sequence)
+            .is_some_and(|tags| !tags.is_empty());
+        if !tagged {
+            self.report("operation-tags", pointer, "operation has no tags");
+        }
+
+        match operation.get("operationId").and_then(Value::as_str) {
+            None => self.report("operation-id", pointer, "operation has no operationId"),
+            Some(id) => {
+                let id_pointer = format!("{}/operationId", pointer);
+                match self.operation_ids.get(id) {
+                    Some(first) => {
+                        let message =
+                            format!("duplicate operationId '{}' (first used at #{})", id, first);
+                        self.report("operation-id-unique", &id_pointer, message);
+                    }
+                    None => {
+                        self.operation_ids
+                            .insert(id.to_string(), pointer.to_string());
+                    }
+                }
+                if !is_camel_case(id) {
+                    self.report(
+                        "operation-id-camel-case",
+                        &id_pointer,
+                        format!("operationId '{}' is not camelCase", id),
+                    );
+                }
+            }
+        }
+
+        let responses = operation.get("responses").and_then(Value::as_mapping);
+        let mut succeeds = false;
+        for (status, response) in responses.into_iter().flatten() {
+            // YAML reads unquoted codes as numbers
+            let status = match status {
+                Value::Number(n) => n.to_string(),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            succeeds |= status.starts_with('2') || status.starts_with('3');
+            if !(status.starts_with('4') || status.starts_with('5')) {
+                continue;
+            }
+            let described = self
+                .resolve(response)
+                .is_none_or(|response| has_text(Some(response), "description"));
+            if !described {
+                self.report(
+                    "error-response-description",
+                    format!("{}/responses/{}/description", pointer, status),
+                    format!("{} response has no description", status),
+                );
+            }
+        }
+        if !succeeds {
+            self.report(
+                "success-response",
+                format!("{}/responses", pointer),
+                "operation has no 2xx or 3xx response",
+            );
+        }
+    }
+
+    fn path_params(&mut self, owner: &'a Value, pointer: &str) {
+        let params = owner.get("parameters").and_then(Value::as_sequence);
+        for (i, param) in params.into_iter().flatten().enumerate() {
+            let Some(param) = self.resolve(param) else {
+                continue;
+            };
+            if param.get("in").and_then(Value::as_str) != Some("path")
+                || has_text(Some(param), "description")
+            {
+                continue;
+            }
+            let name = param
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            self.report(
+                "path-param-description",
+                format!("{}/{}", pointer, i),
+                format!("path parameter '{}' has no description", name),
+            );
+        }
+    }
+
+    fn schemas(&mut self) {
+        let schemas = self
+            .doc
+            .get("components")
+            .and_then(|c| c.get("schemas"))
+            .and_then(Value::as_mapping);
+        let re = PASCAL_CASE_RE.get_or_init(|| Regex::new(r"^[A-Z][a-zA-Z0-9]*$").unwrap());
+        for name in schemas.into_iter().flat_map(|s| s.keys()) {
+            let name = name.as_str().unwrap_or_default();
+            // Module prefixes (`accounts.User`) are not part of the type name
+            let type_name = name.rsplit('.').next().unwrap_or(name);
+            if !re.is_match(type_name) {
+                self.report(
+                    "schema-name-pascal-case",
+                    format!("/components/schemas/{}", refs::escape(name)),
+                    format!("schema name '{}' is not PascalCase", name),
+                );
+            }
+        }
+
+        let mut pointer = String::new();
+        self.properties(self.doc, &mut pointer);
+    }
+
+    // Property names of every schema in the document, inline ones included
+    fn properties(&mut self, value: &'a Value, pointer: &mut String) {
+        match value {
+            Value::Mapping(map) => {
+                for (key, val) in map {
+                    let key = key.as_str().unwrap_or_default();
+                    // Instance data, not schemas
+                    if matches!(key, "example" | "examples" | "default" | "const" | "enum") {
+                        continue;
+                    }
+                    let len = pointer.len();
+                    pointer.push('/');
+                    pointer.push_str(&refs::escape(key));
+                    match val {
+                        Value::Mapping(properties) if key == "properties" => {
+                            for (name, schema) in properties {
+                                let name = name.as_str().unwrap_or_default();
+                                let len = pointer.len();
+                                pointer.push('/');
+                                pointer.push_str(&refs::escape(name));
+                                if !is_camel_case(name) {
+                                    self.report(
+                                        "property-camel-case",
+                                        pointer.clone(),
+                                        format!("property name '{}' is not camelCase", name),
+                                    );
+                                }
+                                self.properties(schema, pointer);
+                                pointer.truncate(len);
+                            }
+                        }
+                        _ => self.properties(val, pointer),
+                    }
+                    pointer.truncate(len);
+                }
+            }
+            Value::Sequence(seq) => {
+                for (i, val) in seq.iter().enumerate() {
+                    let len = pointer.len();
+                    pointer.push_str(&format!("/{}", i));
+                    self.properties(val, pointer);
+                    pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// `/users/{id}/posts/{postId}` -> `/users/{}/posts/{}`
+fn anonymous_params(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_param = false;
+    for c in path.chars() {
+        match c {
+            '{' => in_param = true,
+            '}' if in_param => {
+                in_param = false;
+                out.push_str("{}");
+            }
+            _ if in_param => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn is_camel_case(name: &str) -> bool {
+    CAMEL_CASE_RE
+        .get_or_init(|| Regex::new(r"^[a-z][a-zA-Z0-9]*$").unwrap())
+        .is_match(name)
+}
+
+// Whether `field` of `value` is a non-blank string
+fn has_text(value: Option<&Value>, field: &str) -> bool {
+    value
+        .and_then(|v| v.get(field))
+        .and_then(Value::as_str)
+        .is_some_and(|s| !s.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLEAN: &str = r##"
+openapi: 3.0.3
+info: {title: T, version: "1", description: Users}
+paths:
+  /users/{userId}:
+    parameters:
+      - {name: userId, in: path, required: true, description: The user}
+    get:
+      operationId: getUser
+      summary: Get a user
+      tags: [users]
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema:
+                type: object
+                properties: {displayName: {type: string}}
+        '404': {$ref: "#/components/responses/NotFound"}
+components:
+  responses:
+    NotFound: {description: No such user}
+  schemas:
+    User:
+      type: object
+      properties:
+        userId: {type: string}
+        tags: {type: array, items: {type: object, properties: {labelText: {type: string}}}}
+      example: {user_id: "1"}
+"##;
+
+    // (rule, pointer) of each finding for CLEAN with `patch` applied
+    fn lint(patch: impl FnOnce(&mut Value)) -> Vec<(String, String)> {
+        lint_with(patch, &HashMap::new())
+    }
+
+    fn lint_with(
+        patch: impl FnOnce(&mut Value),
+        rules: &HashMap<String, bool>,
+    ) -> Vec<(String, String)> {
+        let mut doc: Value = serde_yaml::from_str(CLEAN).unwrap();
+        patch(&mut doc);
+        lint_document(&doc, rules)
+            .into_iter()
+            .map(|d| (d.rule.unwrap(), d.pointer.unwrap()))
+            .collect()
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    fn op(doc: &mut Value) -> &mut Value {
+        &mut doc["paths"]["/users/{userId}"]["get"]
+    }
+
+    fn remove(value: &mut Value, key: &str) {
+        value.as_mapping_mut().unwrap().remove(key);
+    }
+
+    fn finding(rule: &str, pointer: &str) -> Vec<(String, String)> {
+        vec![(rule.to_string(), pointer.to_string())]
+    }
+
+    const OP: &str = "/paths/~1users~1{userId}/get";
+
+    #[test]
+    fn test_clean_document() {
+        assert_eq!(lint(|_| {}), vec![]);
+    }
+
+    #[test]
+    fn test_info_description() {
+        let found = lint(|doc| remove(&mut doc["info"], "description"));
+        assert_eq!(found, finding("info-description", "/info/description"));
+    }
+
+    #[test]
+    fn test_path_trailing_slash() {
+        let found = lint(|doc| {
+            let paths = doc["paths"].as_mapping_mut().unwrap();
+            let item = paths.remove("/users/{userId}").unwrap();
+            paths.insert(yaml("/users/{userId}/"), item);
+        });
+        assert_eq!(
+            found,
+            finding("path-trailing-slash", "/paths/~1users~1{userId}~1")
+        );
+    }
+
+    #[test]
+    fn test_path_template_conflict() {
+        let found = lint(|doc| {
+            doc["paths"]["/users/{id}"] = yaml("{}");
+            doc["paths"]["/users/{userId}/posts"] = yaml("{}");
+        });
+        assert_eq!(
+            found,
+            finding("path-template-conflict", "/paths/~1users~1{id}")
+        );
+        let found = lint(|doc| doc["paths"]["/users/me"] = yaml("{}"));
+        assert_eq!(found, vec![]);
+    }
+
+    #[test]
+    fn test_path_trailing_slash_duplicate() {
+        let found = lint(|doc| doc["paths"]["/users/{userId}/"] = yaml("{}"));
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "path-trailing-slash-duplicate".to_string(),
+                    "/paths/~1users~1{userId}~1".to_string()
+                ),
+                (
+                    "path-trailing-slash".to_string(),
+                    "/paths/~1users~1{userId}~1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operation_summary() {
+        let found = lint(|doc| op(doc)["summary"] = yaml("' '"));
+        assert_eq!(found, finding("operation-summary", OP));
+    }
+
+    #[test]
+    fn test_operation_tags() {
+        let found = lint(|doc| op(doc)["tags"] = yaml("[]"));
+        assert_eq!(found, finding("operation-tags", OP));
+    }
+
+    #[test]
+    fn test_operation_id() {
+        let found = lint(|doc| remove(op(doc), "operationId"));
+        assert_eq!(found, finding("operation-id", OP));
+    }
+
+    #[test]
+    fn test_operation_id_unique() {
+        let found = lint(|doc| {
+            let get = op(doc).clone();
+            doc["paths"]["/users/{userId}"]["delete"] = get;
+        });
+        assert_eq!(
+            found,
+            finding(
+                "operation-id-unique",
+                "/paths/~1users~1{userId}/delete/operationId"
+            )
+        );
+    }
+
+    #[test]
+    fn test_operation_id_camel_case() {
+        let found = lint(|doc| op(doc)["operationId"] = yaml("get_user"));
+        assert_eq!(
+            found,
+            finding("operation-id-camel-case", &format!("{}/operationId", OP))
+        );
+    }
+
+    #[test]
+    fn test_success_response() {
+        let found = lint(|doc| remove(&mut op(doc)["responses"], "200"));
+        assert_eq!(
+            found,
+            finding("success-response", &format!("{}/responses", OP))
+        );
+    }
+
+    #[test]
+    fn test_error_response_description() {
+        let found = lint(|doc| {
+            op(doc)["responses"]["500"] = yaml("{description: ''}");
+            doc["components"]["responses"]["NotFound"]["description"] = yaml("''");
+        });
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "error-response-description".to_string(),
+                    format!("{}/responses/404/description", OP)
+                ),
+                (
+                    "error-response-description".to_string(),
+                    format!("{}/responses/500/description", OP)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_param_description() {
+        let found = lint(|doc| {
+            remove(
+                &mut doc["paths"]["/users/{userId}"]["parameters"][0],
+                "description",
+            )
+        });
+        assert_eq!(
+            found,
+            finding(
+                "path-param-description",
+                "/paths/~1users~1{userId}/parameters/0"
+            )
+        );
+    }
+
+    #[test]
+    fn test_schema_name_pascal_case() {
+        let found = lint(|doc| {
+            let schemas = doc["components"]["schemas"].as_mapping_mut().unwrap();
+            let user = schemas.remove("User").unwrap();
+            schemas.insert(yaml("accounts.User"), user.clone());
+            schemas.insert(yaml("user_list"), user);
+        });
+        assert_eq!(
+            found,
+            finding("schema-name-pascal-case", "/components/schemas/user_list")
+        );
+    }
+
+    #[test]
+    fn test_property_camel_case() {
+        let found = lint(|doc| {
+            let user = &mut doc["components"]["schemas"]["User"];
+            user["properties"]["tags"]["items"]["properties"]["label_text"] = yaml("{}");
+        });
+        assert_eq!(
+            found,
+            finding(
+                "property-camel-case",
+                "/components/schemas/User/properties/tags/items/properties/label_text"
+            )
+        );
+    }
+
+    #[test]
+    fn test_disabled_rules() {
+        let rules = HashMap::from([
+            ("operation-summary".to_string(), false),
+            ("operation-tags".to_string(), true),
+        ]);
+        let found = lint_with(
+            |doc| {
+                remove(op(doc), "summary");
+                remove(op(doc), "tags");
+            },
+            &rules,
+        );
+        assert_eq!(found, finding("operation-tags", OP));
+
+        let diagnostic = &lint_document(&yaml("{openapi: 3.0.0}"), &HashMap::new())[0];
+        assert_eq!(diagnostic.code(), Some("E0033"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "#/info/description: the API has no description [info-description]"
+        );
+    }
+}
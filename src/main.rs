@@ -1,38 +1,297 @@
 #[cfg(feature = "cli")]
+use clap::CommandFactory;
+#[cfg(feature = "cli")]
 use colored::Colorize;
 #[cfg(feature = "cli")]
-use oas_forge::{Generator, config::Config};
+use oas_forge::config::{Command, Config, DiffFormat, MessageFormat};
+#[cfg(feature = "cli")]
+use oas_forge::diagnostics::{Diagnostic, Severity, code};
+#[cfg(feature = "cli")]
+use oas_forge::{Format, Generator, diff, lint};
+#[cfg(feature = "cli")]
+use std::io::IsTerminal;
+#[cfg(feature = "cli")]
+use std::sync::atomic::{AtomicBool, Ordering};
 
+// `--message-format json`: diagnostics as JSON lines on stdout, everything else on stderr
 #[cfg(feature = "cli")]
-fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+static JSON_MESSAGES: AtomicBool = AtomicBool::new(false);
+
+// `--quiet`: errors only
+#[cfg(feature = "cli")]
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Scans smaller than this finish before a progress indicator is worth showing
+#[cfg(feature = "cli")]
+const PROGRESS_THRESHOLD: usize = 200;
 
+#[cfg(feature = "cli")]
+fn main() -> anyhow::Result<()> {
     // Load configuration (CLI + TOML + Cargo.toml)
-    let config = Config::load();
-    let output = config
+    let mut config = Config::load().unwrap_or_else(|e| fail(e));
+    let quiet = config.quiet.unwrap_or(false);
+    QUIET.store(quiet, Ordering::Relaxed);
+    init_logger(quiet, config.verbose);
+    config
         .output
-        .clone()
-        .unwrap_or_else(|| std::path::PathBuf::from("openapi.yaml"));
+        .get_or_insert_with(|| vec![std::path::PathBuf::from("openapi.yaml")]);
+    JSON_MESSAGES.store(
+        config.message_format == Some(MessageFormat::Json),
+        Ordering::Relaxed,
+    );
+
+    if let Some(Command::Diff { old, new, format }) = &config.command {
+        let old = diff::read_document(old).unwrap_or_else(|e| fail(e));
+        let new = diff::read_document(new).unwrap_or_else(|e| fail(e));
+        let changes = diff::diff(&old, &new);
+        match format {
+            DiffFormat::Text => println!("{}", changes),
+            DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+        }
+        return Ok(());
+    }
+
+    match config.command {
+        Some(Command::Completions { shell }) => {
+            let mut command = Config::command();
+            clap_complete::generate(
+                shell,
+                &mut command,
+                env!("CARGO_BIN_NAME"),
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        Some(Command::Init { force }) => {
+            let written = oas_forge::init::scaffold(std::path::Path::new("."), force)
+                .unwrap_or_else(|e| fail(e));
+            for path in written {
+                say(format!("{} Wrote {:?}", "SUCCESS:".green().bold(), path));
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let Some(Command::ListRoutes { format } | Command::ListSchemas { format }) = config.command {
+        let routes = matches!(config.command, Some(Command::ListRoutes { .. }));
+        let inventory = Generator::new()
+            .with_config(config)
+            .inventory()
+            .unwrap_or_else(|e| fail(e));
+        match (format, routes) {
+            (DiffFormat::Text, true) => print!("{}", inventory.routes_table()),
+            (DiffFormat::Text, false) => print!("{}", inventory.schemas_table()),
+            (DiffFormat::Json, true) => {
+                println!("{}", serde_json::to_string_pretty(&inventory.routes)?)
+            }
+            (DiffFormat::Json, false) => {
+                println!("{}", serde_json::to_string_pretty(&inventory.schemas)?)
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Lint) = config.command {
+        let rules = config.lint.take().unwrap_or_default();
+        let doc = Generator::new()
+            .with_config(config)
+            .generate_value()
+            .unwrap_or_else(|e| fail(e));
+        let findings = lint::lint_document(&doc, &rules);
+        for finding in &findings {
+            eprintln!("{} {}", "LINT:".yellow().bold(), finding);
+            emit(finding);
+        }
+        if findings.is_empty() {
+            say(format!("{} No lint findings", "SUCCESS:".green().bold()));
+            return Ok(());
+        }
+        eprintln!(
+            "{} lint failed with {} findings",
+            "ERROR:".red().bold(),
+            findings.len()
+        );
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "serve")]
+    if let Some(Command::Serve { port }) = config.command {
+        say(format!(
+            "{} Serving on http://127.0.0.1:{}",
+            "INFO:".blue().bold(),
+            port
+        ));
+        let generator = Generator::new().with_config(config);
+        oas_forge::serve::serve(generator, ("127.0.0.1", port)).unwrap_or_else(|e| fail(e));
+        return Ok(());
+    }
+
+    say(format!("{} Starting oas-forge...", "INFO:".blue().bold()));
+
+    let export_registry = config.export_registry.clone();
+    let check = config.check.unwrap_or(false);
+    // `--include -` reads one snippet from stdin
+    let stdin = config.include.as_mut().is_some_and(|includes| {
+        let count = includes.len();
+        includes.retain(|path| path.as_os_str() != "-");
+        includes.len() != count
+    });
+    let mut generator = Generator::new().with_config(config);
+    if !quiet && !json_messages() && std::io::stderr().is_terminal() {
+        generator = generator.on_progress(show_progress);
+    }
+    if stdin {
+        generator = generator
+            .include_reader(std::io::stdin(), Format::Yaml)
+            .unwrap_or_else(|e| fail(e));
+    }
+
+    // Index-only run for other crates to import
+    if let Some(path) = export_registry {
+        let registry = generator.export_registry(&path).unwrap_or_else(|e| fail(e));
+        say(format!(
+            "{} Exported {} fragments, {} blueprints and {} schemas to {:?}",
+            "SUCCESS:".green().bold(),
+            registry.fragments.len(),
+            registry.blueprints.len(),
+            registry.schemas.len(),
+            path
+        ));
+        return Ok(());
+    }
 
-    println!("{} Starting oas-forge...", "INFO:".blue().bold());
+    if check {
+        let outdated = generator.check_outputs().unwrap_or_else(|e| fail(e));
+        if outdated.is_empty() {
+            say(format!(
+                "{} Outputs are up to date",
+                "SUCCESS:".green().bold()
+            ));
+            return Ok(());
+        }
+        for (path, changes) in &outdated {
+            eprintln!(
+                "{} {:?} is out of date ({} differences):",
+                "ERROR:".red().bold(),
+                path,
+                changes.len()
+            );
+            for change in changes {
+                eprintln!("  {}", change);
+            }
+            let mut diagnostic =
+                Diagnostic::info(format!("out of date ({} differences)", changes.len()));
+            diagnostic.severity = Severity::Error;
+            diagnostic.file = Some(path.clone());
+            emit(&diagnostic.with_code(code::OUTDATED_OUTPUT));
+        }
+        std::process::exit(1);
+    }
 
-    // Set up Generator
-    match Generator::new().with_config(config).generate() {
-        Ok(_) => {
-            println!(
+    let report = generator.generate_file().unwrap_or_else(|e| fail(e));
+    for output in &report.outputs {
+        if output.changed {
+            say(format!(
                 "{} Successfully generated OpenAPI definition at {:?}",
                 "SUCCESS:".green().bold(),
-                output
-            );
-            Ok(())
+                output.path
+            ));
+        } else {
+            say(format!(
+                "{} {:?} is unchanged",
+                "INFO:".blue().bold(),
+                output.path
+            ));
         }
-        Err(e) => {
-            eprintln!("{} {}", "ERROR:".red().bold(), e);
-            Err(anyhow::anyhow!(e))
+    }
+    // Warnings were already logged when found
+    for diagnostic in &report.diagnostics {
+        emit(diagnostic);
+    }
+    emit(&Diagnostic::info(report.summary()));
+    say(format!("{} {}", "SUMMARY:".blue().bold(), report.summary()));
+    Ok(())
+}
+
+// Flags win over RUST_LOG, which wins over the default
+#[cfg(feature = "cli")]
+fn init_logger(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => Some("error"),
+        (false, 0) => None,
+        (false, 1) => Some("debug"),
+        (false, _) => Some("trace"),
+    };
+    match level {
+        Some(level) => env_logger::Builder::new().parse_filters(level).init(),
+        None => env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+            .init(),
+    }
+}
+
+// File counter on stderr, for scans large enough to take a while
+#[cfg(feature = "cli")]
+fn show_progress(n: usize, total: usize, _file: &std::path::Path) {
+    if total < PROGRESS_THRESHOLD {
+        return;
+    }
+    eprint!("\rScanning files: {}/{}", n, total);
+    if n == total {
+        eprintln!();
+    }
+}
+
+#[cfg(feature = "cli")]
+fn json_messages() -> bool {
+    JSON_MESSAGES.load(Ordering::Relaxed)
+}
+
+// Human-readable status line
+#[cfg(feature = "cli")]
+fn say(message: String) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    if json_messages() {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+// A diagnostic as a JSON line, in JSON message mode only
+#[cfg(feature = "cli")]
+fn emit(diagnostic: &Diagnostic) {
+    if json_messages() {
+        match serde_json::to_string(diagnostic) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("{} {}", "ERROR:".red().bold(), e),
         }
     }
 }
 
+#[cfg(feature = "cli")]
+fn fail(error: oas_forge::error::Error) -> ! {
+    for diagnostic in Diagnostic::from_error(&error) {
+        emit(&diagnostic);
+    }
+    match error {
+        oas_forge::error::Error::Multiple(errors) => {
+            for e in &errors {
+                eprintln!("{} {}", "ERROR:".red().bold(), e);
+            }
+            eprintln!(
+                "{} generation failed with {} errors",
+                "ERROR:".red().bold(),
+                errors.len()
+            );
+        }
+        e => eprintln!("{} {}", "ERROR:".red().bold(), e),
+    }
+    std::process::exit(1);
+}
+
 #[cfg(not(feature = "cli"))]
 fn main() {
     eprintln!("This binary requires the 'cli' feature to be enabled.");
@@ -1,52 +1,734 @@
+use crate::diagnostics::{Diagnostic, code};
 use crate::error::{Error, Result};
+use crate::refs;
 use crate::scanner::Snippet;
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+
+/// Extension key recording which `file:line` contributed a path item or schema.
+pub const SOURCE_KEY: &str = "x-oas-forge-source";
+
+/// Extension key selecting how a node merges: `x-oas-forge-merge: replace` in a mapping, or
+/// a leading `{x-oas-forge-merge: replace}` item in a sequence, replaces what earlier
+/// snippets defined at that node instead of merging into it. Never part of the output.
+pub const MERGE_KEY: &str = "x-oas-forge-merge";
+
+/// Top-level key marking an include as an override file (`x-oas-forge-overrides: true`):
+/// its `paths` and `components` are merged after every other snippet, replacing what they
+/// defined wherever the two disagree.
+pub const OVERRIDES_KEY: &str = "x-oas-forge-overrides";
 
 /// Merges multiple OpenAPI YAML/JSON fragments into a single Value.
 pub fn merge_openapi(snippets: Vec<Snippet>) -> Result<Value> {
-    let mut root: Option<Value> = None;
-    let mut others: Vec<Value> = Vec::new();
+    merge_openapi_with(snippets, false)
+}
+
+/// Like [`merge_openapi`]; with `annotate_sources`, each path item and schema gets a
+/// [`SOURCE_KEY`] pointing at the snippet that first defined it.
+pub fn merge_openapi_with(snippets: Vec<Snippet>, annotate_sources: bool) -> Result<Value> {
+    let options = MergeOptions {
+        annotate_sources,
+        ..Default::default()
+    };
+    merge_openapi_with_options(snippets, options)
+}
+
+/// Like [`merge_openapi`], with explicit [`MergeOptions`].
+pub fn merge_openapi_with_options(snippets: Vec<Snippet>, options: MergeOptions) -> Result<Value> {
+    merge(snippets, options, None).map(|(doc, _)| doc)
+}
+
+/// Like [`merge_openapi_with_options`], using `default_root` when no snippet is a root.
+/// Also returns whether it was used.
+pub(crate) fn merge_with_default_root(
+    snippets: Vec<Snippet>,
+    options: MergeOptions,
+    default_root: Value,
+) -> Result<(Value, bool)> {
+    merge(snippets, options, Some(default_root))
+}
+
+/// Options for [`merge_openapi_with_options`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergeOptions {
+    /// See [`merge_openapi_with`]
+    pub annotate_sources: bool,
+    /// What happens when snippets define the same schema differently
+    pub on_conflict: OnConflict,
+}
+
+/// Handling of a `components.schemas` entry defined differently by two snippets.
+///
+/// Configured as `"error"` (default) or `"last"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum OnConflict {
+    /// Fail, naming both sources
+    #[default]
+    Error,
+    /// The definition merged last replaces earlier ones
+    Last,
+}
+
+impl std::str::FromStr for OnConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(OnConflict::Error),
+            "last" => Ok(OnConflict::Last),
+            other => Err(format!(
+                "unknown conflict strategy '{}', expected 'error' or 'last'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for OnConflict {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Order of the method keys within each path item, see [`shape_paths`].
+///
+/// Configured as `"merge"` (default, as merged) or `"method-order"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum OperationOrder {
+    /// Keep the order the snippets were merged in
+    #[default]
+    Merge,
+    /// get, post, put, patch, delete, then head, options and trace
+    MethodOrder,
+}
+
+impl std::str::FromStr for OperationOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(OperationOrder::Merge),
+            "method-order" => Ok(OperationOrder::MethodOrder),
+            other => Err(format!(
+                "unknown operation order '{}', expected 'merge' or 'method-order'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for OperationOrder {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Order of the entries in `paths`, see [`shape_paths`].
+///
+/// Configured as `"merge"` (default, as merged), `"tag"` or `"alpha"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub enum PathGrouping {
+    /// Keep the order the snippets were merged in
+    #[default]
+    Merge,
+    /// By the first tag of each path's operations, in the order of the top-level `tags`
+    /// (undeclared tags alphabetically after them, untagged paths last), then alphabetically
+    Tag,
+    /// Alphabetically
+    Alpha,
+}
+
+impl std::str::FromStr for PathGrouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(PathGrouping::Merge),
+            "tag" => Ok(PathGrouping::Tag),
+            "alpha" => Ok(PathGrouping::Alpha),
+            other => Err(format!(
+                "unknown path grouping '{}', expected 'merge', 'tag' or 'alpha'",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for PathGrouping {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, String> {
+        value.parse()
+    }
+}
+
+// Points at the failing line as written (before macros and fragments were expanded),
+// showing the doc comment itself when known, with a caret under the column.
+fn source_mapped_error(snippet: &Snippet, error: serde_yaml::Error) -> Error {
+    let location = error.location();
+    let processed_line = location
+        .as_ref()
+        .map_or(0, |loc| loc.line().saturating_sub(1));
+    let line = snippet.source_line(processed_line);
+    let (first_line, original) = snippet.original();
+    let index = line.saturating_sub(first_line);
+    let original_text = original.lines().nth(index);
+    let raw_text = snippet.raw().and_then(|raw| raw.lines().nth(index));
+
+    // The column refers to the processed line, so it only carries over to lines that
+    // preprocessing left alone; within the doc comment it shifts by the `///` prefix
+    let column = location
+        .map(|loc| loc.column())
+        .filter(|_| snippet.content.lines().nth(processed_line) == original_text)
+        .map(|column| match (raw_text, original_text) {
+            (Some(raw), Some(text)) if raw.ends_with(text) => raw.len() - text.len() + column,
+            _ => column,
+        });
+
+    let shown: Vec<&str> = snippet.raw().unwrap_or(original).lines().collect();
+    let width = (line + 2).to_string().len();
+    let mut context = Vec::new();
+    for (idx, text) in shown
+        .iter()
+        .enumerate()
+        .skip(index.saturating_sub(2))
+        .take(5)
+    {
+        context.push(format!("{:>width$} | {}", idx + first_line, text));
+        if idx == index {
+            let caret = column
+                .map(|c| c.saturating_sub(1))
+                .unwrap_or_else(|| text.len() - text.trim_start().len());
+            context.push(format!("{:>width$} | {}^", "", " ".repeat(caret)));
+        }
+    }
+
+    Error::SourceMapped {
+        file: snippet.file_path.clone(),
+        line,
+        column,
+        source: error,
+        context: context.join("\n"),
+    }
+}
+
+fn merge(
+    snippets: Vec<Snippet>,
+    options: MergeOptions,
+    default_root: Option<Value>,
+) -> Result<(Value, bool)> {
+    let annotate_sources = options.annotate_sources;
+    let mut root: Option<(Value, String)> = None;
+    let mut others: Vec<(Value, String, i32)> = Vec::new();
+    let mut overrides: Vec<(Value, String)> = Vec::new();
+    // Broken snippets are skipped and reported together at the end
+    let mut errors = Vec::new();
 
     for snippet in snippets {
         let value: Value = match serde_yaml::from_str(&snippet.content) {
             Ok(v) => v,
             Err(e) => {
-                // Construct context string
-                let context: String = snippet
-                    .content
-                    .lines()
-                    .take(5)
-                    .enumerate()
-                    .map(|(idx, line)| format!("    {:02} | {}", idx + snippet.line_number, line))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-
-                return Err(Error::SourceMapped {
-                    file: snippet.file_path.clone(),
-                    line: snippet.line_number,
-                    source: e,
-                    context,
-                });
+                errors.push(source_mapped_error(&snippet, e));
+                continue;
             }
         };
+        let source = format!("{}:{}", snippet.file_path.display(), snippet.line_number);
 
-        if is_root(&value) {
+        if value.get(OVERRIDES_KEY).and_then(Value::as_bool) == Some(true) {
+            overrides.push((value, source));
+        } else if is_root(&value) {
             if root.is_some() {
                 return Err(Error::MultipleRootsFound);
             }
-            root = Some(value);
+            root = Some((value, source));
         } else {
-            others.push(value);
+            others.push((value, source, snippet.priority));
         }
     }
+    // Stable, so equal priorities keep scan order
+    others.sort_by_key(|(_, _, priority)| *priority);
 
-    let mut root = root.ok_or(Error::NoRootFound)?;
+    // The root may well be one of the snippets that failed to parse
+    Error::from_all(errors)?;
+    let synthesized = root.is_none();
+    let (mut root, root_source) = match (root, default_root) {
+        (Some(root), _) => root,
+        (None, Some(default_root)) => (default_root, "Cargo.toml".to_string()),
+        (None, None) => return Err(Error::NoRootFound),
+    };
+    let empty = Value::Null;
+    annotate(&mut root, &empty, &root_source);
 
-    for other in others {
+    // Which snippet defined each schema, for conflict reports
+    let mut schema_sources: HashMap<Value, String> = schemas(&root)
+        .map(|schemas| {
+            schemas
+                .keys()
+                .map(|name| (name.clone(), root_source.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut conflicts = Vec::new();
+    for (mut other, source, _) in others {
+        if let (Some(existing), Some(incoming)) = (schemas(&root), schemas(&other)) {
+            let mut replaced = Vec::new();
+            for (name, schema) in incoming {
+                let Some(current) = existing.get(name) else {
+                    continue;
+                };
+                // An explicit `replace` marker is an intended override, not a conflict
+                if without_source(current) == *schema
+                    || schema.get(MERGE_KEY).and_then(Value::as_str) == Some("replace")
+                {
+                    continue;
+                }
+                match options.on_conflict {
+                    OnConflict::Error => conflicts.push(Error::SchemaConflict {
+                        name: name.as_str().unwrap_or_default().to_string(),
+                        first: schema_sources.get(name).cloned().unwrap_or_default(),
+                        second: source.clone(),
+                    }),
+                    OnConflict::Last => replaced.push(name.clone()),
+                }
+            }
+            // Replaced wholesale rather than merged into the earlier definition
+            if let Some(Value::Mapping(existing)) = root
+                .get_mut("components")
+                .and_then(|c| c.get_mut("schemas"))
+            {
+                for name in replaced {
+                    existing.remove(&name);
+                }
+            }
+        }
+        if let Some(incoming) = schemas(&other) {
+            for name in incoming.keys() {
+                schema_sources
+                    .entry(name.clone())
+                    .or_insert_with(|| source.clone());
+            }
+        }
+        annotate(&mut other, &root, &source);
         deep_merge(&mut root, other);
     }
+    Error::from_all(conflicts)?;
+    for (layer, source) in overrides {
+        for pointer in apply_override_file(&mut root, layer, &source) {
+            log::info!("{}: overrides #{}", source, pointer);
+        }
+    }
+    strip_merge_markers(&mut root);
+
+    // Final pass, so annotations never leak into output when the option is off
+    if !annotate_sources {
+        strip_sources(&mut root);
+    }
 
-    Ok(root)
+    Ok((root, synthesized))
+}
+
+// Merges the `paths` and `components` of an override file, returning the pointers of the
+// values it replaced
+fn apply_override_file(root: &mut Value, layer: Value, source: &str) -> Vec<String> {
+    let mut replaced = Vec::new();
+    let Value::Mapping(layer) = layer else {
+        return replaced;
+    };
+    for (key, value) in layer {
+        let Some(name) = key.as_str() else { continue };
+        match name {
+            "paths" | "components" => {
+                let mut pointer = format!("/{}", name);
+                match root.get_mut(name) {
+                    Some(target) => replace_merge(target, value, &mut pointer, &mut replaced),
+                    None => {
+                        if let Value::Mapping(map) = root {
+                            map.insert(key, value);
+                        }
+                    }
+                }
+            }
+            OVERRIDES_KEY => {}
+            other => log::warn!(
+                "{}: override files only apply 'paths' and 'components'; ignoring '{}'",
+                source,
+                other
+            ),
+        }
+    }
+    replaced
+}
+
+// Mappings merge key by key; anything else replaces the target
+fn replace_merge(
+    target: &mut Value,
+    source: Value,
+    pointer: &mut String,
+    replaced: &mut Vec<String>,
+) {
+    match (target, source) {
+        (Value::Mapping(t_map), Value::Mapping(s_map)) => {
+            for (key, s_val) in s_map {
+                match t_map.get_mut(&key) {
+                    Some(t_val) => {
+                        let name = match &key {
+                            Value::Number(n) => n.to_string(),
+                            other => other.as_str().unwrap_or_default().to_string(),
+                        };
+                        let len = pointer.len();
+                        pointer.push('/');
+                        pointer.push_str(&refs::escape(&name));
+                        replace_merge(t_val, s_val, pointer, replaced);
+                        pointer.truncate(len);
+                    }
+                    None => {
+                        t_map.insert(key, s_val);
+                    }
+                }
+            }
+        }
+        (target, source) => {
+            if *target != source {
+                replaced.push(pointer.clone());
+                *target = source;
+            }
+        }
+    }
+}
+
+fn schemas(doc: &Value) -> Option<&serde_yaml::Mapping> {
+    doc.get("components")?.get("schemas")?.as_mapping()
+}
+
+fn without_source(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Value::Mapping(map) = &mut schema {
+        map.remove(SOURCE_KEY);
+    }
+    schema
+}
+
+// Tags path items and schemas in `value` that `merged` does not define yet with `source`.
+fn annotate(value: &mut Value, merged: &Value, source: &str) {
+    let existing = [
+        merged.get("paths"),
+        merged.get("components").and_then(|c| c.get("schemas")),
+    ];
+    for (section, existing) in annotated_sections(value).into_iter().zip(existing) {
+        let Some(section) = section else { continue };
+        for (key, item) in section.iter_mut() {
+            if existing.is_some_and(|m| m.get(key).is_some()) {
+                continue;
+            }
+            if let Value::Mapping(item) = item {
+                item.insert(SOURCE_KEY.into(), source.into());
+            }
+        }
+    }
+}
+
+fn strip_sources(doc: &mut Value) {
+    for section in annotated_sections(doc).into_iter().flatten() {
+        for (_, item) in section.iter_mut() {
+            if let Value::Mapping(item) = item {
+                item.remove(SOURCE_KEY);
+            }
+        }
+    }
+}
+
+// `paths` and `components.schemas`, when present
+fn annotated_sections(doc: &mut Value) -> [Option<&mut serde_yaml::Mapping>; 2] {
+    let Value::Mapping(map) = doc else {
+        return [None, None];
+    };
+    let mut paths = None;
+    let mut schemas = None;
+    for (key, value) in map.iter_mut() {
+        match key.as_str() {
+            Some("paths") => paths = value.as_mapping_mut(),
+            Some("components") => {
+                schemas = value.get_mut("schemas").and_then(Value::as_mapping_mut)
+            }
+            _ => {}
+        }
+    }
+    [paths, schemas]
+}
+
+const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Top-level keys in the order they are emitted by [`sort_document`]; others follow alphabetically.
+const TOP_LEVEL_ORDER: [&str; 7] = [
+    "openapi",
+    "info",
+    "servers",
+    "tags",
+    "security",
+    "paths",
+    "components",
+];
+
+/// Puts a merged document into canonical order: known top-level keys first,
+/// then `paths` and `components.schemas` sorted alphabetically.
+pub fn sort_document(doc: &mut Value) {
+    let Value::Mapping(map) = doc else {
+        return;
+    };
+    let rank = |key: &Value| {
+        let key = key.as_str().unwrap_or_default();
+        let pos = TOP_LEVEL_ORDER.iter().position(|k| *k == key);
+        (pos.unwrap_or(TOP_LEVEL_ORDER.len()), key.to_string())
+    };
+    sort_mapping_by(map, rank);
+
+    if let Some(Value::Mapping(paths)) = map.get_mut("paths") {
+        sort_mapping_by(paths, |k| k.as_str().unwrap_or_default().to_string());
+    }
+    if let Some(Value::Mapping(schemas)) =
+        map.get_mut("components").and_then(|c| c.get_mut("schemas"))
+    {
+        sort_mapping_by(schemas, |k| k.as_str().unwrap_or_default().to_string());
+    }
+}
+
+// Methods in the order of `OperationOrder::MethodOrder`
+const METHOD_ORDER: [&str; 8] = [
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+/// Reorders `paths` and the operations within each path item. Only mapping order changes;
+/// path-level keys (`parameters`, `summary`, ...) stay ahead of the operations.
+pub fn shape_paths(doc: &mut Value, operations: OperationOrder, grouping: PathGrouping) {
+    let declared: Vec<String> = doc
+        .get("tags")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag.get("name")?.as_str().map(str::to_string))
+        .collect();
+    let Some(Value::Mapping(paths)) = doc.get_mut("paths") else {
+        return;
+    };
+
+    if operations == OperationOrder::MethodOrder {
+        for (_, item) in paths.iter_mut() {
+            if let Value::Mapping(item) = item {
+                sort_mapping_by(item, |k| {
+                    let key = k.as_str().unwrap_or_default();
+                    METHOD_ORDER
+                        .iter()
+                        .position(|m| *m == key)
+                        .map_or(0, |pos| pos + 1)
+                });
+            }
+        }
+    }
+
+    match grouping {
+        PathGrouping::Merge => {}
+        PathGrouping::Alpha => {
+            sort_mapping_by(paths, |k| k.as_str().unwrap_or_default().to_string());
+        }
+        PathGrouping::Tag => {
+            // (declared, undeclared, untagged), position among the declared tags, tag
+            let groups: HashMap<String, (u8, usize, String)> = paths
+                .iter()
+                .filter_map(|(path, item)| {
+                    let group = match first_tag(item) {
+                        Some(tag) => match declared.iter().position(|d| *d == tag) {
+                            Some(pos) => (0, pos, String::new()),
+                            None => (1, 0, tag),
+                        },
+                        None => (2, 0, String::new()),
+                    };
+                    Some((path.as_str()?.to_string(), group))
+                })
+                .collect();
+            sort_mapping_by(paths, |k| {
+                let path = k.as_str().unwrap_or_default();
+                (groups.get(path).cloned(), path.to_string())
+            });
+        }
+    }
+}
+
+// First tag of a path item's operations, taken in method order
+fn first_tag(item: &Value) -> Option<String> {
+    METHOD_ORDER.iter().find_map(|method| {
+        item.get(method)?
+            .get("tags")?
+            .as_sequence()?
+            .first()?
+            .as_str()
+            .map(str::to_string)
+    })
+}
+
+/// What [`sanitize`] keeps that it would otherwise remove.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SanitizeOptions {
+    /// Mapping entries whose value is null (e.g. an intentional `example: null`)
+    pub keep_nulls: bool,
+    /// The [`SOURCE_KEY`] annotations requested with `annotate_sources`
+    pub keep_sources: bool,
+}
+
+/// Final cleanup of a merged document: removes internal `x-oas-forge-*` and
+/// `x-openapi-extend` keys, null-valued entries and empty `parameters`/`tags` arrays.
+/// Example and default values are left as written.
+pub fn sanitize(doc: &mut Value, options: SanitizeOptions) {
+    let Value::Mapping(map) = doc else {
+        if let Value::Sequence(seq) = doc {
+            seq.iter_mut().for_each(|item| sanitize(item, options));
+        }
+        return;
+    };
+    map.retain(|key, value| {
+        let key = key.as_str().unwrap_or_default();
+        let internal = (key.starts_with("x-oas-forge-")
+            && !(options.keep_sources && key == SOURCE_KEY))
+            || key.starts_with("x-openapi-extend");
+        !internal && (options.keep_nulls || !value.is_null())
+    });
+    for (key, value) in map.iter_mut() {
+        if !matches!(
+            key.as_str(),
+            Some("example" | "examples" | "default" | "const")
+        ) {
+            sanitize(value, options);
+        }
+    }
+    map.retain(|key, value| {
+        !(matches!(key.as_str(), Some("parameters" | "tags"))
+            && value.as_sequence().is_some_and(Vec::is_empty))
+    });
+}
+
+/// Rewrites the 3.0 boolean `exclusiveMinimum`/`exclusiveMaximum` for 3.1 documents, where
+/// they hold the bound itself: `{minimum: 0, exclusiveMinimum: true}` becomes
+/// `{exclusiveMinimum: 0}`.
+pub fn exclusive_bounds_to_numbers(doc: &mut Value) {
+    match doc {
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                if !matches!(
+                    key.as_str(),
+                    Some("example" | "examples" | "default" | "const")
+                ) {
+                    exclusive_bounds_to_numbers(value);
+                }
+            }
+            for (bound, exclusive) in [
+                ("minimum", "exclusiveMinimum"),
+                ("maximum", "exclusiveMaximum"),
+            ] {
+                // A property called `exclusiveMinimum` is a schema, not the keyword
+                let Some(flag) = map.get(exclusive).and_then(Value::as_bool) else {
+                    continue;
+                };
+                map.remove(exclusive);
+                if flag {
+                    if let Some(value) = map.remove(bound) {
+                        map.insert(exclusive.into(), value);
+                    }
+                }
+            }
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(exclusive_bounds_to_numbers),
+        _ => {}
+    }
+}
+
+/// Rewrites the 3.0 `nullable` keyword for 3.1 documents, where null is a type:
+/// `{type: string, nullable: true}` becomes `{type: [string, "null"]}` and other schemas,
+/// like a reference wrapped in `allOf`, become an `anyOf` with `{type: "null"}`.
+pub fn nullable_to_null_type(doc: &mut Value) {
+    match doc {
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                if !matches!(
+                    key.as_str(),
+                    Some("example" | "examples" | "default" | "const")
+                ) {
+                    nullable_to_null_type(value);
+                }
+            }
+            // A property called `nullable` is a schema, not the keyword
+            let Some(nullable) = map.get("nullable").and_then(Value::as_bool) else {
+                return;
+            };
+            map.remove("nullable");
+            if !nullable {
+                return;
+            }
+            if let Some(Value::Sequence(values)) = map.get_mut("enum") {
+                if !values.contains(&Value::Null) {
+                    values.push(Value::Null);
+                }
+            }
+            let null_type = || {
+                let mut null = Mapping::new();
+                null.insert("type".into(), "null".into());
+                Value::Mapping(null)
+            };
+            match map.get_mut("type") {
+                Some(Value::String(ty)) => {
+                    let types = vec![Value::String(std::mem::take(ty)), "null".into()];
+                    map.insert("type".into(), Value::Sequence(types));
+                }
+                Some(Value::Sequence(types)) => {
+                    if !types.iter().any(|t| t.as_str() == Some("null")) {
+                        types.push("null".into());
+                    }
+                }
+                _ => {
+                    let single_all_of = map
+                        .get("allOf")
+                        .and_then(Value::as_sequence)
+                        .is_some_and(|all| all.len() == 1);
+                    let schema = match map.remove("allOf") {
+                        Some(Value::Sequence(mut all)) if single_all_of => all.remove(0),
+                        all_of => {
+                            if let Some(all_of) = all_of {
+                                map.insert("allOf".into(), all_of);
+                            }
+                            Value::Mapping(std::mem::take(map))
+                        }
+                    };
+                    map.insert("anyOf".into(), Value::Sequence(vec![schema, null_type()]));
+                }
+            }
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(nullable_to_null_type),
+        _ => {}
+    }
+}
+
+/// Merges user `overrides` over a merged document: scalars replace scanned values and
+/// `servers`, if given, replace the scanned list instead of extending it.
+pub fn apply_overrides(doc: &mut Value, overrides: Value) {
+    if overrides.get("servers").is_some() {
+        if let Value::Mapping(map) = doc {
+            map.remove("servers");
+        }
+    }
+    deep_merge(doc, overrides);
+}
+
+fn sort_mapping_by<K: Ord>(map: &mut serde_yaml::Mapping, key: impl Fn(&Value) -> K) {
+    let mut entries: Vec<_> = std::mem::take(map).into_iter().collect();
+    entries.sort_by_key(|(k, _)| key(k));
+    map.extend(entries);
 }
 
 fn is_root(value: &Value) -> bool {
@@ -62,11 +744,20 @@ fn is_root(value: &Value) -> bool {
 /// - Maps: Merged recursively.
 /// - Scalars: Overwritten by the source (right-hand side).
 fn deep_merge(target: &mut Value, source: Value) {
+    merge_at(target, source, None);
+}
+
+// `field` is the mapping key `target` sits under, which decides how sequence items match
+fn merge_at(target: &mut Value, mut source: Value, field: Option<&str>) {
+    if take_replace_marker(&mut source) {
+        *target = source;
+        return;
+    }
     match (target, source) {
         (Value::Mapping(t_map), Value::Mapping(s_map)) => {
             for (key, s_val) in s_map {
                 match t_map.get_mut(&key) {
-                    Some(t_val) => deep_merge(t_val, s_val),
+                    Some(t_val) => merge_at(t_val, s_val, key.as_str()),
                     None => {
                         t_map.insert(key, s_val);
                     }
@@ -74,19 +765,21 @@ fn deep_merge(target: &mut Value, source: Value) {
             }
         }
         (Value::Sequence(t_seq), Value::Sequence(s_seq)) => {
-            t_seq.extend(s_seq);
-            // Deduplicate preserving order
-            let mut seen = std::collections::HashSet::new();
-            let mut unique = Vec::new();
-            for item in t_seq.drain(..) {
-                // We use the string representation for deduping to handle potential Hash/Eq oddities with YAML Values widely
-                // But serde_yaml::Value does impl Hash/Eq.
-                // However, let's trust serde_yaml's Hash implementation.
-                if seen.insert(item.clone()) {
-                    unique.push(item);
+            // Items with an identity (parameters, tags, servers) merge into the earlier item
+            // with the same one; others are deduplicated by equality, preserving order
+            let items: Vec<Value> = std::mem::take(t_seq).into_iter().chain(s_seq).collect();
+            for item in items {
+                let id = identity(field, &item);
+                let existing = t_seq.iter().position(|t| match &id {
+                    Some(id) => identity(field, t).as_ref() == Some(id),
+                    None => *t == item,
+                });
+                match existing {
+                    Some(idx) if id.is_some() => merge_at(&mut t_seq[idx], item, None),
+                    Some(_) => {}
+                    None => t_seq.push(item),
                 }
             }
-            *t_seq = unique;
         }
         (t, s) => {
             *t = s;
@@ -94,6 +787,839 @@ fn deep_merge(target: &mut Value, source: Value) {
     }
 }
 
+// Parameters are identified by `(name, in)`, objects in `tags` by `name`, in `servers` by `url`
+fn identity(field: Option<&str>, item: &Value) -> Option<Vec<Value>> {
+    let map = item.as_mapping()?;
+    let get = |key: &str| map.get(key).cloned();
+    match (get("name"), get("in")) {
+        (Some(name), Some(location)) => return Some(vec![name, location]),
+        (Some(name), None) if field == Some("tags") => return Some(vec![name]),
+        _ => {}
+    }
+    match field {
+        Some("servers") => get("url").map(|url| vec![url]),
+        _ => None,
+    }
+}
+
+// Removes a `MERGE_KEY: replace` marker from the top of `value`, telling whether it was there
+fn take_replace_marker(value: &mut Value) -> bool {
+    let is_marker =
+        |map: &serde_yaml::Mapping| map.get(MERGE_KEY).and_then(Value::as_str) == Some("replace");
+    match value {
+        Value::Mapping(map) if is_marker(map) => {
+            map.remove(MERGE_KEY);
+            true
+        }
+        Value::Sequence(seq)
+            if seq
+                .first()
+                .and_then(Value::as_mapping)
+                .is_some_and(|first| first.len() == 1 && is_marker(first)) =>
+        {
+            seq.remove(0);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Markers on nodes that had nothing to replace are still in the document
+fn strip_merge_markers(value: &mut Value) {
+    take_replace_marker(value);
+    match value {
+        Value::Mapping(map) => {
+            map.remove(MERGE_KEY);
+            map.values_mut().for_each(strip_merge_markers);
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(strip_merge_markers),
+        _ => {}
+    }
+}
+
+/// Converts a merged OpenAPI 3.x document to Swagger 2.0.
+///
+/// Whatever 2.0 cannot express (callbacks, links, cookie parameters, extra media types, ...)
+/// is dropped with a logged warning; [`downconvert_v2_with`] collects them instead.
+pub fn downconvert_v2(doc: Value) -> Result<Value> {
+    let mut warnings = Vec::new();
+    let converted = downconvert_v2_with(doc, &mut warnings)?;
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+    Ok(converted)
+}
+
+/// Like [`downconvert_v2`], recording what was dropped in `warnings` (pointers refer to the 3.x document).
+pub fn downconvert_v2_with(doc: Value, warnings: &mut Vec<Diagnostic>) -> Result<Value> {
+    let Value::Mapping(mut doc) = doc else {
+        return Err(Error::Downconvert("document is not a mapping".to_string()));
+    };
+    let version = doc
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !version.starts_with("3.") {
+        return Err(Error::Downconvert(format!(
+            "expected an OpenAPI 3.x document, found version '{}'",
+            version
+        )));
+    }
+    let components = doc.remove("components").unwrap_or(Value::Null);
+    let mut converter = V2Converter {
+        components: &components,
+        warnings,
+    };
+
+    let mut out = Mapping::new();
+    out.insert("swagger".into(), "2.0".into());
+    for (key, value) in doc {
+        match key.as_str() {
+            Some("openapi") => {}
+            Some("servers") => converter.servers(&value, &mut out),
+            Some("paths") => {
+                out.insert(key, converter.paths(value));
+            }
+            Some("webhooks") => converter.dropped("/webhooks", "webhooks"),
+            _ => {
+                out.insert(key, value);
+            }
+        }
+    }
+    converter.components(&mut out);
+
+    let mut out = Value::Mapping(out);
+    rewrite_v2_refs(&mut out);
+    Ok(out)
+}
+
+/// `$ref` prefixes moved by [`downconvert_v2`]
+const V2_REF_PREFIXES: [(&str, &str); 3] = [
+    ("#/components/schemas/", "#/definitions/"),
+    ("#/components/parameters/", "#/parameters/"),
+    ("#/components/responses/", "#/responses/"),
+];
+
+const FORM_MEDIA_TYPES: [&str; 2] = ["application/x-www-form-urlencoded", "multipart/form-data"];
+
+/// Schema keywords allowed on non-body parameters, headers and `items` in 2.0
+const SIMPLE_SCHEMA_KEYS: [&str; 16] = [
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+struct V2Converter<'a> {
+    components: &'a Value,
+    warnings: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> V2Converter<'a> {
+    fn dropped(&mut self, pointer: &str, what: &str) {
+        self.warnings.push(
+            Diagnostic::at_pointer(
+                format!(
+                    "{} cannot be represented in Swagger 2.0 and were dropped",
+                    what
+                ),
+                pointer,
+            )
+            .with_code(code::DOWNCONVERT),
+        );
+    }
+
+    fn warn(&mut self, pointer: &str, message: String) {
+        self.warnings
+            .push(Diagnostic::at_pointer(message, pointer).with_code(code::DOWNCONVERT));
+    }
+
+    // Follows a `$ref` into `components.<kind>`; other values are returned as-is
+    fn component<'v>(&self, value: &'v Value, kind: &str) -> Option<&'v Value>
+    where
+        'a: 'v,
+    {
+        let Some(target) = value.get("$ref").and_then(Value::as_str) else {
+            return Some(value);
+        };
+        let prefix = format!("#/components/{}/", kind);
+        target.strip_prefix(&prefix)?;
+        refs::resolve(self.components, &target.replacen("/components", "", 1))
+    }
+
+    fn servers(&mut self, servers: &Value, out: &mut Mapping) {
+        let Some(servers) = servers.as_sequence() else {
+            return;
+        };
+        if servers.len() > 1 {
+            self.warn(
+                "/servers",
+                format!(
+                    "Swagger 2.0 has a single host; {} servers after the first were dropped",
+                    servers.len() - 1
+                ),
+            );
+        }
+        let Some(server) = servers.first() else {
+            return;
+        };
+        let mut url = server
+            .get("url")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        for (name, variable) in server
+            .get("variables")
+            .and_then(Value::as_mapping)
+            .into_iter()
+            .flatten()
+        {
+            let default = variable.get("default").and_then(Value::as_str);
+            if let (Some(name), Some(default)) = (name.as_str(), default) {
+                url = url.replace(&format!("{{{}}}", name), default);
+            }
+        }
+
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme), rest),
+            None => (None, url.as_str()),
+        };
+        let (host, base_path) = match (scheme, rest.find('/')) {
+            (None, _) => (None, rest),
+            (Some(_), Some(i)) => (Some(&rest[..i]), &rest[i..]),
+            (Some(_), None) => (Some(rest), ""),
+        };
+        if let Some(host) = host {
+            out.insert("host".into(), host.into());
+        }
+        let base_path = base_path.trim_end_matches('/');
+        if !base_path.is_empty() {
+            out.insert("basePath".into(), base_path.into());
+        }
+        if let Some(scheme) = scheme {
+            out.insert("schemes".into(), Value::Sequence(vec![scheme.into()]));
+        }
+    }
+
+    fn paths(&mut self, paths: Value) -> Value {
+        let Value::Mapping(paths) = paths else {
+            return paths;
+        };
+        let converted = paths.into_iter().map(|(path, item)| {
+            let pointer = format!("/paths/{}", refs::escape(path.as_str().unwrap_or_default()));
+            let item = self.path_item(item, &pointer);
+            (path, item)
+        });
+        Value::Mapping(converted.collect())
+    }
+
+    fn path_item(&mut self, item: Value, pointer: &str) -> Value {
+        let Value::Mapping(item) = item else {
+            return item;
+        };
+        let mut out = Mapping::new();
+        for (key, value) in item {
+            let name = key.as_str().unwrap_or_default().to_string();
+            let pointer = format!("{}/{}", pointer, refs::escape(&name));
+            match name.as_str() {
+                "parameters" => {
+                    out.insert(key, self.parameters(value, &pointer));
+                }
+                "trace" => self.dropped(&pointer, "TRACE operations"),
+                "summary" | "description" => self.dropped(&pointer, "path item summaries"),
+                "servers" => self.dropped(&pointer, "path item servers"),
+                method if HTTP_METHODS.contains(&method) => {
+                    out.insert(key, self.operation(value, &pointer));
+                }
+                _ => {
+                    out.insert(key, value);
+                }
+            }
+        }
+        Value::Mapping(out)
+    }
+
+    fn operation(&mut self, operation: Value, pointer: &str) -> Value {
+        let Value::Mapping(operation) = operation else {
+            return operation;
+        };
+        let mut out = Mapping::new();
+        let mut consumes = Vec::new();
+        let mut produces = Vec::new();
+        let mut body = Vec::new();
+        for (key, value) in operation {
+            let name = key.as_str().unwrap_or_default().to_string();
+            let pointer = format!("{}/{}", pointer, name);
+            match name.as_str() {
+                "parameters" => {
+                    out.insert(key, self.parameters(value, &pointer));
+                }
+                "requestBody" => body = self.request_body(&value, &pointer, &mut consumes),
+                "responses" => {
+                    out.insert(key, self.responses(value, &pointer, &mut produces));
+                }
+                "callbacks" => self.dropped(&pointer, "callbacks"),
+                "servers" => self.dropped(&pointer, "operation servers"),
+                _ => {
+                    out.insert(key, value);
+                }
+            }
+        }
+
+        if !body.is_empty() {
+            let params = out
+                .entry("parameters".into())
+                .or_insert_with(|| Value::Sequence(Vec::new()));
+            if let Value::Sequence(params) = params {
+                params.extend(body);
+            }
+        }
+        for (key, media_types) in [("consumes", consumes), ("produces", produces)] {
+            if !media_types.is_empty() {
+                let media_types = media_types.into_iter().map(Value::String).collect();
+                out.insert(key.into(), Value::Sequence(media_types));
+            }
+        }
+        Value::Mapping(out)
+    }
+
+    // The first media type's schema; the others are reported if their schema differs
+    fn pick_media_type<'v>(
+        &mut self,
+        content: &'v Mapping,
+        pointer: &str,
+        media_types: &mut Vec<String>,
+    ) -> Option<(&'v str, &'v Value)> {
+        let mut first: Option<(&'v str, &'v Value)> = None;
+        let mut dropped = Vec::new();
+        for (media_type, media) in content {
+            let Some(media_type) = media_type.as_str() else {
+                continue;
+            };
+            if !media_types.iter().any(|m| m == media_type) {
+                media_types.push(media_type.to_string());
+            }
+            match first {
+                None => first = Some((media_type, media)),
+                Some((_, kept)) if kept.get("schema") != media.get("schema") => {
+                    dropped.push(media_type)
+                }
+                Some(_) => {}
+            }
+        }
+        if let (Some((kept, _)), false) = (first, dropped.is_empty()) {
+            self.warn(
+                pointer,
+                format!(
+                    "Swagger 2.0 has one schema per body; kept '{}', dropped the schemas of {}",
+                    kept,
+                    dropped.join(", ")
+                ),
+            );
+        }
+        first
+    }
+
+    fn request_body(
+        &mut self,
+        body: &Value,
+        pointer: &str,
+        consumes: &mut Vec<String>,
+    ) -> Vec<Value> {
+        let Some(body) = self.component(body, "requestBodies") else {
+            self.dropped(pointer, "unresolved request bodies");
+            return Vec::new();
+        };
+        let Some(content) = body.get("content").and_then(Value::as_mapping) else {
+            return Vec::new();
+        };
+        let content_pointer = format!("{}/content", pointer);
+        let Some((media_type, media)) = self.pick_media_type(content, &content_pointer, consumes)
+        else {
+            return Vec::new();
+        };
+        let empty = Value::Mapping(Mapping::new());
+        let schema = media.get("schema").unwrap_or(&empty);
+        let schema_pointer = format!("{}/{}/schema", content_pointer, refs::escape(media_type));
+
+        if FORM_MEDIA_TYPES.contains(&media_type) {
+            return self.form_parameters(schema, &schema_pointer);
+        }
+
+        let mut param = Mapping::new();
+        param.insert("name".into(), "body".into());
+        param.insert("in".into(), "body".into());
+        for key in ["description", "required"] {
+            if let Some(value) = body.get(key) {
+                param.insert(key.into(), value.clone());
+            }
+        }
+        param.insert(
+            "schema".into(),
+            self.schema(schema.clone(), &schema_pointer),
+        );
+        vec![Value::Mapping(param)]
+    }
+
+    // `formData` parameters from the properties of a form body's object schema
+    fn form_parameters(&mut self, schema: &Value, pointer: &str) -> Vec<Value> {
+        let Some(schema) = self.component(schema, "schemas") else {
+            self.dropped(pointer, "unresolved form schemas");
+            return Vec::new();
+        };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let properties = schema.get("properties").and_then(Value::as_mapping);
+        let mut params = Vec::new();
+        for (name, property) in properties.into_iter().flatten() {
+            let Some(name) = name.as_str() else { continue };
+            let property_pointer = format!("{}/properties/{}", pointer, refs::escape(name));
+            let mut param = Mapping::new();
+            param.insert("name".into(), name.into());
+            param.insert("in".into(), "formData".into());
+            if let Some(description) = property.get("description") {
+                param.insert("description".into(), description.clone());
+            }
+            if required.contains(&name) {
+                param.insert("required".into(), true.into());
+            }
+            let is_binary = property.get("format").and_then(Value::as_str) == Some("binary");
+            if is_binary {
+                param.insert("type".into(), "file".into());
+            } else {
+                param.extend(self.simple_schema(property, &property_pointer));
+            }
+            params.push(Value::Mapping(param));
+        }
+        params
+    }
+
+    fn responses(&mut self, responses: Value, pointer: &str, produces: &mut Vec<String>) -> Value {
+        let Value::Mapping(responses) = responses else {
+            return responses;
+        };
+        let mut out = Mapping::new();
+        for (code, response) in responses {
+            let name = match &code {
+                Value::Number(n) => n.to_string(),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            let pointer = format!("{}/{}", pointer, refs::escape(&name));
+            // Referenced responses stay references, but still contribute their media types
+            if response.get("$ref").is_some() {
+                if let Some(content) = self
+                    .component(&response, "responses")
+                    .and_then(|r| r.get("content"))
+                    .and_then(Value::as_mapping)
+                {
+                    for media_type in content.keys().filter_map(Value::as_str) {
+                        if !produces.iter().any(|m| m == media_type) {
+                            produces.push(media_type.to_string());
+                        }
+                    }
+                }
+                out.insert(code, response);
+                continue;
+            }
+            out.insert(code, self.response(response, &pointer, produces));
+        }
+        Value::Mapping(out)
+    }
+
+    fn response(&mut self, response: Value, pointer: &str, produces: &mut Vec<String>) -> Value {
+        let Value::Mapping(response) = response else {
+            return response;
+        };
+        let mut out = Mapping::new();
+        for (key, value) in &response {
+            let name = key.as_str().unwrap_or_default();
+            let pointer = format!("{}/{}", pointer, name);
+            match name {
+                "content" => {
+                    let Some(content) = value.as_mapping() else {
+                        continue;
+                    };
+                    let Some((media_type, media)) =
+                        self.pick_media_type(content, &pointer, produces)
+                    else {
+                        continue;
+                    };
+                    let media_pointer = format!("{}/{}", pointer, refs::escape(media_type));
+                    if let Some(schema) = media.get("schema") {
+                        let schema_pointer = format!("{}/schema", media_pointer);
+                        out.insert(
+                            "schema".into(),
+                            self.schema(schema.clone(), &schema_pointer),
+                        );
+                    }
+                    if let Some(example) = media.get("example") {
+                        let mut examples = Mapping::new();
+                        examples.insert(media_type.into(), example.clone());
+                        out.insert("examples".into(), Value::Mapping(examples));
+                    }
+                }
+                "headers" => {
+                    let mut headers = Mapping::new();
+                    for (header, definition) in value.as_mapping().into_iter().flatten() {
+                        let header_pointer = format!(
+                            "{}/{}",
+                            pointer,
+                            refs::escape(header.as_str().unwrap_or_default())
+                        );
+                        let Some(definition) = self.component(definition, "headers") else {
+                            self.dropped(&header_pointer, "unresolved headers");
+                            continue;
+                        };
+                        let mut converted = Mapping::new();
+                        if let Some(description) = definition.get("description") {
+                            converted.insert("description".into(), description.clone());
+                        }
+                        let schema = definition.get("schema").unwrap_or(&Value::Null);
+                        converted.extend(self.simple_schema(schema, &header_pointer));
+                        headers.insert(header.clone(), Value::Mapping(converted));
+                    }
+                    out.insert(key.clone(), Value::Mapping(headers));
+                }
+                "links" => self.dropped(&pointer, "response links"),
+                _ => {
+                    out.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Value::Mapping(out)
+    }
+
+    fn parameters(&mut self, params: Value, pointer: &str) -> Value {
+        let Value::Sequence(params) = params else {
+            return params;
+        };
+        let converted = params
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, param)| self.parameter(param, &format!("{}/{}", pointer, i)));
+        Value::Sequence(converted.collect())
+    }
+
+    // Non-body parameter: the schema's keywords move onto the parameter itself
+    fn parameter(&mut self, param: Value, pointer: &str) -> Option<Value> {
+        let Value::Mapping(mut param) = param else {
+            return Some(param);
+        };
+        if param.contains_key("$ref") {
+            return Some(Value::Mapping(param));
+        }
+        if param.get("in").and_then(Value::as_str) == Some("cookie") {
+            self.dropped(pointer, "cookie parameters");
+            return None;
+        }
+        if let Some(content) = param.remove("content") {
+            self.dropped(&format!("{}/content", pointer), "parameter media types");
+            let schema = content
+                .as_mapping()
+                .and_then(|c| c.values().next())
+                .and_then(|media| media.get("schema"))
+                .cloned();
+            if let Some(schema) = schema {
+                param.insert("schema".into(), schema);
+            }
+        }
+        if let Some(schema) = param.remove("schema") {
+            let schema_pointer = format!("{}/schema", pointer);
+            param.extend(self.simple_schema(&schema, &schema_pointer));
+        }
+
+        let style = param.remove("style");
+        let explode = param.remove("explode").and_then(|e| e.as_bool());
+        if param.get("type").and_then(Value::as_str) == Some("array") {
+            let format = match (style.as_ref().and_then(Value::as_str), explode) {
+                (Some("spaceDelimited"), _) => Some("ssv"),
+                (Some("pipeDelimited"), _) => Some("pipes"),
+                (Some("form") | None, Some(false)) => Some("csv"),
+                (None | Some("form"), _)
+                    if param.get("in").and_then(Value::as_str) == Some("query") =>
+                {
+                    Some("multi")
+                }
+                (Some("deepObject"), _) => {
+                    self.dropped(pointer, "deepObject parameter styles");
+                    None
+                }
+                _ => None,
+            };
+            if let Some(format) = format {
+                param.insert("collectionFormat".into(), format.into());
+            }
+        }
+        if let Some(example) = param.remove("example") {
+            param.insert("x-example".into(), example);
+        }
+        param.remove("examples");
+        Some(Value::Mapping(param))
+    }
+
+    // Keywords for a non-body parameter, header or form field from `schema`
+    fn simple_schema(&mut self, schema: &Value, pointer: &str) -> Mapping {
+        let Some(schema) = self.component(schema, "schemas") else {
+            self.dropped(pointer, "unresolved parameter schemas");
+            return Mapping::new();
+        };
+        let converted = self.schema(schema.clone(), pointer);
+        let mut out = Mapping::new();
+        for key in SIMPLE_SCHEMA_KEYS.iter().chain(&["x-nullable"]) {
+            if let Some(value) = converted.get(*key) {
+                out.insert((*key).into(), value.clone());
+            }
+        }
+        match out.get("type").and_then(Value::as_str) {
+            Some("object") | None => {
+                self.warn(
+                    pointer,
+                    "Swagger 2.0 only allows primitive and array parameters; treated as string"
+                        .to_string(),
+                );
+                out.insert("type".into(), "string".into());
+            }
+            _ => {}
+        }
+        out
+    }
+
+    // JSON Schema differences between 3.x and 2.0, applied recursively
+    fn schema(&mut self, schema: Value, pointer: &str) -> Value {
+        let Value::Mapping(schema) = schema else {
+            return schema;
+        };
+        let mut out = Mapping::new();
+        for (key, value) in schema {
+            let name = key.as_str().unwrap_or_default().to_string();
+            let child = format!("{}/{}", pointer, refs::escape(&name));
+            match name.as_str() {
+                "nullable" => {
+                    out.insert("x-nullable".into(), value);
+                }
+                // 3.1: `type: [string, "null"]`
+                "type" if value.is_sequence() => {
+                    let types: Vec<&Value> = value.as_sequence().into_iter().flatten().collect();
+                    let non_null: Vec<&Value> = types
+                        .iter()
+                        .copied()
+                        .filter(|t| t.as_str() != Some("null"))
+                        .collect();
+                    if non_null.len() > 1 {
+                        self.dropped(&child, "multiple types");
+                    }
+                    if let Some(first) = non_null.first() {
+                        out.insert(key, (*first).clone());
+                    }
+                    if non_null.len() < types.len() {
+                        out.insert("x-nullable".into(), true.into());
+                    }
+                }
+                "const" => {
+                    out.insert("enum".into(), Value::Sequence(vec![value]));
+                }
+                "examples" => {
+                    if let Some(first) = value.as_sequence().and_then(|e| e.first()) {
+                        out.insert("example".into(), first.clone());
+                    }
+                }
+                "oneOf" | "anyOf" => {
+                    self.warn(
+                        &child,
+                        format!(
+                            "'{}' is not supported in Swagger 2.0; kept as 'x-{}'",
+                            name, name
+                        ),
+                    );
+                    out.insert(format!("x-{}", name).into(), value);
+                }
+                "not" | "writeOnly" => self.dropped(&child, &format!("'{}' keywords", name)),
+                "discriminator" => {
+                    let property = value.get("propertyName").cloned().unwrap_or(value);
+                    out.insert(key, property);
+                }
+                "properties" | "patternProperties" => {
+                    let Value::Mapping(properties) = value else {
+                        out.insert(key, value);
+                        continue;
+                    };
+                    let converted = properties.into_iter().map(|(prop, schema)| {
+                        let pointer = format!(
+                            "{}/{}",
+                            child,
+                            refs::escape(prop.as_str().unwrap_or_default())
+                        );
+                        let schema = self.schema(schema, &pointer);
+                        (prop, schema)
+                    });
+                    out.insert(key, Value::Mapping(converted.collect()));
+                }
+                "allOf" => {
+                    let Value::Sequence(parts) = value else {
+                        out.insert(key, value);
+                        continue;
+                    };
+                    let converted = parts
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, part)| self.schema(part, &format!("{}/{}", child, i)));
+                    out.insert(key, Value::Sequence(converted.collect()));
+                }
+                "items" | "additionalProperties" => {
+                    out.insert(key, self.schema(value, &child));
+                }
+                _ => {
+                    out.insert(key, value);
+                }
+            }
+        }
+        Value::Mapping(out)
+    }
+
+    fn security_scheme(&mut self, scheme: &Value, pointer: &str) -> Option<Value> {
+        let mut out = Mapping::new();
+        if let Some(description) = scheme.get("description") {
+            out.insert("description".into(), description.clone());
+        }
+        let field = |key: &str| scheme.get(key).and_then(Value::as_str);
+        match (field("type"), field("scheme"), field("in")) {
+            (Some("apiKey"), _, Some("cookie")) => {
+                self.dropped(pointer, "cookie API keys");
+                return None;
+            }
+            (Some("apiKey"), _, _) => return Some(scheme.clone()),
+            (Some("http"), Some(s), _) if s.eq_ignore_ascii_case("basic") => {
+                out.insert("type".into(), "basic".into());
+            }
+            (Some("http"), Some(s), _) if s.eq_ignore_ascii_case("bearer") => {
+                self.warn(
+                    pointer,
+                    "bearer authentication is written as an 'Authorization' header API key"
+                        .to_string(),
+                );
+                out.insert("type".into(), "apiKey".into());
+                out.insert("name".into(), "Authorization".into());
+                out.insert("in".into(), "header".into());
+            }
+            (Some("oauth2"), _, _) => {
+                let flows = scheme.get("flows").and_then(Value::as_mapping);
+                let mut flows = flows.into_iter().flatten();
+                let (flow, definition) = flows.next()?;
+                let rest: Vec<&str> = flows.filter_map(|(name, _)| name.as_str()).collect();
+                if !rest.is_empty() {
+                    self.dropped(
+                        pointer,
+                        &format!("additional OAuth2 flows ({})", rest.join(", ")),
+                    );
+                }
+                let flow = match flow.as_str() {
+                    Some("clientCredentials") => "application",
+                    Some("authorizationCode") => "accessCode",
+                    Some(other) => other,
+                    None => return None,
+                };
+                out.insert("type".into(), "oauth2".into());
+                out.insert("flow".into(), flow.into());
+                for key in ["authorizationUrl", "tokenUrl", "scopes"] {
+                    if let Some(value) = definition.get(key) {
+                        out.insert(key.into(), value.clone());
+                    }
+                }
+            }
+            _ => {
+                self.dropped(pointer, "security schemes of this type");
+                return None;
+            }
+        }
+        Some(Value::Mapping(out))
+    }
+
+    fn components(&mut self, out: &mut Mapping) {
+        let components = self.components;
+        for (kind, entries) in components.as_mapping().into_iter().flatten() {
+            let Some(kind) = kind.as_str() else { continue };
+            let Some(entries) = entries.as_mapping() else {
+                continue;
+            };
+            let target = match kind {
+                "schemas" => "definitions",
+                "parameters" => "parameters",
+                "responses" => "responses",
+                "securitySchemes" => "securityDefinitions",
+                // Inlined where they are used
+                "requestBodies" => continue,
+                _ => {
+                    if !entries.is_empty() {
+                        self.dropped(
+                            &format!("/components/{}", kind),
+                            &format!("'{}' components", kind),
+                        );
+                    }
+                    continue;
+                }
+            };
+            let mut converted = Mapping::new();
+            for (name, entry) in entries {
+                let pointer = format!(
+                    "/components/{}/{}",
+                    kind,
+                    refs::escape(name.as_str().unwrap_or_default())
+                );
+                let entry = match kind {
+                    "schemas" => Some(self.schema(entry.clone(), &pointer)),
+                    "parameters" => self.parameter(entry.clone(), &pointer),
+                    "responses" => Some(self.response(entry.clone(), &pointer, &mut Vec::new())),
+                    _ => self.security_scheme(entry, &pointer),
+                };
+                if let Some(entry) = entry {
+                    converted.insert(name.clone(), entry);
+                }
+            }
+            out.insert(target.into(), Value::Mapping(converted));
+        }
+    }
+}
+
+fn rewrite_v2_refs(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, val) in map.iter_mut() {
+                if key.as_str() == Some("$ref") {
+                    if let Value::String(target) = val {
+                        for (from, to) in V2_REF_PREFIXES {
+                            if let Some(rest) = target.strip_prefix(from) {
+                                *target = format!("{}{}", to, rest);
+                                break;
+                            }
+                        }
+                    }
+                } else {
+                    rewrite_v2_refs(val);
+                }
+            }
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(rewrite_v2_refs),
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,11 +1648,17 @@ mod tests {
             content: root.to_string(),
             file_path: std::path::PathBuf::from("root.yaml"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
         let frag_snippet = Snippet {
             content: fragment.to_string(),
             file_path: std::path::PathBuf::from("frag.yaml"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
 
         let result = merge_openapi(vec![root_snippet, frag_snippet]).unwrap();
@@ -144,6 +1676,9 @@ mod tests {
             content: fragment.to_string(),
             file_path: std::path::PathBuf::from("frag.yaml"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
         let res = merge_openapi(vec![snip]);
         assert!(matches!(res, Err(Error::NoRootFound)));
@@ -157,11 +1692,17 @@ mod tests {
             content: root1.to_string(),
             file_path: std::path::PathBuf::from("r1.yaml"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
         let s2 = Snippet {
             content: root2.to_string(),
             file_path: std::path::PathBuf::from("r2.yaml"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
 
         let res = merge_openapi(vec![s1, s2]);
@@ -175,6 +1716,9 @@ mod tests {
             content: bad_yaml.to_string(),
             file_path: std::path::PathBuf::from("bad.yaml"),
             line_number: 10,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
         let res = merge_openapi(vec![snippet]);
         match res {
@@ -192,6 +1736,466 @@ mod tests {
             _ => panic!("Expected SourceMapped error"),
         }
     }
+    #[test]
+    fn test_sort_document() {
+        let mut doc: Value = serde_yaml::from_str(
+            "components: {schemas: {b: {}, a: {}}}\npaths: {/z: {}, /a: {}}\nx-extra: 1\ninfo: {}\nopenapi: 3.0.0\n",
+        )
+        .unwrap();
+        sort_document(&mut doc);
+        let out = serde_yaml::to_string(&doc).unwrap();
+        let order = [
+            "openapi",
+            "info",
+            "paths",
+            "/a",
+            "/z",
+            "components",
+            "    a:",
+            "    b:",
+            "x-extra",
+        ];
+        let positions: Vec<_> = order.iter().map(|k| out.find(k).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", out);
+    }
+
+    // Paths and methods deliberately out of order; /health has no tags
+    const UNSHAPED_PATHS: &str = r#"
+tags: [{name: users}, {name: orders}]
+paths:
+  /users/{id}:
+    delete: {tags: [users]}
+    parameters: []
+    get: {tags: [users]}
+  /health:
+    get: {}
+  /orders:
+    post: {tags: [orders]}
+    get: {tags: [orders]}
+  /audit:
+    get: {tags: [admin]}
+  /users:
+    patch: {tags: [users]}
+    put: {tags: [users]}
+    post: {tags: [users]}
+"#;
+
+    fn shaped(operations: OperationOrder, grouping: PathGrouping) -> String {
+        let mut doc: Value = serde_yaml::from_str(UNSHAPED_PATHS).unwrap();
+        shape_paths(&mut doc, operations, grouping);
+        let mut out = String::new();
+        for (path, item) in doc["paths"].as_mapping().unwrap() {
+            let keys: Vec<_> = item
+                .as_mapping()
+                .unwrap()
+                .keys()
+                .map(|k| k.as_str().unwrap())
+                .collect();
+            out.push_str(&format!("{} {}\n", path.as_str().unwrap(), keys.join(",")));
+        }
+        out
+    }
+
+    #[test]
+    fn test_shape_paths_merge_order() {
+        assert_eq!(
+            shaped(OperationOrder::Merge, PathGrouping::Merge),
+            "/users/{id} delete,parameters,get\n/health get\n/orders post,get\n\
+             /audit get\n/users patch,put,post\n"
+        );
+    }
+
+    #[test]
+    fn test_shape_paths_method_order() {
+        assert_eq!(
+            shaped(OperationOrder::MethodOrder, PathGrouping::Merge),
+            "/users/{id} parameters,get,delete\n/health get\n/orders get,post\n\
+             /audit get\n/users post,put,patch\n"
+        );
+    }
+
+    #[test]
+    fn test_shape_paths_grouped_by_tag() {
+        assert_eq!(
+            shaped(OperationOrder::MethodOrder, PathGrouping::Tag),
+            "/users post,put,patch\n/users/{id} parameters,get,delete\n/orders get,post\n\
+             /audit get\n/health get\n"
+        );
+    }
+
+    #[test]
+    fn test_shape_paths_alpha() {
+        assert_eq!(
+            shaped(OperationOrder::Merge, PathGrouping::Alpha),
+            "/audit get\n/health get\n/orders post,get\n/users patch,put,post\n\
+             /users/{id} delete,parameters,get\n"
+        );
+    }
+
+    #[test]
+    fn test_override_files_merge_last() {
+        let snippet = |content: &str| Snippet {
+            content: content.to_string(),
+            file_path: std::path::PathBuf::from("a.yaml"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let snippets = vec![
+            snippet(
+                "x-oas-forge-overrides: true\ninfo: {title: Ignored}\npaths:\n  /a:\n    get:\n      summary: Better\n      tags: [b]\n      responses: {404: {description: Gone}}",
+            ),
+            snippet("openapi: 3.0.0\ninfo: {title: T}\npaths: {}"),
+            snippet(
+                "paths:\n  /a:\n    get:\n      summary: Old\n      tags: [a]\n      responses: {200: {description: ok}}",
+            ),
+        ];
+        let doc = merge_openapi(snippets).unwrap();
+        let get = &doc["paths"]["/a"]["get"];
+        assert_eq!(get["summary"].as_str(), Some("Better"));
+        assert_eq!(get["tags"], serde_yaml::from_str::<Value>("[b]").unwrap());
+        assert!(get["responses"].get(200).is_some());
+        assert!(get["responses"].get(404).is_some());
+        assert_eq!(doc["info"]["title"].as_str(), Some("T"));
+        assert!(doc.get(OVERRIDES_KEY).is_none());
+
+        let mut root: Value = serde_yaml::from_str("paths: {/a: {get: {summary: Old}}}").unwrap();
+        let layer =
+            serde_yaml::from_str("paths: {/a: {get: {summary: New, description: D}}}").unwrap();
+        let replaced = apply_override_file(&mut root, layer, "a.yaml");
+        assert_eq!(replaced, vec!["/paths/~1a/get/summary".to_string()]);
+    }
+
+    #[test]
+    fn test_nullable_to_null_type() {
+        let mut doc: Value = serde_yaml::from_str(
+            r##"
+name: {type: string, nullable: true}
+tags: {type: array, items: {type: string}, nullable: false}
+owner: {allOf: [{$ref: "#/components/schemas/User"}], nullable: true, description: Owner}
+mode: {type: string, enum: [a, b], nullable: true}
+mixed: {oneOf: [{type: string}, {type: integer}], nullable: true}
+flags: {properties: {nullable: {type: boolean}}}
+example: {nullable: true}
+"##,
+        )
+        .unwrap();
+        nullable_to_null_type(&mut doc);
+        let expected: Value = serde_yaml::from_str(
+            r##"
+name: {type: [string, "null"]}
+tags: {type: array, items: {type: string}}
+owner: {description: Owner, anyOf: [{$ref: "#/components/schemas/User"}, {type: "null"}]}
+mode: {type: [string, "null"], enum: [a, b, null]}
+mixed: {anyOf: [{oneOf: [{type: string}, {type: integer}]}, {type: "null"}]}
+flags: {properties: {nullable: {type: boolean}}}
+example: {nullable: true}
+"##,
+        )
+        .unwrap();
+        assert_eq!(doc, expected);
+    }
+
+    #[test]
+    fn test_exclusive_bounds_to_numbers() {
+        let mut doc: Value = serde_yaml::from_str(
+            r##"
+ratio: {type: number, minimum: 0, exclusiveMinimum: true, maximum: 1.5, exclusiveMaximum: true}
+count: {type: integer, minimum: 1, exclusiveMinimum: false, maximum: 9}
+done: {type: number, exclusiveMinimum: 0}
+flags: {properties: {exclusiveMinimum: {type: boolean}}}
+example: {minimum: 0, exclusiveMinimum: true}
+"##,
+        )
+        .unwrap();
+        exclusive_bounds_to_numbers(&mut doc);
+        let expected: Value = serde_yaml::from_str(
+            r##"
+ratio: {type: number, exclusiveMinimum: 0, exclusiveMaximum: 1.5}
+count: {type: integer, minimum: 1, maximum: 9}
+done: {type: number, exclusiveMinimum: 0}
+flags: {properties: {exclusiveMinimum: {type: boolean}}}
+example: {minimum: 0, exclusiveMinimum: true}
+"##,
+        )
+        .unwrap();
+        assert_eq!(doc, expected);
+    }
+
+    #[test]
+    fn test_source_annotations() {
+        let snippet = |content: &str, file: &str, line| Snippet {
+            content: content.to_string(),
+            file_path: std::path::PathBuf::from(file),
+            line_number: line,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let snippets = || {
+            vec![
+                snippet(
+                    "openapi: 3.0.0\ninfo: {title: T}\npaths: {/a: {}}",
+                    "src/lib.rs",
+                    1,
+                ),
+                snippet("paths: {/a: {post: {}}, /b: {get: {}}}", "src/b.rs", 42),
+                snippet(
+                    "components: {schemas: {User: {type: object}}}",
+                    "src/user.rs",
+                    7,
+                ),
+            ]
+        };
+
+        let doc = merge_openapi_with(snippets(), true).unwrap();
+        assert_eq!(doc["paths"]["/a"][SOURCE_KEY], "src/lib.rs:1");
+        assert_eq!(doc["paths"]["/b"][SOURCE_KEY], "src/b.rs:42");
+        assert_eq!(
+            doc["components"]["schemas"]["User"][SOURCE_KEY],
+            "src/user.rs:7"
+        );
+
+        let plain = serde_yaml::to_string(&merge_openapi(snippets()).unwrap()).unwrap();
+        assert!(!plain.contains(SOURCE_KEY));
+    }
+
+    #[test]
+    fn test_merge_replace_marker() {
+        let snippet = |content: &str| Snippet {
+            content: content.to_string(),
+            file_path: std::path::PathBuf::from("test.yaml"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let doc = merge_openapi(vec![
+            snippet(
+                "openapi: 3.0.0\ninfo: {title: T}\nservers: [{url: a}, {url: b}]\n\
+                 components: {schemas: {User: {type: object, required: [id]}}}",
+            ),
+            snippet("servers: [{x-oas-forge-merge: replace}, {url: c}]"),
+            snippet("components: {schemas: {User: {x-oas-forge-merge: replace, type: string}}}"),
+            snippet("paths: {/a: {x-oas-forge-merge: replace, get: {}}}"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            doc["servers"],
+            serde_yaml::from_str::<Value>("[{url: c}]").unwrap()
+        );
+        assert_eq!(
+            doc["components"]["schemas"]["User"],
+            serde_yaml::from_str::<Value>("{type: string}").unwrap()
+        );
+        let out = serde_yaml::to_string(&doc).unwrap();
+        assert!(!out.contains(MERGE_KEY), "{}", out);
+    }
+
+    fn schema_snippets(schemas: &[(&str, &str)]) -> Vec<Snippet> {
+        schemas
+            .iter()
+            .map(|(file, content)| Snippet {
+                content: content.to_string(),
+                file_path: std::path::PathBuf::from(file),
+                line_number: 3,
+                module: String::new(),
+                line_map: None,
+                priority: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_conflicts() {
+        let root = ("root.yaml", "openapi: 3.0.0\ninfo: {title: T}");
+        let identical = schema_snippets(&[
+            root,
+            ("a.rs", "components: {schemas: {User: {type: object}}}"),
+            ("b.rs", "components: {schemas: {User: {type: object}}}"),
+        ]);
+        let doc = merge_openapi_with(identical, true).unwrap();
+        assert_eq!(doc["components"]["schemas"]["User"][SOURCE_KEY], "a.rs:3");
+
+        let conflicting = || {
+            schema_snippets(&[
+                root,
+                ("a.rs", "components: {schemas: {User: {type: object}}}"),
+                ("b.rs", "components: {schemas: {User: {type: string}}}"),
+            ])
+        };
+        let err = merge_openapi(conflicting()).unwrap_err();
+        assert_eq!(err.code(), "E0032");
+        assert_eq!(
+            err.to_string(),
+            "Conflicting definitions of schema 'User' in a.rs:3 and b.rs:3"
+        );
+
+        let options = MergeOptions {
+            on_conflict: OnConflict::Last,
+            ..Default::default()
+        };
+        let doc = merge_openapi_with_options(conflicting(), options).unwrap();
+        assert_eq!(
+            doc["components"]["schemas"]["User"],
+            serde_yaml::from_str::<Value>("{type: string}").unwrap()
+        );
+        assert_eq!("last".parse::<OnConflict>(), Ok(OnConflict::Last));
+        assert!("first".parse::<OnConflict>().is_err());
+    }
+
+    fn merged(snippets: &[&str]) -> Value {
+        let snippets = snippets
+            .iter()
+            .map(|content| Snippet {
+                content: content.to_string(),
+                file_path: std::path::PathBuf::from("test.yaml"),
+                line_number: 1,
+                module: String::new(),
+                line_map: None,
+                priority: 0,
+            })
+            .collect();
+        merge_openapi(snippets).unwrap()
+    }
+
+    #[test]
+    fn test_merge_parameters_by_name_and_location() {
+        let doc = merged(&[
+            "openapi: 3.0.0\ninfo: {title: T}\npaths:\n  /u/{id}:\n    parameters:\n      - {name: id, in: path, required: true}\n      - {name: id, in: query}",
+            "paths:\n  /u/{id}:\n    parameters:\n      - {name: id, in: path, description: User id}\n      - $ref: '#/components/parameters/Page'",
+        ]);
+        let params = doc["paths"]["/u/{id}"]["parameters"].as_sequence().unwrap();
+        assert_eq!(params.len(), 3);
+        assert_eq!(
+            params[0],
+            serde_yaml::from_str::<Value>(
+                "{name: id, in: path, required: true, description: User id}"
+            )
+            .unwrap()
+        );
+        assert_eq!(params[1]["in"].as_str(), Some("query"));
+    }
+
+    #[test]
+    fn test_merge_tags_by_name() {
+        let doc = merged(&[
+            "openapi: 3.0.0\ninfo: {title: T}\ntags: [{name: Users}]\npaths: {/a: {get: {tags: [Users]}}}",
+            "tags: [{name: Users, description: Accounts}, {name: Orders}]\npaths: {/a: {get: {tags: [Users, Admin]}}}",
+        ]);
+        assert_eq!(
+            doc["tags"],
+            serde_yaml::from_str::<Value>("[{name: Users, description: Accounts}, {name: Orders}]")
+                .unwrap()
+        );
+        assert_eq!(
+            doc["paths"]["/a"]["get"]["tags"],
+            serde_yaml::from_str::<Value>("[Users, Admin]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_servers_by_url() {
+        let doc = merged(&[
+            "openapi: 3.0.0\ninfo: {title: T}\nservers: [{url: 'https://a'}]",
+            "servers: [{url: 'https://a', description: Production}, {url: 'https://b'}]",
+        ]);
+        assert_eq!(
+            doc["servers"],
+            serde_yaml::from_str::<Value>(
+                "[{url: 'https://a', description: Production}, {url: 'https://b'}]"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut doc: Value = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: T, description: null}
+x-oas-forge-fragments: {}
+paths:
+  /a:
+    x-oas-forge-source: src/a.rs:1
+    get:
+      summary: null
+      parameters: []
+      tags: []
+      x-openapi-extend: Base
+      responses:
+        '200':
+          description: OK
+          content:
+            application/json:
+              example: {next: null}
+components:
+  schemas:
+    User: {type: object, default: null}
+"#,
+        )
+        .unwrap();
+        let dirty = doc.clone();
+
+        sanitize(&mut doc, SanitizeOptions::default());
+        let expected: Value = serde_yaml::from_str(
+            r#"
+openapi: 3.0.0
+info: {title: T}
+paths:
+  /a:
+    get:
+      responses:
+        '200':
+          description: OK
+          content:
+            application/json:
+              example: {next: null}
+components:
+  schemas:
+    User: {type: object}
+"#,
+        )
+        .unwrap();
+        assert_eq!(doc, expected);
+
+        let mut doc = dirty;
+        sanitize(
+            &mut doc,
+            SanitizeOptions {
+                keep_nulls: true,
+                keep_sources: true,
+            },
+        );
+        assert_eq!(doc["paths"]["/a"][SOURCE_KEY].as_str(), Some("src/a.rs:1"));
+        assert!(doc["info"]["description"].is_null());
+        assert!(doc["info"].get("description").is_some());
+        assert!(doc.get("x-oas-forge-fragments").is_none());
+    }
+
+    #[test]
+    fn test_merge_priority() {
+        let snippet = |content: &str, priority| Snippet {
+            content: content.to_string(),
+            file_path: std::path::PathBuf::from("test.yaml"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority,
+        };
+        let doc = merge_openapi(vec![
+            snippet("info: {version: from-high}", 10),
+            snippet("openapi: 3.0.0\ninfo: {title: T, version: root}", 0),
+            snippet("info: {version: from-default}", 0),
+            snippet("info: {version: from-low}", -5),
+        ])
+        .unwrap();
+        assert_eq!(doc["info"]["version"].as_str(), Some("from-high"));
+    }
+
     #[test]
     fn test_merge_dedup() {
         // merge_openapi expects root detection (openapi/info).
@@ -211,11 +2215,17 @@ mod tests {
             content: root_full.to_string(),
             file_path: std::path::PathBuf::from("r"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
         let f_snip = Snippet {
             content: frag_full.to_string(),
             file_path: std::path::PathBuf::from("f"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
 
         let res = merge_openapi(vec![r_snip, f_snip]).unwrap();
@@ -229,4 +2239,116 @@ mod tests {
         assert!(yaml.contains("A"));
         assert!(yaml.contains("C"));
     }
+
+    #[test]
+    fn test_downconvert_v2() {
+        let doc: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.0.3
+info: {title: Shop, version: "1"}
+servers:
+  - url: https://api.example.com/v1/
+  - url: http://localhost:8080
+paths:
+  /users/{id}:
+    put:
+      operationId: updateUser
+      parameters:
+        - {name: id, in: path, required: true, schema: {type: integer, format: int64}}
+        - {name: tags, in: query, schema: {type: array, items: {type: string}}}
+        - {name: session, in: cookie, schema: {type: string}}
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: {$ref: "#/components/schemas/User"}
+          application/xml:
+            schema: {$ref: "#/components/schemas/UserXml"}
+      responses:
+        '200':
+          description: Updated
+          content:
+            application/json:
+              schema: {$ref: "#/components/schemas/User"}
+        '404': {$ref: "#/components/responses/NotFound"}
+      callbacks:
+        onUpdate: {}
+components:
+  schemas:
+    User:
+      type: object
+      properties:
+        name: {type: string, nullable: true}
+    UserXml: {type: object}
+  responses:
+    NotFound:
+      description: Missing
+      content:
+        application/problem+json:
+          schema: {type: object}
+  securitySchemes:
+    token: {type: http, scheme: basic}
+"##,
+        )
+        .unwrap();
+
+        let mut warnings = Vec::new();
+        let v2 = downconvert_v2_with(doc, &mut warnings).unwrap();
+        let expected: Value = serde_yaml::from_str(
+            r##"
+swagger: "2.0"
+info: {title: Shop, version: "1"}
+host: api.example.com
+basePath: /v1
+schemes: [https]
+paths:
+  /users/{id}:
+    put:
+      operationId: updateUser
+      parameters:
+        - {name: id, in: path, required: true, type: integer, format: int64}
+        - {name: tags, in: query, type: array, items: {type: string}, collectionFormat: multi}
+        - name: body
+          in: body
+          required: true
+          schema: {$ref: "#/definitions/User"}
+      responses:
+        '200':
+          description: Updated
+          schema: {$ref: "#/definitions/User"}
+        '404': {$ref: "#/responses/NotFound"}
+      consumes: [application/json, application/xml]
+      produces: [application/json, application/problem+json]
+definitions:
+  User:
+    type: object
+    properties:
+      name: {type: string, x-nullable: true}
+  UserXml: {type: object}
+responses:
+  NotFound:
+    description: Missing
+    schema: {type: object}
+securityDefinitions:
+  token: {type: basic}
+"##,
+        )
+        .unwrap();
+        assert_eq!(v2, expected);
+
+        let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings.len(), 4, "{:#?}", warnings);
+        assert!(warnings[0].starts_with("#/servers: "));
+        assert!(warnings[1].starts_with("#/paths/~1users~1{id}/put/parameters/2: cookie"));
+        assert!(
+            warnings[2].contains("kept 'application/json', dropped the schemas of application/xml")
+        );
+        assert!(warnings[3].starts_with("#/paths/~1users~1{id}/put/callbacks: callbacks"));
+    }
+
+    #[test]
+    fn test_downconvert_v2_requires_openapi_3() {
+        let doc: Value = serde_yaml::from_str("swagger: '2.0'\ninfo: {}").unwrap();
+        assert!(matches!(downconvert_v2(doc), Err(Error::Downconvert(_))));
+    }
 }
@@ -0,0 +1,152 @@
+use crate::diagnostics::Diagnostic;
+use crate::error::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A file written by a generation run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WrittenFile {
+    pub path: PathBuf,
+    /// `false` if the file already had this content and was left untouched
+    pub changed: bool,
+}
+
+/// Writes `content` to `path`, see [`write_atomic_with`].
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<bool> {
+    write_atomic_with(path, |out| Ok(out.write_all(content)?))
+}
+
+/// Writes `path` through a temporary file in the same directory that is renamed over it
+/// once `write` succeeded, so a failure never leaves a partial file behind.
+///
+/// When the new content equals the existing file, the file (and its mtime) is left
+/// untouched and `false` is returned.
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<bool> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let written = File::create(&temp)
+        .map_err(Into::into)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(std::fs::read(&temp)?));
+    let content = match written {
+        Ok(content) => content,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+    };
+
+    if std::fs::read(path).is_ok_and(|existing| existing == content) {
+        std::fs::remove_file(&temp)?;
+        log::info!("{:?} unchanged", path);
+        return Ok(false);
+    }
+    if let Err(e) = std::fs::rename(&temp, path) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(true)
+}
+
+/// Writes the instructions a build script prints for cargo: `rerun-if-changed` for each
+/// of `files` and a `warning` per diagnostic line.
+pub fn write_cargo_instructions(
+    out: &mut dyn Write,
+    files: &[PathBuf],
+    diagnostics: &[Diagnostic],
+) -> std::io::Result<()> {
+    for file in files {
+        writeln!(out, "cargo:rerun-if-changed={}", file.display())?;
+    }
+    for diagnostic in diagnostics {
+        for line in diagnostic.to_string().lines() {
+            writeln!(out, "cargo:warning={}", line)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_unchanged_content_is_left_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("openapi.yaml");
+
+        assert!(write_atomic(&path, b"a: 1\n").unwrap());
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        assert!(!write_atomic(&path, b"a: 1\n").unwrap());
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), old);
+
+        assert!(write_atomic(&path, b"a: 2\n").unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a: 2\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_no_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("openapi.yaml");
+        std::fs::write(&path, "complete: true\n").unwrap();
+
+        let result = write_atomic_with(&path, |out| {
+            out.write_all(b"compl")?;
+            Err(Error::Downconvert("injected".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "complete: true\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let missing = dir.path().join("new.yaml");
+        assert!(write_atomic_with(&missing, |_| Err(Error::NoFilesFound(Vec::new()))).is_err());
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn test_cargo_instructions() {
+        let mut out = Vec::new();
+        write_cargo_instructions(
+            &mut out,
+            &[PathBuf::from("src"), PathBuf::from("src/lib.rs")],
+            &[Diagnostic::warning(
+                "unknown fragment\nsecond line",
+                PathBuf::from("src/lib.rs"),
+                3,
+            )],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "cargo:rerun-if-changed=src\n\
+             cargo:rerun-if-changed=src/lib.rs\n\
+             cargo:warning=src/lib.rs:3: unknown fragment\n\
+             cargo:warning=second line\n"
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::index::Registry;
+use crate::diagnostics::Issue;
+use crate::index::{Fragment, Registry};
 use regex::Regex;
 use std::sync::OnceLock;
 
@@ -6,9 +7,112 @@ static INSERT_RE: OnceLock<Regex> = OnceLock::new();
 static EXTEND_RE: OnceLock<Regex> = OnceLock::new();
 
 /// Pre-processes a snippet by expanding @insert and @extend directives.
+/// Missing fragments are logged as warnings.
 pub fn preprocess(content: &str, registry: &Registry) -> String {
-    let lines: Vec<&str> = content.lines().collect();
+    let mut issues = Vec::new();
+    let output = expand(content, registry, &mut issues);
+    for issue in issues {
+        log::warn!("{}", issue);
+    }
+    output
+}
+
+/// Maximum nesting of fragments inside fragments.
+pub const MAX_FRAGMENT_DEPTH: usize = 32;
+
+/// Like [`preprocess`], but records problems in `issues` instead of logging them.
+pub fn expand(content: &str, registry: &Registry, issues: &mut Vec<Issue>) -> String {
+    expand_mapped(content, registry, issues).content
+}
+
+/// Output of [`expand_mapped`].
+#[derive(Debug, Clone, Default)]
+pub struct Expanded {
+    pub content: String,
+    /// For each line of `content`, the index of the input line it came from
+    pub line_map: Vec<usize>,
+    /// Number of fragments inserted or extended, nested ones included
+    pub fragments: usize,
+}
+
+/// Like [`expand`], also tracking where each output line came from. Lines of an
+/// inserted fragment point at the `@insert` line.
+pub fn expand_mapped(content: &str, registry: &Registry, issues: &mut Vec<Issue>) -> Expanded {
+    let mut stack = FragmentStack::default();
+    let (phase_a_output, line_map) = expand_text(content, registry, issues, &mut stack);
+
+    // Phase B: Structural Merge
+    // Try to parse as YAML Value. If fails, return textual output (fallback).
+    let (content, line_map) = match serde_yaml::from_str::<serde_yaml::Value>(&phase_a_output) {
+        Ok(mut root) => {
+            process_value(&mut root, registry, issues, &mut stack);
+            match serde_yaml::to_string(&root) {
+                // Re-serialized text no longer lines up with the input; point at its start
+                Ok(output) => {
+                    let line_map = vec![0; output.lines().count()];
+                    (output, line_map)
+                }
+                Err(_) => (phase_a_output, line_map),
+            }
+        }
+        Err(_) => {
+            // Likely a partial snippet (list item or partial object).
+            // Return text, but @extend markers are present.
+            // If it's a snippet, @extend might not work fully structurally.
+            // For now, we return phase_a_output.
+            // (User Note: Snippet must be valid YAML for @extend to work structurally)
+            (phase_a_output, line_map)
+        }
+    };
+    Expanded {
+        content,
+        line_map,
+        fragments: stack.entered,
+    }
+}
+
+// Fragments currently being expanded (innermost last).
+#[derive(Default)]
+struct FragmentStack {
+    names: Vec<String>,
+    /// Expansions started so far
+    entered: usize,
+}
+
+impl FragmentStack {
+    fn pop(&mut self) {
+        self.names.pop();
+    }
+}
+
+// Pushes `name` onto the expansion stack, refusing cycles and runaway nesting.
+fn enter_fragment(name: &str, stack: &mut FragmentStack, issues: &mut Vec<Issue>) -> bool {
+    if stack.names.iter().any(|s| s == name) {
+        let mut path = stack.names.clone();
+        path.push(name.to_string());
+        issues.push(Issue::FragmentCycle(path));
+        return false;
+    }
+    if stack.names.len() >= MAX_FRAGMENT_DEPTH {
+        issues.push(Issue::FragmentDepth(MAX_FRAGMENT_DEPTH));
+        return false;
+    }
+    stack.names.push(name.to_string());
+    stack.entered += 1;
+    true
+}
+
+// Phase A: Textual Preparation
+// @insert -> text injection (recursively expanded)
+// @extend -> x-openapi-extend injection
+fn expand_text(
+    content: &str,
+    registry: &Registry,
+    issues: &mut Vec<Issue>,
+    stack: &mut FragmentStack,
+) -> (String, Vec<usize>) {
     let mut new_lines = Vec::new();
+    let mut line_map = Vec::new();
 
     // Initialize Regexes once
     // Support optional args: @insert Name OR @insert Name(args)
@@ -18,38 +122,20 @@ pub fn preprocess(content: &str, registry: &Registry) -> String {
     let extend_re =
         EXTEND_RE.get_or_init(|| Regex::new(r"@extend\s+([a-zA-Z0-9_]+)(?:\((.*)\))?").unwrap());
 
-    // Helper to parse args from regex capture
-    fn parse_args_from_caps(args_str: Option<regex::Match>) -> Vec<String> {
-        match args_str {
-            Some(m) => {
-                let s = m.as_str();
-                if s.trim().is_empty() {
-                    Vec::new()
-                } else {
-                    s.split(',')
-                        .map(|x| x.trim().trim_matches('"').to_string())
-                        .collect()
-                }
-            }
-            None => Vec::new(),
-        }
-    }
-
-    // Phase A: Textual Preparation
-    // @insert -> text injection
-    // @extend -> x-openapi-extend injection
-
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i];
-
+    for (idx, line) in content.lines().enumerate() {
         if let Some(caps) = insert_re.captures(line) {
             // @insert logic (Textual)
             let name = caps.get(1).unwrap().as_str();
-            let args = parse_args_from_caps(caps.get(2));
+            let args = parse_args(caps.get(2).map_or("", |m| m.as_str()));
 
             if let Some(fragment) = registry.fragments.get(name) {
-                let expanded = substitute_fragment_args(&fragment.body, &fragment.params, &args);
+                if !enter_fragment(name, stack, issues) {
+                    continue;
+                }
+                let substituted = substitute_fragment_args(name, fragment, &args, issues);
+                let (expanded, _) = expand_text(&substituted, registry, issues, stack);
+                stack.pop();
+
                 let indent = line
                     .chars()
                     .take_while(|c| c.is_whitespace())
@@ -60,7 +146,7 @@ pub fn preprocess(content: &str, registry: &Registry) -> String {
                     }
                 }
             } else {
-                log::warn!("Fragment '{}' not found for @insert", name);
+                issues.push(Issue::FragmentNotFound(name.to_string()));
                 new_lines.push(line.to_string());
             }
         } else if let Some(caps) = extend_re.captures(line) {
@@ -68,8 +154,6 @@ pub fn preprocess(content: &str, registry: &Registry) -> String {
             let name = caps.get(1).unwrap().as_str();
             let args_raw = caps.get(2).map(|m| m.as_str()).unwrap_or("");
 
-            // We preserve indentation and inject a special key.
-            // x-openapi-extend: "Name(arg1, arg2)"
             let indent = line
                 .chars()
                 .take_while(|c| c.is_whitespace())
@@ -80,80 +164,124 @@ pub fn preprocess(content: &str, registry: &Registry) -> String {
             } else {
                 format!("{}({})", name, args_raw)
             };
-            new_lines.push(format!("{}x-openapi-extend: \"{}\"", indent, marker_val));
+            let at = push_extend_marker(&mut new_lines, &indent, &marker_val);
+            // Joined an earlier marker: the lines after it moved down by one
+            if at < line_map.len() {
+                line_map.insert(at, idx);
+            }
         } else {
             new_lines.push(line.to_string());
         }
-        i += 1;
+        line_map.resize(new_lines.len(), idx);
     }
 
-    let phase_a_output = new_lines.join("\n");
+    (new_lines.join("\n"), line_map)
+}
 
-    // Phase B: Structural Merge
-    // Try to parse as YAML Value. If fails, return textual output (fallback).
-    match serde_yaml::from_str::<serde_yaml::Value>(&phase_a_output) {
-        Ok(mut root) => {
-            process_value(&mut root, registry);
-            serde_yaml::to_string(&root).unwrap_or(phase_a_output)
+const EXTEND_KEY: &str = "x-openapi-extend";
+
+/// Emits the `x-openapi-extend` marker for `@extend value` at `indent`.
+///
+/// The marker is a sequence, so every `@extend` line on the same mapping joins the
+/// marker already emitted for it, even with sibling keys in between, instead of
+/// producing a duplicate key:
+/// ```yaml
+/// x-openapi-extend:
+///   - 'Base'
+///   - 'Auditing(x)'
+/// ```
+/// Returns the index of the line the item was written to, for callers keeping a line map.
+pub fn push_extend_marker(lines: &mut Vec<String>, indent: &str, value: &str) -> usize {
+    let item = format!("{}  - '{}'", indent, value.replace('\'', "''"));
+    let item_prefix = format!("{}  - ", indent);
+    let header = format!("{}{}:", indent, EXTEND_KEY);
+
+    // Back to the key owning the mapping at this indent, looking for its marker
+    let mut marker = None;
+    for (i, line) in lines.iter().enumerate().rev() {
+        if line.trim().is_empty() {
+            continue;
         }
-        Err(_) => {
-            // Likely a partial snippet (list item or partial object).
-            // Return text, but @extend markers are present.
-            // If it's a snippet, @extend might not work fully structurally.
-            // For now, we return phase_a_output.
-            // (User Note: Snippet must be valid YAML for @extend to work structurally)
-            phase_a_output
+        let depth = line.len() - line.trim_start().len();
+        if depth < indent.len() {
+            break;
+        }
+        if depth == indent.len() && *line == header {
+            marker = Some(i);
+            break;
+        }
+    }
+    match marker {
+        Some(i) => {
+            let mut at = i + 1;
+            while lines.get(at).is_some_and(|l| l.starts_with(&item_prefix)) {
+                at += 1;
+            }
+            lines.insert(at, item);
+            at
+        }
+        None => {
+            lines.push(header);
+            lines.push(item);
+            lines.len() - 1
         }
     }
 }
 
-fn process_value(val: &mut serde_yaml::Value, registry: &Registry) {
+fn process_value(
+    val: &mut serde_yaml::Value,
+    registry: &Registry,
+    issues: &mut Vec<Issue>,
+    stack: &mut FragmentStack,
+) {
     if let serde_yaml::Value::Mapping(map) = val {
-        // Check for x-openapi-extend
-        let extend_key = serde_yaml::Value::String("x-openapi-extend".to_string());
+        let mut fragments_to_merge = Vec::new();
 
-        let mut fragment_to_merge = None;
-
-        if let Some(extend_val) = map.remove(&extend_key) {
-            if let Some(extend_str) = extend_val.as_str() {
-                fragment_to_merge = Some(extend_str.to_string());
+        if let Some(extend_val) = map.remove(EXTEND_KEY) {
+            match extend_val {
+                serde_yaml::Value::String(extend_str) => fragments_to_merge.push(extend_str),
+                serde_yaml::Value::Sequence(items) => fragments_to_merge.extend(
+                    items
+                        .into_iter()
+                        .filter_map(|v| v.as_str().map(String::from)),
+                ),
+                _ => {}
             }
         }
 
-        // Recurse children FIRST? or merge first?
-        // Merge first so we can process children of merged result?
-        // Or Process children then merge?
-        // Usually merge first adds new keys, which might need processing.
-        // But fragments are already "raw".
-        // Let's merge first.
-
-        if let Some(extend_str) = fragment_to_merge {
+        // Merge first: each fragment is fully expanded (its own @insert/@extend included)
+        // before it is merged, so the children below only hold resolved content.
+        // Declaration order, so later fragments override earlier ones.
+        for extend_str in fragments_to_merge {
             // Parse "Name(args)"
-            // reuse parse logic? We need simple parse here.
             let (name, args) = parse_extend_str(&extend_str);
 
             if let Some(fragment) = registry.fragments.get(&name) {
-                let expanded = substitute_fragment_args(&fragment.body, &fragment.params, &args);
-                if let Ok(frag_val) = serde_yaml::from_str::<serde_yaml::Value>(&expanded) {
-                    merge_values(val, frag_val);
-                } else {
-                    log::warn!("Fragment '{}' body is not valid YAML", name);
+                if enter_fragment(&name, stack, issues) {
+                    let substituted = substitute_fragment_args(&name, fragment, &args, issues);
+                    let (expanded, _) = expand_text(&substituted, registry, issues, stack);
+                    if let Ok(mut frag_val) = serde_yaml::from_str::<serde_yaml::Value>(&expanded) {
+                        process_value(&mut frag_val, registry, issues, stack);
+                        merge_values(val, frag_val);
+                    } else {
+                        log::warn!("Fragment '{}' body is not valid YAML", name);
+                    }
+                    stack.pop();
                 }
             } else {
-                log::warn!("Fragment '{}' not found for @extend", name);
+                issues.push(Issue::FragmentNotFound(name));
             }
         }
 
         // Recurse (re-borrow map after modification)
-        // Check new keys too.
         if let serde_yaml::Value::Mapping(map) = val {
             for (_, v) in map {
-                process_value(v, registry);
+                process_value(v, registry, issues, stack);
             }
         }
     } else if let serde_yaml::Value::Sequence(seq) = val {
         for v in seq {
-            process_value(v, registry);
+            process_value(v, registry, issues, stack);
         }
     }
 }
@@ -175,31 +303,130 @@ fn merge_values(target: &mut serde_yaml::Value, source: serde_yaml::Value) {
     }
 }
 
-fn parse_extend_str(s: &str) -> (String, Vec<String>) {
+fn parse_extend_str(s: &str) -> (String, Vec<FragmentArg>) {
     if let Some(idx) = s.find('(') {
         let name = s[..idx].trim().to_string();
-        let args_str = s[idx + 1..].trim_end_matches(')');
-        let args = if args_str.trim().is_empty() {
-            Vec::new()
-        } else {
-            args_str
-                .split(',')
-                .map(|x| x.trim().trim_matches('"').to_string())
-                .collect()
-        };
-        (name, args)
+        let args_str = s[idx + 1..]
+            .trim_end()
+            .strip_suffix(')')
+            .unwrap_or(&s[idx + 1..]);
+        (name, parse_args(args_str))
     } else {
         (s.trim().to_string(), Vec::new())
     }
 }
 
-// Helper to substitute named args {{param}} in fragment
-fn substitute_fragment_args(fragment: &str, params: &[String], args: &[String]) -> String {
-    let mut result = fragment.to_string();
-    for (i, param) in params.iter().enumerate() {
-        if let Some(arg) = args.get(i) {
-            let placeholder = format!("{{{{{}}}}}", param); // {{param}}
-            result = result.replace(&placeholder, arg);
+/// One argument of `@insert Name(...)` / `@extend Name(...)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FragmentArg {
+    Positional(String),
+    /// `key = value`
+    Named(String, String),
+}
+
+/// Splits `a, "b, c", d` on top-level commas, keeping quoted sections intact.
+pub fn split_args(s: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    if parts.len() == 1 && parts[0].trim().is_empty() {
+        return Vec::new();
+    }
+    parts.into_iter().map(|p| p.trim().to_string()).collect()
+}
+
+// Strips surrounding quotes and unescapes \" inside them
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\""),
+        None => s.to_string(),
+    }
+}
+
+/// Parses the argument list of a fragment call into positional and `key = value` args.
+pub fn parse_args(s: &str) -> Vec<FragmentArg> {
+    split_args(s)
+        .into_iter()
+        .map(|part| {
+            if !part.starts_with('"') {
+                if let Some((key, value)) = part.split_once('=') {
+                    let key = key.trim();
+                    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        return FragmentArg::Named(key.to_string(), unquote(value));
+                    }
+                }
+            }
+            FragmentArg::Positional(unquote(&part))
+        })
+        .collect()
+}
+
+// Helper to substitute named args {{param}} in fragment, falling back to declared defaults
+fn substitute_fragment_args(
+    name: &str,
+    fragment: &Fragment,
+    args: &[FragmentArg],
+    issues: &mut Vec<Issue>,
+) -> String {
+    let mut values: Vec<Option<&String>> = vec![None; fragment.params.len()];
+    let named = args.iter().any(|a| matches!(a, FragmentArg::Named(..)));
+    let positional = args.iter().any(|a| matches!(a, FragmentArg::Positional(_)));
+    if named && positional {
+        issues.push(Issue::FragmentArgs {
+            fragment: name.to_string(),
+            message: "named and positional arguments cannot be mixed".to_string(),
+        });
+        return fragment.body.clone();
+    }
+
+    for (i, arg) in args.iter().enumerate() {
+        match arg {
+            FragmentArg::Positional(value) => {
+                if let Some(slot) = values.get_mut(i) {
+                    *slot = Some(value);
+                }
+            }
+            FragmentArg::Named(key, value) => match fragment.params.iter().position(|p| p == key) {
+                Some(idx) => values[idx] = Some(value),
+                None => issues.push(Issue::FragmentArgs {
+                    fragment: name.to_string(),
+                    message: format!("unknown parameter '{}'", key),
+                }),
+            },
+        }
+    }
+
+    let mut result = fragment.body.clone();
+    for (param, value) in fragment.params.iter().zip(values) {
+        match value.or_else(|| fragment.defaults.get(param)) {
+            Some(value) => {
+                let placeholder = format!("{{{{{}}}}}", param); // {{param}}
+                result = result.replace(&placeholder, value);
+            }
+            None => issues.push(Issue::MissingFragmentArg {
+                fragment: name.to_string(),
+                param: param.clone(),
+            }),
         }
     }
     result
@@ -209,6 +436,295 @@ fn substitute_fragment_args(fragment: &str, params: &[String], args: &[String])
 mod tests {
     use super::*;
 
+    fn registry_with_default() -> Registry {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "CommonError".to_string(),
+            vec!["code".to_string(), "hint".to_string()],
+            "description: Error {{code}}\nx-hint: {{hint}}".to_string(),
+        );
+        registry
+            .fragments
+            .get_mut("CommonError")
+            .unwrap()
+            .defaults
+            .insert("code".to_string(), "Internal Error".to_string());
+        registry
+    }
+
+    #[test]
+    fn test_fragment_default_used() {
+        let registry = registry_with_default();
+        let mut issues = Vec::new();
+        let output = expand("@insert CommonError", &registry, &mut issues);
+        assert!(output.contains("description: Error Internal Error"));
+        // `hint` has no default
+        assert_eq!(
+            issues,
+            vec![Issue::MissingFragmentArg {
+                fragment: "CommonError".to_string(),
+                param: "hint".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fragment_default_overridden() {
+        let registry = registry_with_default();
+        let mut issues = Vec::new();
+        let output = expand(
+            "@insert CommonError(\"Bad Request\", \"retry\")",
+            &registry,
+            &mut issues,
+        );
+        assert!(output.contains("description: Error Bad Request"));
+        assert!(output.contains("x-hint: retry"));
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_split_args_respects_quotes() {
+        assert_eq!(
+            split_args(r#"item = "User", desc = "A page, of users", 3"#),
+            vec![r#"item = "User""#, r#"desc = "A page, of users""#, "3"]
+        );
+        assert!(split_args("  ").is_empty());
+        assert_eq!(
+            parse_args(r#""a = b", "say \"hi\"""#),
+            vec![
+                FragmentArg::Positional("a = b".to_string()),
+                FragmentArg::Positional("say \"hi\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_args_out_of_order() {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "PagedResponse".to_string(),
+            vec!["item".into(), "desc".into(), "code".into()],
+            "x-code: '{{code}}'\ndescription: {{desc}}\nitems: $Page<{{item}}>".to_string(),
+        );
+
+        let mut issues = Vec::new();
+        let output = expand(
+            r#"@insert PagedResponse(code = "200", desc = "A page, of users", item = User)"#,
+            &registry,
+            &mut issues,
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert!(output.contains("x-code: '200'"));
+        assert!(output.contains("description: A page, of users"));
+        assert!(output.contains("$Page<User>"));
+
+        let output = expand(
+            "get:\n  @extend PagedResponse(desc = \"Users\", item = User, code = \"201\")",
+            &registry,
+            &mut issues,
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+        assert!(output.contains("description: Users"));
+        assert!(output.contains("x-code: '201'"));
+    }
+
+    #[test]
+    fn test_mixed_and_unknown_named_args() {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "Pair".to_string(),
+            vec!["a".into(), "b".into()],
+            "a: {{a}}\nb: {{b}}".to_string(),
+        );
+
+        let mut issues = Vec::new();
+        expand("@insert Pair(1, b = 2)", &registry, &mut issues);
+        assert!(
+            matches!(&issues[0], Issue::FragmentArgs { message, .. } if message.contains("mixed"))
+        );
+
+        let mut issues = Vec::new();
+        expand("@insert Pair(a = 1, b = 2, c = 3)", &registry, &mut issues);
+        assert_eq!(
+            issues,
+            vec![Issue::FragmentArgs {
+                fragment: "Pair".to_string(),
+                message: "unknown parameter 'c'".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_inserted_lines_map_to_insert() {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "Broken".to_string(),
+            vec![],
+            "description: x\ncontent: : oops".to_string(),
+        );
+
+        let mut issues = Vec::new();
+        let expanded = expand_mapped(
+            "get:\n  responses:\n    '404':\n      @insert Broken\n  summary: s",
+            &registry,
+            &mut issues,
+        );
+        assert_eq!(expanded.content.lines().count(), expanded.line_map.len());
+        assert_eq!(expanded.line_map, vec![0, 1, 2, 3, 3, 4]);
+        assert_eq!(expanded.fragments, 1);
+    }
+
+    #[test]
+    fn test_nested_fragments() {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "ErrorBody".to_string(),
+            vec!["msg".into()],
+            "content:\n  application/json:\n    example: {{msg}}".to_string(),
+        );
+        registry.insert_fragment(
+            "NotFound".to_string(),
+            vec![],
+            "description: Not Found\n@insert ErrorBody(\"missing\")".to_string(),
+        );
+        registry.insert_fragment(
+            "Audited".to_string(),
+            vec![],
+            "x-audit: true\nresponses:\n  '404':\n    @insert NotFound".to_string(),
+        );
+
+        let mut issues = Vec::new();
+        let output = expand(
+            "get:\n  @extend Audited\n  summary: s",
+            &registry,
+            &mut issues,
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+        let value: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+        let not_found = &value["get"]["responses"]["404"];
+        assert_eq!(not_found["description"].as_str(), Some("Not Found"));
+        assert_eq!(
+            not_found["content"]["application/json"]["example"].as_str(),
+            Some("missing")
+        );
+        assert_eq!(value["get"]["x-audit"].as_bool(), Some(true));
+        assert!(!output.contains('@'));
+    }
+
+    #[test]
+    fn test_fragment_cycle_and_depth() {
+        let mut registry = Registry::new();
+        registry.insert_fragment("A".to_string(), vec![], "a: 1\n@insert B".to_string());
+        registry.insert_fragment("B".to_string(), vec![], "b: 1\n@insert A".to_string());
+
+        let mut issues = Vec::new();
+        expand("@insert A", &registry, &mut issues);
+        assert_eq!(
+            issues,
+            vec![Issue::FragmentCycle(vec![
+                "A".to_string(),
+                "B".to_string(),
+                "A".to_string()
+            ])]
+        );
+
+        // F0 -> F1 -> ... -> F40 never cycles but nests too deep
+        let mut registry = Registry::new();
+        for i in 0..40 {
+            registry.insert_fragment(
+                format!("F{}", i),
+                vec![],
+                format!("f{}: 1\n@insert F{}", i, i + 1),
+            );
+        }
+        let mut issues = Vec::new();
+        expand("@insert F0", &registry, &mut issues);
+        assert_eq!(issues, vec![Issue::FragmentDepth(MAX_FRAGMENT_DEPTH)]);
+    }
+
+    #[test]
+    fn test_multiple_extends() {
+        let mut registry = Registry::new();
+        registry.insert_fragment(
+            "Base".to_string(),
+            vec![],
+            "summary: Base\nresponses:\n  '401':\n    description: Unauthorized".to_string(),
+        );
+        registry.insert_fragment(
+            "Auditing".to_string(),
+            vec![],
+            "summary: Audited\nx-audit: true\nresponses:\n  '403':\n    description: Forbidden"
+                .to_string(),
+        );
+
+        let mut issues = Vec::new();
+        let output = expand(
+            "get:\n  @extend Base\n  @extend Auditing\n  operationId: list",
+            &registry,
+            &mut issues,
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+
+        let value: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+        let get = &value["get"];
+        assert_eq!(get["summary"].as_str(), Some("Audited"));
+        assert_eq!(get["x-audit"].as_bool(), Some(true));
+        assert_eq!(get["operationId"].as_str(), Some("list"));
+        assert!(get["responses"]["401"].is_mapping());
+        assert!(get["responses"]["403"].is_mapping());
+        assert!(!output.contains("x-openapi-extend"));
+    }
+
+    #[test]
+    fn test_extend_marker_accumulates() {
+        let mut lines = vec!["get:".to_string()];
+        push_extend_marker(&mut lines, "  ", "Base");
+        push_extend_marker(&mut lines, "  ", "It's(x)");
+        lines.push("  summary: s".to_string());
+        push_extend_marker(&mut lines, "  ", "Late");
+        assert_eq!(
+            lines,
+            vec![
+                "get:",
+                "  x-openapi-extend:",
+                "    - 'Base'",
+                "    - 'It''s(x)'",
+                "    - 'Late'",
+                "  summary: s",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extends_around_sibling_keys() {
+        let mut registry = Registry::new();
+        registry.insert_fragment("Base".to_string(), vec![], "x-base: true".to_string());
+        registry.insert_fragment("Late".to_string(), vec![], "x-late: true".to_string());
+        let input = "get:\n  @extend Base\n  summary: s\n  @extend Late\npost:\n  @extend Late";
+
+        let (text, line_map) = expand_text(
+            input,
+            &registry,
+            &mut Vec::new(),
+            &mut FragmentStack::default(),
+        );
+        assert_eq!(text.matches("x-openapi-extend").count(), 2, "{}", text);
+        let summary = text.lines().position(|l| l == "  summary: s").unwrap();
+        assert_eq!(line_map[summary], 2);
+        assert_eq!(line_map.len(), text.lines().count());
+
+        let mut issues = Vec::new();
+        let output = expand(input, &registry, &mut issues);
+        assert!(issues.is_empty(), "{:?}", issues);
+        let value: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(value["get"]["x-base"].as_bool(), Some(true));
+        assert_eq!(value["get"]["x-late"].as_bool(), Some(true));
+        assert_eq!(value["get"]["summary"].as_str(), Some("s"));
+        assert_eq!(value["post"]["x-late"].as_bool(), Some(true));
+        assert!(value["post"].get("x-base").is_none());
+        assert!(!output.contains("x-openapi-extend"));
+    }
+
     #[test]
     fn test_insert_with_indentation() {
         let mut registry = Registry::new();
@@ -255,4 +771,20 @@ mod tests {
         // In fallback path: same as input.
         assert_eq!(output, "@insert Missing(\"\")");
     }
+
+    #[test]
+    fn test_missing_fragment_issues() {
+        let registry = Registry::new();
+        let mut issues = Vec::new();
+        expand(
+            "get:\n  @extend Base\n  summary: x\n@insert Gone(\"a\")",
+            &registry,
+            &mut issues,
+        );
+        assert!(issues.contains(&Issue::FragmentNotFound("Gone".to_string())));
+
+        let mut issues = Vec::new();
+        expand("get:\n  @extend Base\n  summary: x", &registry, &mut issues);
+        assert_eq!(issues, vec![Issue::FragmentNotFound("Base".to_string())]);
+    }
 }
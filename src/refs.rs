@@ -0,0 +1,235 @@
+use crate::diagnostics::{Diagnostic, code};
+use crate::merger::SOURCE_KEY;
+use serde_yaml::Value;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Local `$ref` targets (`#/...`) anywhere in `value`.
+pub fn local_refs(value: &Value) -> BTreeSet<String> {
+    let mut refs = BTreeSet::new();
+    visit_refs(value, &mut |target| {
+        refs.insert(target.to_string());
+    });
+    refs
+}
+
+// Calls `f` with every local `$ref` target below `value`
+fn visit_refs(value: &Value, f: &mut impl FnMut(&str)) {
+    visit_refs_at(value, &mut String::new(), None, &mut |target, _, _| {
+        f(target)
+    });
+}
+
+// Like `visit_refs`, also passing the JSON pointer of the `$ref` and the nearest
+// enclosing source annotation
+fn visit_refs_at(
+    value: &Value,
+    pointer: &mut String,
+    source: Option<&str>,
+    f: &mut impl FnMut(&str, &str, Option<&str>),
+) {
+    match value {
+        Value::Mapping(map) => {
+            let source = map.get(SOURCE_KEY).and_then(Value::as_str).or(source);
+            for (key, val) in map {
+                match (key.as_str(), val.as_str()) {
+                    (Some("$ref"), Some(target)) if target.starts_with('#') => {
+                        f(target, &format!("{}/$ref", pointer), source)
+                    }
+                    (Some(key), _) => {
+                        with_token(pointer, key, |p| visit_refs_at(val, p, source, f))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Sequence(seq) => {
+            for (i, val) in seq.iter().enumerate() {
+                with_token(pointer, &i.to_string(), |p| {
+                    visit_refs_at(val, p, source, f)
+                });
+            }
+        }
+        Value::Tagged(tagged) => visit_refs_at(&tagged.value, pointer, source, f),
+        _ => {}
+    }
+}
+
+pub(crate) fn with_token(pointer: &mut String, token: &str, f: impl FnOnce(&mut String)) {
+    let len = pointer.len();
+    pointer.push('/');
+    pointer.push_str(&escape(token));
+    f(pointer);
+    pointer.truncate(len);
+}
+
+/// Escapes `token` for use as a JSON pointer segment (`~` -> `~0`, `/` -> `~1`).
+pub fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Every local `$ref` in `doc` whose target does not exist, as a warning at the
+/// pointer of the `$ref`. With source annotations, the defining file and line are attached.
+pub fn dangling_refs(doc: &Value) -> Vec<Diagnostic> {
+    let mut dangling = Vec::new();
+    visit_refs_at(
+        doc,
+        &mut String::new(),
+        None,
+        &mut |target, pointer, source| {
+            if resolve(doc, target).is_some() {
+                return;
+            }
+            let mut diagnostic =
+                Diagnostic::at_pointer(format!("$ref '{}' does not resolve", target), pointer)
+                    .with_code(code::DANGLING_REF);
+            if let Some((file, line)) = source.and_then(|s| s.rsplit_once(':')) {
+                diagnostic.file = Some(PathBuf::from(file));
+                diagnostic.line = line.parse().ok();
+            }
+            dangling.push(diagnostic);
+        },
+    );
+    dangling
+}
+
+/// Looks up a local ref (`#/components/schemas/User`) as a JSON pointer into `doc`.
+pub fn resolve<'a>(doc: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(doc);
+    }
+    let pointer = pointer.strip_prefix('/')?;
+    pointer.split('/').try_fold(doc, |current, token| {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        match current {
+            Value::Mapping(map) => map.get(token.as_str()),
+            Value::Sequence(seq) => seq.get(token.parse::<usize>().ok()?),
+            _ => None,
+        }
+    })
+}
+
+/// Local refs reachable from `start`, following each target within `doc` transitively.
+///
+/// Targets that do not resolve are included but not followed.
+pub fn reachable_refs(doc: &Value, start: &Value) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<String> = local_refs(start).into_iter().collect();
+    while let Some(target) = pending.pop() {
+        if !seen.insert(target.clone()) {
+            continue;
+        }
+        if let Some(value) = resolve(doc, &target) {
+            pending.extend(local_refs(value).into_iter().filter(|r| !seen.contains(r)));
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_refs_follow_schemas() {
+        let doc: Value = serde_yaml::from_str(
+            r##"
+paths:
+  /users:
+    get:
+      responses:
+        '200': {$ref: "#/components/responses/Users"}
+components:
+  responses:
+    Users:
+      content:
+        application/json:
+          schema: {$ref: "#/components/schemas/Page"}
+  schemas:
+    Page:
+      properties:
+        items: {type: array, items: {$ref: "#/components/schemas/User"}}
+        next: {$ref: "#/components/schemas/Page"}
+    User: {type: object}
+    Unused: {type: object}
+"##,
+        )
+        .unwrap();
+
+        let refs = reachable_refs(&doc, &doc["paths"]);
+        assert_eq!(
+            refs.into_iter().collect::<Vec<_>>(),
+            vec![
+                "#/components/responses/Users",
+                "#/components/schemas/Page",
+                "#/components/schemas/User",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_pointer_escapes() {
+        let doc: Value =
+            serde_yaml::from_str("paths:\n  /a/{id}: {get: {}}\nlist: [x, y]").unwrap();
+        assert!(resolve(&doc, "#/paths/~1a~1{id}/get").is_some());
+        assert_eq!(resolve(&doc, "#/list/1").and_then(Value::as_str), Some("y"));
+        assert!(resolve(&doc, "#/paths/missing").is_none());
+        assert!(resolve(&doc, "other.yaml#/a").is_none());
+    }
+
+    #[test]
+    fn test_dangling_refs() {
+        let doc: Value = serde_yaml::from_str(
+            r##"
+paths:
+  /users:
+    x-oas-forge-source: src/users.rs:12
+    get:
+      responses:
+        '200':
+          content:
+            application/json:
+              schema: {$ref: "#/components/schemas/Usr"}
+components:
+  schemas:
+    User:
+      properties:
+        friends: {type: array, items: {$ref: "#/components/schemas/User"}}
+        org: {$ref: "#/components/schemas/Org"}
+"##,
+        )
+        .unwrap();
+
+        let dangling = dangling_refs(&doc);
+        assert_eq!(dangling.len(), 2);
+        assert_eq!(
+            dangling[0].pointer.as_deref(),
+            Some("/paths/~1users/get/responses/200/content/application~1json/schema/$ref")
+        );
+        assert!(dangling[0].message.contains("#/components/schemas/Usr"));
+        assert_eq!(dangling[0].file, Some(PathBuf::from("src/users.rs")));
+        assert_eq!(dangling[0].line, Some(12));
+        assert_eq!(
+            dangling[1].pointer.as_deref(),
+            Some("/components/schemas/User/properties/org/$ref")
+        );
+        assert_eq!(dangling[1].file, None);
+    }
+
+    #[test]
+    fn test_resolving_refs_are_not_dangling() {
+        let doc: Value = serde_yaml::from_str(
+            r##"
+paths:
+  /a~b: {get: {responses: {'200': {$ref: "#/components/responses/Ok"}}}}
+  /c: {$ref: "#/paths/~1a~0b"}
+components:
+  responses:
+    Ok: {description: ok}
+"##,
+        )
+        .unwrap();
+        assert!(dangling_refs(&doc).is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+//! `include` entries given as http(s) URLs, fetched with the `remote` feature.
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Cache directory used unless configured otherwise.
+pub const DEFAULT_CACHE_DIR: &str = "target/oas-forge-cache";
+
+/// How remote includes are fetched.
+#[derive(Debug, Clone)]
+pub struct RemoteOptions {
+    /// Per-request timeout
+    pub timeout: Duration,
+    /// Fetched bodies and their ETags, revalidated on later runs
+    pub cache_dir: PathBuf,
+    /// Only use cached bodies, never the network
+    pub offline: bool,
+}
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            cache_dir: PathBuf::from(DEFAULT_CACHE_DIR),
+            offline: false,
+        }
+    }
+}
+
+/// Whether an include entry is a URL rather than a local path.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+/// Returns the body of `url`, revalidating a cached copy by its ETag.
+pub fn fetch(url: &str, options: &RemoteOptions) -> Result<String> {
+    let cache = Cache::new(&options.cache_dir, url);
+    let cached = cache.load();
+    if options.offline {
+        return cached
+            .map(|(body, _)| body)
+            .ok_or_else(|| remote_error(url, None, "not cached, and offline".to_string()));
+    }
+
+    let etag = cached.as_ref().and_then(|(_, etag)| etag.as_deref());
+    match get(url, etag, options.timeout)? {
+        Fetched::NotModified => match cached {
+            Some((body, _)) => Ok(body),
+            None => Err(remote_error(
+                url,
+                Some(304),
+                "HTTP status 304 without a cached copy".to_string(),
+            )),
+        },
+        Fetched::Body { body, etag } => {
+            if let Err(e) = cache.store(&body, etag.as_deref()) {
+                log::warn!("Could not cache {}: {}", url, e);
+            }
+            Ok(body)
+        }
+    }
+}
+
+fn remote_error(url: &str, status: Option<u16>, message: String) -> Error {
+    Error::Remote {
+        url: url.to_string(),
+        status,
+        message,
+    }
+}
+
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+enum Fetched {
+    NotModified,
+    Body { body: String, etag: Option<String> },
+}
+
+#[cfg(feature = "remote")]
+fn get(url: &str, etag: Option<&str>, timeout: Duration) -> Result<Fetched> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.get(url);
+    if let Some(etag) = etag {
+        request = request.header("If-None-Match", etag);
+    }
+    let mut response = request
+        .call()
+        .map_err(|e| remote_error(url, None, e.to_string()))?;
+    let status = response.status().as_u16();
+    match status {
+        304 => Ok(Fetched::NotModified),
+        200..=299 => {
+            let etag = response
+                .headers()
+                .get("etag")
+                .and_then(|value| value.to_str().ok())
+                .map(String::from);
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| remote_error(url, Some(status), e.to_string()))?;
+            Ok(Fetched::Body { body, etag })
+        }
+        _ => Err(remote_error(
+            url,
+            Some(status),
+            format!("HTTP status {}", status),
+        )),
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn get(url: &str, _etag: Option<&str>, _timeout: Duration) -> Result<Fetched> {
+    Err(remote_error(
+        url,
+        None,
+        "remote includes need the `remote` feature".to_string(),
+    ))
+}
+
+// `<key>.body` and `<key>.etag`, the key being a hash of the URL
+struct Cache {
+    body: PathBuf,
+    etag: PathBuf,
+}
+
+impl Cache {
+    fn new(dir: &Path, url: &str) -> Self {
+        // FNV-1a, stable across runs and toolchains
+        let key = url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        Self {
+            body: dir.join(format!("{:016x}.body", key)),
+            etag: dir.join(format!("{:016x}.etag", key)),
+        }
+    }
+
+    fn load(&self) -> Option<(String, Option<String>)> {
+        let body = std::fs::read_to_string(&self.body).ok()?;
+        Some((body, std::fs::read_to_string(&self.etag).ok()))
+    }
+
+    fn store(&self, body: &str, etag: Option<&str>) -> Result<()> {
+        crate::output::write_atomic(&self.body, body.as_bytes())?;
+        match etag {
+            Some(etag) => {
+                crate::output::write_atomic(&self.etag, etag.as_bytes())?;
+            }
+            None => {
+                let _ = std::fs::remove_file(&self.etag);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "remote"))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    // Answers one request per response, returning the request header blocks
+    fn server(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/shared.yaml", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    request.push_str(&line);
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_fetch_revalidates_cache() {
+        let cache = tempfile::tempdir().unwrap();
+        let options = RemoteOptions {
+            cache_dir: cache.path().to_path_buf(),
+            ..Default::default()
+        };
+        let (url, server) = server(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 11\r\nConnection: close\r\n\r\nversion: 1\n",
+            "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+
+        assert_eq!(fetch(&url, &options).unwrap(), "version: 1\n");
+        assert_eq!(fetch(&url, &options).unwrap(), "version: 1\n");
+        let requests = server.join().unwrap();
+        assert!(!requests[0].to_lowercase().contains("if-none-match"));
+        assert!(requests[1].contains("\"v1\""));
+
+        // The server is gone; offline mode serves the cached body
+        let offline = RemoteOptions {
+            offline: true,
+            ..options
+        };
+        assert_eq!(fetch(&url, &offline).unwrap(), "version: 1\n");
+        let err = fetch("https://example.invalid/other.yaml", &offline).unwrap_err();
+        assert!(err.to_string().contains("example.invalid/other.yaml"));
+    }
+
+    #[test]
+    fn test_fetch_reports_status() {
+        let cache = tempfile::tempdir().unwrap();
+        let options = RemoteOptions {
+            cache_dir: cache.path().to_path_buf(),
+            ..Default::default()
+        };
+        let (url, server) = server(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let err = fetch(&url, &options).unwrap_err();
+        server.join().unwrap();
+        assert!(matches!(
+            err,
+            Error::Remote {
+                status: Some(404),
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            format!("Failed to fetch {}: HTTP status 404", url)
+        );
+    }
+
+    #[test]
+    fn test_remote_include_is_scanned() {
+        let cache = tempfile::tempdir().unwrap();
+        let (url, server) = server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 47\r\nConnection: close\r\n\r\ncomponents: {schemas: {Error: {type: object}}}\n",
+        ]);
+        let options = crate::scanner::ScanOptions {
+            remote: RemoteOptions {
+                cache_dir: cache.path().to_path_buf(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let snippets =
+            crate::scanner::scan_with_options(&[], &[PathBuf::from(&url)], &options).unwrap();
+        server.join().unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].file_path, PathBuf::from(&url));
+        assert!(snippets[0].content.contains("Error"));
+    }
+}
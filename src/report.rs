@@ -0,0 +1,94 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::output::WrittenFile;
+use crate::scanner::Scan;
+use serde::Serialize;
+use serde_yaml::Value;
+
+const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// What a generation run produced, plus the warnings it logged.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub paths: usize,
+    pub operations: usize,
+    pub schemas: usize,
+    /// Fragment expansions (`@insert`/`@extend`), nested ones included
+    pub fragments_expanded: usize,
+    /// Concrete schemas generated from blueprints
+    pub blueprints_instantiated: usize,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Files written by [`crate::Generator::generate_file`]
+    pub outputs: Vec<WrittenFile>,
+}
+
+impl Report {
+    /// Builds the report for the merged `document` of `scan`.
+    pub fn new(document: &Value, scan: &Scan) -> Self {
+        let paths = document.get("paths").and_then(Value::as_mapping);
+        let operations = paths.map_or(0, |paths| {
+            paths
+                .values()
+                .filter_map(Value::as_mapping)
+                .flat_map(|item| item.keys())
+                .filter(|key| key.as_str().is_some_and(|k| HTTP_METHODS.contains(&k)))
+                .count()
+        });
+        let schemas = document
+            .get("components")
+            .and_then(|c| c.get("schemas"))
+            .and_then(Value::as_mapping)
+            .map_or(0, |schemas| schemas.len());
+
+        Self {
+            paths: paths.map_or(0, |paths| paths.len()),
+            operations,
+            schemas,
+            fragments_expanded: scan.fragments_expanded,
+            blueprints_instantiated: scan.blueprints_instantiated,
+            diagnostics: scan.warnings.clone(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .count()
+    }
+
+    /// One-line summary, e.g. `12 paths, 34 operations, 28 schemas, 2 warnings`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} paths, {} operations, {} schemas, {} warnings",
+            self.paths,
+            self.operations,
+            self.schemas,
+            self.warnings()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_summary() {
+        let document: Value = serde_yaml::from_str(
+            "paths:\n  /a: {get: {}, post: {}, parameters: []}\n  /b: {get: {}}\ncomponents:\n  schemas: {A: {}}\n",
+        )
+        .unwrap();
+        let scan = Scan {
+            warnings: vec![Diagnostic::warning("x", "a.rs".into(), 1)],
+            ..Default::default()
+        };
+        let report = Report::new(&document, &scan);
+        assert_eq!(
+            report.summary(),
+            "2 paths, 3 operations, 1 schemas, 1 warnings"
+        );
+    }
+}
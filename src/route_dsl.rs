@@ -0,0 +1,786 @@
+//! Parsing of the route DSL directives (`@route`, `@path-param`, `@return`, ...) found
+//! in handler doc comments. Each function handles one trimmed doc line.
+
+use crate::visitor::{TypeMappers, is_byte_buffer, map_syn_type_to_openapi};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::sync::OnceLock;
+
+// Matches {id}, {id: u32}, {id: u32 "Description"}
+// Group 2: Type (trimmed), Group 3: Description (content inside quotes)
+static INLINE_PARAM_RE: OnceLock<Regex> = OnceLock::new();
+static PATH_VARIABLE_RE: OnceLock<Regex> = OnceLock::new();
+
+/// `@return`/`@body` type for raw binary content: `@return 200: file "The PDF" application/pdf`.
+pub(crate) const FILE_KEYWORD: &str = "file";
+/// Media type of other content that names none, unless the module sets another.
+pub(crate) const JSON_MIME: &str = "application/json";
+/// Media type of `file` or byte buffer (`Vec<u8>`) content that names none.
+pub(crate) const FILE_MIME: &str = "application/octet-stream";
+
+fn binary_schema() -> Value {
+    json!({ "type": "string", "format": "binary" })
+}
+
+// `file` or a byte buffer such as `Vec<u8>`, sent as raw content
+fn is_binary_type(type_str: &str) -> bool {
+    type_str == FILE_KEYWORD
+        || syn::parse_str::<syn::Type>(type_str).is_ok_and(|ty| is_byte_buffer(&ty))
+}
+
+/// Whether content of `mime` is plain text, which a structured schema cannot describe.
+pub(crate) fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+}
+
+/// Splits a trailing media type list off a `@return` type: `$Report [application/json,
+/// text/csv]` gives `("$Report", Some([...]))`.
+pub(crate) fn split_mime_list(type_str: &str) -> (&str, Option<Vec<&str>>) {
+    let type_str = type_str.trim();
+    let list = type_str
+        .strip_suffix(']')
+        .and_then(|s| s.rsplit_once('['))
+        .filter(|(ty, _)| ty.ends_with(char::is_whitespace));
+    match list {
+        Some((ty, inner)) => {
+            let mimes: Vec<&str> = inner.split(',').map(str::trim).collect();
+            if mimes.iter().all(|m| m.contains('/')) {
+                (ty.trim(), Some(mimes))
+            } else {
+                (type_str, None)
+            }
+        }
+        None => (type_str, None),
+    }
+}
+
+/// A parsed `@route METHOD /path` line.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RouteLine {
+    /// Lower case, e.g. `get`
+    pub method: String,
+    /// With inline parameter declarations reduced to `{name}`
+    pub path: String,
+    /// Parameter objects of the typed or described inline declarations
+    pub params: Vec<Value>,
+}
+
+/// `@route GET /users/{id: u32 "User ID"}`; `None` without both method and path.
+pub(crate) fn parse_route_line(line: &str, mappers: &TypeMappers) -> Option<RouteLine> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    let raw_path = parts[2..].join(" ");
+    let inline_param_re = INLINE_PARAM_RE
+        .get_or_init(|| Regex::new(r#"\{(\w+)(?::\s*([^"}]+))?(?:\s*"([^"]+)")?\}"#).unwrap());
+
+    let mut path = String::new();
+    let mut params = Vec::new();
+    let mut last_end = 0;
+    for cap in inline_param_re.captures_iter(&raw_path) {
+        let full_match = cap.get(0).unwrap();
+        let name = cap.get(1).unwrap().as_str();
+        let type_str = cap.get(2).map(|m| m.as_str().trim());
+        let desc = cap.get(3).map(|m| m.as_str());
+
+        path.push_str(&raw_path[last_end..full_match.start()]);
+        path.push('{');
+        path.push_str(name);
+        path.push('}');
+        last_end = full_match.end();
+
+        // A bare {id} must be declared by a @path-param line
+        if type_str.is_none() && desc.is_none() {
+            continue;
+        }
+        let mut param = json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": type_schema(type_str.unwrap_or("String"), mappers).0
+        });
+        if let Some(d) = desc {
+            param["description"] = json!(d);
+        }
+        params.push(param);
+    }
+    path.push_str(&raw_path[last_end..]);
+
+    Some(RouteLine {
+        method: parts[1].to_lowercase(),
+        path,
+        params,
+    })
+}
+
+/// `@tag Users` or `@tags [Users, Admin]`.
+pub(crate) fn parse_tag_line(line: &str) -> Vec<String> {
+    let rest = line
+        .strip_prefix("@tags")
+        .or_else(|| line.strip_prefix("@tag"))
+        .unwrap_or(line)
+        .trim();
+    match rest.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+        Some(inner) => inner.split(',').map(|t| t.trim().to_string()).collect(),
+        None => vec![rest.to_string()],
+    }
+}
+
+/// `@query-param filter: Option<String> required deprecated example="x" "Name filter"`,
+/// likewise for `@path-param`, `@header-param` and `@cookie-param`; the parameter object.
+pub(crate) fn parse_param_line(line: &str, mappers: &TypeMappers) -> Option<Value> {
+    let (location, rest) = [
+        ("query", "@query-param"),
+        ("path", "@path-param"),
+        ("header", "@header-param"),
+        ("cookie", "@cookie-param"),
+    ]
+    .into_iter()
+    .find_map(|(location, directive)| Some((location, line.strip_prefix(directive)?.trim())))?;
+
+    let (name, residue) = rest.split_once(':')?;
+    let name = name.trim();
+    let tokens = tokenize(residue.trim());
+    let first = tokens.first()?;
+
+    // Identify Type
+    let is_flag = first == "deprecated"
+        || first == "required"
+        || first.contains('=')
+        || first.starts_with('"');
+    let (type_str, start_idx) = if !is_flag && syn::parse_str::<syn::Type>(first).is_ok() {
+        (first.as_str(), 1)
+    } else {
+        ("String", 0)
+    };
+    let (schema, mut required) = type_schema(type_str, mappers);
+
+    let mut deprecated = false;
+    let mut example = None;
+    let mut desc = None;
+    for token in tokens.iter().skip(start_idx) {
+        if token == "deprecated" {
+            deprecated = true;
+        } else if token == "required" {
+            required = true;
+        } else if let Some(value) = token.strip_prefix("example=") {
+            example = Some(value.trim_matches('"'));
+        } else if token.starts_with('"') {
+            desc = Some(token.trim_matches('"'));
+        }
+    }
+
+    let mut param = json!({
+        "name": name,
+        "in": location,
+        "required": required || location == "path",
+        "schema": schema
+    });
+    if deprecated {
+        param["deprecated"] = json!(true);
+    }
+    if let Some(ex) = example {
+        param["example"] = json!(ex);
+    }
+    if let Some(d) = desc {
+        param["description"] = json!(d);
+    }
+    Some(param)
+}
+
+/// `@body User text/plain`; the `requestBody` object, `default_mime` unless a MIME type
+/// follows.
+pub(crate) fn parse_body_line(
+    line: &str,
+    mappers: &TypeMappers,
+    default_mime: &str,
+) -> Option<Value> {
+    let rest = line.strip_prefix("@body").unwrap_or(line);
+    let mut parts = rest.split_whitespace();
+    let schema_ref = parts.next()?;
+    let is_binary = is_binary_type(schema_ref);
+    let mime = parts
+        .next()
+        .unwrap_or(if is_binary { FILE_MIME } else { default_mime });
+
+    let schema = if is_binary {
+        binary_schema()
+    } else if schema_ref.contains('<') {
+        // Generic, left raw for the monomorphizer
+        json!({ "$ref": schema_ref })
+    } else if let Ok(ty) = syn::parse_str::<syn::Type>(schema_ref) {
+        map_syn_type_to_openapi(&ty, mappers).0
+    } else {
+        let name = schema_ref.strip_prefix('$').unwrap_or(schema_ref);
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    };
+    Some(json!({
+        "content": {
+            mime: {
+                "schema": schema
+            }
+        }
+    }))
+}
+
+/// `@return 200: User "Description"`, optionally followed by a media type (`default_mime`
+/// otherwise); the status code and response object. A list after the type,
+/// `User [application/json, text/csv]`, gives the same schema under each media type, as a
+/// string for text ones. Unit responses (`()`, `unit` or only a description) have no content.
+pub(crate) fn parse_return_line(
+    line: &str,
+    mappers: &TypeMappers,
+    default_mime: &str,
+) -> Option<(String, Value)> {
+    let rest = line.strip_prefix("@return").unwrap_or(line).trim();
+    let (code, residue) = rest.split_once(':')?;
+    let residue = residue.trim();
+
+    let (type_str, desc, mime) = match residue.find('"') {
+        Some(quote_start) => {
+            let quoted = &residue[quote_start + 1..];
+            let (desc, tail) = quoted.rsplit_once('"').unwrap_or((quoted, ""));
+            let tail = tail.trim();
+            (
+                residue[..quote_start].trim(),
+                Some(desc),
+                (!tail.is_empty()).then_some(tail),
+            )
+        }
+        None if residue.ends_with(']') => (residue, None, None),
+        None => match residue.rsplit_once(char::is_whitespace) {
+            Some((type_str, mime)) if mime.contains('/') => (type_str.trim(), None, Some(mime)),
+            _ => (residue, None, None),
+        },
+    };
+
+    let (type_str, listed) = split_mime_list(type_str);
+
+    let mut response = json!({ "description": desc.unwrap_or("") });
+    if type_str.is_empty() || type_str == "()" || type_str == "unit" {
+        return Some((code.trim().to_string(), response));
+    }
+    let is_file = is_binary_type(type_str);
+    let schema = if is_file {
+        binary_schema()
+    } else if type_str.contains('<') {
+        json!({ "$ref": type_str })
+    } else if let Ok(ty) = syn::parse_str::<syn::Type>(type_str) {
+        map_syn_type_to_openapi(&ty, mappers).0
+    } else if let Some(stripped) = type_str.strip_prefix('$') {
+        json!({ "$ref": format!("#/components/schemas/{}", stripped) })
+    } else if type_str == "String" || type_str == "str" {
+        json!({ "type": "string" })
+    } else {
+        json!({ "$ref": format!("#/components/schemas/{}", type_str) })
+    };
+    let default_mime = if is_file { FILE_MIME } else { default_mime };
+    let mimes = listed.unwrap_or_else(|| vec![mime.unwrap_or(default_mime)]);
+    let content: serde_json::Map<String, Value> = mimes
+        .into_iter()
+        .map(|mime| {
+            let schema = if is_text_mime(mime) && !is_file {
+                json!({ "type": "string" })
+            } else {
+                schema.clone()
+            };
+            (mime.to_string(), json!({ "schema": schema }))
+        })
+        .collect();
+    response["content"] = Value::Object(content);
+    Some((code.trim().to_string(), response))
+}
+
+/// `@response-header 200 X-Rate-Limit $RateLimitRemaining` or
+/// `@response-header 200 X-Total-Count u64 "Description"`; the status code, header name and
+/// header object. A `$Name` refers to `components/headers`.
+pub(crate) fn parse_response_header_line(
+    line: &str,
+    mappers: &TypeMappers,
+) -> Option<(String, String, Value)> {
+    let rest = line.strip_prefix("@response-header").unwrap_or(line).trim();
+    let (code, rest) = rest.split_once(char::is_whitespace)?;
+    let (name, rest) = rest.trim().split_once(char::is_whitespace)?;
+    let rest = rest.trim();
+
+    let header = if let Some(component) = rest.strip_prefix('$') {
+        json!({ "$ref": format!("#/components/headers/{}", component) })
+    } else {
+        let (type_str, desc) = match rest.find('"') {
+            Some(quote) => (rest[..quote].trim(), Some(rest[quote..].trim_matches('"'))),
+            None => (rest, None),
+        };
+        let ty = syn::parse_str::<syn::Type>(type_str).ok()?;
+        let mut header = json!({ "schema": map_syn_type_to_openapi(&ty, mappers).0 });
+        if let Some(desc) = desc {
+            header["description"] = json!(desc);
+        }
+        header
+    };
+    Some((code.to_string(), name.to_string(), header))
+}
+
+/// `@example 200 $PagedUsersExample` or `@example 200 firstPage $PagedUsersExample`; the status
+/// code, example name (the component's by default) and a reference into `components/examples`.
+pub(crate) fn parse_example_line(line: &str) -> Option<(String, String, Value)> {
+    let rest = line.strip_prefix("@example").unwrap_or(line).trim();
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let (code, name, component) = match words[..] {
+        [code, component] => (code, None, component),
+        [code, name, component] => (code, Some(name), component),
+        _ => return None,
+    };
+    let component = component.strip_prefix('$')?;
+    Some((
+        code.to_string(),
+        name.unwrap_or(component).to_string(),
+        json!({ "$ref": format!("#/components/examples/{}", component) }),
+    ))
+}
+
+/// The response for `code` in `operation`, added with an empty description if missing.
+pub(crate) fn response_mut<'a>(operation: &'a mut Value, code: &str) -> &'a mut Value {
+    let response = &mut operation["responses"][code];
+    if response.is_null() {
+        *response = json!({ "description": "" });
+    }
+    response
+}
+
+/// `@link 201 GetUserById operationId=get_user parameters.id=$response.body#/id`; the status
+/// code, link name and link object. `parameters.*` keys are collected into `parameters`,
+/// other keys (`operationId`, `operationRef`, `requestBody`, `description`) are kept as is.
+/// Words that are not `key=value` are skipped and reported in `ignored`.
+pub(crate) fn parse_link_line(
+    line: &str,
+    ignored: &mut Vec<String>,
+) -> Option<(String, String, Value)> {
+    let rest = line.strip_prefix("@link").unwrap_or(line).trim();
+    let mut words = rest.split_whitespace();
+    let code = words.next()?;
+    let name = words.next()?;
+
+    let mut link = json!({});
+    for pair in words {
+        let Some((key, value)) = pair.split_once('=') else {
+            ignored.push(format!(
+                "ignoring '{}' in @link {}: expected key=value",
+                pair, name
+            ));
+            continue;
+        };
+        let value = json!(value.trim_matches('"'));
+        match key.strip_prefix("parameters.") {
+            Some(param) => link["parameters"][param] = value,
+            None => link[key] = value,
+        }
+    }
+    Some((code.to_string(), name.to_string(), link))
+}
+
+/// `@server https://eu.example.com "EU region"`; a server object, `None` without a URL.
+pub(crate) fn parse_server_line(line: &str) -> Option<Value> {
+    let rest = line.strip_prefix("@server").unwrap_or(line).trim();
+    let (url, desc) = match rest.split_once(char::is_whitespace) {
+        Some((url, desc)) => (url, Some(desc.trim().trim_matches('"'))),
+        None => (rest, None),
+    };
+    if url.is_empty() {
+        return None;
+    }
+    let mut server = json!({ "url": url });
+    if let Some(desc) = desc.filter(|d| !d.is_empty()) {
+        server["description"] = json!(desc);
+    }
+    Some(server)
+}
+
+/// `@security oauth("read", "write")`; a security requirement object.
+pub(crate) fn parse_security_line(line: &str) -> Value {
+    let rest = line.strip_prefix("@security").unwrap_or(line).trim();
+    let (scheme, scopes) = match rest.find('(') {
+        Some(paren_start) => {
+            let inner = &rest[paren_start + 1..rest.len() - 1];
+            let scopes: Vec<String> = inner
+                .split(',')
+                .map(|s| s.trim().trim_matches('"').to_string())
+                .collect();
+            (rest[..paren_start].trim(), scopes)
+        }
+        None => (rest, vec![]),
+    };
+    json!({ scheme: scopes })
+}
+
+/// Joins doc comment lines into a description: blank lines are kept, leading and trailing
+/// ones dropped, and lines lose only the indentation they all share.
+pub(crate) fn join_description(lines: &[&str]) -> Option<String> {
+    let is_blank = |line: &&str| line.trim().is_empty();
+    let start = lines.iter().position(|l| !is_blank(l))?;
+    let end = lines.iter().rposition(|l| !is_blank(l))? + 1;
+    let lines = &lines[start..end];
+    let indent = lines
+        .iter()
+        .filter(|l| !is_blank(l))
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: Vec<&str> = lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or(""))
+        .collect();
+    Some(text.join("\n"))
+}
+
+/// Checks that the `{variables}` of `path` and the `declared` path parameters match.
+pub(crate) fn check_path_params(
+    path: &str,
+    declared: &std::collections::HashSet<String>,
+) -> Result<(), String> {
+    let re = PATH_VARIABLE_RE.get_or_init(|| Regex::new(r"\{(\w+)\}").unwrap());
+    for cap in re.captures_iter(path) {
+        let var = cap.get(1).unwrap().as_str();
+        if !declared.contains(var) {
+            return Err(format!(
+                "Missing definition for path parameter '{}' in route '{}'",
+                var, path
+            ));
+        }
+    }
+    for name in declared {
+        if !path.contains(&format!("{{{}}}", name)) {
+            return Err(format!(
+                "Declared path parameter '{}' is unused in route '{}'",
+                name, path
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn type_schema(type_str: &str, mappers: &TypeMappers) -> (Value, bool) {
+    match syn::parse_str::<syn::Type>(type_str) {
+        Ok(ty) => map_syn_type_to_openapi(&ty, mappers),
+        Err(_) => (json!({ "type": "string" }), true),
+    }
+}
+
+// Whitespace separated, keeping "quoted strings" (quotes included) together
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = false;
+    for c in s.chars() {
+        if c == '"' {
+            in_quote = !in_quote;
+            current.push(c);
+        } else if c.is_whitespace() && !in_quote {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_line() {
+        let mappers = TypeMappers::default();
+        let route = parse_route_line(
+            r#"@route GET /users/{id: u32 "User ID"}/posts/{post}"#,
+            &mappers,
+        )
+        .unwrap();
+        assert_eq!(route.method, "get");
+        assert_eq!(route.path, "/users/{id}/posts/{post}");
+        assert_eq!(
+            route.params,
+            vec![json!({
+                "name": "id",
+                "in": "path",
+                "required": true,
+                "description": "User ID",
+                "schema": { "type": "integer", "format": "int32" }
+            })]
+        );
+        assert_eq!(parse_route_line("@route GET", &mappers), None);
+    }
+
+    #[test]
+    fn test_parse_param_line() {
+        let mappers = TypeMappers::default();
+        let param = parse_param_line(
+            r#"@query-param sort: Option<String> deprecated example="name" "Sort key""#,
+            &mappers,
+        )
+        .unwrap();
+        assert_eq!(
+            param,
+            json!({
+                "name": "sort",
+                "in": "query",
+                "required": false,
+                "deprecated": true,
+                "example": "name",
+                "description": "Sort key",
+                "schema": { "type": "string" }
+            })
+        );
+
+        // Without a type, path parameters are required strings
+        let param = parse_param_line(r#"@path-param id: "Identifier""#, &mappers).unwrap();
+        assert_eq!(param["schema"], json!({ "type": "string" }));
+        assert_eq!(param["required"], json!(true));
+
+        assert_eq!(parse_param_line("@header-param X-Token", &mappers), None);
+        assert_eq!(parse_param_line("@form-param a: String", &mappers), None);
+    }
+
+    #[test]
+    fn test_parse_return_line() {
+        let mappers = TypeMappers::default();
+        let (code, response) =
+            parse_return_line(r#"@return 200: $User "Found""#, &mappers, JSON_MIME).unwrap();
+        assert_eq!(code, "200");
+        assert_eq!(
+            response,
+            json!({
+                "description": "Found",
+                "content": {
+                    "application/json": {
+                        "schema": { "$ref": "#/components/schemas/User" }
+                    }
+                }
+            })
+        );
+
+        let (_, response) =
+            parse_return_line(r#"@return 204: "Deleted""#, &mappers, JSON_MIME).unwrap();
+        assert_eq!(response, json!({ "description": "Deleted" }));
+        let (_, response) =
+            parse_return_line("@return 200: $Page<User>", &mappers, JSON_MIME).unwrap();
+        assert_eq!(
+            response["content"]["application/json"]["schema"],
+            json!({ "$ref": "$Page<User>" })
+        );
+        assert_eq!(parse_return_line("@return 200", &mappers, JSON_MIME), None);
+    }
+
+    #[test]
+    fn test_parse_return_mime_list() {
+        let mappers = TypeMappers::default();
+        let (_, response) = parse_return_line(
+            r#"@return 200: $Report [application/json, text/csv, application/yaml] "The report""#,
+            &mappers,
+            JSON_MIME,
+        )
+        .unwrap();
+        let report = json!({ "schema": { "$ref": "#/components/schemas/Report" } });
+        assert_eq!(
+            response,
+            json!({
+                "description": "The report",
+                "content": {
+                    "application/json": report,
+                    "text/csv": { "schema": { "type": "string" } },
+                    "application/yaml": report
+                }
+            })
+        );
+
+        let (_, response) =
+            parse_return_line("@return 200: $Report [text/csv]", &mappers, JSON_MIME).unwrap();
+        assert_eq!(response["content"]["text/csv"]["schema"]["type"], "string");
+        assert_eq!(split_mime_list("[u8]"), ("[u8]", None));
+    }
+
+    #[test]
+    fn test_parse_file_content() {
+        let mappers = TypeMappers::default();
+        let (_, response) = parse_return_line(
+            r#"@return 200: file "The PDF" application/pdf"#,
+            &mappers,
+            JSON_MIME,
+        )
+        .unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "description": "The PDF",
+                "content": {
+                    "application/pdf": {
+                        "schema": { "type": "string", "format": "binary" }
+                    }
+                }
+            })
+        );
+        let (_, response) = parse_return_line("@return 200: file", &mappers, JSON_MIME).unwrap();
+        assert_eq!(
+            response["content"]["application/octet-stream"]["schema"]["format"],
+            "binary"
+        );
+
+        assert_eq!(
+            parse_body_line("@body file image/png", &mappers, JSON_MIME),
+            Some(json!({
+                "content": {
+                    "image/png": {
+                        "schema": { "type": "string", "format": "binary" }
+                    }
+                }
+            }))
+        );
+        assert_eq!(
+            parse_body_line("@body Vec<u8>", &mappers, JSON_MIME),
+            Some(json!({
+                "content": {
+                    "application/octet-stream": {
+                        "schema": { "type": "string", "format": "binary" }
+                    }
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_tags_and_security() {
+        assert_eq!(
+            parse_tag_line("@tags [Users, Admin]"),
+            vec!["Users", "Admin"]
+        );
+        assert_eq!(parse_tag_line("@tag Users"), vec!["Users"]);
+        assert_eq!(
+            parse_security_line(r#"@security oauth("read", "write")"#),
+            json!({ "oauth": ["read", "write"] })
+        );
+        assert_eq!(
+            parse_security_line("@security apiKey"),
+            json!({ "apiKey": [] })
+        );
+    }
+
+    #[test]
+    fn test_parse_response_header_and_example_lines() {
+        let mappers = TypeMappers::default();
+        assert_eq!(
+            parse_response_header_line(
+                "@response-header 200 X-Rate-Limit $RateLimitRemaining",
+                &mappers
+            ),
+            Some((
+                "200".to_string(),
+                "X-Rate-Limit".to_string(),
+                json!({ "$ref": "#/components/headers/RateLimitRemaining" })
+            ))
+        );
+        let (_, _, header) = parse_response_header_line(
+            r#"@response-header 200 X-Total-Count String "Total items""#,
+            &mappers,
+        )
+        .unwrap();
+        assert_eq!(
+            header,
+            json!({ "schema": { "type": "string" }, "description": "Total items" })
+        );
+
+        let reference = json!({ "$ref": "#/components/examples/PagedUsersExample" });
+        assert_eq!(
+            parse_example_line("@example 200 $PagedUsersExample"),
+            Some((
+                "200".to_string(),
+                "PagedUsersExample".to_string(),
+                reference.clone()
+            ))
+        );
+        assert_eq!(
+            parse_example_line("@example 200 firstPage $PagedUsersExample"),
+            Some(("200".to_string(), "firstPage".to_string(), reference))
+        );
+        assert_eq!(parse_example_line("@example 200 firstPage"), None);
+    }
+
+    #[test]
+    fn test_parse_link_line() {
+        let mut ignored = Vec::new();
+        assert_eq!(
+            parse_link_line(
+                "@link 201 GetUserById operationId=get_user parameters.id=$response.body#/id",
+                &mut ignored
+            ),
+            Some((
+                "201".to_string(),
+                "GetUserById".to_string(),
+                json!({
+                    "operationId": "get_user",
+                    "parameters": { "id": "$response.body#/id" }
+                })
+            ))
+        );
+        let (_, _, link) = parse_link_line(
+            "@link 200 Next operationRef=#/paths/~1users/get requestBody=$request.body",
+            &mut ignored,
+        )
+        .unwrap();
+        assert_eq!(link["operationRef"], "#/paths/~1users/get");
+        assert_eq!(link["requestBody"], "$request.body");
+        assert_eq!(parse_link_line("@link 201", &mut ignored), None);
+        assert!(ignored.is_empty());
+
+        let (_, _, link) =
+            parse_link_line("@link 201 Self operationId=me stray", &mut ignored).unwrap();
+        assert_eq!(link, json!({ "operationId": "me" }));
+        assert_eq!(
+            ignored,
+            vec!["ignoring 'stray' in @link Self: expected key=value".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_server_line() {
+        assert_eq!(
+            parse_server_line(r#"@server https://eu.example.com "EU region""#),
+            Some(json!({ "url": "https://eu.example.com", "description": "EU region" }))
+        );
+        assert_eq!(
+            parse_server_line("@server /v2"),
+            Some(json!({ "url": "/v2" }))
+        );
+        assert_eq!(parse_server_line("@server"), None);
+    }
+
+    #[test]
+    fn test_join_description() {
+        let lines = ["", " First", "", "     indented", " last", ""];
+        assert_eq!(
+            join_description(&lines).as_deref(),
+            Some("First\n\n    indented\nlast")
+        );
+        assert_eq!(join_description(&["", "  "]), None);
+    }
+
+    #[test]
+    fn test_check_path_params() {
+        let declared = ["id".to_string()].into_iter().collect();
+        assert!(check_path_params("/users/{id}", &declared).is_ok());
+        assert!(
+            check_path_params("/users/{id}/{post}", &declared)
+                .unwrap_err()
+                .contains("Missing definition for path parameter 'post'")
+        );
+        assert!(
+            check_path_params("/users", &declared)
+                .unwrap_err()
+                .contains("is unused")
+        );
+    }
+}
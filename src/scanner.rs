@@ -1,12 +1,18 @@
-use crate::error::{Error, Result};
-use crate::generics::Monomorphizer;
-use crate::index::Registry;
+use crate::diagnostics::{Diagnostic, Issue, code};
+use crate::error::{Error, Result, RootStatus};
+use crate::generics::{Monomorphizer, NamingStrategy};
+use crate::index::{Registry, SourceLocation};
 use crate::preprocessor;
-use crate::visitor::{self, ExtractedItem};
+use crate::remote::{self, RemoteOptions};
+use crate::route_dsl::{FILE_KEYWORD, FILE_MIME, is_text_mime};
+use crate::template;
+use crate::visitor::{self, DocStyle, ExtractedItem, InputMetadata, SchemaNaming, TypeMappers};
 use regex::Regex;
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::sync::OnceLock;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use walkdir::WalkDir;
 
 /// Represents a source-mapped snippet of OpenAPI definition.
@@ -15,19 +21,175 @@ pub struct Snippet {
     pub content: String,
     pub file_path: PathBuf,
     pub line_number: usize,
+    /// Dotted Rust module path the snippet came from, used to resolve `$Name` locally
+    pub module: String,
+    /// Origin of each line once preprocessing has rewritten `content` (`None`: as written)
+    pub line_map: Option<LineMap>,
+    /// Merge order (`@openapi priority: N`): higher merges later, ties keep scan order
+    pub priority: i32,
+}
+
+impl Snippet {
+    /// The snippet as written, before any preprocessing, and the source line it starts at.
+    pub fn original(&self) -> (usize, &str) {
+        match &self.line_map {
+            Some(map) => (map.first_line, &map.original),
+            None => (self.line_number, &self.content),
+        }
+    }
+
+    /// Source lines of the snippet as written, if known (e.g. the doc comment).
+    pub fn raw(&self) -> Option<&str> {
+        self.line_map.as_ref().and_then(|map| map.raw.as_deref())
+    }
+
+    /// Source line of the 0-based `line` of `content`.
+    pub fn source_line(&self, line: usize) -> usize {
+        match &self.line_map {
+            Some(map) => map.first_line + map.lookup(line),
+            None => self.line_number + line,
+        }
+    }
+}
+
+/// Maps lines of preprocessed snippet content back to the snippet as written.
+#[derive(Debug, Clone, Default)]
+pub struct LineMap {
+    /// Snippet content before preprocessing
+    pub original: String,
+    /// Source line of the first line of `original`
+    pub first_line: usize,
+    /// For each processed line, the 0-based index of the line in `original` it came from
+    pub lines: Vec<usize>,
+    /// The source lines of `original` as written (doc comment markers included)
+    pub raw: Option<String>,
+}
+
+impl LineMap {
+    fn new(original: &str, first_line: usize) -> Self {
+        Self {
+            original: original.to_string(),
+            first_line,
+            lines: (0..original.lines().count()).collect(),
+            raw: None,
+        }
+    }
+
+    // Follows a pass that produced its output line `i` from its input line `step[i]`.
+    fn then(mut self, step: &[usize]) -> Self {
+        self.lines = step.iter().map(|&i| self.lookup(i)).collect();
+        self
+    }
+
+    fn lookup(&self, line: usize) -> usize {
+        self.lines
+            .get(line)
+            .or(self.lines.last())
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Options controlling the scan and expansion passes.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Naming scheme for concrete schemas generated from blueprints.
+    pub naming: NamingStrategy,
+    /// Turn recorded issues (missing fragments/blueprints, unresolved refs) into errors.
+    pub strict: bool,
+    /// Naming scheme for schemas reflected from Rust items.
+    pub schema_naming: SchemaNaming,
+    /// How doc comments of reflected Rust items become `title` and `description`.
+    pub doc_style: DocStyle,
+    /// `{{NAME}}` template variables; override the built-ins.
+    pub variables: HashMap<String, String>,
+    /// Skip Rust files that fail to parse with a warning (ignored in strict mode).
+    pub skip_parse_errors: bool,
+    /// Custom Rust type to schema mappings, consulted before the built-in ones.
+    pub type_mappers: TypeMappers,
+    /// Definitions known before scanning (e.g. registered through the builder).
+    pub registry: Registry,
+    /// Registries exported by other crates; local definitions take precedence.
+    pub imports: Vec<Registry>,
+    /// Stop after indexing: [`Scan::registry`] holds the definitions, no snippets are produced.
+    pub index_only: bool,
+    /// Tags and extensions for operations found under an input root, keyed by the root.
+    pub input_metadata: HashMap<PathBuf, InputMetadata>,
+    /// How includes given as URLs are fetched.
+    pub remote: RemoteOptions,
+    /// Snippets not backed by a scanned file (e.g. read from stdin), processed like includes
+    /// and ordered after all scanned and imported ones.
+    pub snippets: Vec<Snippet>,
+    /// Called once per scanned file.
+    pub progress: Option<ProgressCallback>,
+    /// Skip parsing Rust files found under the roots that contain none of the
+    /// [`visitor::MARKERS`]; types declared only in such files are not reflected.
+    /// Explicit includes are always parsed.
+    pub prefilter: bool,
+}
+
+/// Receives `(n, total, file)` as the n-th of `total` files is scanned, e.g. to drive a
+/// progress bar.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<ProgressFn>);
+
+type ProgressFn = dyn Fn(usize, usize, &Path) + Send + Sync;
+
+impl ProgressCallback {
+    pub fn new(f: impl Fn(usize, usize, &Path) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ProgressCallback")
+    }
+}
+
+// Reports issues found while processing a snippet: warnings, or the first one as an error
+// in strict mode (fatal issues such as fragment cycles always fail).
+fn handle_issues(
+    issues: Vec<Issue>,
+    snippet: &Snippet,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<()> {
+    for issue in issues {
+        if options.strict || issue.is_fatal() {
+            return Err(issue.into_error(snippet.file_path.clone(), snippet.line_number));
+        }
+        warn(
+            warnings,
+            Diagnostic::warning(
+                issue.to_string(),
+                snippet.file_path.clone(),
+                snippet.line_number,
+            )
+            .with_code(issue.number()),
+        );
+    }
+    Ok(())
+}
+
+fn warn(warnings: &mut Vec<Diagnostic>, diagnostic: Diagnostic) {
+    log::warn!("{}", diagnostic);
+    warnings.push(diagnostic);
 }
 
 // DX Macros Preprocessor
 // Implementation of auto-quoting and short-hands.
-fn preprocess_macros(snippet: &Snippet, registry: &mut Registry) -> Snippet {
+fn preprocess_macros(
+    snippet: &Snippet,
+    registry: &mut Registry,
+    options: &ScanOptions,
+    issues: &mut Vec<Issue>,
+) -> Snippet {
     let content = &snippet.content;
     let mut new_lines = Vec::new();
+    let mut step = Vec::new();
 
     // Regex definition
-    static GENERIC_RE: OnceLock<Regex> = OnceLock::new();
-    let generic_re =
-        GENERIC_RE.get_or_init(|| Regex::new(r"\$([a-zA-Z0-9_]+)<([a-zA-Z0-9_, ]+)>").unwrap());
-
     static MACRO_INSERT_RE: OnceLock<Regex> = OnceLock::new();
     let macro_insert_re = MACRO_INSERT_RE
         .get_or_init(|| Regex::new(r"^(\s*)(-)?\s*@insert\s+([a-zA-Z0-9_]+)$").unwrap());
@@ -38,22 +200,36 @@ fn preprocess_macros(snippet: &Snippet, registry: &mut Registry) -> Snippet {
 
     static MACRO_RETURN_RE: OnceLock<Regex> = OnceLock::new();
     let macro_return_re = MACRO_RETURN_RE.get_or_init(|| {
-        Regex::new(r#"^(\s*)@return\s+(\d{3})\s*:\s*([^\s"]+)(?:\s+"(.*)")?$"#).unwrap()
+        Regex::new(
+            r#"^(\s*)@return\s+(\d{3})\s*:\s*([^\s"]+)(?:\s+\[([^\]"]*)\])?(?:\s+"(.*)")?(?:\s+([^\s"]+/[^\s"]+))?$"#,
+        )
+        .unwrap()
     });
 
     static ARRAY_SHORT_RE: OnceLock<Regex> = OnceLock::new();
     let array_short_re =
         ARRAY_SHORT_RE.get_or_init(|| Regex::new(r"\$Vec<([a-zA-Z0-9_]+)>").unwrap());
 
-    for line in content.lines() {
+    // Generic-looking text in descriptions and examples is prose, not a blueprint use
+    let prose = prose_offsets(content);
+
+    for (idx, line) in content.lines().enumerate() {
         let mut current_lines = vec![line.to_string()];
+        let mut current_prose = vec![prose[idx]];
 
         // 0. Expand @return (Route Helper)
         if let Some(caps) = macro_return_re.captures(line) {
             let indent = &caps[1];
             let status = &caps[2];
             let schema_raw = &caps[3];
-            let desc = caps.get(4).map(|m| m.as_str()).unwrap_or("Success");
+            let desc = caps.get(5).map(|m| m.as_str()).unwrap_or("Success");
+            let is_file = schema_raw == FILE_KEYWORD;
+            let mimes: Vec<&str> = match (caps.get(4), caps.get(6)) {
+                (Some(list), _) => list.as_str().split(',').map(str::trim).collect(),
+                (None, Some(mime)) => vec![mime.as_str()],
+                (None, None) if is_file => vec![FILE_MIME],
+                (None, None) => vec!["application/json"],
+            };
 
             // If schema is $Vec<T>, it will be processed in the next step.
             // If schema is $User, we wrap it in $ref (unless it's already a ref?)
@@ -67,50 +243,75 @@ fn preprocess_macros(snippet: &Snippet, registry: &mut Registry) -> Snippet {
             // Heuristic: If schema starts with `$Vec`, use it directly as the schema value.
             // Else use `$ref: schema_raw`.
 
-            let schema_line = if schema_raw.starts_with("$Vec") {
+            let schema_line = if is_file {
+                format!("{0}        {{ type: string, format: binary }}", indent)
+            } else if schema_raw.starts_with("$Vec") {
                 format!("{0}        {1}", indent, schema_raw) // Direct inject
             } else {
                 format!("{0}        $ref: {1}", indent, schema_raw) // Ref inject
             };
 
-            let expanded = format!(
-                "{0}'{1}':\n{0}  description: \"{2}\"\n{0}  content:\n{0}    application/json:\n{0}      schema:\n{3}",
-                indent, status, desc, schema_line
+            let mut expanded = format!(
+                "{0}'{1}':\n{0}  description: \"{2}\"\n{0}  content:",
+                indent, status, desc
             );
+            for mime in mimes {
+                let schema_line = if is_text_mime(mime) && !is_file {
+                    format!("{0}        type: string", indent)
+                } else {
+                    schema_line.clone()
+                };
+                expanded.push_str(&format!(
+                    "\n{0}    {1}:\n{0}      schema:\n{2}",
+                    indent, mime, schema_line
+                ));
+            }
             current_lines = expanded.lines().map(|s| s.to_string()).collect();
+            current_prose = prose_offsets(&expanded);
         }
 
-        for sub_line in current_lines {
-            let mut processed_line = sub_line.clone();
+        for (sub_line, prose_from) in current_lines.into_iter().zip(current_prose) {
+            // Only the part before any prose is expanded; escaped `\$` is left as written
+            let (code, prose_tail) = sub_line.split_at(prose_from.unwrap_or(sub_line.len()));
+            let mut processed_line = String::with_capacity(code.len());
 
             // 1. Array Shorthand ($Vec<T>)
             // Replace ALL occurrences in the line
-            while let Some(caps) = array_short_re.captures(&processed_line.clone()) {
-                let full_match = caps.get(0).unwrap().as_str();
-                let type_name = caps.get(1).unwrap().as_str();
+            let mut copied = 0;
+            for caps in array_short_re.captures_iter(code) {
+                let full_match = caps.get(0).unwrap();
+                if code[..full_match.start()].ends_with('\\') {
+                    continue;
+                }
                 // Inline JSON syntax for array
-                let replacement = format!(
+                processed_line.push_str(&code[copied..full_match.start()]);
+                processed_line.push_str(&format!(
                     "{{ type: array, items: {{ $ref: \"#/components/schemas/{}\" }} }}",
-                    type_name
-                );
-                processed_line = processed_line.replace(full_match, &replacement);
+                    &caps[1]
+                ));
+                copied = full_match.end();
             }
+            processed_line.push_str(&code[copied..]);
 
-            // 2. Generics Flattening (Inline) + Instantiation
-            // (Existing logic)
-            while let Some(caps) = generic_re.captures(&processed_line.clone()) {
-                let full_match = caps.get(0).unwrap().as_str();
-                let name = caps.get(1).unwrap().as_str();
-                let args_raw = caps.get(2).unwrap().as_str();
-
-                // Instantiate via Monomorphizer
-                let mut mono = Monomorphizer::new(registry);
-                let concrete_name = mono.monomorphize(name, args_raw);
+            // 1b. Container Shorthands with generic arguments ($Vec<Page<User>>, $Map<K, V>, $Option<T>)
+            if CONTAINER_SHORTHANDS
+                .iter()
+                .any(|name| processed_line.contains(&format!("${}<", name)))
+            {
+                let mut mono = Monomorphizer::new(registry).with_naming(options.naming.clone());
+                processed_line = expand_container_shorthands(&processed_line, &mut mono);
+                issues.extend(mono.take_issues());
+            }
 
-                // Replace with Smart Ref format ($Name)
-                let replacement = format!("${}", concrete_name);
-                processed_line = processed_line.replace(full_match, &replacement);
+            // 2. Generics Flattening (Inline) + Instantiation
+            // Depth-aware scan, so nested multi-arg forms like $Result<Page<User>, Error>
+            // are instantiated whole and replaced with Smart Ref format ($Name)
+            if processed_line.contains('<') {
+                let mut mono = Monomorphizer::new(registry).with_naming(options.naming.clone());
+                processed_line = mono.process(&processed_line);
+                issues.extend(mono.take_issues());
             }
+            processed_line.push_str(prose_tail);
 
             // 3. Short-hand @insert
             if let Some(caps) = macro_insert_re.captures(&processed_line) {
@@ -129,264 +330,2003 @@ fn preprocess_macros(snippet: &Snippet, registry: &mut Registry) -> Snippet {
 
             // 4. Auto-Quoting @extend
             if let Some(caps) = macro_extend_re.captures(&processed_line) {
-                let indent = &caps[1];
-                let content = &caps[2];
-                let escaped_content = content.replace('\'', "''");
-                new_lines.push(format!("{}x-openapi-extend: '{}'", indent, escaped_content));
+                let at = preprocessor::push_extend_marker(&mut new_lines, &caps[1], &caps[2]);
+                // Joined an earlier marker: the lines after it moved down by one
+                if at < step.len() {
+                    step.insert(at, idx);
+                }
                 continue;
             }
 
             new_lines.push(processed_line);
         }
+        step.resize(new_lines.len(), idx);
     }
 
+    let line_map = snippet
+        .line_map
+        .clone()
+        .unwrap_or_else(|| LineMap::new(content, snippet.line_number))
+        .then(&step);
     Snippet {
         content: new_lines.join("\n"),
         file_path: snippet.file_path.clone(),
         line_number: snippet.line_number,
+        module: snippet.module.clone(),
+        line_map: Some(line_map),
+        priority: snippet.priority,
     }
 }
 
-pub fn substitute_smart_references(content: &str, schemas: &HashSet<String>) -> String {
-    let mut result = String::with_capacity(content.len());
-    let chars: Vec<char> = content.chars().collect();
-    let mut i = 0;
+const CONTAINER_SHORTHANDS: [&str; 3] = ["Vec", "Map", "Option"];
+
+// Replaces `$Vec<T>` / `$Map<K, V>` / `$Option<T>` with inline schemas. Arguments are parsed
+// depth-aware, so generic ones ($Map<String, Page<User>>) are instantiated first.
+fn expand_container_shorthands(text: &str, mono: &mut Monomorphizer) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some((pos, name)) = CONTAINER_SHORTHANDS
+        .iter()
+        .filter_map(|name| rest.find(&format!("${}<", name)).map(|pos| (pos, *name)))
+        .min_by_key(|(pos, _)| *pos)
+    {
+        let open = pos + name.len() + 1;
+        result.push_str(&rest[..pos]);
+        if rest[..pos].ends_with('\\') {
+            result.push_str(&rest[pos..=open]);
+            rest = &rest[open + 1..];
+            continue;
+        }
+        match matching_angle(rest, open) {
+            Some(close) => {
+                let schema = container_schema(name, &rest[open + 1..close], mono);
+                result.push_str(&schema.to_string());
+                rest = &rest[close + 1..];
+            }
+            None => {
+                result.push_str(&rest[pos..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
 
-    while i < chars.len() {
-        if chars[i] == '$' {
-            let mut j = i + 1;
-            if j < chars.len() && (chars[j].is_alphabetic() || chars[j] == '_') {
-                while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
-                    j += 1;
+// Byte index of the `>` closing the `<` at `open`
+fn matching_angle(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
                 }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Splits `String, Page<User>` on top-level commas
+fn split_type_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
 
-                let ident: String = chars[i + 1..j].iter().collect();
+fn container_schema(name: &str, args: &str, mono: &mut Monomorphizer) -> serde_json::Value {
+    let args = split_type_args(args);
+    match name {
+        "Vec" => serde_json::json!({
+            "type": "array",
+            "items": args.first().map_or(serde_json::json!({}), |t| type_schema(t, mono)),
+        }),
+        // Keys are always strings in JSON; only the value type matters
+        "Map" => serde_json::json!({
+            "type": "object",
+            "additionalProperties": args.last().map_or(serde_json::json!({}), |v| type_schema(v, mono)),
+        }),
+        _ => visitor::nullable_schema(
+            args.first()
+                .map_or(serde_json::json!({}), |t| type_schema(t, mono)),
+        ),
+    }
+}
 
-                if schemas.contains(&ident) {
-                    let is_quoted = i > 0 && chars[i - 1] == '"';
+// Inline schema for a shorthand argument: nested shorthand, primitive, blueprint instance or plain ref
+fn type_schema(ty: &str, mono: &mut Monomorphizer) -> serde_json::Value {
+    let ty = ty.trim().trim_start_matches('$');
+    if let Some(name) = CONTAINER_SHORTHANDS
+        .iter()
+        .find(|name| ty.starts_with(&format!("{}<", name)) && ty.ends_with('>'))
+    {
+        return container_schema(name, &ty[name.len() + 1..ty.len() - 1], mono);
+    }
+    if let Some(schema) = visitor::primitive_schema(ty) {
+        return schema;
+    }
+    if ty.contains('<') {
+        // $Page<User> -> $Page_User, resolved to a ref in PASS 4
+        return serde_json::json!({ "$ref": mono.process(&format!("${}", ty)) });
+    }
+    // Resolved like any other smart ref in PASS 4 (module scoping, unresolved reporting)
+    serde_json::json!({ "$ref": format!("${}", ty) })
+}
 
-                    if !is_quoted {
-                        result.push('"');
-                    }
-                    result.push_str("#/components/schemas/");
-                    result.push_str(&ident);
-                    if !is_quoted {
-                        result.push('"');
-                    }
+pub fn substitute_smart_references<'a>(
+    content: &'a str,
+    schemas: &HashSet<String>,
+) -> Cow<'a, str> {
+    substitute_smart_references_in(content, schemas, "")
+}
 
-                    i = j;
-                    continue;
-                }
+/// Like [`substitute_smart_references`], resolving names relative to the dotted `module`.
+///
+/// `$User` prefers `module.User` (then each parent module, then a unique `*.User`);
+/// `$accounts.User` and `$accounts::User` address a schema by its qualified name.
+/// Content without a reference to rewrite is returned borrowed.
+pub fn substitute_smart_references_in<'a>(
+    content: &'a str,
+    schemas: &HashSet<String>,
+    module: &str,
+) -> Cow<'a, str> {
+    if !content.contains('$') {
+        return Cow::Borrowed(content);
+    }
+    // Allocated on the first line that changes; CRLF line endings are normalized
+    let mut result: Option<String> = None;
+    let mut start = 0;
+    for (piece, prose_from) in content.split_inclusive('\n').zip(prose_offsets(content)) {
+        let line = piece
+            .strip_suffix('\n')
+            .map_or(piece, |l| l.strip_suffix('\r').unwrap_or(l));
+        let (code, prose) = line.split_at(prose_from.unwrap_or(line.len()));
+        let code = substitute_in_text(code, schemas, module);
+        let unchanged = matches!(code, Cow::Borrowed(_)) && !piece.ends_with("\r\n");
+        if result.is_none() && unchanged {
+            start += piece.len();
+            continue;
+        }
+        let out = result.get_or_insert_with(|| {
+            let mut out = String::with_capacity(content.len() + 64);
+            out.push_str(&content[..start]);
+            out
+        });
+        out.push_str(&code);
+        out.push_str(prose);
+        if piece.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    result.map_or(Cow::Borrowed(content), Cow::Owned)
+}
+
+static PROSE_KEY_RE: OnceLock<Regex> = OnceLock::new();
+
+// For each line, the byte offset from which it is prose that smart refs must not touch:
+// values of `description` / `summary` (inline, block scalars, continuation lines) and
+// whole `example` / `examples` subtrees. Text-based, so it works for partial snippets too.
+fn prose_offsets(content: &str) -> Vec<Option<usize>> {
+    let re = PROSE_KEY_RE.get_or_init(|| {
+        Regex::new(r#"^(\s*)(?:-\s+)?["']?(description|summary|examples?)["']?\s*:"#).unwrap()
+    });
+    let mut offsets = Vec::new();
+    let mut block_indent: Option<usize> = None;
+
+    for line in content.lines() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(key_indent) = block_indent {
+            if line.trim().is_empty() || indent > key_indent {
+                offsets.push(Some(0));
+                continue;
             }
+            block_indent = None;
         }
-        result.push(chars[i]);
-        i += 1;
+
+        let mut offset = None;
+        if let Some(caps) = re.captures(line) {
+            let key_end = caps.get(0).unwrap().end();
+            let has_value = !line[key_end..].trim().is_empty();
+            // A bare `description:` is more likely a property schema than prose
+            if has_value || caps[2].starts_with("example") {
+                block_indent = Some(caps[1].len());
+                offset = Some(key_end);
+            }
+        }
+        offsets.push(offset);
     }
-    result
+    offsets
 }
 
-fn finalize_substitution(content: &str) -> String {
-    let version = std::env::var("CARGO_PKG_VERSION").unwrap_or_else(|_| "0.0.0".to_string());
-    let step1 = content.replace(r"\$", "$");
-    step1.replace("{{CARGO_PKG_VERSION}}", &version)
+fn substitute_in_text<'a>(
+    content: &'a str,
+    schemas: &HashSet<String>,
+    module: &str,
+) -> Cow<'a, str> {
+    let is_start = |c: char| c.is_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let bytes = content.as_bytes();
+    let mut result = String::new();
+    // End of the text already moved to `result`; zero while nothing was replaced
+    let mut copied = 0;
+
+    for (i, _) in content.match_indices('$') {
+        let escaped = i > 0 && bytes[i - 1] == b'\\';
+        if escaped || !content[i + 1..].starts_with(is_start) {
+            continue;
+        }
+        // Segment ends, so `$accounts::User` yields [end of "accounts", end of "User"]
+        let mut ends = Vec::new();
+        let mut j = i + 1;
+        loop {
+            j += content[j..]
+                .find(|c| !is_ident(c))
+                .unwrap_or(content.len() - j);
+            ends.push(j);
+            let sep = if content[j..].starts_with('.') {
+                1
+            } else if content[j..].starts_with("::") {
+                2
+            } else {
+                break;
+            };
+            if !content[j + sep..].starts_with(is_start) {
+                break;
+            }
+            j += sep;
+        }
+        if RUNTIME_EXPRESSIONS.contains(&&content[i + 1..ends[0]]) {
+            continue;
+        }
+
+        // Longest qualified path first, falling back to the leading segments
+        let resolved = ends.iter().rev().find_map(|&end| {
+            let path = content[i + 1..end].replace("::", ".");
+            resolve_schema_name(&path, schemas, module).map(|n| (n, end))
+        });
+
+        if let Some((name, end)) = resolved {
+            let is_quoted = i > 0 && bytes[i - 1] == b'"';
+
+            result.push_str(&content[copied..i]);
+            if !is_quoted {
+                result.push('"');
+            }
+            result.push_str("#/components/schemas/");
+            result.push_str(&name);
+            if !is_quoted {
+                result.push('"');
+            }
+            copied = end;
+        }
+    }
+    if copied == 0 {
+        return Cow::Borrowed(content);
+    }
+    result.push_str(&content[copied..]);
+    Cow::Owned(result)
 }
 
-pub fn scan_directories(roots: &[PathBuf], includes: &[PathBuf]) -> Result<Vec<Snippet>> {
-    let mut registry = Registry::new();
-    let mut operation_snippets: Vec<Snippet> = Vec::new();
-    let mut files_found = false;
+fn resolve_schema_name(path: &str, schemas: &HashSet<String>, module: &str) -> Option<String> {
+    let parent = |m: &str| {
+        m.rfind('.')
+            .map_or(String::new(), |pos| m[..pos].to_string())
+    };
+    let (mut scope, mut rest) = (module.to_string(), path);
+    if let Some(stripped) = rest.strip_prefix("crate.") {
+        (scope, rest) = (String::new(), stripped);
+    } else if let Some(stripped) = rest.strip_prefix("self.") {
+        rest = stripped;
+    }
+    while let Some(stripped) = rest.strip_prefix("super.") {
+        (scope, rest) = (parent(&scope), stripped);
+    }
 
-    let mut all_paths = Vec::new();
+    // Innermost module first, then each parent up to the crate root
+    loop {
+        let candidate = if scope.is_empty() {
+            rest.to_string()
+        } else {
+            format!("{}.{}", scope, rest)
+        };
+        if schemas.contains(&candidate) {
+            return Some(candidate);
+        }
+        if scope.is_empty() {
+            break;
+        }
+        scope = parent(&scope);
+    }
 
-    for root in roots {
-        for entry in WalkDir::new(root) {
-            let entry = entry.map_err(|e| Error::Io(std::io::Error::other(e)))?;
-            let path = entry.path().to_path_buf();
-            if path.is_file() {
-                all_paths.push(path);
+    // Unique match in another module
+    let suffix = format!(".{}", rest);
+    let mut matches = schemas.iter().filter(|s| s.ends_with(&suffix));
+    if let (Some(only), None) = (matches.next(), matches.next()) {
+        return Some(only.clone());
+    }
+
+    // `accounts.User` when schemas are not module-qualified
+    match rest.rsplit_once('.') {
+        Some((_, name)) if schemas.contains(name) => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+// Rust module path of a source file below `root`, e.g. src/accounts/mod.rs -> ["accounts"]
+fn file_module_path(root: &std::path::Path, path: &std::path::Path) -> Vec<String> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if parts.first().is_some_and(|p| p == "src") {
+        parts.remove(0);
+    }
+    if parts
+        .last()
+        .is_some_and(|p| p == "mod" || (parts.len() == 1 && (p == "lib" || p == "main")))
+    {
+        parts.pop();
+    }
+    parts
+}
+
+// JSON Schema keywords that legitimately start with `$`
+const DOLLAR_KEYWORDS: &[&str] = &[
+    "ref",
+    "schema",
+    "id",
+    "defs",
+    "anchor",
+    "comment",
+    "dynamicRef",
+    "dynamicAnchor",
+    "vocabulary",
+];
+
+// Runtime expressions of links and callbacks (`$response.body#/id`), never smart references
+const RUNTIME_EXPRESSIONS: &[&str] = &["request", "response", "url", "method", "statusCode"];
+
+/// Finds `$Name` smart references left after substitution.
+///
+/// Only YAML values are considered: escaped `\$`, comments, mapping keys and prose
+/// (descriptions, summaries, examples) are skipped.
+pub fn find_unresolved_references(content: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for (line, prose_from) in content.lines().zip(prose_offsets(content)) {
+        let line = &line[..prose_from.unwrap_or(line.len())];
+        let code = strip_yaml_comment(line);
+        let bytes = code.as_bytes();
+        for (idx, _) in code.match_indices('$') {
+            if idx > 0 && bytes[idx - 1] == b'\\' {
+                continue;
+            }
+            let rest = &code[idx + 1..];
+            if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                continue;
+            }
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let ident = &rest[..end];
+            let after = &rest[end..];
+            let is_key = after.starts_with(':')
+                && (after.len() == 1 || after[1..].starts_with(char::is_whitespace));
+            if is_key
+                || DOLLAR_KEYWORDS.contains(&ident)
+                || RUNTIME_EXPRESSIONS.contains(&ident)
+                || found.iter().any(|f| f == ident)
+            {
+                continue;
             }
+            found.push(ident.to_string());
         }
     }
-    for path in includes {
-        if path.exists() {
-            all_paths.push(path.to_path_buf());
-        }
-    }
-
-    if !all_paths.is_empty() {
-        files_found = true;
-    }
-
-    // PASS 1: Indexing
-    for path in all_paths {
-        if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-            match ext {
-                "rs" => {
-                    let extracted = visitor::extract_from_file(path.clone())?;
-                    for item in extracted {
-                        match item {
-                            ExtractedItem::Schema {
-                                name,
-                                content,
-                                line,
-                            } => {
-                                if let Some(n) = name {
-                                    registry.insert_schema(n, content.clone());
-                                }
-                                operation_snippets.push(Snippet {
-                                    content,
-                                    file_path: path.clone(),
-                                    line_number: line,
-                                });
-                            }
-                            ExtractedItem::Fragment {
-                                name,
-                                params,
-                                content,
-                                ..
-                            } => {
-                                registry.insert_fragment(name, params, content);
-                            }
-                            ExtractedItem::Blueprint {
-                                name,
-                                params,
-                                content,
-                                ..
-                            } => {
-                                registry.insert_blueprint(name, params, content);
-                            }
-                        }
-                    }
-                }
-                "json" | "yaml" | "yml" => {
-                    let content = std::fs::read_to_string(&path)?;
-                    operation_snippets.push(Snippet {
-                        content,
-                        file_path: path.clone(),
-                        line_number: 1,
-                    });
-                }
-                _ => {}
+    found
+}
+
+// `line` without its YAML comment: a `#` at line start or after whitespace, outside quotes
+fn strip_yaml_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut prev = ' ';
+    let mut chars = line.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match quote {
+            Some('"') if c == '\\' => {
+                chars.next();
+            }
+            // `''` escapes a quote inside single quotes
+            Some('\'') if c == '\'' && chars.peek().is_some_and(|&(_, n)| n == '\'') => {
+                chars.next();
             }
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            // Quotes only open a scalar at the start of a token
+            None if (c == '"' || c == '\'') && (prev.is_whitespace() || ":[{,-".contains(prev)) => {
+                quote = Some(c)
+            }
+            None if c == '#' && prev.is_whitespace() => return &line[..idx],
+            None => {}
         }
+        prev = c;
+    }
+    line
+}
+
+fn finalize_substitution(content: &str) -> Cow<'_, str> {
+    if !content.contains(r"\$") {
+        return Cow::Borrowed(content);
     }
+    Cow::Owned(content.replace(r"\$", "$"))
+}
+
+pub fn scan_directories(roots: &[PathBuf], includes: &[PathBuf]) -> Result<Vec<Snippet>> {
+    scan_with_options(roots, includes, &ScanOptions::default())
+}
 
-    // PASS 2: Pre-Processing
-    let mut preprocessed_snippets = Vec::new();
-    for snippet in operation_snippets {
-        // 2a. Expand Macros
-        let macrod_snippet = preprocess_macros(&snippet, &mut registry);
+/// Same as [`scan_directories`], with explicit [`ScanOptions`].
+pub fn scan_with_options(
+    roots: &[PathBuf],
+    includes: &[PathBuf],
+    options: &ScanOptions,
+) -> Result<Vec<Snippet>> {
+    scan(roots, includes, options).map(|scan| scan.snippets)
+}
 
-        // 2b. Expand Fragments
-        let expanded_content = preprocessor::preprocess(&macrod_snippet.content, &registry);
+/// Snippets produced by [`scan`], plus what happened along the way.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub snippets: Vec<Snippet>,
+    /// Problems that were logged as warnings
+    pub warnings: Vec<Diagnostic>,
+    /// Fragment expansions (`@insert`/`@extend`), nested ones included
+    pub fragments_expanded: usize,
+    /// Concrete schemas generated from blueprints
+    pub blueprints_instantiated: usize,
+    /// Every definition seen, imported ones included
+    pub registry: Registry,
+    /// Files visited, in scan order
+    pub files: Vec<PathBuf>,
+}
 
-        preprocessed_snippets.push(Snippet {
-            content: expanded_content,
-            file_path: macrod_snippet.file_path,
-            line_number: macrod_snippet.line_number,
-        });
+// Schemas registered programmatically are not backed by a scanned snippet; emit one so they
+// end up in `components.schemas`. Sorted for deterministic output.
+fn seeded_schema_snippets(registry: &Registry) -> Vec<Snippet> {
+    let mut names: Vec<&String> = registry
+        .schema_sources
+        .iter()
+        .filter(|(_, source)| source.is_programmatic())
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| schema_snippet(registry, name))
+        .collect()
+}
+
+// A registered schema as a snippet of its own, attributed to where it was defined
+fn schema_snippet(registry: &Registry, name: &str) -> Snippet {
+    let source = registry
+        .schema_sources
+        .get(name)
+        .cloned()
+        .unwrap_or_else(SourceLocation::programmatic);
+    Snippet {
+        content: registry.schemas[name].clone(),
+        file_path: source.file,
+        line_number: source.line.max(1),
+        module: String::new(),
+        line_map: None,
+        priority: 0,
     }
+}
 
-    // PASS 3: Monomorphization
-    let mut monomorphizer = Monomorphizer::new(&mut registry);
-    let mut mono_snippets: Vec<Snippet> = Vec::new();
+fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext, "rs" | "json" | "yaml" | "yml" | "md"))
+}
 
-    for snippet in preprocessed_snippets {
-        let mono_content = monomorphizer.process(&snippet.content);
-        mono_snippets.push(Snippet {
-            content: mono_content,
-            file_path: snippet.file_path,
-            line_number: snippet.line_number,
-        });
+/// Like [`scan_with_options`], also reporting warnings and expansion counts.
+///
+/// Runs every [`Pipeline`] stage in order.
+pub fn scan(roots: &[PathBuf], includes: &[PathBuf], options: &ScanOptions) -> Result<Scan> {
+    let pipeline = Pipeline::extract(roots, includes, options)?;
+    if options.index_only {
+        return Pipeline {
+            snippets: Vec::new(),
+            ..pipeline
+        }
+        .finish();
     }
+    pipeline
+        .preprocess(options)
+        .monomorphize(options)
+        .substitute(options)
+        .finish()
+}
 
-    // Inject Concrete Schemas
-    let mut generated_snippets = Vec::new();
-    for (name, content) in &registry.concrete_schemas {
-        let wrapped = format!(
-            "components:\n  schemas:\n    {}:\n{}",
-            name,
-            indent(content)
-        );
-        generated_snippets.push(Snippet {
-            content: wrapped,
-            file_path: PathBuf::from("<generated>"),
-            line_number: 1,
-        });
+/// The scan as separate stages, for callers that run their own passes in between or skip
+/// some: [`Pipeline::extract`], [`Pipeline::preprocess`], [`Pipeline::monomorphize`] and
+/// [`Pipeline::substitute`], then [`Pipeline::finish`] (see [`scan`]). The snippets can
+/// be rewritten between stages; [`crate::merger`] merges the final ones.
+///
+/// A snippet that fails a stage is dropped and its error kept; the others continue, and
+/// [`Pipeline::finish`] reports every error at once.
+#[derive(Debug)]
+pub struct Pipeline {
+    pub snippets: Vec<Snippet>,
+    /// Every definition seen so far, imported ones included
+    pub registry: Registry,
+    /// Problems that were logged as warnings
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Error>,
+    /// Fragment expansions so far, nested ones included
+    pub fragments_expanded: usize,
+    /// Files visited, in scan order
+    pub files: Vec<PathBuf>,
+    roots: Vec<PathBuf>,
+    /// Inputs that contributed no files, reported if nothing is found at all
+    searched: Vec<(PathBuf, RootStatus)>,
+    files_found: bool,
+}
+
+impl Pipeline {
+    /// PASS 1: reads the inputs, indexes their definitions and extracts the raw snippets
+    /// (in-memory [`ScanOptions::snippets`] and imported schemas included).
+    ///
+    /// Fails right away only for a missing input directory.
+    pub fn extract(roots: &[PathBuf], includes: &[PathBuf], options: &ScanOptions) -> Result<Self> {
+        let mut warnings = Vec::new();
+        let mut registry = options.registry.clone();
+        let mut operation_snippets = seeded_schema_snippets(&registry);
+
+        let inputs = collect_inputs(roots, includes)?;
+        let files: Vec<PathBuf> = inputs.paths.iter().map(|(path, _)| path.clone()).collect();
+        let files_found = inputs.files_found(options);
+
+        // PASS 1: Indexing
+        // Parsing is independent per file and runs in parallel; registry insertion
+        // happens here, in path order, so results stay deterministic.
+        let rust_files = inputs
+            .paths
+            .iter()
+            .filter(|(path, _)| is_rust_file(path))
+            .map(|(path, root)| extract_job(path, root.as_deref(), options))
+            .collect();
+        let mut extractions = extract_all(rust_files, options).into_iter();
+
+        // Failures are collected per file/snippet, so one broken doc block does not hide the rest
+        let mut errors = Vec::new();
+
+        let total = inputs.paths.len();
+        for (i, (path, _)) in inputs.paths.iter().enumerate() {
+            if let Some(progress) = &options.progress {
+                progress.0(i + 1, total, path);
+            }
+            let extraction = is_rust_file(path)
+                .then(|| extractions.next().expect("one extraction per Rust file"));
+            operation_snippets.extend(index_file(
+                path,
+                extraction,
+                &mut registry,
+                options,
+                &mut warnings,
+                &mut errors,
+            ));
+        }
+        operation_snippets.extend(index_trailing(
+            &inputs.remote,
+            &mut registry,
+            options,
+            &mut warnings,
+            &mut errors,
+        ));
+
+        Ok(Pipeline {
+            snippets: operation_snippets,
+            registry,
+            warnings,
+            errors,
+            fragments_expanded: 0,
+            files,
+            roots: roots.to_vec(),
+            searched: inputs.searched,
+            files_found,
+        })
+    }
+
+    /// PASS 2: expands DSL macros (`@return`, `$Vec<T>`, inline generics, `@insert`,
+    /// `@extend`) and fragments.
+    pub fn preprocess(mut self, options: &ScanOptions) -> Self {
+        let mut preprocessed_snippets = Vec::new();
+        for snippet in std::mem::take(&mut self.snippets) {
+            match preprocess_snippet(
+                snippet,
+                &mut self.registry,
+                options,
+                &mut self.warnings,
+                &mut self.fragments_expanded,
+            ) {
+                Ok(snippet) => preprocessed_snippets.push(snippet),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        self.snippets = preprocessed_snippets;
+        self
     }
-    mono_snippets.extend(generated_snippets);
 
-    // PASS 4: Substitution
-    let mut all_schemas = registry.schemas.keys().cloned().collect::<HashSet<_>>();
-    all_schemas.extend(registry.concrete_schemas.keys().cloned());
+    /// PASS 3: instantiates blueprints used by the snippets and adds a snippet per concrete
+    /// schema.
+    pub fn monomorphize(mut self, options: &ScanOptions) -> Self {
+        let mut monomorphizer =
+            Monomorphizer::new(&mut self.registry).with_naming(options.naming.clone());
+        let mut mono_snippets: Vec<Snippet> = Vec::new();
 
-    let mut final_snippets = Vec::new();
-    for snippet in mono_snippets {
-        let subbed = substitute_smart_references(&snippet.content, &all_schemas);
-        let finalized_content = finalize_substitution(&subbed);
-        final_snippets.push(Snippet {
-            content: finalized_content,
-            file_path: snippet.file_path,
-            line_number: snippet.line_number,
-        });
+        for snippet in std::mem::take(&mut self.snippets) {
+            match monomorphize_snippet(snippet, &mut monomorphizer, options, &mut self.warnings) {
+                Ok(snippet) => mono_snippets.push(snippet),
+                Err(e) => self.errors.push(e),
+            }
+        }
+
+        // Inject Concrete Schemas
+        for result in expand_concrete_schemas(
+            &mut self.registry,
+            &mut HashSet::new(),
+            options,
+            &mut self.warnings,
+            &mut self.fragments_expanded,
+        ) {
+            match result {
+                Ok(snippet) => mono_snippets.push(snippet),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        self.snippets = mono_snippets;
+        self
     }
 
-    if !files_found {
-        return Err(Error::NoFilesFound);
+    /// PASS 4: resolves smart references (`$Name`) and `{{NAME}}` template variables.
+    pub fn substitute(mut self, options: &ScanOptions) -> Self {
+        let all_schemas = schema_names(&self.registry);
+        let mut variables = template::builtin_variables(&self.roots);
+        variables.extend(options.variables.clone());
+
+        let mut final_snippets = Vec::new();
+        for snippet in std::mem::take(&mut self.snippets) {
+            match substitute_snippet(
+                snippet,
+                &all_schemas,
+                &variables,
+                options,
+                &mut self.warnings,
+            ) {
+                Ok(snippet) => final_snippets.push(snippet),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        self.snippets = final_snippets;
+        self
     }
 
-    Ok(final_snippets)
+    /// Reports the errors of all stages, or the result.
+    pub fn finish(self) -> Result<Scan> {
+        if !self.files_found {
+            return Err(Error::NoFilesFound(self.searched));
+        }
+        Error::from_all(self.errors)?;
+        Ok(Scan {
+            snippets: self.snippets,
+            warnings: self.warnings,
+            fragments_expanded: self.fragments_expanded,
+            blueprints_instantiated: self.registry.concrete_schemas.len(),
+            registry: self.registry,
+            files: self.files,
+        })
+    }
 }
 
-fn indent(s: &str) -> String {
-    s.lines()
-        .map(|l| format!("      {}", l))
-        .collect::<Vec<_>>()
-        .join("\n")
+/// Yields the snippets of a scan one at a time, see [`iter_snippets`].
+///
+/// Indexing (PASS 1) runs up front, reading one file at a time and keeping only the
+/// definitions; each file is then read again and its snippets expanded (PASS 2 to 4) as
+/// they are pulled. A snippet's concrete blueprint schemas follow right after it, and the
+/// non-file snippets (remote includes, imported schemas, [`ScanOptions::snippets`]) come
+/// last. Errors are yielded in place of the snippets that failed; indexing errors first.
+#[derive(Debug)]
+pub struct SnippetIter {
+    options: ScanOptions,
+    registry: Registry,
+    variables: HashMap<String, String>,
+    warnings: Vec<Diagnostic>,
+    /// Files still to be expanded
+    files: std::vec::IntoIter<(PathBuf, Option<PathBuf>)>,
+    /// Extracted but not yet expanded: the current file's snippets
+    pending: std::collections::VecDeque<Snippet>,
+    /// Snippets kept from indexing, expanded after all files
+    trailing: Vec<Snippet>,
+    ready: std::collections::VecDeque<Result<Snippet>>,
+    concrete_emitted: HashSet<String>,
+    fragments_expanded: usize,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Scans like [`scan_directories`], yielding snippets as files are processed instead of
+/// collecting them. See [`SnippetIter`].
+pub fn iter_snippets(roots: &[PathBuf], includes: &[PathBuf]) -> SnippetIter {
+    iter_snippets_with_options(roots, includes, ScanOptions::default())
+}
 
-    #[test]
-    fn test_escaping() {
-        let input = r"price: \$100";
-        let output = finalize_substitution(input);
-        assert_eq!(output, "price: $100");
+/// Same as [`iter_snippets`], with explicit [`ScanOptions`].
+pub fn iter_snippets_with_options(
+    roots: &[PathBuf],
+    includes: &[PathBuf],
+    options: ScanOptions,
+) -> SnippetIter {
+    let mut iter = SnippetIter {
+        registry: options.registry.clone(),
+        variables: template::builtin_variables(roots),
+        warnings: Vec::new(),
+        files: Vec::new().into_iter(),
+        pending: Default::default(),
+        trailing: Vec::new(),
+        ready: Default::default(),
+        concrete_emitted: HashSet::new(),
+        fragments_expanded: 0,
+        options,
+    };
+    iter.variables.extend(iter.options.variables.clone());
+    if let Err(e) = iter.index(roots, includes) {
+        iter.ready.push_back(Err(e));
     }
+    iter
+}
 
-    #[test]
-    fn test_vec_macro() {
-        let mut registry = Registry::new();
-        let snippet = Snippet {
-            content: "tags: $Vec<Tag>".to_string(),
-            file_path: PathBuf::from("test.rs"),
-            line_number: 1,
-        };
-        let processed = preprocess_macros(&snippet, &mut registry);
-        assert!(processed.content.contains("type: array"));
-        assert!(processed.content.contains("items:"));
-        assert!(
-            processed
-                .content
-                .contains("$ref: \"#/components/schemas/Tag\"")
+impl SnippetIter {
+    /// Every definition indexed, plus the concrete schemas instantiated so far.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Problems logged as warnings so far.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Fragment expansions so far, nested ones included.
+    pub fn fragments_expanded(&self) -> usize {
+        self.fragments_expanded
+    }
+
+    // PASS 1, one file at a time; only the definitions are kept
+    fn index(&mut self, roots: &[PathBuf], includes: &[PathBuf]) -> Result<()> {
+        let inputs = collect_inputs(roots, includes)?;
+        if !inputs.files_found(&self.options) {
+            return Err(Error::NoFilesFound(inputs.searched));
+        }
+        let options = &self.options;
+        let mut errors = Vec::new();
+        self.pending.extend(seeded_schema_snippets(&self.registry));
+        let total = inputs.paths.len();
+        for (i, (path, root)) in inputs.paths.iter().enumerate() {
+            if let Some(progress) = &options.progress {
+                progress.0(i + 1, total, path);
+            }
+            let extraction = is_rust_file(path).then(|| {
+                let job = extract_job(path, root.as_deref(), options);
+                extract_one(&job, options)
+            });
+            index_file(
+                path,
+                extraction,
+                &mut self.registry,
+                options,
+                &mut self.warnings,
+                &mut errors,
+            );
+        }
+        self.trailing = index_trailing(
+            &inputs.remote,
+            &mut self.registry,
+            options,
+            &mut self.warnings,
+            &mut errors,
         );
+        self.ready.extend(errors.into_iter().map(Err));
+        self.files = inputs.paths.into_iter();
+        Ok(())
     }
 
-    #[test]
-    fn test_return_helper() {
-        let mut registry = Registry::new();
-        let snippet = Snippet {
-            content: "@return 200: $User \"Success\"".to_string(),
-            file_path: PathBuf::from("test.rs"),
-            line_number: 1,
+    // Re-extracts the next file's snippets; its definitions were indexed already
+    fn extract_next_file(&mut self) -> bool {
+        let Some((path, root)) = self.files.next() else {
+            return false;
         };
-        let processed = preprocess_macros(&snippet, &mut registry);
-        assert!(processed.content.contains("'200':"));
-        assert!(processed.content.contains("description: \"Success\""));
-        assert!(processed.content.contains("schema:"));
-        assert!(processed.content.contains("$ref: $User"));
+        let options = &self.options;
+        let extraction = is_rust_file(&path).then(|| {
+            let job = extract_job(&path, root.as_deref(), options);
+            extract_one(&job, options)
+        });
+        // Indexing reported this file's problems
+        let snippets = index_file(
+            &path,
+            extraction,
+            &mut Registry::default(),
+            options,
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+        self.pending.extend(snippets);
+        true
+    }
+
+    // PASS 2 to 4 for one snippet, followed by the concrete schemas it instantiated
+    fn expand(&mut self, snippet: Snippet) {
+        let options = &self.options;
+        let expanded = preprocess_snippet(
+            snippet,
+            &mut self.registry,
+            options,
+            &mut self.warnings,
+            &mut self.fragments_expanded,
+        )
+        .and_then(|snippet| {
+            let mut monomorphizer =
+                Monomorphizer::new(&mut self.registry).with_naming(options.naming.clone());
+            monomorphize_snippet(snippet, &mut monomorphizer, options, &mut self.warnings)
+        });
+        let generated = expand_concrete_schemas(
+            &mut self.registry,
+            &mut self.concrete_emitted,
+            options,
+            &mut self.warnings,
+            &mut self.fragments_expanded,
+        );
+
+        let all_schemas = schema_names(&self.registry);
+        for snippet in std::iter::once(expanded).chain(generated) {
+            let result = snippet.and_then(|snippet| {
+                substitute_snippet(
+                    snippet,
+                    &all_schemas,
+                    &self.variables,
+                    options,
+                    &mut self.warnings,
+                )
+            });
+            self.ready.push_back(result);
+        }
+    }
+}
+
+impl Iterator for SnippetIter {
+    type Item = Result<Snippet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(item);
+            }
+            if let Some(snippet) = self.pending.pop_front() {
+                self.expand(snippet);
+                continue;
+            }
+            if !self.extract_next_file() {
+                if self.trailing.is_empty() {
+                    return None;
+                }
+                self.pending.extend(std::mem::take(&mut self.trailing));
+            }
+        }
+    }
+}
+
+// Scan inputs: files with the root they were found under (`None` for includes), URL
+// includes, and the inputs that contributed nothing
+struct Inputs {
+    paths: Vec<(PathBuf, Option<PathBuf>)>,
+    remote: Vec<String>,
+    searched: Vec<(PathBuf, RootStatus)>,
+}
+
+impl Inputs {
+    fn files_found(&self, options: &ScanOptions) -> bool {
+        !self.remote.is_empty()
+            || !options.snippets.is_empty()
+            || self.paths.iter().any(|(path, _)| is_source_file(path))
+    }
+}
+
+// Fails only for a missing input directory
+fn collect_inputs(roots: &[PathBuf], includes: &[PathBuf]) -> Result<Inputs> {
+    let mut paths = Vec::new();
+    let mut searched = Vec::new();
+
+    for root in roots {
+        if !root.exists() {
+            return Err(Error::InputNotFound(root.clone()));
+        }
+        let mut any_file = false;
+        let mut any_source = false;
+        // Sorted walk, so snippet order does not depend on the filesystem
+        for entry in WalkDir::new(root).sort_by_file_name() {
+            let entry = entry.map_err(|e| Error::Io(std::io::Error::other(e)))?;
+            let path = entry.path().to_path_buf();
+            if path.is_file() {
+                any_file = true;
+                any_source |= is_source_file(&path);
+                paths.push((path, Some(root.clone())));
+            }
+        }
+        match (any_file, any_source) {
+            (false, _) => searched.push((root.clone(), RootStatus::Empty)),
+            (true, false) => searched.push((root.clone(), RootStatus::NoMatchingFiles)),
+            _ => {}
+        }
+    }
+    let mut remote = Vec::new();
+    for path in includes {
+        if remote::is_url(path) {
+            remote.push(path.to_string_lossy().into_owned());
+            continue;
+        }
+        if !path.exists() {
+            searched.push((path.clone(), RootStatus::NotFound));
+            continue;
+        }
+        if !is_source_file(path) {
+            searched.push((path.clone(), RootStatus::NoMatchingFiles));
+        }
+        paths.push((path.to_path_buf(), None));
+    }
+    // Root and include order on the command line must not change the output either
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Inputs {
+        paths,
+        remote,
+        searched,
+    })
+}
+
+fn is_rust_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "rs")
+}
+
+fn extract_job(path: &Path, root: Option<&Path>, options: &ScanOptions) -> ExtractJob {
+    let module_path = match (options.schema_naming, root) {
+        (SchemaNaming::ModulePrefixed, Some(root)) => file_module_path(root, path),
+        _ => Vec::new(),
+    };
+    let metadata = root
+        .and_then(|root| options.input_metadata.get(root))
+        .cloned()
+        .unwrap_or_default();
+    let prefilter = options.prefilter && root.is_some();
+    (path.to_path_buf(), module_path, metadata, prefilter)
+}
+
+// PASS 1 for one file: indexes its definitions and returns its snippets. `extraction` is
+// the visitor's result for Rust files.
+fn index_file(
+    path: &Path,
+    extraction: Option<Extraction>,
+    registry: &mut Registry,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+    errors: &mut Vec<Error>,
+) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let at = |line| SourceLocation {
+        file: path.to_path_buf(),
+        line,
+    };
+    match path.extension().and_then(|s| s.to_str()) {
+        Some("rs") => {
+            let extracted = match extraction.expect("Rust files come with their extraction") {
+                Ok((extracted, visitor_warnings)) => {
+                    for diagnostic in visitor_warnings {
+                        warn(warnings, diagnostic);
+                    }
+                    extracted
+                }
+                Err(Error::Parse { file, source })
+                    if options.skip_parse_errors && !options.strict =>
+                {
+                    let message = format!("skipping file that does not parse: {}", source);
+                    let start = source.span().start();
+                    let mut diagnostic =
+                        Diagnostic::warning(message, file, start.line).with_code(code::RUST_SYNTAX);
+                    diagnostic.column = Some(start.column + 1);
+                    warn(warnings, diagnostic);
+                    return snippets;
+                }
+                Err(Error::Multiple(all)) => {
+                    errors.extend(all);
+                    return snippets;
+                }
+                Err(e) => {
+                    errors.push(e);
+                    return snippets;
+                }
+            };
+            for item in extracted {
+                match item {
+                    ExtractedItem::Schema {
+                        name,
+                        content,
+                        line,
+                        module,
+                        first_line,
+                        raw,
+                        priority,
+                    } => {
+                        if let Some(n) = name {
+                            if let Err(e) = registry.define_schema(n, content.clone(), at(line)) {
+                                errors.push(e);
+                                continue;
+                            }
+                        }
+                        let line_map = first_line.map(|first| LineMap {
+                            raw,
+                            ..LineMap::new(&content, first)
+                        });
+                        snippets.push(Snippet {
+                            content,
+                            file_path: path.to_path_buf(),
+                            line_number: line,
+                            module,
+                            line_map,
+                            priority,
+                        });
+                    }
+                    ExtractedItem::Component {
+                        section,
+                        name,
+                        content,
+                        line,
+                        first_line,
+                    } => {
+                        if let Err(e) = registry.define_component(section, &name, at(line)) {
+                            errors.push(e);
+                            continue;
+                        }
+                        snippets.push(Snippet {
+                            line_map: first_line.map(|first| LineMap::new(&content, first)),
+                            content,
+                            file_path: path.to_path_buf(),
+                            line_number: line,
+                            module: String::new(),
+                            priority: 0,
+                        });
+                    }
+                    ExtractedItem::Fragment {
+                        name,
+                        params,
+                        defaults,
+                        content,
+                        line,
+                    } => {
+                        if let Err(e) =
+                            registry.define_fragment(name, params, defaults, content, at(line))
+                        {
+                            errors.push(e);
+                        }
+                    }
+                    ExtractedItem::Blueprint {
+                        name,
+                        params,
+                        content,
+                        line,
+                    } => {
+                        if let Err(e) = registry.define_blueprint(name, params, content, at(line)) {
+                            errors.push(e);
+                        }
+                    }
+                }
+            }
+        }
+        Some("json" | "yaml" | "yml") => {
+            let indexed = std::fs::read_to_string(path)
+                .map_err(Error::from)
+                .and_then(|content| index_yaml_definitions(path, content, registry));
+            match indexed {
+                Ok(Some(content)) => snippets.push(Snippet {
+                    content,
+                    file_path: path.to_path_buf(),
+                    line_number: 1,
+                    module: String::new(),
+                    line_map: None,
+                    priority: 0,
+                }),
+                Ok(None) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+        Some("md") => {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(e) => {
+                    errors.push(e.into());
+                    return snippets;
+                }
+            };
+            for (fence_line, block) in markdown_blocks(&content) {
+                let content = match index_yaml_definitions(path, block.clone(), registry) {
+                    Ok(Some(content)) => content,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        errors.push(e);
+                        continue;
+                    }
+                };
+                // Unless definitions were split off, block lines follow the fence
+                let line_map = (content == block).then(|| LineMap::new(&block, fence_line + 1));
+                snippets.push(Snippet {
+                    content,
+                    file_path: path.to_path_buf(),
+                    line_number: fence_line,
+                    module: String::new(),
+                    line_map,
+                    priority: 0,
+                });
+            }
+        }
+        _ => {}
+    }
+    snippets
+}
+
+// PASS 1 for what follows the files: remote includes, imported registries and in-memory
+// snippets
+fn index_trailing(
+    remote_includes: &[String],
+    registry: &mut Registry,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+    errors: &mut Vec<Error>,
+) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+
+    // Remote includes are treated like local YAML/JSON includes
+    for url in remote_includes {
+        let indexed = remote::fetch(url, &options.remote)
+            .and_then(|content| index_yaml_definitions(Path::new(url), content, registry));
+        match indexed {
+            Ok(Some(content)) => snippets.push(Snippet {
+                content,
+                file_path: PathBuf::from(url),
+                line_number: 1,
+                module: String::new(),
+                line_map: None,
+                priority: 0,
+            }),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+
+    // Imported definitions come after local ones, so collisions keep the local definition
+    for imported in &options.imports {
+        let (schemas, collisions) = registry.import(imported.clone());
+        for collision in collisions {
+            if options.strict {
+                errors.push(Error::ImportCollision {
+                    kind: collision.kind,
+                    name: collision.name,
+                });
+                continue;
+            }
+            let local = collision.local.unwrap_or_else(SourceLocation::programmatic);
+            let message = format!(
+                "imported {} '{}' is shadowed by a local definition",
+                collision.kind, collision.name
+            );
+            warn(
+                warnings,
+                Diagnostic::warning(message, local.file, local.line)
+                    .with_code(code::IMPORT_COLLISION),
+            );
+        }
+        snippets.extend(schemas.iter().map(|name| schema_snippet(registry, name)));
+    }
+
+    // In-memory snippets come last, after everything scanned or imported
+    for snippet in &options.snippets {
+        match index_yaml_definitions(&snippet.file_path, snippet.content.clone(), registry) {
+            Ok(Some(content)) => snippets.push(Snippet {
+                content,
+                ..snippet.clone()
+            }),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    snippets
+}
+
+// PASS 2 for one snippet
+fn preprocess_snippet(
+    snippet: Snippet,
+    registry: &mut Registry,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+    fragments_expanded: &mut usize,
+) -> Result<Snippet> {
+    let mut issues = Vec::new();
+
+    // 2a. Expand Macros
+    let macrod_snippet = preprocess_macros(&snippet, registry, options, &mut issues);
+
+    // 2b. Expand Fragments
+    let expanded = preprocessor::expand_mapped(&macrod_snippet.content, registry, &mut issues);
+    *fragments_expanded += expanded.fragments;
+    handle_issues(issues, &snippet, options, warnings)?;
+
+    Ok(Snippet {
+        content: expanded.content,
+        file_path: macrod_snippet.file_path,
+        line_number: macrod_snippet.line_number,
+        module: macrod_snippet.module,
+        line_map: macrod_snippet
+            .line_map
+            .map(|map| map.then(&expanded.line_map)),
+        priority: macrod_snippet.priority,
+    })
+}
+
+// PASS 3 for one snippet; instantiated schemas land in the monomorphizer's registry
+fn monomorphize_snippet(
+    snippet: Snippet,
+    monomorphizer: &mut Monomorphizer,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Snippet> {
+    // Prose (descriptions, examples) is left alone, as in PASS 2
+    let content = &snippet.content;
+    let mono_content = if content.contains('<') {
+        content
+            .lines()
+            .zip(prose_offsets(content))
+            .map(|(line, prose_from)| {
+                let (code, prose) = line.split_at(prose_from.unwrap_or(line.len()));
+                monomorphizer.process(code) + prose
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            + if content.ends_with('\n') { "\n" } else { "" }
+    } else {
+        content.clone()
+    };
+    handle_issues(monomorphizer.take_issues(), &snippet, options, warnings)?;
+    Ok(Snippet {
+        content: mono_content,
+        ..snippet
+    })
+}
+
+// Rounds of concrete schemas instantiated by other concrete schemas, against blueprints
+// that keep nesting themselves ($Tree<$Tree<T>>)
+const MAX_CONCRETE_ROUNDS: usize = 32;
+
+// PASS 2 and 3 for the concrete schemas not in `emitted` yet, in name order: blueprint bodies
+// may use macros ($Vec<T>), fragments (@insert) and further generics, whose concrete
+// schemas follow
+fn expand_concrete_schemas(
+    registry: &mut Registry,
+    emitted: &mut HashSet<String>,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+    fragments_expanded: &mut usize,
+) -> Vec<Result<Snippet>> {
+    let mut results = Vec::new();
+    for _ in 0..MAX_CONCRETE_ROUNDS {
+        let pending: Vec<Snippet> = registry
+            .concrete_schemas
+            .iter()
+            .filter(|(name, _)| !emitted.contains(*name))
+            .map(|(name, content)| concrete_snippet(name, content))
+            .collect();
+        if pending.is_empty() {
+            return results;
+        }
+        emitted.extend(registry.concrete_schemas.keys().cloned());
+        for snippet in pending {
+            let result =
+                preprocess_snippet(snippet, registry, options, warnings, fragments_expanded)
+                    .and_then(|snippet| {
+                        let mut monomorphizer =
+                            Monomorphizer::new(registry).with_naming(options.naming.clone());
+                        monomorphize_snippet(snippet, &mut monomorphizer, options, warnings)
+                    });
+            results.push(result);
+        }
+    }
+    log::warn!(
+        "Concrete schemas still nest further blueprints after {} rounds; stopped expanding",
+        MAX_CONCRETE_ROUNDS
+    );
+    results
+}
+
+fn concrete_snippet(name: &str, content: &str) -> Snippet {
+    Snippet {
+        content: format!(
+            "components:\n  schemas:\n    {}:\n{}",
+            name,
+            indent(content)
+        ),
+        file_path: PathBuf::from("<generated>"),
+        line_number: 1,
+        module: String::new(),
+        line_map: None,
+        priority: 0,
+    }
+}
+
+// Names `$Name` can refer to
+fn schema_names(registry: &Registry) -> HashSet<String> {
+    registry
+        .schemas
+        .keys()
+        .chain(registry.concrete_schemas.keys())
+        .cloned()
+        .collect()
+}
+
+// PASS 4 for one snippet
+fn substitute_snippet(
+    snippet: Snippet,
+    all_schemas: &HashSet<String>,
+    variables: &HashMap<String, String>,
+    options: &ScanOptions,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Snippet> {
+    let subbed = substitute_smart_references_in(&snippet.content, all_schemas, &snippet.module);
+    let unresolved = find_unresolved_references(&subbed)
+        .into_iter()
+        .map(Issue::UnresolvedReference)
+        .collect();
+    handle_issues(unresolved, &snippet, options, warnings)?;
+    let mut issues = Vec::new();
+    let rendered = template::render(&subbed, variables, &mut issues);
+    handle_issues(issues, &snippet, options, warnings)?;
+    let content = match finalize_substitution(&rendered) {
+        Cow::Owned(finalized) => finalized,
+        Cow::Borrowed(_) => rendered,
+    };
+    Ok(Snippet { content, ..snippet })
+}
+
+// Extracted items and the visitor's warnings
+type Extraction = Result<(Vec<ExtractedItem>, Vec<Diagnostic>)>;
+
+// File, module path, input metadata and whether the pre-filter applies
+type ExtractJob = (PathBuf, Vec<String>, InputMetadata, bool);
+
+fn extract_one(job: &ExtractJob, options: &ScanOptions) -> Extraction {
+    let (path, module, metadata, prefilter) = job;
+    let content = std::fs::read_to_string(path)?;
+    if *prefilter && !visitor::has_markers(&content) {
+        log::debug!("Skipping {:?}: no markers", path);
+        return Ok((Vec::new(), Vec::new()));
+    }
+    visitor::extract_from_source(
+        path.clone(),
+        &content,
+        module.clone(),
+        options.schema_naming,
+        &options.type_mappers,
+        metadata,
+        options.doc_style,
+    )
+}
+
+// Runs PASS 1 extraction on worker threads; results are returned in input order.
+fn extract_all(jobs: Vec<ExtractJob>, options: &ScanOptions) -> Vec<Extraction> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(jobs.len());
+    if workers <= 1 {
+        return jobs.iter().map(|job| extract_one(job, options)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Extraction>> = (0..jobs.len()).map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(idx) else {
+                            break;
+                        };
+                        done.push((idx, extract_one(job, options)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (idx, extracted) in handle.join().expect("extraction worker panicked") {
+                results[idx] = Some(extracted);
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.expect("every file is extracted"))
+        .collect()
+}
+
+const YAML_FRAGMENTS_KEY: &str = "x-oas-forge-fragments";
+const YAML_BLUEPRINTS_KEY: &str = "x-oas-forge-blueprints";
+
+/// Registers fragments/blueprints declared in a standalone YAML file:
+///
+/// ```yaml
+/// x-oas-forge-fragments:
+///   NotFound:
+///     params: [resource]
+///     defaults: { resource: Item }
+///     body: |
+///       description: "{{resource}} not found"
+/// x-oas-forge-blueprints:
+///   Page:
+///     params: [T]
+///     body:
+///       type: object
+///       properties:
+///         items: { type: array, items: { $ref: $T } }
+/// ```
+///
+/// Returns the rest of the file as snippet content, or `None` if nothing else is left.
+// Fenced code blocks marked `openapi` (alone or after `yaml`/`yml`/`json`), with the
+// 1-based line of their opening fence
+fn markdown_blocks(content: &str) -> Vec<(usize, String)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(usize, String, usize, bool)> = None;
+    let mut lines = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim_start();
+        let fence_len = |c: char| trimmed.chars().take_while(|&x| x == c).count();
+        match &open {
+            Some((fence_line, fence, fence_indent, marked)) => {
+                let closes = trimmed.starts_with(fence.as_str())
+                    && trimmed.trim_end().chars().all(|c| fence.starts_with(c));
+                if closes {
+                    if *marked {
+                        blocks.push((*fence_line, lines.join("\n") + "\n"));
+                    }
+                    open = None;
+                    lines.clear();
+                } else {
+                    let strip = indent.min(*fence_indent);
+                    lines.push(&line[strip..]);
+                }
+            }
+            None => {
+                let len = fence_len('`').max(fence_len('~'));
+                if len >= 3 {
+                    let fence = trimmed[..len].to_string();
+                    let info: Vec<&str> = trimmed[len..].split_whitespace().collect();
+                    let marked = matches!(
+                        info.as_slice(),
+                        ["openapi"] | ["yaml" | "yml" | "json", "openapi"]
+                    );
+                    open = Some((idx + 1, fence, indent, marked));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+fn index_yaml_definitions(
+    path: &std::path::Path,
+    content: String,
+    registry: &mut Registry,
+) -> Result<Option<String>> {
+    if !content.contains(YAML_FRAGMENTS_KEY) && !content.contains(YAML_BLUEPRINTS_KEY) {
+        return Ok(Some(content));
+    }
+    // Invalid files are left to the merger, which reports them with context
+    let Ok(serde_yaml::Value::Mapping(mut root)) = serde_yaml::from_str(&content) else {
+        return Ok(Some(content));
+    };
+
+    for key in [YAML_FRAGMENTS_KEY, YAML_BLUEPRINTS_KEY] {
+        let Some(serde_yaml::Value::Mapping(entries)) = root.remove(key) else {
+            continue;
+        };
+        for (name, entry) in entries {
+            let Some(name) = name.as_str().map(String::from) else {
+                continue;
+            };
+            let params: Vec<String> = entry
+                .get("params")
+                .and_then(|p| p.as_sequence())
+                .map(|seq| {
+                    seq.iter()
+                        .filter_map(|p| p.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let body = match entry.get("body") {
+                Some(serde_yaml::Value::String(body)) => body.clone(),
+                Some(body) => serde_yaml::to_string(body)?,
+                None => {
+                    log::warn!("{}: '{}' has no body, skipping", path.display(), name);
+                    continue;
+                }
+            };
+            // Best-effort line of the entry, for duplicate reports
+            let line = content
+                .lines()
+                .position(|l| l.trim_start().starts_with(&format!("{}:", name)))
+                .map_or(1, |idx| idx + 1);
+            let source = SourceLocation {
+                file: path.to_path_buf(),
+                line,
+            };
+
+            if key == YAML_FRAGMENTS_KEY {
+                let defaults = entry
+                    .get("defaults")
+                    .and_then(|d| d.as_mapping())
+                    .map(|m| {
+                        m.iter()
+                            .filter_map(|(k, v)| {
+                                let value = match v {
+                                    serde_yaml::Value::String(s) => s.clone(),
+                                    other => serde_yaml::to_string(other).ok()?.trim().to_string(),
+                                };
+                                Some((k.as_str()?.to_string(), value))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                registry.define_fragment(name, params, defaults, body, source)?;
+            } else {
+                registry.define_blueprint(name, params, body, source)?;
+            }
+        }
+    }
+
+    if root.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(serde_yaml::to_string(&root)?))
+    }
+}
+
+pub(crate) fn indent(s: &str) -> String {
+    s.lines()
+        .map(|l| format!("      {}", l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaping() {
+        let input = r"price: \$100";
+        let output = finalize_substitution(input);
+        assert_eq!(output, "price: $100");
+    }
+
+    #[test]
+    fn test_prose_is_not_substituted() {
+        let schemas: HashSet<String> = ["User", "Tokens"].into_iter().map(String::from).collect();
+        let content = "\
+User:
+  description: costs $User tokens per call
+  summary: \"$Tokens\"
+  properties:
+    owner:
+      $ref: $User
+    description:
+      $ref: $Tokens
+  example:
+    path: $HOME/$User
+  x-note: |
+    not prose, $User
+notes:
+  description: |
+    multi-line $User
+    mentions $Tokens
+  $ref: $User
+price: \\$User
+";
+        let out = substitute_smart_references(content, &schemas);
+        assert!(out.contains("description: costs $User tokens per call"));
+        assert!(out.contains("summary: \"$Tokens\""));
+        assert!(out.contains("path: $HOME/$User"));
+        assert!(out.contains("multi-line $User\n    mentions $Tokens"));
+        assert!(out.contains("owner:\n      $ref: \"#/components/schemas/User\""));
+        // A property named `description` is still a schema position
+        assert!(out.contains("description:\n      $ref: \"#/components/schemas/Tokens\""));
+        assert!(out.contains("not prose, \"#/components/schemas/User\""));
+        assert!(out.contains("  $ref: \"#/components/schemas/User\"\nprice"));
+        assert!(out.contains("price: \\$User"));
+        assert!(out.ends_with('\n'));
+
+        assert!(find_unresolved_references("description: costs $Nope\nexample: $Nada").is_empty());
+    }
+
+    #[test]
+    fn test_find_unresolved_references() {
+        let content = "$ref: $Usr\nother: \"#/components/schemas/User\"\nprice: \\$Money\nlink: $response.body#/id\ncost: $100";
+        assert_eq!(find_unresolved_references(content), vec!["Usr".to_string()]);
+
+        let content = "# see $Old\n$Custom: 1\nname: x # was $Legacy\nitems: [$A, $A]";
+        assert_eq!(find_unresolved_references(content), vec!["A".to_string()]);
+
+        // `#` inside quoted scalars does not start a comment
+        let content = "title: \"Costs # of $Usr\"\n$ref: '#/x' # $Gone\nitems: ['a #b', $Acct] # $Old\nname: 'it''s # $Quoted'";
+        assert_eq!(
+            find_unresolved_references(content),
+            vec!["Usr".to_string(), "Acct".to_string(), "Quoted".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_runtime_expressions_are_not_substituted() {
+        // Even when a schema happens to share the name
+        let schemas: HashSet<String> = ["response", "User"].map(String::from).into();
+        let content = "id: $response.body#/id\nuser: $User";
+        assert_eq!(
+            substitute_smart_references(content, &schemas),
+            "id: $response.body#/id\nuser: \"#/components/schemas/User\""
+        );
+    }
+
+    #[test]
+    fn test_module_scoped_references() {
+        let schemas: HashSet<String> =
+            ["accounts.User", "billing.User", "billing.Invoice", "Error"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+        let sub = |content: &str, module: &str| {
+            substitute_smart_references_in(content, &schemas, module).into_owned()
+        };
+
+        assert_eq!(
+            sub("$User", "billing"),
+            "\"#/components/schemas/billing.User\""
+        );
+        assert_eq!(
+            sub("$User", "billing.invoices"),
+            "\"#/components/schemas/billing.User\""
+        );
+        assert_eq!(
+            sub("$accounts::User", "billing"),
+            "\"#/components/schemas/accounts.User\""
+        );
+        assert_eq!(
+            sub("$super::accounts.User", "billing"),
+            "\"#/components/schemas/accounts.User\""
+        );
+        assert_eq!(
+            sub("$Invoice", "accounts"),
+            "\"#/components/schemas/billing.Invoice\""
+        );
+        assert_eq!(sub("$Error", "accounts"), "\"#/components/schemas/Error\"");
+        // Ambiguous outside both modules
+        assert_eq!(sub("$User", ""), "$User");
+        // Trailing punctuation is not part of the path
+        assert_eq!(
+            sub("see $Error.", ""),
+            "see \"#/components/schemas/Error\"."
+        );
+    }
+
+    #[test]
+    fn test_file_module_path() {
+        let root = std::path::Path::new("/proj");
+        let module = |p: &str| file_module_path(root, std::path::Path::new(p)).join(".");
+        assert_eq!(module("/proj/src/lib.rs"), "");
+        assert_eq!(module("/proj/src/accounts.rs"), "accounts");
+        assert_eq!(module("/proj/src/billing/mod.rs"), "billing");
+        assert_eq!(module("/proj/src/billing/invoices.rs"), "billing.invoices");
+    }
+
+    #[test]
+    fn test_snippet_order_is_deterministic() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [
+            "zeta", "alpha", "mid", "beta", "omega", "gamma", "delta", "kappa",
+        ];
+        for (i, name) in names.iter().enumerate() {
+            let sub = dir.path().join(if i % 2 == 0 { "a" } else { "b" });
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(
+                sub.join(format!("{}.rs", name)),
+                format!("/// @openapi\nstruct S{} {{ id: u64 }}\n", i),
+            )
+            .unwrap();
+        }
+
+        let order = || {
+            scan_directories(&[dir.path().to_path_buf()], &[])
+                .unwrap()
+                .into_iter()
+                .map(|s| s.file_path)
+                .collect::<Vec<_>>()
+        };
+        let first = order();
+        assert_eq!(first.len(), names.len());
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+        for _ in 0..5 {
+            assert_eq!(order(), first);
+        }
+    }
+
+    #[test]
+    fn test_vec_macro() {
+        let mut registry = Registry::new();
+        let snippet = Snippet {
+            content: "tags: $Vec<Tag>".to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let processed = preprocess_macros(
+            &snippet,
+            &mut registry,
+            &ScanOptions::default(),
+            &mut Vec::new(),
+        );
+        assert!(processed.content.contains("type: array"));
+        assert!(processed.content.contains("items:"));
+        assert!(
+            processed
+                .content
+                .contains("$ref: \"#/components/schemas/Tag\"")
+        );
+    }
+
+    fn expand_line(content: &str, registry: &mut Registry) -> String {
+        let snippet = Snippet {
+            content: content.to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        preprocess_macros(&snippet, registry, &ScanOptions::default(), &mut Vec::new()).content
+    }
+
+    #[test]
+    fn test_vec_macro_nested_generic() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint("Page".to_string(), vec!["T".to_string()], "x: $T".into());
+        let content = expand_line("pages: $Vec<Page<User>>", &mut registry);
+
+        let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(value["pages"]["type"].as_str(), Some("array"));
+        assert_eq!(value["pages"]["items"]["$ref"].as_str(), Some("$Page_User"));
+        assert!(registry.concrete_schemas.contains_key("Page_User"));
+        assert!(!registry.blueprints.contains_key("Vec"));
+    }
+
+    #[test]
+    fn test_map_macro() {
+        let mut registry = Registry::new();
+        let content = expand_line("users: $Map<String, User>", &mut registry);
+        let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(value["users"]["type"].as_str(), Some("object"));
+        assert_eq!(
+            value["users"]["additionalProperties"]["$ref"].as_str(),
+            Some("$User")
+        );
+
+        let content = expand_line("counts: $Map<String, u64>", &mut registry);
+        assert!(content.contains(r#""additionalProperties":{"format":"int64","type":"integer"}"#));
+    }
+
+    #[test]
+    fn test_option_macro() {
+        let mut registry = Registry::new();
+        let content = expand_line("owner: $Option<User>", &mut registry);
+        let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(value["owner"]["allOf"][0]["$ref"].as_str(), Some("$User"));
+        assert!(value["owner"].get("$ref").is_none());
+        assert_eq!(value["owner"]["nullable"].as_bool(), Some(true));
+
+        // Resolved in PASS 4 relative to the snippet's module
+        let schemas: HashSet<String> = ["billing.User".to_string()].into();
+        let resolved = substitute_smart_references_in(&content, &schemas, "billing");
+        assert!(
+            resolved.contains("#/components/schemas/billing.User"),
+            "{}",
+            resolved
+        );
+
+        let content = expand_line("tags: $Option<$Map<String, Tag>>", &mut registry);
+        let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(value["tags"]["type"].as_str(), Some("object"));
+        assert_eq!(value["tags"]["nullable"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_map_macro_with_generic_value() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint("Page".to_string(), vec!["T".to_string()], "x: $T".into());
+        let content = expand_line("pages: $Map<String, Page<User>>", &mut registry);
+        assert!(content.contains(r#""additionalProperties":{"$ref":"$Page_User"}"#));
+        assert!(registry.concrete_schemas.contains_key("Page_User"));
+    }
+
+    #[test]
+    fn test_generics_in_prose_are_left_alone() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint("Page".to_string(), vec!["T".to_string()], "x: $T".into());
+        let content = "info:
+  description: |
+    Paginated responses are written `$Page<User>` or $Vec<User>.
+  summary: $Page<Tag>
+example: {page: $Page<Order>}
+schema: $Page<User>
+other: \\$Page<Item>
+items: \\$Vec<Item>";
+        let expanded = expand_line(content, &mut registry);
+        assert!(expanded.contains("written `$Page<User>` or $Vec<User>."));
+        assert!(expanded.contains("summary: $Page<Tag>"));
+        assert!(expanded.contains("example: {page: $Page<Order>}"));
+        assert!(expanded.contains("schema: $Page_User"));
+        assert!(expanded.contains("other: \\$Page<Item>"));
+        assert!(expanded.contains("items: \\$Vec<Item>"));
+
+        // PASS 3 leaves the same text alone
+        let mut monomorphizer = Monomorphizer::new(&mut registry);
+        let snippet = Snippet {
+            content: content.to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let options = ScanOptions::default();
+        let snippet =
+            monomorphize_snippet(snippet, &mut monomorphizer, &options, &mut Vec::new()).unwrap();
+        assert!(snippet.content.contains("summary: $Page<Tag>"));
+        assert!(snippet.content.contains("other: \\$Page<Item>"));
+        assert!(snippet.content.contains("schema: $Page_User"));
+
+        let concrete: Vec<_> = registry.concrete_schemas.keys().collect();
+        assert_eq!(concrete, ["Page_User"]);
+    }
+
+    #[test]
+    fn test_inline_generic_uses_naming_strategy() {
+        let mut registry = Registry::new();
+        registry.insert_blueprint("Page".to_string(), vec!["T".to_string()], "x: $T".into());
+        let snippet = Snippet {
+            content: "schema: $Page<User>".to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let options = ScanOptions {
+            naming: NamingStrategy::Of,
+            ..Default::default()
+        };
+        let processed = preprocess_macros(&snippet, &mut registry, &options, &mut Vec::new());
+        assert_eq!(processed.content, "schema: $PageOfUser");
+        assert!(registry.concrete_schemas.contains_key("PageOfUser"));
+    }
+
+    #[test]
+    fn test_return_helper() {
+        let mut registry = Registry::new();
+        let snippet = Snippet {
+            content: "@return 200: $User \"Success\"".to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let processed = preprocess_macros(
+            &snippet,
+            &mut registry,
+            &ScanOptions::default(),
+            &mut Vec::new(),
+        );
+        assert!(processed.content.contains("'200':"));
+        assert!(processed.content.contains("description: \"Success\""));
+        assert!(processed.content.contains("schema:"));
+        assert!(processed.content.contains("$ref: $User"));
+    }
+
+    #[test]
+    fn test_return_helper_file() {
+        let mut registry = Registry::new();
+        let snippet = Snippet {
+            content: "responses:\n  @return 200: file \"The PDF\" application/pdf".to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let processed = preprocess_macros(
+            &snippet,
+            &mut registry,
+            &ScanOptions::default(),
+            &mut Vec::new(),
+        );
+        let doc: serde_yaml::Value = serde_yaml::from_str(&processed.content).unwrap();
+        let response = &doc["responses"]["200"];
+        assert_eq!(response["description"], "The PDF");
+        assert_eq!(
+            response["content"]["application/pdf"]["schema"],
+            serde_yaml::from_str::<serde_yaml::Value>("{type: string, format: binary}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_return_helper_mime_list() {
+        let mut registry = Registry::new();
+        let snippet = Snippet {
+            content: "responses:\n  @return 200: $Report [application/json, text/csv, application/yaml] \"The report\"".to_string(),
+            file_path: PathBuf::from("test.rs"),
+            line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
+        };
+        let processed = preprocess_macros(
+            &snippet,
+            &mut registry,
+            &ScanOptions::default(),
+            &mut Vec::new(),
+        );
+        let doc: serde_yaml::Value = serde_yaml::from_str(&processed.content).unwrap();
+        let response = &doc["responses"]["200"];
+        assert_eq!(response["description"], "The report");
+        let content = response["content"].as_mapping().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(
+            content["application/yaml"]["schema"]["$ref"],
+            content["application/json"]["schema"]["$ref"]
+        );
+        assert_eq!(content["text/csv"]["schema"]["type"], "string");
     }
 
     #[test]
@@ -396,8 +2336,16 @@ mod tests {
             content: "@return 400: $Vec<Error>".to_string(),
             file_path: PathBuf::from("test.rs"),
             line_number: 1,
+            module: String::new(),
+            line_map: None,
+            priority: 0,
         };
-        let processed = preprocess_macros(&snippet, &mut registry);
+        let processed = preprocess_macros(
+            &snippet,
+            &mut registry,
+            &ScanOptions::default(),
+            &mut Vec::new(),
+        );
         assert!(processed.content.contains("'400':"));
         assert!(processed.content.contains("type: array"));
         assert!(
@@ -406,4 +2354,368 @@ mod tests {
                 .contains("$ref: \"#/components/schemas/Error\"")
         );
     }
+
+    #[test]
+    fn test_missing_root_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("sr");
+        match scan_directories(std::slice::from_ref(&missing), &[]) {
+            Err(Error::InputNotFound(path)) => assert_eq!(path, missing),
+            other => panic!("expected InputNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_files_found_lists_searched_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty");
+        let docs = dir.path().join("docs");
+        std::fs::create_dir(&empty).unwrap();
+        std::fs::create_dir(&docs).unwrap();
+        std::fs::write(docs.join("notes.txt"), "Notes").unwrap();
+        let include = dir.path().join("missing.yaml");
+
+        let err = scan_directories(
+            &[empty.clone(), docs.clone()],
+            std::slice::from_ref(&include),
+        )
+        .unwrap_err();
+        let Error::NoFilesFound(searched) = &err else {
+            panic!("expected NoFilesFound, got {:?}", err);
+        };
+        assert_eq!(
+            searched,
+            &[
+                (empty.clone(), RootStatus::Empty),
+                (docs, RootStatus::NoMatchingFiles),
+                (include, RootStatus::NotFound),
+            ]
+        );
+        assert!(
+            err.to_string()
+                .contains(&format!("\n- {}: empty", empty.display()))
+        );
+    }
+
+    #[test]
+    fn test_progress_reported_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.rs"),
+            "/// @openapi\n/// paths: {}\nfn a() {}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("b.yaml"), "components: {}\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "Notes").unwrap();
+
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let options = ScanOptions {
+            progress: Some(ProgressCallback::new(move |n, total, file| {
+                let name = file.file_name().unwrap().to_string_lossy().into_owned();
+                recorded.lock().unwrap().push((n, total, name));
+            })),
+            ..Default::default()
+        };
+        scan(&[dir.path().to_path_buf()], &[], &options).unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                (1, 3, "a.rs".to_string()),
+                (2, 3, "b.yaml".to_string()),
+                (3, 3, "notes.txt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefilter_skips_unannotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("api.rs"),
+            "/// @route GET /ping\n/// @return 200: \"pong\"\nfn ping() {}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.rs"), "fn broken( {\n").unwrap();
+        let roots = [dir.path().to_path_buf()];
+
+        assert!(matches!(
+            scan(&roots, &[], &ScanOptions::default()),
+            Err(Error::Parse { .. })
+        ));
+
+        let options = ScanOptions {
+            prefilter: true,
+            ..Default::default()
+        };
+        let snippets = scan(&roots, &[], &options).unwrap().snippets;
+        assert_eq!(snippets.len(), 1);
+        assert!(snippets[0].content.contains("/ping"));
+
+        // Explicit includes are always parsed
+        let includes = [dir.path().join("broken.rs")];
+        assert!(matches!(
+            scan(&[], &includes, &options),
+            Err(Error::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_iter_snippets_matches_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.rs"),
+            r#"
+//! @openapi-fragment Paged
+//! - name: page
+//!   in: query
+
+//! @openapi<T>
+//! type: object
+//! properties:
+//!   items: {type: array, items: $T}
+struct Page<T> { items: Vec<T> }
+
+/// @openapi
+struct User { id: u64 }
+
+/// @route GET /users
+/// @return 200: $Page<User> "Users"
+fn list() {}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b.yaml"),
+            "paths:\n  /ping:\n    get:\n      parameters:\n        @insert Paged\n      responses: {'200': {description: ok}}\n",
+        )
+        .unwrap();
+        let roots = [dir.path().to_path_buf()];
+
+        let mut expected: Vec<String> = scan_directories(&roots, &[])
+            .unwrap()
+            .into_iter()
+            .map(|s| s.content)
+            .collect();
+        let mut streamed: Vec<String> = iter_snippets(&roots, &[])
+            .map(|s| s.unwrap().content)
+            .collect();
+        expected.sort();
+        streamed.sort();
+        assert_eq!(streamed, expected);
+        assert!(streamed.iter().any(|s| s.contains("Page_User:")));
+        assert!(streamed.iter().any(|s| s.contains("name: page")));
+    }
+
+    #[test]
+    fn test_iter_snippets_reads_files_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let route = |path: &str| {
+            format!(
+                "/// @route GET {}\n/// @return 200: \"ok\"\nfn f() {{}}\n",
+                path
+            )
+        };
+        std::fs::write(dir.path().join("a.rs"), route("/a")).unwrap();
+        std::fs::write(dir.path().join("b.rs"), route("/b")).unwrap();
+
+        // Indexing reads every file once
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counted = reads.clone();
+        let options = ScanOptions {
+            progress: Some(ProgressCallback::new(move |_, _, _| {
+                counted.fetch_add(1, Ordering::Relaxed);
+            })),
+            ..Default::default()
+        };
+        let mut snippets = iter_snippets_with_options(&[dir.path().to_path_buf()], &[], options);
+        assert_eq!(reads.load(Ordering::Relaxed), 2);
+
+        let first = snippets.next().unwrap().unwrap();
+        assert!(first.content.contains("/a:"));
+
+        // b.rs is only read for expansion once its snippets are pulled
+        std::fs::write(dir.path().join("b.rs"), route("/b2")).unwrap();
+        let second = snippets.next().unwrap().unwrap();
+        assert!(second.content.contains("/b2:"));
+        assert!(snippets.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_snippets_yields_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn broken( {\n").unwrap();
+        std::fs::write(dir.path().join("b.yaml"), "x: $Missing\n").unwrap();
+        let options = ScanOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let items: Vec<_> =
+            iter_snippets_with_options(&[dir.path().to_path_buf()], &[], options).collect();
+        assert!(matches!(items[0], Err(Error::Parse { .. })));
+        assert!(matches!(&items[1], Err(e) if e.to_string().contains("Missing")));
+        assert_eq!(items.len(), 2);
+
+        let missing = dir.path().join("missing");
+        let mut snippets = iter_snippets(&[missing], &[]);
+        assert!(matches!(
+            snippets.next(),
+            Some(Err(Error::InputNotFound(_)))
+        ));
+        assert!(snippets.next().is_none());
+    }
+
+    // The char-based implementation the byte-index one replaced, kept as the reference
+    fn reference_substitute(content: &str, schemas: &HashSet<String>, module: &str) -> String {
+        if !content.contains('$') {
+            return content.to_string();
+        }
+        let mut result = String::new();
+        for (line, prose_from) in content.lines().zip(prose_offsets(content)) {
+            let (code, prose) = line.split_at(prose_from.unwrap_or(line.len()));
+            result.push_str(&reference_substitute_text(code, schemas, module));
+            result.push_str(prose);
+            result.push('\n');
+        }
+        if !content.ends_with('\n') {
+            result.pop();
+        }
+        result
+    }
+
+    fn reference_substitute_text(content: &str, schemas: &HashSet<String>, module: &str) -> String {
+        let mut result = String::new();
+        let chars: Vec<char> = content.chars().collect();
+        let is_start = |c: char| c.is_alphabetic() || c == '_';
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let mut i = 0;
+        while i < chars.len() {
+            let escaped = i > 0 && chars[i - 1] == '\\';
+            if chars[i] == '$' && !escaped && i + 1 < chars.len() && is_start(chars[i + 1]) {
+                let mut ends = Vec::new();
+                let mut j = i + 1;
+                loop {
+                    while j < chars.len() && is_ident(chars[j]) {
+                        j += 1;
+                    }
+                    ends.push(j);
+                    let sep = if chars.get(j) == Some(&'.') {
+                        1
+                    } else if chars.get(j) == Some(&':') && chars.get(j + 1) == Some(&':') {
+                        2
+                    } else {
+                        break;
+                    };
+                    if !chars.get(j + sep).is_some_and(|c| is_start(*c)) {
+                        break;
+                    }
+                    j += sep;
+                }
+                let resolved = ends.iter().rev().find_map(|&end| {
+                    let path: String = chars[i + 1..end].iter().collect();
+                    resolve_schema_name(&path.replace("::", "."), schemas, module).map(|n| (n, end))
+                });
+                if let Some((name, end)) = resolved {
+                    let is_quoted = i > 0 && chars[i - 1] == '"';
+                    if !is_quoted {
+                        result.push('"');
+                    }
+                    result.push_str("#/components/schemas/");
+                    result.push_str(&name);
+                    if !is_quoted {
+                        result.push('"');
+                    }
+                    i = end;
+                    continue;
+                }
+            }
+            result.push(chars[i]);
+            i += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn test_substitution_matches_reference() {
+        let schemas: HashSet<String> = ["User", "accounts.User", "billing.Invoice", "Ünï"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let fragments = [
+            "$",
+            "$",
+            "$",
+            "User",
+            "Invoice",
+            "accounts",
+            "billing",
+            "Ünï",
+            "crate",
+            "super",
+            "self",
+            "x",
+            "_",
+            "1",
+            ".",
+            "::",
+            ":",
+            "\\",
+            "\"",
+            "'",
+            " ",
+            "  ",
+            "\n",
+            "\n",
+            "\r\n",
+            "\r",
+            "- ",
+            "#",
+            "é",
+            "日本",
+            "$ref: ",
+            "description: ",
+            "summary: |",
+            "example:\n  ",
+            "examples: ",
+            "type: object",
+            "{",
+            "}",
+            "[",
+            ", ",
+        ];
+        let modules = ["", "accounts", "billing.invoices"];
+
+        // xorshift, so failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        for _ in 0..5000 {
+            let len = next(24);
+            let content: String = (0..len).map(|_| fragments[next(fragments.len())]).collect();
+            let module = modules[next(modules.len())];
+
+            let expected = reference_substitute(&content, &schemas, module);
+            let actual = substitute_smart_references_in(&content, &schemas, module);
+            assert_eq!(
+                actual, expected,
+                "content: {:?}, module: {:?}",
+                content, module
+            );
+            assert_eq!(
+                matches!(actual, Cow::Borrowed(_)),
+                expected == content,
+                "content: {:?}",
+                content
+            );
+
+            let expected = content.replace(r"\$", "$");
+            let actual = finalize_substitution(&content);
+            assert_eq!(actual, expected);
+            assert_eq!(matches!(actual, Cow::Borrowed(_)), expected == content);
+        }
+    }
 }
@@ -0,0 +1,212 @@
+use crate::error::Result;
+use crate::{Format, Generator};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>API documentation</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+const REDOC: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>API documentation</title>
+</head>
+<body>
+  <redoc spec-url="/openapi.json"></redoc>
+  <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+</body>
+</html>
+"#;
+
+/// An HTTP response produced by [`SpecHandler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+}
+
+/// Serves the generated document and documentation pages, independent of any HTTP stack:
+///
+/// - `/` Swagger UI, `/redoc` Redoc
+/// - `/openapi.json` and `/openapi.yaml` the document, regenerated on every request
+///
+/// Mount it in an existing app by forwarding request paths to [`SpecHandler::handle`].
+#[derive(Clone)]
+pub struct SpecHandler {
+    generator: Generator,
+}
+
+impl SpecHandler {
+    pub fn new(generator: Generator) -> Self {
+        Self { generator }
+    }
+
+    /// Answers a GET request for `path` (a query string is ignored).
+    pub fn handle(&self, path: &str) -> Response {
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+        match path {
+            "/" | "/index.html" => Response::new(200, "text/html; charset=utf-8", SWAGGER_UI),
+            "/redoc" => Response::new(200, "text/html; charset=utf-8", REDOC),
+            "/openapi.json" => self.spec(Format::JsonPretty, "application/json"),
+            "/openapi.yaml" => self.spec(Format::Yaml, "application/yaml"),
+            _ => Response::new(404, "text/plain; charset=utf-8", "Not found"),
+        }
+    }
+
+    // Regenerated per request, so edits show up on reload
+    fn spec(&self, format: Format, content_type: &'static str) -> Response {
+        let generated = self
+            .generator
+            .clone()
+            .generate_value()
+            .and_then(|value| format.serialize(&value));
+        match generated {
+            Ok(body) => Response::new(200, content_type, body),
+            Err(e) => {
+                log::error!("{}", e);
+                Response::new(500, "text/plain; charset=utf-8", e.to_string())
+            }
+        }
+    }
+}
+
+/// Serves [`SpecHandler`] on `addr` until the process exits; requests are handled one at a time.
+pub fn serve(generator: Generator, addr: impl ToSocketAddrs) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let handler = SpecHandler::new(generator);
+    for stream in listener.incoming() {
+        if let Err(e) = stream.and_then(|stream| handle_connection(&handler, stream)) {
+            log::warn!("Connection failed: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(handler: &SpecHandler, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not needed, but must be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => handler.handle(path),
+        _ => Response::new(405, "text/plain; charset=utf-8", "Method not allowed"),
+    };
+    log::info!("{} -> {}", request_line.trim(), response.status);
+
+    let reason = match response.status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn handler() -> (tempfile::TempDir, SpecHandler) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: Served, version: \"1\"}\n",
+        )
+        .unwrap();
+        let handler = SpecHandler::new(Generator::new().input(dir.path()));
+        (dir, handler)
+    }
+
+    #[test]
+    fn test_handler_content_types() {
+        let (_dir, handler) = handler();
+
+        let json = handler.handle("/openapi.json");
+        assert_eq!((json.status, json.content_type), (200, "application/json"));
+        let value: serde_json::Value = serde_json::from_str(&json.body).unwrap();
+        assert_eq!(value["info"]["title"], "Served");
+
+        let yaml = handler.handle("/openapi.yaml?v=2");
+        assert_eq!((yaml.status, yaml.content_type), (200, "application/yaml"));
+        assert!(yaml.body.contains("title: Served"));
+
+        let ui = handler.handle("/");
+        assert_eq!(ui.content_type, "text/html; charset=utf-8");
+        assert!(ui.body.contains("/openapi.json"));
+
+        assert_eq!(handler.handle("/missing").status, 404);
+    }
+
+    #[test]
+    fn test_handler_reports_generation_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let handler = SpecHandler::new(Generator::new().input(dir.path()));
+        let response = handler.handle("/openapi.json");
+        assert_eq!(response.status, 500);
+        assert!(response.body.contains("No files found"));
+    }
+
+    #[test]
+    fn test_serves_over_http() {
+        let (_dir, handler) = handler();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(&handler, stream).unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET /openapi.yaml HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/yaml\r\n"));
+        assert!(response.ends_with("title: Served\n  version: '1'\n"));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::refs;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// Tag used for operations without tags.
+pub const DEFAULT_TAG: &str = "default";
+
+pub(crate) const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Splits a merged document into one document per operation tag.
+///
+/// Each document keeps the root fields (info, servers, ...), the paths with operations
+/// carrying the tag, every component those reference (transitively) and all security
+/// schemes. Operations with several tags appear in each; untagged ones go to [`DEFAULT_TAG`].
+pub fn split_by_tag(doc: &Value) -> BTreeMap<String, Value> {
+    let mut paths_by_tag: BTreeMap<String, Mapping> = BTreeMap::new();
+    let paths = doc.get("paths").and_then(Value::as_mapping);
+
+    for (path, item) in paths.into_iter().flatten() {
+        let Value::Mapping(item) = item else { continue };
+        for (method, operation) in item {
+            if !method.as_str().is_some_and(|m| HTTP_METHODS.contains(&m)) {
+                continue;
+            }
+            for tag in operation_tags(operation) {
+                let tagged_paths = paths_by_tag.entry(tag).or_default();
+                let tagged_item = tagged_paths
+                    .entry(path.clone())
+                    .or_insert_with(|| Value::Mapping(path_level_fields(item)));
+                if let Value::Mapping(tagged_item) = tagged_item {
+                    tagged_item.insert(method.clone(), operation.clone());
+                }
+            }
+        }
+    }
+
+    paths_by_tag
+        .into_iter()
+        .map(|(tag, paths)| {
+            let split = tag_document(doc, &tag, Value::Mapping(paths));
+            (tag, split)
+        })
+        .collect()
+}
+
+fn operation_tags(operation: &Value) -> Vec<String> {
+    let tags: Vec<String> = operation
+        .get("tags")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t.as_str().map(String::from))
+        .collect();
+    if tags.is_empty() {
+        vec![DEFAULT_TAG.to_string()]
+    } else {
+        tags
+    }
+}
+
+// Path item fields shared by its operations (summary, parameters, servers, ...)
+fn path_level_fields(item: &Mapping) -> Mapping {
+    item.iter()
+        .filter(|(key, _)| !key.as_str().is_some_and(|k| HTTP_METHODS.contains(&k)))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+fn tag_document(doc: &Value, tag: &str, paths: Value) -> Value {
+    let reachable = refs::reachable_refs(doc, &paths);
+    let mut out = Mapping::new();
+
+    for (key, value) in doc.as_mapping().into_iter().flatten() {
+        match key.as_str() {
+            Some("paths") => {
+                out.insert(key.clone(), paths.clone());
+            }
+            Some("components") => {
+                let components = referenced_components(value, &reachable);
+                if !components.is_empty() {
+                    out.insert(key.clone(), Value::Mapping(components));
+                }
+            }
+            Some("tags") => {
+                let tags: Vec<Value> = value
+                    .as_sequence()
+                    .into_iter()
+                    .flatten()
+                    .filter(|t| t.get("name").and_then(Value::as_str) == Some(tag))
+                    .cloned()
+                    .collect();
+                if !tags.is_empty() {
+                    out.insert(key.clone(), Value::Sequence(tags));
+                }
+            }
+            _ => {
+                out.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Mapping(out)
+}
+
+// Components that are referenced, in their original order; security schemes are shared.
+fn referenced_components(
+    components: &Value,
+    reachable: &std::collections::BTreeSet<String>,
+) -> Mapping {
+    let mut out = Mapping::new();
+    for (kind, entries) in components.as_mapping().into_iter().flatten() {
+        let Some(kind_name) = kind.as_str() else {
+            continue;
+        };
+        let Some(entries) = entries.as_mapping() else {
+            continue;
+        };
+        let kept: Mapping = entries
+            .iter()
+            .filter(|(name, _)| {
+                kind_name == "securitySchemes"
+                    || name.as_str().is_some_and(|name| {
+                        reachable.contains(&format!(
+                            "#/components/{}/{}",
+                            kind_name,
+                            refs::escape(name)
+                        ))
+                    })
+            })
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        if !kept.is_empty() {
+            out.insert(kind.clone(), Value::Mapping(kept));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_by_tag() {
+        let doc: Value = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info: {title: Shop, version: "1"}
+tags: [{name: users}, {name: orders}]
+paths:
+  /users:
+    parameters: [{name: x, in: query}]
+    get: {tags: [users], responses: {'200': {description: ok}}}
+  /orders:
+    get:
+      tags: [orders, users]
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: "#/components/schemas/Order"}
+  /health:
+    get: {responses: {'200': {description: ok}}}
+components:
+  schemas:
+    Order: {type: object}
+  securitySchemes:
+    token: {type: http, scheme: bearer}
+"##,
+        )
+        .unwrap();
+
+        let split = split_by_tag(&doc);
+        assert_eq!(
+            split.keys().collect::<Vec<_>>(),
+            vec!["default", "orders", "users"]
+        );
+
+        let users = &split["users"];
+        assert!(users["paths"]["/users"]["parameters"].is_sequence());
+        assert!(users["paths"]["/orders"]["get"].is_mapping());
+        assert!(users["components"]["schemas"]["Order"].is_mapping());
+        assert_eq!(users["tags"].as_sequence().unwrap().len(), 1);
+
+        let default = &split["default"];
+        assert!(default["paths"]["/health"].is_mapping());
+        assert!(default["paths"].get("/users").is_none());
+        assert!(default["components"].get("schemas").is_none());
+        assert!(default["components"]["securitySchemes"]["token"].is_mapping());
+        assert_eq!(default["info"]["title"].as_str(), Some("Shop"));
+    }
+}
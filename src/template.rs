@@ -0,0 +1,169 @@
+use crate::diagnostics::Issue;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static PLACEHOLDER_RE: OnceLock<Regex> = OnceLock::new();
+
+/// Built-in `{{NAME}}` variables for a scan of `roots`: `CARGO_PKG_VERSION`,
+/// `CARGO_PKG_NAME`, `CARGO_PKG_DESCRIPTION`, `GIT_SHA` and `BUILD_DATE`.
+///
+/// Package values come from the nearest Cargo.toml at or above an input
+/// directory, falling back to the environment of the running process.
+pub fn builtin_variables(roots: &[PathBuf]) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    let package = roots.iter().find_map(|root| find_package(root));
+
+    let Package {
+        name,
+        version,
+        description,
+    } = package.unwrap_or_default();
+    vars.insert(
+        "CARGO_PKG_VERSION".to_string(),
+        version
+            .or_else(|| std::env::var("CARGO_PKG_VERSION").ok())
+            .unwrap_or_else(|| "0.0.0".to_string()),
+    );
+    vars.insert(
+        "CARGO_PKG_NAME".to_string(),
+        name.or_else(|| std::env::var("CARGO_PKG_NAME").ok())
+            .unwrap_or_default(),
+    );
+    vars.insert(
+        "CARGO_PKG_DESCRIPTION".to_string(),
+        description
+            .or_else(|| std::env::var("CARGO_PKG_DESCRIPTION").ok())
+            .unwrap_or_default(),
+    );
+    vars.insert(
+        "GIT_SHA".to_string(),
+        git_sha(roots.first().map(PathBuf::as_path)).unwrap_or_default(),
+    );
+    vars.insert("BUILD_DATE".to_string(), build_date());
+    vars
+}
+
+/// Replaces `{{NAME}}` placeholders with `vars`; unknown names are recorded in `issues` and left as-is.
+pub fn render(content: &str, vars: &HashMap<String, String>, issues: &mut Vec<Issue>) -> String {
+    let re = PLACEHOLDER_RE
+        .get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap());
+    re.replace_all(content, |caps: &regex::Captures| match vars.get(&caps[1]) {
+        Some(value) => value.clone(),
+        None => {
+            let name = caps[1].to_string();
+            if !issues.contains(&Issue::UnknownVariable(name.clone())) {
+                issues.push(Issue::UnknownVariable(name));
+            }
+            caps[0].to_string()
+        }
+    })
+    .into_owned()
+}
+
+#[derive(Default)]
+struct Package {
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+}
+
+// The first Cargo.toml with a [package] at or above `dir`
+fn find_package(dir: &Path) -> Option<Package> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors().find_map(|d| {
+        let content = std::fs::read_to_string(d.join("Cargo.toml")).ok()?;
+        let manifest: toml::Table = toml::from_str(&content).ok()?;
+        let package = manifest.get("package")?.as_table()?;
+        let field = |key: &str| package.get(key).and_then(|v| v.as_str()).map(String::from);
+        Some(Package {
+            name: field("name"),
+            version: field("version"),
+            description: field("description"),
+        })
+    })
+}
+
+fn git_sha(dir: Option<&Path>) -> Option<String> {
+    let mut cmd = std::process::Command::new("git");
+    if let Some(dir) = dir.filter(|d| d.is_dir()) {
+        cmd.arg("-C").arg(dir);
+    }
+    let output = cmd.args(["rev-parse", "HEAD"]).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// UTC date as YYYY-MM-DD; honors SOURCE_DATE_EPOCH for reproducible builds
+fn build_date() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64)
+        });
+    let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian (year, month, day)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_known_and_unknown() {
+        let vars = HashMap::from([("API_NAME".to_string(), "Billing".to_string())]);
+        let mut issues = Vec::new();
+        let out = render(
+            "title: {{API_NAME}}\nx: {{ API_NAME }} {{MISSING}} {{MISSING}}",
+            &vars,
+            &mut issues,
+        );
+        assert_eq!(out, "title: Billing\nx: Billing {{MISSING}} {{MISSING}}");
+        assert_eq!(issues, vec![Issue::UnknownVariable("MISSING".to_string())]);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn test_package_from_scanned_crate() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"billing-api\"\nversion = \"2.4.1\"\ndescription = \"Billing\"\n",
+        )
+        .unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir(&src).unwrap();
+
+        let vars = builtin_variables(&[src]);
+        assert_eq!(vars["CARGO_PKG_VERSION"], "2.4.1");
+        assert_eq!(vars["CARGO_PKG_NAME"], "billing-api");
+        assert_eq!(vars["CARGO_PKG_DESCRIPTION"], "Billing");
+        assert_eq!(vars["BUILD_DATE"].len(), 10);
+    }
+}
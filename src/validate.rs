@@ -0,0 +1,558 @@
+use crate::diagnostics::{Diagnostic, code};
+use crate::merger::SOURCE_KEY;
+use crate::refs;
+use regex::Regex;
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::OnceLock;
+
+static PATH_PARAM_RE: OnceLock<Regex> = OnceLock::new();
+static COMPONENT_NAME_RE: OnceLock<Regex> = OnceLock::new();
+static RESPONSE_CODE_RE: OnceLock<Regex> = OnceLock::new();
+
+pub(crate) const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+const PARAMETER_LOCATIONS: [&str; 4] = ["query", "header", "path", "cookie"];
+
+/// OpenAPI version the document is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecVersion {
+    #[default]
+    V3_0,
+    V3_1,
+}
+
+impl SpecVersion {
+    /// Version declared by the document's `openapi` field (`3.0.x` or `3.1.x`).
+    pub fn from_document(doc: &Value) -> Option<Self> {
+        let version = doc.get("openapi")?.as_str()?;
+        if version.starts_with("3.0") {
+            Some(SpecVersion::V3_0)
+        } else if version.starts_with("3.1") {
+            Some(SpecVersion::V3_1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks the merged document for structural problems the OpenAPI schema would reject.
+///
+/// Diagnostics point at the offending value with a JSON pointer. Everything is an error,
+/// except sibling keys next to `$ref` in 3.0, which are ignored by tools rather than invalid.
+pub fn validate_document(doc: &Value, version: SpecVersion) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        doc,
+        version,
+        operation_ids: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    validator.root();
+    validator.paths();
+    validator.components();
+    if version == SpecVersion::V3_0 {
+        validator.ref_siblings(doc, &mut String::new());
+    }
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    doc: &'a Value,
+    version: SpecVersion,
+    /// operationId -> pointer of the first operation using it
+    operation_ids: HashMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, pointer: impl Into<String>, message: impl Into<String>) {
+        self.diagnostics
+            .push(Diagnostic::error_at_pointer(message, pointer).with_code(code::INVALID_DOCUMENT));
+    }
+
+    // Follows a `$ref` to its target; `None` if it dangles (reported separately)
+    fn resolve(&self, value: &'a Value) -> Option<&'a Value> {
+        match value.get("$ref").and_then(Value::as_str) {
+            Some(target) => refs::resolve(self.doc, target),
+            None => Some(value),
+        }
+    }
+
+    fn root(&mut self) {
+        match self.doc.get("openapi") {
+            None => self.error("/openapi", "missing required field 'openapi'"),
+            Some(version) if !version.as_str().is_some_and(|v| v.starts_with("3.")) => {
+                let version = serde_yaml::to_string(version).unwrap_or_default();
+                self.error(
+                    "/openapi",
+                    format!("unsupported OpenAPI version {}", version.trim()),
+                )
+            }
+            _ => {}
+        }
+
+        match self.doc.get("info") {
+            None => self.error("/info", "missing required field 'info'"),
+            Some(info) => {
+                for field in ["title", "version"] {
+                    if info.get(field).is_none() {
+                        self.error(
+                            format!("/info/{}", field),
+                            format!("missing required field 'info.{}'", field),
+                        );
+                    }
+                }
+            }
+        }
+
+        // 3.1 allows a document with only webhooks or components
+        if self.version == SpecVersion::V3_0 && self.doc.get("paths").is_none() {
+            self.error("/paths", "missing required field 'paths'");
+        }
+    }
+
+    fn paths(&mut self) {
+        let Some(paths) = self.doc.get("paths").and_then(Value::as_mapping) else {
+            return;
+        };
+        let re = PATH_PARAM_RE.get_or_init(|| Regex::new(r"\{([^{}/]+)\}").unwrap());
+        for (path, item) in paths {
+            let Some(path) = path.as_str() else { continue };
+            let pointer = format!("/paths/{}", refs::escape(path));
+            if !path.starts_with('/') {
+                self.error(&pointer, format!("path '{}' must start with '/'", path));
+            }
+            let Some(item) = self.resolve(item) else {
+                continue;
+            };
+
+            let template: BTreeSet<&str> = re
+                .captures_iter(path)
+                .map(|c| c.get(1).unwrap().as_str())
+                .collect();
+
+            let shared = self.parameters(item, &format!("{}/parameters", pointer));
+            for name in shared.iter().filter(|n| !template.contains(n.as_str())) {
+                self.error(
+                    format!("{}/parameters", pointer),
+                    format!("path parameter '{}' does not appear in the path", name),
+                );
+            }
+
+            for (method, operation) in item.as_mapping().into_iter().flatten() {
+                let Some(method) = method.as_str().filter(|m| HTTP_METHODS.contains(m)) else {
+                    continue;
+                };
+                let op_pointer = format!("{}/{}", pointer, method);
+                self.operation(operation, &op_pointer);
+
+                let own = self.parameters(operation, &format!("{}/parameters", op_pointer));
+                for name in own.iter().filter(|n| !template.contains(n.as_str())) {
+                    self.error(
+                        format!("{}/parameters", op_pointer),
+                        format!("path parameter '{}' does not appear in the path", name),
+                    );
+                }
+                for name in &template {
+                    if !shared.contains(*name) && !own.contains(*name) {
+                        self.error(
+                            &op_pointer,
+                            format!("path parameter '{{{}}}' is not declared", name),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &'a Value, pointer: &str) {
+        if let Some(id) = operation.get("operationId").and_then(Value::as_str) {
+            match self.operation_ids.get(id) {
+                Some(first) => {
+                    let message =
+                        format!("duplicate operationId '{}' (first used at #{})", id, first);
+                    self.error(format!("{}/operationId", pointer), message);
+                }
+                None => {
+                    self.operation_ids
+                        .insert(id.to_string(), pointer.to_string());
+                }
+            }
+        }
+
+        let responses = operation.get("responses").and_then(Value::as_mapping);
+        let Some(responses) = responses.filter(|r| !r.is_empty()) else {
+            self.error(pointer, "operation has no responses");
+            return;
+        };
+        let code_re = RESPONSE_CODE_RE
+            .get_or_init(|| Regex::new(r"^(default|[1-5]XX|[1-5][0-9][0-9])$").unwrap());
+        for (code, response) in responses {
+            // YAML reads unquoted codes as numbers
+            let code = match code {
+                Value::Number(n) => n.to_string(),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            let response_pointer = format!("{}/responses/{}", pointer, refs::escape(&code));
+            if !code_re.is_match(&code) {
+                self.error(
+                    &response_pointer,
+                    format!("invalid response code '{}'", code),
+                );
+            }
+            if let Some(response) = self.resolve(response) {
+                if response.get("description").is_none() {
+                    self.error(
+                        &response_pointer,
+                        "response is missing required field 'description'",
+                    );
+                }
+            }
+        }
+    }
+
+    // Checks the `parameters` of a path item or operation; returns the declared path parameters
+    fn parameters(&mut self, owner: &'a Value, pointer: &str) -> HashSet<String> {
+        let mut path_params = HashSet::new();
+        let mut seen = HashSet::new();
+        let params = owner.get("parameters").and_then(Value::as_sequence);
+        for (i, param) in params.into_iter().flatten().enumerate() {
+            let param_pointer = format!("{}/{}", pointer, i);
+            let Some(param) = self.resolve(param) else {
+                continue;
+            };
+            let name = param.get("name").and_then(Value::as_str);
+            let location = param.get("in").and_then(Value::as_str);
+            if name.is_none() {
+                self.error(&param_pointer, "parameter is missing required field 'name'");
+            }
+            match location {
+                None => self.error(&param_pointer, "parameter is missing required field 'in'"),
+                Some(l) if !PARAMETER_LOCATIONS.contains(&l) => {
+                    self.error(
+                        format!("{}/in", param_pointer),
+                        format!("invalid parameter location '{}'", l),
+                    );
+                }
+                Some("path") if param.get("required").and_then(Value::as_bool) != Some(true) => {
+                    self.error(
+                        &param_pointer,
+                        format!(
+                            "path parameter '{}' must have 'required: true'",
+                            name.unwrap_or_default()
+                        ),
+                    );
+                }
+                _ => {}
+            }
+            let (Some(name), Some(location)) = (name, location) else {
+                continue;
+            };
+            if !seen.insert((name, location)) {
+                self.error(
+                    &param_pointer,
+                    format!("duplicate parameter '{}' in {}", name, location),
+                );
+            }
+            if location == "path" {
+                path_params.insert(name.to_string());
+            }
+        }
+        path_params
+    }
+
+    fn components(&mut self) {
+        let Some(components) = self.doc.get("components").and_then(Value::as_mapping) else {
+            return;
+        };
+        let re = COMPONENT_NAME_RE.get_or_init(|| Regex::new(r"^[a-zA-Z0-9._-]+$").unwrap());
+        for (kind, entries) in components {
+            let Some(kind) = kind.as_str() else { continue };
+            for name in entries.as_mapping().into_iter().flat_map(|m| m.keys()) {
+                let name = name.as_str().unwrap_or_default();
+                if !re.is_match(name) {
+                    self.error(
+                        format!("/components/{}/{}", kind, refs::escape(name)),
+                        format!(
+                            "component name '{}' may only contain letters, digits, '.', '-' and '_'",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // 3.0 ignores everything next to `$ref`
+    fn ref_siblings(&mut self, value: &Value, pointer: &mut String) {
+        match value {
+            Value::Mapping(map) => {
+                if map.contains_key("$ref") {
+                    let siblings: Vec<&str> = map
+                        .keys()
+                        .filter_map(Value::as_str)
+                        .filter(|k| *k != "$ref" && *k != SOURCE_KEY)
+                        .collect();
+                    if !siblings.is_empty() {
+                        self.diagnostics.push(
+                            Diagnostic::at_pointer(
+                                format!(
+                                    "keys next to '$ref' are ignored in OpenAPI 3.0: {}",
+                                    siblings.join(", ")
+                                ),
+                                pointer.clone(),
+                            )
+                            .with_code(code::INVALID_DOCUMENT),
+                        );
+                    }
+                }
+                for (key, val) in map {
+                    let len = pointer.len();
+                    pointer.push('/');
+                    pointer.push_str(&refs::escape(key.as_str().unwrap_or_default()));
+                    self.ref_siblings(val, pointer);
+                    pointer.truncate(len);
+                }
+            }
+            Value::Sequence(seq) => {
+                for (i, val) in seq.iter().enumerate() {
+                    let len = pointer.len();
+                    pointer.push_str(&format!("/{}", i));
+                    self.ref_siblings(val, pointer);
+                    pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+
+    const VALID: &str = r##"
+openapi: 3.0.3
+info: {title: T, version: "1"}
+paths:
+  /users/{id}:
+    parameters:
+      - {name: id, in: path, required: true}
+    get:
+      operationId: getUser
+      parameters:
+        - {$ref: "#/components/parameters/Verbose"}
+      responses:
+        '200': {description: ok}
+        default: {$ref: "#/components/responses/Error"}
+components:
+  parameters:
+    Verbose: {name: verbose, in: query}
+  responses:
+    Error: {description: failed}
+"##;
+
+    // (pointer, message) of each diagnostic for VALID with `patch` applied
+    fn check(patch: impl FnOnce(&mut Value)) -> Vec<(String, String)> {
+        let mut doc: Value = serde_yaml::from_str(VALID).unwrap();
+        patch(&mut doc);
+        let version = SpecVersion::from_document(&doc).unwrap_or_default();
+        validate_document(&doc, version)
+            .into_iter()
+            .map(|d| (d.pointer.unwrap(), d.message))
+            .collect()
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    fn op(doc: &mut Value) -> &mut Value {
+        &mut doc["paths"]["/users/{id}"]["get"]
+    }
+
+    #[test]
+    fn test_valid_document() {
+        assert_eq!(check(|_| {}), vec![]);
+    }
+
+    #[test]
+    fn test_openapi_version() {
+        let diags = check(|doc| doc["openapi"] = yaml("2.0"));
+        assert_eq!(diags[0].0, "/openapi");
+        assert!(diags[0].1.contains("unsupported"));
+
+        let diags = check(|doc| {
+            doc.as_mapping_mut().unwrap().remove("openapi");
+        });
+        assert_eq!(diags[0].0, "/openapi");
+    }
+
+    #[test]
+    fn test_info_title_and_version() {
+        let diags = check(|doc| doc["info"] = yaml("{description: x}"));
+        assert_eq!(
+            diags.iter().map(|d| d.0.as_str()).collect::<Vec<_>>(),
+            vec!["/info/title", "/info/version"]
+        );
+    }
+
+    #[test]
+    fn test_paths_required_in_3_0_only() {
+        let remove = |doc: &mut Value| {
+            doc.as_mapping_mut().unwrap().remove("paths");
+        };
+        assert_eq!(check(remove)[0].0, "/paths");
+        assert_eq!(
+            check(|doc| {
+                remove(doc);
+                doc["openapi"] = yaml("3.1.0");
+            }),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_path_must_start_with_slash() {
+        let diags = check(|doc| doc["paths"]["health"] = yaml("{}"));
+        assert_eq!(
+            diags,
+            vec![(
+                "/paths/health".into(),
+                "path 'health' must start with '/'".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_operation_requires_responses() {
+        let diags = check(|doc| {
+            op(doc).as_mapping_mut().unwrap().remove("responses");
+        });
+        assert_eq!(
+            diags,
+            vec![(
+                "/paths/~1users~1{id}/get".into(),
+                "operation has no responses".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_response_requires_description() {
+        let diags = check(|doc| op(doc)["responses"]["200"] = yaml("{content: {}}"));
+        assert_eq!(diags[0].0, "/paths/~1users~1{id}/get/responses/200");
+        assert!(diags[0].1.contains("description"));
+    }
+
+    #[test]
+    fn test_response_code_format() {
+        let diags = check(|doc| op(doc)["responses"]["OK"] = yaml("{description: ok}"));
+        assert_eq!(diags[0].1, "invalid response code 'OK'");
+        assert_eq!(
+            check(|doc| op(doc)["responses"]["4XX"] = yaml("{description: x}")),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_parameter_requires_name_and_in() {
+        let diags = check(|doc| op(doc)["parameters"] = yaml("[{in: query}, {name: q}]"));
+        assert_eq!(
+            diags,
+            vec![
+                (
+                    "/paths/~1users~1{id}/get/parameters/0".into(),
+                    "parameter is missing required field 'name'".into()
+                ),
+                (
+                    "/paths/~1users~1{id}/get/parameters/1".into(),
+                    "parameter is missing required field 'in'".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameter_location() {
+        let diags = check(|doc| op(doc)["parameters"] = yaml("[{name: q, in: body}]"));
+        assert_eq!(diags[0].0, "/paths/~1users~1{id}/get/parameters/0/in");
+        assert_eq!(diags[0].1, "invalid parameter location 'body'");
+    }
+
+    #[test]
+    fn test_path_parameter_must_be_required() {
+        let diags =
+            check(|doc| doc["paths"]["/users/{id}"]["parameters"] = yaml("[{name: id, in: path}]"));
+        assert_eq!(diags[0].0, "/paths/~1users~1{id}/parameters/0");
+        assert!(diags[0].1.contains("required: true"));
+    }
+
+    #[test]
+    fn test_path_parameters_declared() {
+        let diags = check(|doc| {
+            doc["paths"]["/users/{id}"]["parameters"] =
+                yaml("[{name: uid, in: path, required: true}]")
+        });
+        assert_eq!(
+            diags,
+            vec![
+                (
+                    "/paths/~1users~1{id}/parameters".into(),
+                    "path parameter 'uid' does not appear in the path".into()
+                ),
+                (
+                    "/paths/~1users~1{id}/get".into(),
+                    "path parameter '{id}' is not declared".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_parameters() {
+        let diags = check(|doc| {
+            op(doc)["parameters"] = yaml("[{name: q, in: query}, {name: q, in: query}]")
+        });
+        assert_eq!(diags[0].0, "/paths/~1users~1{id}/get/parameters/1");
+        assert_eq!(diags[0].1, "duplicate parameter 'q' in query");
+    }
+
+    #[test]
+    fn test_duplicate_operation_id() {
+        let diags = check(|doc| {
+            doc["paths"]["/users"] =
+                yaml("{post: {operationId: getUser, responses: {'201': {description: x}}}}")
+        });
+        assert_eq!(diags[0].0, "/paths/~1users/post/operationId");
+        assert!(diags[0].1.contains("#/paths/~1users~1{id}/get"));
+    }
+
+    #[test]
+    fn test_component_names() {
+        let diags = check(|doc| doc["components"]["schemas"] = yaml("{'Page<User>': {}}"));
+        assert_eq!(diags[0].0, "/components/schemas/Page<User>");
+    }
+
+    #[test]
+    fn test_ref_siblings_in_3_0() {
+        let sibling = |doc: &mut Value| {
+            op(doc)["responses"]["default"] =
+                yaml(r##"{$ref: "#/components/responses/Error", description: x}"##)
+        };
+        let mut doc: Value = serde_yaml::from_str(VALID).unwrap();
+        sibling(&mut doc);
+        let diags = validate_document(&doc, SpecVersion::V3_0);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(
+            diags[0].pointer.as_deref(),
+            Some("/paths/~1users~1{id}/get/responses/default")
+        );
+
+        assert!(validate_document(&doc, SpecVersion::V3_1).is_empty());
+    }
+}
@@ -1,9 +1,73 @@
-use regex::Regex;
+use crate::diagnostics::Diagnostic;
+use crate::preprocessor::FragmentArg;
+use crate::route_dsl;
+use serde::Deserialize;
 use serde_json::{Value, json};
+use std::sync::Arc;
 use syn::spanned::Spanned;
 use syn::visit::{self, Visit};
 use syn::{Attribute, Expr, File, ImplItemFn, ItemEnum, ItemFn, ItemMod, ItemStruct, ItemType};
 
+/// Route DSL directives recognised in the doc comments of handler functions.
+pub const ROUTE_DIRECTIVES: [&str; 13] = [
+    "@route",
+    "@tag",
+    "@query-param",
+    "@path-param",
+    "@header-param",
+    "@cookie-param",
+    "@body",
+    "@return",
+    "@security",
+    "@response-header",
+    "@example",
+    "@server",
+    "@link",
+];
+
+const PRODUCES_DIRECTIVE: &str = "@produces";
+const CONSUMES_DIRECTIVE: &str = "@consumes";
+
+/// Directives recognised in module doc comments.
+pub const MODULE_DIRECTIVES: [&str; 3] = ["@server", PRODUCES_DIRECTIVE, CONSUMES_DIRECTIVE];
+
+/// Doc comment markers the visitor reacts to: `@openapi` (including `@openapi-type` and
+/// `@openapi-fragment`), the [`ROUTE_DIRECTIVES`], the [`MODULE_DIRECTIVES`] and the
+/// field directives (`@readonly`, `@writeonly`).
+pub const MARKERS: [&str; MARKER_COUNT] = collect_markers();
+
+const MARKER_COUNT: usize =
+    1 + ROUTE_DIRECTIVES.len() + MODULE_DIRECTIVES.len() + FIELD_DIRECTIVES.len();
+
+const fn collect_markers() -> [&'static str; MARKER_COUNT] {
+    let mut markers = ["@openapi"; MARKER_COUNT];
+    let mut at = 1;
+    let mut i = 0;
+    while i < ROUTE_DIRECTIVES.len() {
+        markers[at] = ROUTE_DIRECTIVES[i];
+        at += 1;
+        i += 1;
+    }
+    i = 0;
+    while i < MODULE_DIRECTIVES.len() {
+        markers[at] = MODULE_DIRECTIVES[i];
+        at += 1;
+        i += 1;
+    }
+    i = 0;
+    while i < FIELD_DIRECTIVES.len() {
+        markers[at] = FIELD_DIRECTIVES[i].0;
+        at += 1;
+        i += 1;
+    }
+    markers
+}
+
+/// Whether `content` contains any of the [`MARKERS`]; a cheap check that needs no parsing.
+pub fn has_markers(content: &str) -> bool {
+    MARKERS.iter().any(|marker| content.contains(marker))
+}
+
 /// Extracted item type
 #[derive(Debug)]
 pub enum ExtractedItem {
@@ -12,13 +76,34 @@ pub enum ExtractedItem {
         name: Option<String>,
         content: String,
         line: usize,
+        /// Dotted module path the item was found in ("" for the crate root)
+        module: String,
+        /// Source line of the first line of `content`, when its lines follow the
+        /// doc comment one-to-one from there
+        first_line: Option<usize>,
+        /// The source lines `content` maps to, as written (`///` markers included)
+        raw: Option<String>,
+        /// `@openapi priority: N`, see [`crate::scanner::Snippet::priority`]
+        priority: i32,
     },
     /// @openapi-fragment Name(args...)
     Fragment {
         name: String,
         params: Vec<String>,
+        /// `param = "default"` values from the header
+        defaults: std::collections::BTreeMap<String, String>,
+        content: String,
+        line: usize,
+    },
+    /// @openapi-header Name / @openapi-example Name, see [`COMPONENT_DIRECTIVES`]
+    Component {
+        /// Section under `components` (`headers`, `examples`)
+        section: &'static str,
+        name: String,
         content: String,
         line: usize,
+        /// As for [`ExtractedItem::Schema`]
+        first_line: Option<usize>,
     },
     /// @openapi<T, U>
     Blueprint {
@@ -29,13 +114,459 @@ pub enum ExtractedItem {
     },
 }
 
+/// Metadata applied to every operation scanned from one input directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputMetadata {
+    /// Tags for operations that declare none, ahead of module tags
+    pub tags: Vec<String>,
+    /// `x-` extension fields added to each operation, unless it sets them itself
+    pub extensions: std::collections::BTreeMap<String, Value>,
+}
+
+impl InputMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.extensions.is_empty()
+    }
+}
+
+/// How reflected Rust items are named in `components/schemas`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaNaming {
+    /// Bare type name (`User`)
+    #[default]
+    Plain,
+    /// Qualified by the Rust module path (`accounts.User`)
+    ModulePrefixed,
+}
+
+impl std::str::FromStr for SchemaNaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(SchemaNaming::Plain),
+            "module_prefixed" | "module-prefixed" => Ok(SchemaNaming::ModulePrefixed),
+            other => Err(format!(
+                "unknown schema naming '{}', expected 'plain' or 'module_prefixed'",
+                other
+            )),
+        }
+    }
+}
+
+/// How the doc comment of a reflected Rust item becomes schema text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocStyle {
+    /// All lines joined with spaces into `description`
+    #[default]
+    Joined,
+    /// Like rustdoc's summary line: the first paragraph becomes the `title` when more follow,
+    /// and those the `description`, with line breaks and markdown kept
+    Paragraphs,
+}
+
+impl std::str::FromStr for DocStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "joined" => Ok(DocStyle::Joined),
+            "paragraphs" => Ok(DocStyle::Paragraphs),
+            other => Err(format!(
+                "unknown doc style '{}', expected 'joined' or 'paragraphs'",
+                other
+            )),
+        }
+    }
+}
+
+/// `title`/`description` of the doc comment `lines` in `style`; `null` without text.
+fn doc_text(lines: &[String], style: DocStyle) -> Value {
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    if lines.is_empty()
+        || style == DocStyle::Paragraphs && lines.iter().all(|l| l.trim().is_empty())
+    {
+        return Value::Null;
+    }
+    match style {
+        DocStyle::Joined => {
+            let joined: Vec<&str> = lines.iter().map(|l| l.trim()).collect();
+            json!({ "description": joined.join(" ") })
+        }
+        DocStyle::Paragraphs => {
+            let start = lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(0);
+            let end = lines[start..]
+                .iter()
+                .position(|l| l.trim().is_empty())
+                .map_or(lines.len(), |n| start + n);
+            let summary: Vec<&str> = lines[start..end].iter().map(|l| l.trim()).collect();
+            let summary = summary.join(" ");
+            match route_dsl::join_description(&lines[end..]) {
+                Some(description) => json!({ "title": summary, "description": description }),
+                None => json!({ "description": summary }),
+            }
+        }
+    }
+}
+
+/// Maps a Rust type to a schema ahead of the built-in mapping; `None` falls through to it.
+///
+/// Implemented for closures, e.g. `|ty: &syn::Type| None`.
+pub trait TypeMapper: Send + Sync {
+    fn map_type(&self, ty: &syn::Type) -> Option<Value>;
+}
+
+impl<F> TypeMapper for F
+where
+    F: Fn(&syn::Type) -> Option<Value> + Send + Sync,
+{
+    fn map_type(&self, ty: &syn::Type) -> Option<Value> {
+        self(ty)
+    }
+}
+
+/// [`TypeMapper`]s consulted in order; the first schema returned wins.
+#[derive(Clone, Default)]
+pub struct TypeMappers(Vec<Arc<dyn TypeMapper>>);
+
+impl TypeMappers {
+    pub fn push(&mut self, mapper: impl TypeMapper + 'static) {
+        self.0.push(Arc::new(mapper));
+    }
+
+    fn map(&self, ty: &syn::Type) -> Option<Value> {
+        self.0.iter().find_map(|mapper| mapper.map_type(ty))
+    }
+}
+
+impl std::fmt::Debug for TypeMappers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypeMappers({} mappers)", self.0.len())
+    }
+}
+
 #[derive(Default)]
 pub struct OpenApiVisitor {
     pub items: Vec<ExtractedItem>,
     pub current_tags: Vec<String>,
+    /// `@produces` / `@consumes` of the innermost module declaring them: the default media
+    /// type of `@return` and `@body` content
+    pub current_produces: Option<String>,
+    pub current_consumes: Option<String>,
+    /// `@server` lines of the enclosing modules, added to the path items of their routes
+    pub current_servers: Vec<Value>,
+    /// Module stack: the file's own module path followed by inline `mod` items.
+    pub module_path: Vec<String>,
+    pub schema_naming: SchemaNaming,
+    pub doc_style: DocStyle,
+    /// Route definition errors found while visiting, as (line, message).
+    pub route_errors: Vec<(usize, String)>,
+    /// Ignored directives and attributes found while visiting, as (line, message).
+    pub warnings: Vec<(usize, String)>,
+    /// Custom type mappings, consulted before the built-in ones.
+    pub type_mappers: TypeMappers,
+    /// Metadata of the input directory the file was found in
+    pub input_metadata: InputMetadata,
 }
 
 impl OpenApiVisitor {
+    fn current_module(&self) -> String {
+        self.module_path.join(".")
+    }
+
+    // `@produces application/protobuf`, `@consumes ...` and `@server ...` in module docs
+    fn apply_module_directives(&mut self, attrs: &[Attribute]) {
+        for line in doc_strings(attrs) {
+            if line.starts_with("@server") {
+                self.current_servers
+                    .extend(route_dsl::parse_server_line(&line));
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let (Some(directive), Some(mime)) = (words.next(), words.next()) else {
+                continue;
+            };
+            match directive {
+                PRODUCES_DIRECTIVE => self.current_produces = Some(mime.to_string()),
+                CONSUMES_DIRECTIVE => self.current_consumes = Some(mime.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    // Name under which a reflected item is registered
+    fn schema_name(&self, ident: &str) -> String {
+        match self.schema_naming {
+            SchemaNaming::ModulePrefixed if !self.module_path.is_empty() => {
+                format!("{}.{}", self.current_module(), ident)
+            }
+            _ => ident.to_string(),
+        }
+    }
+
+    // Object schema of named fields, honouring serde/schemars attributes and field docs
+    fn object_schema(&self, fields: &syn::FieldsNamed) -> Value {
+        let mut properties = serde_json::Map::new();
+        let mut required_fields = Vec::new();
+        let mut has_fields = false;
+
+        for field in &fields.named {
+            let field_attrs = SchemaAttrs::from_attrs(&field.attrs);
+            if field_attrs.skip {
+                continue;
+            }
+            has_fields = true;
+            let field_name = field_attrs
+                .rename
+                .clone()
+                .unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+
+            // schemars(with = "Type") replaces the reflected field type
+            let with_ty = field_attrs
+                .with
+                .as_deref()
+                .and_then(|w| syn::parse_str::<syn::Type>(w).ok());
+            let (mut field_schema, is_required) =
+                map_syn_type_to_openapi(with_ty.as_ref().unwrap_or(&field.ty), &self.type_mappers);
+
+            let mut field_desc = Vec::new();
+            for attr in &field.attrs {
+                if attr.path().is_ident("doc") {
+                    if let syn::Meta::NameValue(meta) = &attr.meta {
+                        if let Expr::Lit(expr_lit) = &meta.value {
+                            if let syn::Lit::Str(lit_str) = &expr_lit.lit {
+                                let val = lit_str.value();
+                                if val.trim().starts_with("@openapi") {
+                                    break;
+                                }
+                                if is_field_directive(val.trim()) {
+                                    continue;
+                                }
+                                field_desc.push(val);
+                            }
+                        }
+                    }
+                }
+            }
+            if let Value::Object(map) = &mut field_schema {
+                if let Value::Object(doc) = doc_text(&field_desc, self.doc_style) {
+                    map.extend(doc);
+                }
+            }
+
+            field_attrs.apply(&mut field_schema);
+
+            for line in doc_strings(&field.attrs) {
+                if let Some((_, flag)) = FIELD_DIRECTIVES.iter().find(|(d, _)| line == *d) {
+                    json_merge(&mut field_schema, json!({ *flag: true }));
+                }
+            }
+
+            // validator crate constraints (overridden by @openapi below)
+            apply_validate_attrs(&field.attrs, &mut field_schema);
+
+            // Field Level Overrides
+            let mut openapi_lines = Vec::new();
+            let mut collecting_openapi = false;
+
+            for attr in &field.attrs {
+                if attr.path().is_ident("doc") {
+                    if let syn::Meta::NameValue(meta) = &attr.meta {
+                        if let Expr::Lit(expr_lit) = &meta.value {
+                            if let syn::Lit::Str(lit_str) = &expr_lit.lit {
+                                let val = lit_str.value();
+                                let trimmed = val.trim();
+
+                                if is_field_directive(trimmed) {
+                                    // Applied above
+                                } else if trimmed.starts_with("@openapi") {
+                                    collecting_openapi = true;
+                                    let rest = trimmed.strip_prefix("@openapi").unwrap().trim();
+                                    if !rest.is_empty() {
+                                        openapi_lines.push(rest.to_string());
+                                    }
+                                } else if collecting_openapi {
+                                    openapi_lines.push(val.to_string());
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    collecting_openapi = false;
+                }
+            }
+
+            if !openapi_lines.is_empty() {
+                let override_yaml = openapi_lines.join("\n");
+                if let Ok(override_val) = serde_yaml::from_str::<Value>(&override_yaml) {
+                    if !override_val.is_null() {
+                        json_merge(&mut field_schema, override_val);
+                    }
+                }
+            }
+
+            properties.insert(field_name.clone(), field_schema);
+            if is_required {
+                required_fields.push(field_name);
+            }
+        }
+
+        if !has_fields {
+            return json!({ "type": "object" });
+        }
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties
+        });
+        if !required_fields.is_empty() {
+            schema["required"] = json!(required_fields);
+        }
+        schema
+    }
+
+    // Serialized data of an enum variant: newtypes take the inner type's schema, struct
+    // variants become inline objects and tuples fixed-length arrays. Unit variants carry none.
+    fn variant_payload(&self, variant: &syn::Variant) -> Option<Value> {
+        match &variant.fields {
+            syn::Fields::Named(fields) => Some(self.object_schema(fields)),
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Some(map_syn_type_to_openapi(&fields.unnamed[0].ty, &self.type_mappers).0)
+            }
+            syn::Fields::Unnamed(fields) => Some(json!({
+                "type": "array",
+                "minItems": fields.unnamed.len(),
+                "maxItems": fields.unnamed.len()
+            })),
+            syn::Fields::Unit => None,
+        }
+    }
+
+    // Branch of an internally (`tag`) or adjacently (`tag` + `content`) tagged enum: an
+    // object whose tag property holds the variant name
+    fn tagged_variant_schema(
+        &self,
+        variant: &syn::Variant,
+        tag: &str,
+        content: Option<&str>,
+        rename_all: Option<&str>,
+    ) -> Value {
+        let name = variant_name(variant, rename_all);
+        let tag_object = json!({
+            "type": "object",
+            "properties": { tag: { "type": "string", "enum": [name] } },
+            "required": [tag]
+        });
+        match (self.variant_payload(variant), content) {
+            (None, _) => tag_object,
+            (Some(payload), Some(content)) => json!({
+                "type": "object",
+                "properties": {
+                    tag: tag_object["properties"][tag],
+                    content: payload
+                },
+                "required": [tag, content]
+            }),
+            // Struct variant fields sit next to the tag
+            (Some(mut payload), None) if payload.get("properties").is_some() => {
+                payload["properties"][tag] = tag_object["properties"][tag].clone();
+                let mut required = vec![json!(tag)];
+                if let Some(Value::Array(fields)) = payload.get("required") {
+                    required.extend(fields.iter().cloned());
+                }
+                payload["required"] = json!(required);
+                payload
+            }
+            (Some(payload), None) => json!({ "allOf": [tag_object, payload] }),
+        }
+    }
+
+    // Variant docs and schemars attributes on the variant's branch
+    fn document_variant(&self, variant: &syn::Variant, schema: &mut Value) {
+        let desc: Vec<String> = doc_strings(&variant.attrs).collect();
+        if let (Value::Object(map), Value::Object(doc)) =
+            (&mut *schema, doc_text(&desc, self.doc_style))
+        {
+            map.extend(doc);
+        }
+        SchemaAttrs::from_attrs(&variant.attrs).apply(schema);
+    }
+
+    // Reports the schemars(example = "...") attributes of an item and its fields
+    fn warn_schemars_examples(&mut self, attrs: &[Attribute], fields: &syn::Fields) {
+        let field_attrs = fields.iter().map(|field| field.attrs.as_slice());
+        for attrs in std::iter::once(attrs).chain(field_attrs) {
+            if let Some(path) = SchemaAttrs::from_attrs(attrs).example_fn {
+                let line = attrs.first().map_or(0, |attr| attr.span().start().line);
+                self.warnings.push((
+                    line,
+                    format!(
+                        "Ignoring schemars(example = {:?}): it names a function; use `@openapi example:` instead",
+                        path
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Adds a `$ref` schema per `@openapi-alias` of the item registered as `name`
+    fn push_aliases(&mut self, attrs: &[Attribute], name: &str, line: usize) {
+        for alias in alias_names(attrs) {
+            let alias = self.schema_name(&alias);
+            let content = wrap_in_schema(&alias, &format!("$ref: '#/components/schemas/{}'", name));
+            self.items.push(ExtractedItem::Schema {
+                module: self.current_module(),
+                name: Some(alias),
+                content,
+                line,
+                first_line: None,
+                raw: None,
+                priority: 0,
+            });
+        }
+    }
+
+    // File-level doc block: an `@openapi-type Name` body (a blueprint for `Name<T>`) or a
+    // root/fragment block
+    fn flush_file_block(&mut self, type_name: Option<String>, lines: &[String], line: usize) {
+        let body = lines.join("\n");
+        let Some(name) = type_name else {
+            self.parse_doc_block(&body, None, line);
+            return;
+        };
+        if let (Some(start), Some(end)) = (name.find('<'), name.rfind('>')) {
+            let indent = lines
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.len() - l.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            let content = lines
+                .iter()
+                .map(|l| l.get(indent..).unwrap_or("").trim_end())
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.items.push(ExtractedItem::Blueprint {
+                name: name[..start].trim().to_string(),
+                params: parse_blueprint_params(&name[start + 1..end]),
+                content: content.trim_matches('\n').to_string(),
+                line,
+            });
+            return;
+        }
+        let wrapped = wrap_in_schema(&name, &body);
+        self.items.push(ExtractedItem::Schema {
+            module: self.current_module(),
+            name: Some(name),
+            content: wrapped,
+            line,
+            first_line: None,
+            raw: None,
+            priority: 0,
+        });
+    }
+
     // Helper to process doc attributes on items (structs, fns, types)
     // Updated: No longer accepts generated_content. Strictly for @openapi blocks (Paths/Fragments).
     fn check_attributes(
@@ -89,56 +620,124 @@ impl OpenApiVisitor {
             .collect();
         let content = unindented.join("\n");
 
+        // (header, body, index of the body's first line in `content`)
         let mut sections = Vec::new();
         let mut current_header = String::new();
         let mut current_body = Vec::new();
+        let mut current_start = 0;
+        // Inside a code fence, and whether its lines belong to the body
+        let mut fence: Option<bool> = None;
+        // Past a rustdoc example or `# Examples`, until the next header
+        let mut skipping = false;
 
-        for line in content.lines() {
+        for (idx, line) in content.lines().enumerate() {
             let trimmed = line.trim();
+            if let Some(included) = fence {
+                if line.starts_with("```") {
+                    fence = None;
+                    if included {
+                        current_body.push(String::new());
+                    }
+                } else if included {
+                    current_body.push(line.to_string());
+                }
+                continue;
+            }
+            if !trimmed.starts_with("@openapi") && skipping {
+                continue;
+            }
+            // Only at the base indentation; deeper ones are inside YAML block scalars
+            if let Some(tag) = line.strip_prefix("```") {
+                let included = YAML_FENCE_TAGS.contains(&tag.trim());
+                fence = Some(included);
+                skipping = !included;
+                if included {
+                    // Keeps body lines one-to-one with the doc comment
+                    current_body.push(String::new());
+                }
+                continue;
+            }
+            if EXAMPLES_HEADINGS.contains(&line.trim_end()) {
+                skipping = true;
+                continue;
+            }
             if trimmed.starts_with("@openapi") {
+                skipping = false;
                 if !current_header.is_empty() || !current_body.is_empty() {
-                    sections.push((current_header.clone(), current_body.join("\n")));
+                    sections.push((
+                        current_header.clone(),
+                        current_body.join("\n"),
+                        current_start,
+                    ));
                 }
                 current_header = trimmed.to_string();
                 current_body.clear();
+                current_start = idx + 1;
             } else if trimmed.starts_with('{') && current_header.is_empty() {
                 if !current_header.is_empty() || !current_body.is_empty() {
-                    sections.push((current_header.clone(), current_body.join("\n")));
+                    sections.push((
+                        current_header.clone(),
+                        current_body.join("\n"),
+                        current_start,
+                    ));
                 }
                 current_header = "@json".to_string();
                 current_body.push(line.to_string());
+                current_start = idx;
             } else {
                 current_body.push(line.to_string());
             }
         }
         if !current_header.is_empty() || !current_body.is_empty() {
-            sections.push((current_header, current_body.join("\n")));
+            sections.push((current_header, current_body.join("\n"), current_start));
         }
 
-        for (header, body) in sections {
+        for (header, body, start) in sections {
             let mut body_content = body.trim().to_string();
+            let leading_blank = body.lines().take_while(|l| l.trim().is_empty()).count();
+            let body_line = line + start + leading_blank;
 
             if header.starts_with("@openapi-fragment") {
                 let rest = header.strip_prefix("@openapi-fragment").unwrap().trim();
-                let (name, params) = if let Some(idx) = rest.find('(') {
+                let (name, params, defaults) = if let Some(idx) = rest.find('(') {
                     let name = rest[..idx].trim().to_string();
                     let params_str = rest[idx + 1..].trim_end_matches(')');
-                    let params: Vec<String> = params_str
-                        .split(',')
-                        .map(|p| p.trim().to_string())
-                        .filter(|p| !p.is_empty())
-                        .collect();
-                    (name, params)
+                    let mut params = Vec::new();
+                    let mut defaults = std::collections::BTreeMap::new();
+                    for arg in crate::preprocessor::parse_args(params_str) {
+                        match arg {
+                            // code = "Internal Error"
+                            FragmentArg::Named(param, default) => {
+                                defaults.insert(param.clone(), default);
+                                params.push(param);
+                            }
+                            FragmentArg::Positional(param) => {
+                                if !param.is_empty() {
+                                    params.push(param);
+                                }
+                            }
+                        }
+                    }
+                    (name, params, defaults)
                 } else {
-                    (rest.to_string(), Vec::new())
+                    (rest.to_string(), Vec::new(), Default::default())
                 };
 
                 self.items.push(ExtractedItem::Fragment {
                     name,
                     params,
+                    defaults,
                     content: body_content,
                     line,
                 });
+            } else if let Some((section, name)) = component_header(&header) {
+                self.items.push(ExtractedItem::Component {
+                    section,
+                    content: wrap_in_component(section, &name, &body_content),
+                    name,
+                    line,
+                    first_line: body_line.checked_sub(WRAP_HEADER_LINES),
+                });
             } else if header.starts_with("@openapi-type") {
                 let name = header
                     .strip_prefix("@openapi-type")
@@ -148,19 +747,18 @@ impl OpenApiVisitor {
                 // Wrap content in schema definition
                 let wrapped = wrap_in_schema(&name, &body_content);
                 self.items.push(ExtractedItem::Schema {
+                    module: self.current_module(),
                     name: Some(name),
                     content: wrapped,
                     line,
+                    first_line: body_line.checked_sub(WRAP_HEADER_LINES),
+                    raw: None,
+                    priority: 0,
                 });
             } else if header.starts_with("@openapi") && header.contains('<') {
                 if let Some(start) = header.find('<') {
                     if let Some(end) = header.rfind('>') {
-                        let params_str = &header[start + 1..end];
-                        let params: Vec<String> = params_str
-                            .split(',')
-                            .map(|p| p.trim().to_string())
-                            .filter(|p| !p.is_empty())
-                            .collect();
+                        let params = parse_blueprint_params(&header[start + 1..end]);
 
                         if let Some(ident) = &item_ident {
                             self.items.push(ExtractedItem::Blueprint {
@@ -176,39 +774,46 @@ impl OpenApiVisitor {
                 || header == "@json"
                 || header.is_empty()
             {
-                // TAG INJECTION
-                if !self.current_tags.is_empty() {
-                    let tags_yaml_list = self
-                        .current_tags
-                        .iter()
-                        .map(|t| format!("- {}", t))
-                        .collect::<Vec<_>>();
-
-                    let verbs = [
-                        "get:", "post:", "put:", "delete:", "patch:", "head:", "options:", "trace:",
-                    ];
-                    let mut new_lines = Vec::new();
-                    let mut injected_any = false;
-
-                    for line in body_content.lines() {
-                        new_lines.push(line.to_string());
-                        let trimmed = line.trim();
-                        if verbs.iter().any(|v| trimmed == *v) {
-                            let indent = line.chars().take_while(|c| *c == ' ').count();
-                            let child_indent = " ".repeat(indent + 2);
-
-                            if !body_content.contains("tags:") {
-                                new_lines.push(format!("{}tags:", child_indent));
-                                for tag in &tags_yaml_list {
-                                    new_lines.push(format!("{}  {}", child_indent, tag));
-                                }
-                                injected_any = true;
-                            }
+                let mut mirrors_doc = true;
+                // A module's replacing tag list is an ordinary one in the document
+                if body_content
+                    .lines()
+                    .any(|l| l.starts_with(TAGS_REPLACE_KEY))
+                {
+                    body_content = body_content
+                        .lines()
+                        .map(|l| match l.strip_prefix(TAGS_REPLACE_KEY) {
+                            Some(rest) => format!("tags:{}", rest),
+                            None => l.to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    mirrors_doc = false;
+                }
+                // TAG INJECTION (module and input tags, input extensions)
+                let extensions = &self.input_metadata.extensions;
+                if !self.current_tags.is_empty() || !extensions.is_empty() {
+                    let mut tags: Vec<String> = Vec::new();
+                    for tag in &self.current_tags {
+                        if !tags.contains(tag) {
+                            tags.push(tag.clone());
                         }
                     }
-
-                    if injected_any {
-                        body_content = new_lines.join("\n");
+                    // Template placeholders would not survive a YAML round trip
+                    let parsed = serde_yaml::from_str::<serde_yaml::Value>(&body_content)
+                        .ok()
+                        .filter(|_| !body_content.contains("{{"));
+                    let injected = match parsed {
+                        Some(mut doc) => inject_operation_defaults(&mut doc, &tags, extensions)
+                            .then(|| serde_yaml::to_string(&doc).ok())
+                            .flatten()
+                            .map(|yaml| yaml.trim_start_matches("---\n").to_string()),
+                        // Directives like `@insert` are expanded later
+                        None => inject_operation_lines(&body_content, &tags, extensions),
+                    };
+                    if let Some(injected) = injected {
+                        body_content = injected;
+                        mirrors_doc = false;
                     }
                 }
 
@@ -234,8 +839,10 @@ impl OpenApiVisitor {
                     }
                 });
 
+                let mut first_line = Some(body_line);
                 let final_content = if !starts_with_toplevel && !body_content.trim().is_empty() {
                     if let Some(n) = &item_ident {
+                        first_line = body_line.checked_sub(WRAP_HEADER_LINES);
                         wrap_in_schema(n, &body_content)
                     } else {
                         body_content
@@ -244,28 +851,520 @@ impl OpenApiVisitor {
                     body_content
                 };
 
+                let priority = header_priority(&header).unwrap_or_else(|message| {
+                    self.warnings.push((line, message));
+                    0
+                });
                 self.items.push(ExtractedItem::Schema {
+                    module: self.current_module(),
                     name: item_ident.clone(),
                     content: final_content,
                     line,
+                    first_line: first_line.filter(|_| mirrors_doc),
+                    raw: None,
+                    priority,
                 });
             }
         }
     }
 }
 
+// Adds `tags` to the operations under `paths` without tags of their own, and `extensions`
+// they do not set; whether anything was added
+fn inject_operation_defaults(
+    doc: &mut serde_yaml::Value,
+    tags: &[String],
+    extensions: &std::collections::BTreeMap<String, Value>,
+) -> bool {
+    let Some(paths) = doc
+        .get_mut("paths")
+        .and_then(serde_yaml::Value::as_mapping_mut)
+    else {
+        return false;
+    };
+    let mut injected = false;
+    for item in paths.values_mut() {
+        let Some(item) = item.as_mapping_mut() else {
+            continue;
+        };
+        for (method, operation) in item.iter_mut() {
+            let is_operation = method
+                .as_str()
+                .is_some_and(|m| crate::validate::HTTP_METHODS.contains(&m));
+            let Some(operation) = operation.as_mapping_mut().filter(|_| is_operation) else {
+                continue;
+            };
+            // Injected keys go first, as in `inject_operation_lines`
+            let mut added = serde_yaml::Mapping::new();
+            let untagged = operation
+                .get("tags")
+                .and_then(serde_yaml::Value::as_sequence)
+                .is_none_or(Vec::is_empty);
+            if untagged && !tags.is_empty() {
+                let tags = tags.iter().map(|t| t.as_str().into()).collect();
+                added.insert("tags".into(), serde_yaml::Value::Sequence(tags));
+            }
+            for (key, value) in extensions {
+                if !operation.contains_key(key.as_str()) {
+                    if let Ok(value) = serde_yaml::to_value(value) {
+                        added.insert(key.as_str().into(), value);
+                    }
+                }
+            }
+            if !added.is_empty() {
+                if added.contains_key("tags") {
+                    operation.remove("tags");
+                }
+                added.extend(std::mem::take(operation));
+                *operation = added;
+                injected = true;
+            }
+        }
+    }
+    injected
+}
+
+// Line-based `inject_operation_defaults` for bodies that are not YAML yet; only an
+// operation's own keys (one level below the verb line) count as already set
+fn inject_operation_lines(
+    body: &str,
+    tags: &[String],
+    extensions: &std::collections::BTreeMap<String, Value>,
+) -> Option<String> {
+    let verbs = [
+        "get:", "post:", "put:", "delete:", "patch:", "head:", "options:", "trace:",
+    ];
+    let lines: Vec<&str> = body.lines().collect();
+    let indent_of = |line: &str| line.chars().take_while(|c| *c == ' ').count();
+    let mut new_lines = Vec::new();
+    let mut injected = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        new_lines.push(line.to_string());
+        if !verbs.contains(&line.trim()) {
+            continue;
+        }
+        let indent = indent_of(line);
+        let children: Vec<&str> = lines[i + 1..]
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .take_while(|l| indent_of(l) > indent)
+            .copied()
+            .collect();
+        let child_depth = children.first().map_or(indent + 2, |l| indent_of(l));
+        let has_key = |key: &str| {
+            children
+                .iter()
+                .any(|l| indent_of(l) == child_depth && l.trim().split(':').next() == Some(key))
+        };
+        let child_indent = " ".repeat(child_depth);
+
+        if !tags.is_empty() && !has_key("tags") {
+            new_lines.push(format!("{}tags:", child_indent));
+            for tag in tags {
+                new_lines.push(format!("{}  - {}", child_indent, tag));
+            }
+            injected = true;
+        }
+        for (key, value) in extensions {
+            if !has_key(key) {
+                new_lines.push(format!("{}{}: {}", child_indent, key, value));
+                injected = true;
+            }
+        }
+    }
+    injected.then(|| new_lines.join("\n"))
+}
+
+// `tags-replace: [..]` on a module: its tags replace the inherited ones instead of extending them
+const TAGS_REPLACE_KEY: &str = "tags-replace:";
+
+// `@openapi priority: N`; anything else after `@openapi` means the default, 0
+fn header_priority(header: &str) -> Result<i32, String> {
+    let Some(value) = header
+        .strip_prefix("@openapi")
+        .and_then(|rest| rest.trim().strip_prefix("priority:"))
+    else {
+        return Ok(0);
+    };
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Ignoring invalid priority in '{}'", header))
+}
+
+// Helper to extract blueprint type params from the inside of `<...>`.
+// Lifetimes ('a) and const params (const N: usize) are skipped, bounds are dropped (T: Clone -> T).
+fn parse_blueprint_params(params_str: &str) -> Vec<String> {
+    params_str
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty() && !p.starts_with('\'') && !p.starts_with("const "))
+        .map(|p| p.split([':', '=']).next().unwrap_or(p).trim().to_string())
+        .collect()
+}
+
 // Helper to wrap content in components/schemas
+// Lines `wrap_in_schema` puts above the content
+const WRAP_HEADER_LINES: usize = 3;
+
+/// Tags of code fences in `@openapi` doc blocks whose content is part of the body; other
+/// fences (e.g. Rust examples) end the body.
+const YAML_FENCE_TAGS: [&str; 3] = ["yaml", "yml", "openapi"];
+
+/// Rustdoc headings after which a doc block holds no more OpenAPI content.
+const EXAMPLES_HEADINGS: [&str; 2] = ["# Examples", "# Example"];
+
+// `@openapi-alias Old, Older`: further schema names referring to the item's schema
+const ALIAS_DIRECTIVE: &str = "@openapi-alias";
+
+// Struct directive adding a `{Name}Create` schema without the read-only properties
+const SPLIT_READONLY_DIRECTIVE: &str = "@openapi-split-readonly";
+
+// Field directives and the schema flag each one sets
+const FIELD_DIRECTIVES: [(&str, &str); 2] =
+    [("@readonly", "readOnly"), ("@writeonly", "writeOnly")];
+
+// Trimmed `///` lines of an item
+fn doc_strings(attrs: &[Attribute]) -> impl Iterator<Item = String> + '_ {
+    attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if attr.path().is_ident("doc") => match &meta.value {
+            Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit_str),
+                ..
+            }) => Some(lit_str.value().trim().to_string()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn alias_names(attrs: &[Attribute]) -> Vec<String> {
+    doc_strings(attrs)
+        .filter_map(|line| {
+            line.strip_prefix(ALIAS_DIRECTIVE).map(|rest| {
+                rest.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|alias| !alias.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .flatten()
+        .collect()
+}
+
+fn is_field_directive(line: &str) -> bool {
+    FIELD_DIRECTIVES
+        .iter()
+        .any(|(directive, _)| line == *directive)
+}
+
+// `schema` without its read-only properties
+fn without_read_only(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    let read_only: Vec<String> = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, property)| property["readOnly"] == json!(true))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if let Some(properties) = schema["properties"].as_object_mut() {
+        properties.retain(|name, _| !read_only.contains(name));
+    }
+    if let Some(map) = schema.as_object_mut() {
+        if let Some(Value::Array(required)) = map.get_mut("required") {
+            required.retain(|name| {
+                !name
+                    .as_str()
+                    .is_some_and(|n| read_only.iter().any(|r| r == n))
+            });
+            if required.is_empty() {
+                map.remove("required");
+            }
+        }
+        if let Some(Value::Object(example)) = map.get_mut("example") {
+            example.retain(|name, _| !read_only.contains(name));
+        }
+    }
+    schema
+}
+
+// Struct directive building the schema's `example` from its `#[derive(Default)]` values
+const EXAMPLE_FROM_DIRECTIVE: &str = "@openapi-example-from";
+
+fn derives(attrs: &[Attribute], trait_name: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .any(|attr| {
+            let mut found = false;
+            let _ = attr.parse_nested_meta(|meta| {
+                found |= meta
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|s| s.ident == trait_name);
+                Ok(())
+            });
+            found
+        })
+}
+
+// What `Default::default()` gives a field with this schema, where the schema tells
+fn default_value(schema: &Value) -> Option<Value> {
+    match schema.get("type").and_then(Value::as_str)? {
+        "integer" | "number" => Some(json!(0)),
+        "string" => Some(json!("")),
+        "boolean" => Some(json!(false)),
+        "array" => Some(json!([])),
+        // Maps; structs are referenced, not inlined
+        "object" if schema.get("properties").is_none() => Some(json!({})),
+        _ => None,
+    }
+}
+
+// Example object of a struct schema: field examples, else the field's default value.
+// Optional fields default to None and are left out; fields without a known default are
+// reported in `skipped`.
+fn default_example(name: &str, schema: &Value, skipped: &mut Vec<String>) -> Value {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut example = serde_json::Map::new();
+    for (field, property) in schema["properties"].as_object().into_iter().flatten() {
+        if let Some(value) = property.get("example") {
+            example.insert(field.clone(), value.clone());
+            continue;
+        }
+        if !required.contains(&field.as_str()) {
+            continue;
+        }
+        match default_value(property) {
+            Some(value) => {
+                example.insert(field.clone(), value);
+            }
+            None => skipped.push(format!(
+                "{}: cannot derive an example value for field '{}', skipping it",
+                name, field
+            )),
+        }
+    }
+    Value::Object(example)
+}
+
 fn wrap_in_schema(name: &str, content: &str) -> String {
+    wrap_in_component("schemas", name, content)
+}
+
+fn wrap_in_component(section: &str, name: &str, content: &str) -> String {
     let indented = content
         .lines()
         .map(|l| format!("      {}", l))
         .collect::<Vec<_>>()
         .join("\n");
-    format!("components:\n  schemas:\n    {}:\n{}", name, indented)
+    format!("components:\n  {}:\n    {}:\n{}", section, name, indented)
+}
+
+/// Directives declaring a reusable component other than a schema, with the section under
+/// `components` their body is wrapped in.
+pub const COMPONENT_DIRECTIVES: [(&str, &str); 2] = [
+    ("@openapi-header", "headers"),
+    ("@openapi-example", "examples"),
+];
+
+// `@openapi-header RateLimitRemaining` -> ("headers", "RateLimitRemaining")
+fn component_header(header: &str) -> Option<(&'static str, String)> {
+    let mut words = header.split_whitespace();
+    let directive = words.next()?;
+    let name = words.next()?;
+    COMPONENT_DIRECTIVES
+        .iter()
+        .find(|(d, _)| *d == directive)
+        .map(|(_, section)| (*section, name.to_string()))
+}
+
+// Helper to read method + path from actix-web / rocket routing attributes,
+// e.g. #[get("/users/{id}")], #[post("/users/<id>")] or #[route("/x", method = "GET")]
+fn framework_route(attrs: &[Attribute]) -> Option<(String, String)> {
+    const VERBS: [&str; 8] = [
+        "get", "post", "put", "delete", "patch", "head", "options", "trace",
+    ];
+
+    for attr in attrs {
+        let Some(seg) = attr.path().segments.last() else {
+            continue;
+        };
+        let attr_name = seg.ident.to_string();
+        if !VERBS.contains(&attr_name.as_str()) && attr_name != "route" {
+            continue;
+        }
+
+        let Ok(args) = attr
+            .parse_args_with(syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated)
+        else {
+            continue;
+        };
+
+        let mut raw_path = None;
+        let mut method = VERBS
+            .contains(&attr_name.as_str())
+            .then(|| attr_name.clone());
+
+        for arg in &args {
+            match arg {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) if raw_path.is_none() => raw_path = Some(s.value()),
+                Expr::Assign(assign) => {
+                    if let (
+                        Expr::Path(key),
+                        Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(s),
+                            ..
+                        }),
+                    ) = (&*assign.left, &*assign.right)
+                    {
+                        if key.path.is_ident("method") {
+                            method = Some(s.value().to_lowercase());
+                        } else if key.path.is_ident("uri") || key.path.is_ident("path") {
+                            raw_path = Some(s.value());
+                        }
+                    }
+                }
+                // rocket: #[route(GET, uri = "/x")]
+                Expr::Path(p) if method.is_none() => {
+                    if let Some(ident) = p.path.get_ident() {
+                        method = Some(ident.to_string().to_lowercase());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let (Some(method), Some(raw_path)) = (method, raw_path) {
+            return Some((method, normalize_framework_path(&raw_path)));
+        }
+    }
+    None
+}
+
+// Converts framework path syntax to OpenAPI templates:
+// rocket `<id>` / `<path..>` -> `{id}` / `{path}`, actix `{id:\d+}` -> `{id}`,
+// and drops any query part (`?<q>`).
+fn normalize_framework_path(raw: &str) -> String {
+    let path = raw.split('?').next().unwrap_or(raw);
+    let mut out = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' | '{' => {
+                let close = if c == '<' { '>' } else { '}' };
+                let inner: String = chars.by_ref().take_while(|ch| *ch != close).collect();
+                let name = inner.split(':').next().unwrap_or("").trim_end_matches("..");
+                out.push('{');
+                out.push_str(name.trim());
+                out.push('}');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Returns the inline schema for a built-in type name (`String`, `u64`, `Vec<String>`, ...),
+/// or `None` when the type would map to a schema reference.
+pub fn primitive_schema(type_str: &str) -> Option<Value> {
+    let ty = syn::parse_str::<syn::Type>(type_str).ok()?;
+    let (schema, _) = map_syn_type_to_openapi(&ty, &TypeMappers::default());
+    if contains_ref(&schema) {
+        None
+    } else {
+        Some(schema)
+    }
+}
+
+/// Makes `schema` nullable. Keys next to a `$ref` are ignored in OpenAPI 3.0, so a
+/// reference moves into `allOf` first.
+pub(crate) fn nullable_schema(schema: Value) -> Value {
+    let mut schema = ref_in_all_of(schema);
+    if let Value::Object(map) = &mut schema {
+        map.insert("nullable".to_string(), Value::Bool(true));
+    }
+    schema
+}
+
+// `{$ref}` -> `{allOf: [{$ref}]}`, so sibling keys added afterwards take effect
+fn ref_in_all_of(schema: Value) -> Value {
+    match &schema {
+        Value::Object(map) if map.contains_key("$ref") => json!({ "allOf": [schema] }),
+        _ => schema,
+    }
 }
 
-// Helper for type mapping
-fn map_syn_type_to_openapi(ty: &syn::Type) -> (Value, bool) {
+fn contains_ref(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.contains_key("$ref") || map.values().any(contains_ref),
+        Value::Array(items) => items.iter().any(contains_ref),
+        _ => false,
+    }
+}
+
+// Extension marking Option<Option<T>> fields, which tell an absent value (left unchanged)
+// apart from null (cleared) in patch requests
+const PATCH_FIELD_KEY: &str = "x-patch-field";
+
+// Extension marking arrays and maps reflected from sorted collections (BTreeSet, BTreeMap)
+const SORTED_KEY: &str = "x-sorted";
+
+// Byte buffers: Vec<u8>, bytes::Bytes / BytesMut and serde_bytes::ByteBuf
+pub(crate) fn is_byte_buffer(ty: &syn::Type) -> bool {
+    fn is_u8(ty: &syn::Type) -> bool {
+        matches!(ty, syn::Type::Path(p) if p.path.is_ident("u8"))
+    }
+    match ty {
+        syn::Type::Path(p) => p.path.segments.last().is_some_and(|seg| {
+            match seg.ident.to_string().as_str() {
+                "Bytes" | "BytesMut" | "ByteBuf" => true,
+                "Vec" => match &seg.arguments {
+                    syn::PathArguments::AngleBracketed(args) => {
+                        matches!(args.args.first(), Some(syn::GenericArgument::Type(inner)) if is_u8(inner))
+                    }
+                    _ => false,
+                },
+                _ => false,
+            }
+        }),
+        _ => false,
+    }
+}
+
+// Schema of a type inside an Option or container, where an Option means null rather than
+// absent; the flag is false when it was one
+fn nested_schema(ty: &syn::Type, mappers: &TypeMappers) -> (Value, bool) {
+    let (schema, required) = map_syn_type_to_openapi(ty, mappers);
+    if required {
+        (schema, required)
+    } else {
+        (nullable_schema(schema), required)
+    }
+}
+
+// Helper for type mapping: the schema and whether a field of the type is required. An outer
+// Option makes the field optional, Options nested in it or in containers make the value
+// nullable, and smart pointers (Box, Arc, Rc, Cow) are transparent.
+pub(crate) fn map_syn_type_to_openapi(ty: &syn::Type, mappers: &TypeMappers) -> (Value, bool) {
+    if let Some(schema) = mappers.map(ty) {
+        return (schema, true);
+    }
+    // Base64 in JSON; `@openapi format: binary` on the field for raw content
+    if is_byte_buffer(ty) {
+        return (json!({ "type": "string", "format": "byte" }), true);
+    }
     match ty {
         syn::Type::Path(p) => {
             if let Some(seg) = p.path.segments.last() {
@@ -274,7 +1373,7 @@ fn map_syn_type_to_openapi(ty: &syn::Type) -> (Value, bool) {
                 if ["Box", "Arc", "Rc", "Cow"].contains(&ident.as_str()) {
                     if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
                         if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                            return map_syn_type_to_openapi(inner);
+                            return map_syn_type_to_openapi(inner, mappers);
                         }
                     }
                 }
@@ -302,37 +1401,57 @@ fn map_syn_type_to_openapi(ty: &syn::Type) -> (Value, bool) {
                     }
                     "ObjectId" => (json!({ "type": "string", "format": "objectid" }), true),
                     "Value" => (json!({}), true),
+                    // Option<T> may be absent; Option<Option<T>> may also be null
                     "Option" => {
                         if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
                             if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                                let (inner_val, _) = map_syn_type_to_openapi(inner);
+                                let (mut inner_val, inner_required) = nested_schema(inner, mappers);
+                                if !inner_required {
+                                    inner_val[PATCH_FIELD_KEY] = json!(true);
+                                }
                                 return (inner_val, false);
                             }
                         }
                         (json!({}), false)
                     }
-                    "Vec" | "LinkedList" | "HashSet" => {
+                    "Vec" | "LinkedList" | "VecDeque" | "BinaryHeap" | "HashSet" | "BTreeSet"
+                    | "IndexSet" => {
+                        let mut schema = json!({ "type": "array" });
                         if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
                             if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
-                                let (inner_val, _) = map_syn_type_to_openapi(inner);
-                                return (json!({ "type": "array", "items": inner_val }), true);
+                                schema["items"] = nested_schema(inner, mappers).0;
                             }
                         }
-                        (json!({ "type": "array" }), true)
+                        if ident.ends_with("Set") {
+                            schema["uniqueItems"] = json!(true);
+                        }
+                        if ident.starts_with("BTree") {
+                            schema[SORTED_KEY] = json!(true);
+                        }
+                        (schema, true)
                     }
-                    "HashMap" | "BTreeMap" => {
+                    "HashMap" | "BTreeMap" | "IndexMap" => {
+                        let mut schema = json!({ "type": "object" });
                         if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
-                            if args.args.len() >= 2 {
-                                if let syn::GenericArgument::Type(val_type) = &args.args[1] {
-                                    let (val_schema, _) = map_syn_type_to_openapi(val_type);
-                                    return (
-                                        json!({ "type": "object", "additionalProperties": val_schema }),
-                                        true,
-                                    );
-                                }
+                            if let Some(syn::GenericArgument::Type(val_type)) = args.args.get(1) {
+                                schema["additionalProperties"] = nested_schema(val_type, mappers).0;
                             }
                         }
-                        (json!({ "type": "object" }), true)
+                        if ident.starts_with("BTree") {
+                            schema[SORTED_KEY] = json!(true);
+                        }
+                        (schema, true)
+                    }
+                    // Qualified paths (accounts::User) keep their module for smart-ref resolution
+                    _ if p.path.segments.len() > 1 => {
+                        let path = p
+                            .path
+                            .segments
+                            .iter()
+                            .map(|s| s.ident.to_string())
+                            .collect::<Vec<_>>()
+                            .join("::");
+                        (json!({ "$ref": format!("${}", path) }), true)
                     }
                     _ => (json!({ "$ref": format!("${}", ident) }), true),
                 }
@@ -340,10 +1459,267 @@ fn map_syn_type_to_openapi(ty: &syn::Type) -> (Value, bool) {
                 (json!({ "type": "object" }), true)
             }
         }
+        // References are transparent: &T / &'a mut T
+        syn::Type::Reference(r) => map_syn_type_to_openapi(&r.elem, mappers),
+        syn::Type::Paren(p) => map_syn_type_to_openapi(&p.elem, mappers),
+        syn::Type::Group(g) => map_syn_type_to_openapi(&g.elem, mappers),
+        // Slices and fixed-size arrays: [T] / [T; N]
+        syn::Type::Slice(s) => {
+            let (inner_val, _) = nested_schema(&s.elem, mappers);
+            (json!({ "type": "array", "items": inner_val }), true)
+        }
+        syn::Type::Array(a) => {
+            let (inner_val, _) = nested_schema(&a.elem, mappers);
+            (json!({ "type": "array", "items": inner_val }), true)
+        }
         _ => (json!({ "type": "object" }), true),
     }
 }
 
+// Subset of serde / schemars attributes that shape the reflected schema.
+// schemars values take precedence over serde ones; @openapi overrides beat both.
+// schemars(example = "...") names a function producing the example, which cannot be
+// evaluated here; it is skipped with a warning in favour of `@openapi example:`.
+#[derive(Default)]
+struct SchemaAttrs {
+    rename: Option<String>,
+    // Container rule for variant names, e.g. `snake_case`
+    rename_all: Option<String>,
+    skip: bool,
+    with: Option<String>,
+    // schemars(example = "path") names a function, which cannot be evaluated; only reported
+    example_fn: Option<String>,
+    description: Option<String>,
+    untagged: bool,
+    tag: Option<String>,
+    content: Option<String>,
+}
+
+impl SchemaAttrs {
+    fn from_attrs(attrs: &[Attribute]) -> Self {
+        let serde = Self::parse(attrs, "serde");
+        let schemars = Self::parse(attrs, "schemars");
+        Self {
+            rename: schemars.rename.or(serde.rename),
+            rename_all: schemars.rename_all.or(serde.rename_all),
+            skip: schemars.skip || serde.skip,
+            // serde(with) names a module, not a type; only schemars(with) is a type
+            with: schemars.with,
+            example_fn: schemars.example_fn,
+            description: schemars.description,
+            untagged: serde.untagged,
+            tag: serde.tag,
+            content: serde.content,
+        }
+    }
+
+    fn parse(attrs: &[Attribute], name: &str) -> Self {
+        let mut out = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident(name)) {
+            let _ = attr.parse_nested_meta(|meta| {
+                let key = meta
+                    .path
+                    .get_ident()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default();
+
+                let mut value = None;
+                if meta.input.peek(syn::Token![=]) {
+                    let expr: Expr = meta.value()?.parse()?;
+                    if let Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    }) = expr
+                    {
+                        value = Some(s.value());
+                    }
+                } else if meta.input.peek(syn::token::Paren) {
+                    // e.g. rename(serialize = "a", deserialize = "b")
+                    meta.parse_nested_meta(|inner| {
+                        let expr: Expr = inner.value()?.parse()?;
+                        if inner.path.is_ident("serialize") {
+                            if let Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Str(s),
+                                ..
+                            }) = expr
+                            {
+                                value = Some(s.value());
+                            }
+                        }
+                        Ok(())
+                    })?;
+                }
+
+                match key.as_str() {
+                    "rename" => out.rename = value,
+                    "rename_all" => out.rename_all = value,
+                    "skip" | "skip_serializing" => out.skip = true,
+                    "with" => out.with = value,
+                    "example" if name == "schemars" => out.example_fn = value,
+                    "description" => out.description = value,
+                    "untagged" => out.untagged = true,
+                    "tag" => out.tag = value,
+                    "content" => out.content = value,
+                    _ => {}
+                }
+                Ok(())
+            });
+        }
+        out
+    }
+
+    fn apply(&self, schema: &mut Value) {
+        if let Some(desc) = &self.description {
+            json_merge(schema, json!({ "description": desc }));
+        }
+    }
+}
+
+// Serialized name of an enum variant: its own rename, else the container's rename_all rule
+fn variant_name(variant: &syn::Variant, rename_all: Option<&str>) -> String {
+    if let Some(rename) = SchemaAttrs::from_attrs(&variant.attrs).rename {
+        return rename;
+    }
+    let ident = variant.ident.to_string();
+    let words = || {
+        let mut snake = String::new();
+        for (idx, c) in ident.char_indices() {
+            if idx > 0 && c.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        }
+        snake
+    };
+    match rename_all {
+        Some("lowercase") => ident.to_lowercase(),
+        Some("UPPERCASE") => ident.to_uppercase(),
+        Some("camelCase") => {
+            let mut chars = ident.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_lowercase().chain(chars).collect()
+            })
+        }
+        Some("snake_case") => words(),
+        Some("SCREAMING_SNAKE_CASE") => words().to_uppercase(),
+        Some("kebab-case") => words().replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => words().replace('_', "-").to_uppercase(),
+        // PascalCase is how variants are written already
+        _ => ident,
+    }
+}
+
+// Helper translating validator crate attributes into schema constraints:
+// length -> minLength/maxLength (minItems/maxItems for arrays), range -> minimum/maximum,
+// email/url -> format. regex names a static Regex that cannot be read, so it yields no pattern.
+fn apply_validate_attrs(attrs: &[Attribute], schema: &mut Value) {
+    fn number(expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+                syn::Lit::Int(i) => i.base10_parse::<i64>().ok().map(|n| json!(n)),
+                syn::Lit::Float(f) => f.base10_parse::<f64>().ok().map(|n| json!(n)),
+                _ => None,
+            },
+            Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => match number(expr)? {
+                Value::Number(n) if n.is_i64() => Some(json!(-n.as_i64()?)),
+                Value::Number(n) => Some(json!(-n.as_f64()?)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    let is_array = schema.get("type").and_then(|t| t.as_str()) == Some("array");
+    let (min_key, max_key) = if is_array {
+        ("minItems", "maxItems")
+    } else {
+        ("minLength", "maxLength")
+    };
+    let mut constraints = serde_json::Map::new();
+
+    for attr in attrs {
+        if !attr.path().is_ident("validate") {
+            continue;
+        }
+        let _ = attr.parse_nested_meta(|meta| {
+            let rule = meta
+                .path
+                .get_ident()
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            match rule.as_str() {
+                "email" => {
+                    constraints.insert("format".to_string(), json!("email"));
+                }
+                "url" => {
+                    constraints.insert("format".to_string(), json!("uri"));
+                }
+                "length" | "range" => {
+                    meta.parse_nested_meta(|inner| {
+                        let key = inner
+                            .path
+                            .get_ident()
+                            .map(|i| i.to_string())
+                            .unwrap_or_default();
+                        let expr: Expr = inner.value()?.parse()?;
+                        if let Some(n) = number(&expr) {
+                            let target = match (rule.as_str(), key.as_str()) {
+                                ("length", "min") => vec![min_key],
+                                ("length", "max") => vec![max_key],
+                                ("length", "equal") => vec![min_key, max_key],
+                                ("range", "min") => vec!["minimum"],
+                                ("range", "max") => vec!["maximum"],
+                                // 3.0 form: a boolean flag on the bound
+                                ("range", "exclusive_min") => {
+                                    constraints.insert("exclusiveMinimum".to_string(), json!(true));
+                                    vec!["minimum"]
+                                }
+                                ("range", "exclusive_max") => {
+                                    constraints.insert("exclusiveMaximum".to_string(), json!(true));
+                                    vec!["maximum"]
+                                }
+                                _ => vec![],
+                            };
+                            for k in target {
+                                constraints.insert(k.to_string(), n.clone());
+                            }
+                        }
+                        Ok(())
+                    })?;
+                }
+                _ => {
+                    // Unsupported rule (custom, contains, ...): skip its arguments. `regex`
+                    // names a static Regex, not the pattern; use `@openapi pattern:` instead
+                    if rule == "regex" {
+                        log::debug!(
+                            "validate(regex) has no pattern to document; use @openapi pattern:"
+                        );
+                    }
+                    if meta.input.peek(syn::Token![=]) {
+                        let _: Expr = meta.value()?.parse()?;
+                    } else if meta.input.peek(syn::token::Paren) {
+                        let _ = meta.parse_nested_meta(|inner| {
+                            if inner.input.peek(syn::Token![=]) {
+                                let _: Expr = inner.value()?.parse()?;
+                            }
+                            Ok(())
+                        });
+                    }
+                }
+            }
+            Ok(())
+        });
+    }
+
+    if !constraints.is_empty() {
+        json_merge(schema, Value::Object(constraints));
+    }
+}
+
 // Deep Merge Helper for JSON Values
 fn json_merge(a: &mut Value, b: Value) {
     match (a, b) {
@@ -358,6 +1734,9 @@ fn json_merge(a: &mut Value, b: Value) {
 
 impl<'ast> Visit<'ast> for OpenApiVisitor {
     fn visit_file(&mut self, i: &'ast File) {
+        // A file is a module too; nothing to restore, the visitor is per file
+        self.apply_module_directives(&i.attrs);
+
         // State machine for file-level doc blocks
         let mut current_block_type: Option<String> = None;
         let mut current_block_lines = Vec::new();
@@ -375,18 +1754,11 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             if trimmed.starts_with("@openapi-type") {
                                 // Flush previous if exists
                                 if !current_block_lines.is_empty() {
-                                    let body = current_block_lines.join("\n");
-                                    if let Some(name) = current_block_type.take() {
-                                        let wrapped = wrap_in_schema(&name, &body);
-                                        self.items.push(ExtractedItem::Schema {
-                                            name: Some(name),
-                                            content: wrapped,
-                                            line: start_line,
-                                        });
-                                    } else {
-                                        // Standard Root/Fragment block
-                                        self.parse_doc_block(&body, None, start_line);
-                                    }
+                                    self.flush_file_block(
+                                        current_block_type.take(),
+                                        &current_block_lines,
+                                        start_line,
+                                    );
                                     current_block_lines.clear();
                                 }
 
@@ -398,17 +1770,11 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             } else if trimmed.starts_with("@openapi") {
                                 // Flush previous
                                 if !current_block_lines.is_empty() {
-                                    let body = current_block_lines.join("\n");
-                                    if let Some(name) = current_block_type.take() {
-                                        let wrapped = wrap_in_schema(&name, &body);
-                                        self.items.push(ExtractedItem::Schema {
-                                            name: Some(name),
-                                            content: wrapped,
-                                            line: start_line,
-                                        });
-                                    } else {
-                                        self.parse_doc_block(&body, None, start_line);
-                                    }
+                                    self.flush_file_block(
+                                        current_block_type.take(),
+                                        &current_block_lines,
+                                        start_line,
+                                    );
                                     current_block_lines.clear();
                                 }
 
@@ -427,17 +1793,11 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             } else {
                 // Flush on non-doc attr to be safe
                 if !current_block_lines.is_empty() {
-                    let body = current_block_lines.join("\n");
-                    if let Some(name) = current_block_type.take() {
-                        let wrapped = wrap_in_schema(&name, &body);
-                        self.items.push(ExtractedItem::Schema {
-                            name: Some(name),
-                            content: wrapped,
-                            line: start_line,
-                        });
-                    } else {
-                        self.parse_doc_block(&body, None, start_line);
-                    }
+                    self.flush_file_block(
+                        current_block_type.take(),
+                        &current_block_lines,
+                        start_line,
+                    );
                     current_block_lines.clear();
                 }
             }
@@ -445,17 +1805,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
 
         // Flush EOF
         if !current_block_lines.is_empty() {
-            let body = current_block_lines.join("\n");
-            if let Some(name) = current_block_type {
-                let wrapped = wrap_in_schema(&name, &body);
-                self.items.push(ExtractedItem::Schema {
-                    name: Some(name),
-                    content: wrapped,
-                    line: start_line,
-                });
-            } else {
-                self.parse_doc_block(&body, None, start_line);
-            }
+            self.flush_file_block(current_block_type, &current_block_lines, start_line);
         }
 
         visit::visit_file(self, i);
@@ -476,9 +1826,18 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
         }
 
         // Check for DSL trigger
+        // Either an explicit @route line, or a framework routing attribute
+        // (actix-web / rocket) combined with DSL directives.
         let has_route = doc_lines.iter().any(|l| l.trim().starts_with("@route"));
+        let attr_route = framework_route(&i.attrs);
+        let has_dsl = doc_lines.iter().any(|l| {
+            let t = l.trim();
+            ROUTE_DIRECTIVES
+                .iter()
+                .any(|directive| t.starts_with(directive))
+        });
 
-        if !has_route {
+        if !(has_route || attr_route.is_some() && has_dsl) {
             // Legacy Fallback
             self.check_attributes(&i.attrs, None, i.span().start().line);
             visit::visit_item_fn(self, i);
@@ -500,386 +1859,184 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
         let mut description_buffer = Vec::new();
         let mut summary: Option<String> = None;
         let mut declared_path_params = std::collections::HashSet::new();
+        let mut params = Vec::new();
+        let mut tags = Vec::new();
+        let mut response_headers = Vec::new();
+        let mut examples = Vec::new();
+        let mut links = Vec::new();
 
-        for line in &doc_lines {
+        for (idx, line) in doc_lines.iter().enumerate() {
             let trimmed = line.trim();
             if trimmed.is_empty() {
+                // Kept once the description started; surrounding blanks are trimmed later
+                if summary.is_some() {
+                    description_buffer.push(line.as_str());
+                }
                 continue;
             }
 
+            if let Some((_, first)) = line.split_once("@description") {
+                if trimmed.starts_with("@description") {
+                    // Everything after it belongs to the description, verbatim
+                    description_buffer.push(first);
+                    description_buffer.extend(doc_lines[idx + 1..].iter().map(String::as_str));
+                    break;
+                }
+            }
             if trimmed.starts_with("@route") {
-                let parts: Vec<&str> = trimmed.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    method = parts[1].to_lowercase();
-                    let raw_path = parts[2..].join(" ");
-
-                    let mut new_path = String::new();
-                    let mut last_end = 0;
-
-                    // Regex: \{(\w+)(?::\s*([^"}]+))?(?:\s*"([^"]+)")?\}
-                    // Matches {id}, {id: u32}, {id: u32 "Description"}
-                    // Group 2: Type (trimmed), Group 3: Description (content inside quotes)
-                    let re = Regex::new(r#"\{(\w+)(?::\s*([^"}]+))?(?:\s*"([^"]+)")?\}"#).unwrap();
-
-                    for cap in re.captures_iter(&raw_path) {
-                        let full_match = cap.get(0).unwrap();
-                        let name = cap.get(1).unwrap().as_str();
-                        let type_str = cap.get(2).map(|m| m.as_str().trim());
-                        let desc = cap.get(3).map(|m| m.as_str().to_string()); // Directly capture inside quotes
-
-                        new_path.push_str(&raw_path[last_end..full_match.start()]);
-                        new_path.push('{');
-                        new_path.push_str(name);
-                        new_path.push('}');
-                        last_end = full_match.end();
-
-                        let is_bare = type_str.is_none() && desc.is_none();
-
-                        if !is_bare {
-                            declared_path_params.insert(name.to_string());
-
-                            let t = type_str.unwrap_or("String");
-                            let (schema, _is_required) =
-                                if let Ok(ty) = syn::parse_str::<syn::Type>(t) {
-                                    map_syn_type_to_openapi(&ty)
-                                } else {
-                                    (json!({ "type": "string" }), true)
-                                };
-
-                            let mut param_obj = json!({
-                                "name": name,
-                                "in": "path",
-                                "required": true,
-                                "schema": schema
-                            });
-
-                            if let Some(d) = desc {
-                                if let Value::Object(m) = &mut param_obj {
-                                    m.insert("description".to_string(), json!(d));
-                                }
-                            }
-
-                            if let Value::Array(params) = operation.get_mut("parameters").unwrap() {
-                                params.push(param_obj);
-                            }
-                        }
+                if let Some(route) = route_dsl::parse_route_line(trimmed, &self.type_mappers) {
+                    for param in &route.params {
+                        declared_path_params.insert(param["name"].as_str().unwrap().to_string());
                     }
-                    new_path.push_str(&raw_path[last_end..]);
-                    path = new_path;
+                    params.extend(route.params);
+                    method = route.method;
+                    path = route.path;
                 }
             } else if trimmed.starts_with("@tag") {
-                let final_content = if trimmed.starts_with("@tags") {
-                    trimmed.strip_prefix("@tags").unwrap().trim()
-                } else {
-                    trimmed.strip_prefix("@tag").unwrap().trim()
-                };
-
-                if final_content.starts_with('[') && final_content.ends_with(']') {
-                    let inner = &final_content[1..final_content.len() - 1];
-                    for t in inner.split(',') {
-                        if let Value::Array(tags) = operation.get_mut("tags").unwrap() {
-                            tags.push(json!(t.trim()));
-                        }
-                    }
-                } else {
-                    if let Value::Array(tags) = operation.get_mut("tags").unwrap() {
-                        tags.push(json!(final_content));
-                    }
-                }
+                tags.extend(route_dsl::parse_tag_line(trimmed));
             } else if trimmed.contains("-param") && trimmed.starts_with('@') {
-                let (param_type, rest) = if trimmed.starts_with("@query-param") {
-                    (
-                        "query",
-                        trimmed.strip_prefix("@query-param").unwrap().trim(),
-                    )
-                } else if trimmed.starts_with("@path-param") {
-                    ("path", trimmed.strip_prefix("@path-param").unwrap().trim())
-                } else if trimmed.starts_with("@header-param") {
-                    (
-                        "header",
-                        trimmed.strip_prefix("@header-param").unwrap().trim(),
-                    )
-                } else if trimmed.starts_with("@cookie-param") {
-                    (
-                        "cookie",
-                        trimmed.strip_prefix("@cookie-param").unwrap().trim(),
-                    )
-                } else {
-                    continue;
-                };
-
-                if let Some(colon_idx) = rest.find(':') {
-                    let name = rest[..colon_idx].trim();
-                    let residue = rest[colon_idx + 1..].trim();
-
-                    let mut tokens = Vec::new();
-                    let mut current = String::new();
-                    let mut in_quote = false;
-                    for c in residue.chars() {
-                        if c == '"' {
-                            in_quote = !in_quote;
-                            current.push(c);
-                        } else if c.is_whitespace() && !in_quote {
-                            if !current.is_empty() {
-                                tokens.push(current.clone());
-                                current.clear();
-                            }
-                        } else {
-                            current.push(c);
-                        }
-                    }
-                    if !current.is_empty() {
-                        tokens.push(current);
-                    }
-
-                    if tokens.is_empty() {
-                        continue;
-                    }
-
-                    // Identify Type
-                    let first = &tokens[0];
-                    let (type_str, start_idx) = if first == "deprecated"
-                        || first == "required"
-                        || first.contains('=')
-                        || first.starts_with('"')
-                    {
-                        ("String", 0)
-                    } else if syn::parse_str::<syn::Type>(first).is_ok() {
-                        (first.as_str(), 1)
-                    } else {
-                        // Fallback
-                        ("String", 0)
-                    };
-
-                    let (schema, mut is_required) =
-                        if let Ok(ty) = syn::parse_str::<syn::Type>(type_str) {
-                            map_syn_type_to_openapi(&ty)
-                        } else {
-                            (json!({ "type": "string" }), true)
-                        };
-
-                    let mut deprecated = false;
-                    let mut example = None;
-                    let mut desc = None;
-
-                    for token in tokens.iter().skip(start_idx) {
-                        if token == "deprecated" {
-                            deprecated = true;
-                        } else if token == "required" {
-                            is_required = true;
-                        } else if token.starts_with("example=") {
-                            let val = token.strip_prefix("example=").unwrap().trim_matches('"');
-                            example = Some(val.to_string());
-                        } else if token.starts_with('"') {
-                            desc = Some(token.trim_matches('"').to_string());
-                        }
-                    }
-
-                    let mut param_obj = json!({
-                        "name": name,
-                        "in": param_type,
-                        "required": is_required,
-                        "schema": schema
-                    });
-
-                    if deprecated {
-                        param_obj
-                            .as_object_mut()
-                            .unwrap()
-                            .insert("deprecated".to_string(), json!(true));
-                    }
-                    if let Some(ex) = example {
-                        param_obj
-                            .as_object_mut()
-                            .unwrap()
-                            .insert("example".to_string(), json!(ex));
-                    }
-
-                    if param_type == "path" {
-                        declared_path_params.insert(name.to_string());
-                        if let Value::Object(m) = &mut param_obj {
-                            m.insert("required".to_string(), json!(true));
-                        }
-                    }
-
-                    if let Some(d) = desc {
-                        if let Value::Object(m) = &mut param_obj {
-                            m.insert("description".to_string(), json!(d));
-                        }
-                    }
-
-                    if let Value::Array(params) = operation.get_mut("parameters").unwrap() {
-                        params.push(param_obj);
+                if let Some(param) = route_dsl::parse_param_line(trimmed, &self.type_mappers) {
+                    if param["in"] == "path" {
+                        declared_path_params.insert(param["name"].as_str().unwrap().to_string());
                     }
+                    params.push(param);
                 }
             } else if trimmed.starts_with("@body") {
-                let rest = trimmed.strip_prefix("@body").unwrap().trim();
-                let parts: Vec<&str> = rest.split_whitespace().collect();
-                if !parts.is_empty() {
-                    let schema_ref = parts[0];
-                    let mime = if parts.len() > 1 {
-                        parts[1]
-                    } else {
-                        "application/json"
-                    };
-
-                    let schema = if schema_ref.contains('<')
-                        || (schema_ref.starts_with('$') && schema_ref.contains('<'))
-                    {
-                        json!({ "$ref": schema_ref })
-                    } else if let Ok(ty) = syn::parse_str::<syn::Type>(schema_ref) {
-                        map_syn_type_to_openapi(&ty).0
-                    } else {
-                        if schema_ref.starts_with('$') {
-                            json!({ "$ref": format!("#/components/schemas/{}", &schema_ref[1..]) })
-                        } else {
-                            json!({ "$ref": format!("#/components/schemas/{}", schema_ref) })
-                        }
-                    };
-
-                    operation["requestBody"] = json!({
-                        "content": {
-                            mime: {
-                                "schema": schema
-                            }
-                        }
-                    });
+                let consumes = self.current_consumes.as_deref();
+                if let Some(body) = route_dsl::parse_body_line(
+                    trimmed,
+                    &self.type_mappers,
+                    consumes.unwrap_or(route_dsl::JSON_MIME),
+                ) {
+                    operation["requestBody"] = body;
                 }
             } else if trimmed.starts_with("@return") {
-                let rest = trimmed.strip_prefix("@return").unwrap().trim();
-                let parts = if let Some(idx) = rest.find(':') {
-                    // Check structure to be safe
-                    Some(idx)
-                } else {
-                    None
-                };
-
-                if let Some(colon_idx) = parts {
-                    let code = rest[..colon_idx].trim();
-                    let residue = rest[colon_idx + 1..].trim();
-
-                    let (type_str, desc, is_unit) = if residue.starts_with('"') {
-                        ("()", Some(residue.trim_matches('"').to_string()), true)
-                    } else {
-                        if let Some(quote_start) = residue.find('"') {
-                            (
-                                residue[..quote_start].trim(),
-                                Some(residue[quote_start + 1..residue.len() - 1].to_string()),
-                                false,
-                            )
-                        } else {
-                            (residue, None, false)
-                        }
-                    };
-
-                    let is_explicit_unit = type_str == "()" || type_str == "unit";
-                    let effective_unit = is_unit || is_explicit_unit;
-
-                    let schema = if effective_unit {
-                        json!({})
-                    } else if type_str.contains('<')
-                        || (type_str.starts_with('$') && type_str.contains('<'))
-                    {
-                        json!({ "$ref": type_str })
-                    } else if let Ok(ty) = syn::parse_str::<syn::Type>(type_str) {
-                        map_syn_type_to_openapi(&ty).0
+                if let Some((code, response)) = route_dsl::parse_return_line(
+                    trimmed,
+                    &self.type_mappers,
+                    self.current_produces
+                        .as_deref()
+                        .unwrap_or(route_dsl::JSON_MIME),
+                ) {
+                    operation["responses"][code] = response;
+                }
+            } else if trimmed.starts_with("@response-header") {
+                response_headers.extend(route_dsl::parse_response_header_line(
+                    trimmed,
+                    &self.type_mappers,
+                ));
+            } else if trimmed.starts_with("@example") {
+                examples.extend(route_dsl::parse_example_line(trimmed));
+            } else if trimmed.starts_with("@link") {
+                let mut ignored = Vec::new();
+                links.extend(route_dsl::parse_link_line(trimmed, &mut ignored));
+                let line = i.span().start().line;
+                self.warnings
+                    .extend(ignored.into_iter().map(|message| (line, message)));
+            } else if trimmed.starts_with("@server") {
+                if let Some(server) = route_dsl::parse_server_line(trimmed) {
+                    if let Value::Array(servers) = &mut operation["servers"] {
+                        servers.push(server);
                     } else {
-                        if type_str.starts_with('$') {
-                            json!({ "$ref": format!("#/components/schemas/{}", &type_str[1..]) })
-                        } else if type_str == "String" || type_str == "str" {
-                            json!({ "type": "string" })
-                        } else {
-                            json!({ "$ref": format!("#/components/schemas/{}", type_str) })
-                        }
-                    };
-
-                    let mut resp_obj = json!({
-                        "description": desc.unwrap_or_else(|| "".to_string())
-                    });
-
-                    if !effective_unit {
-                        resp_obj["content"] = json!({
-                            "application/json": {
-                                "schema": schema
-                            }
-                        });
-                    }
-
-                    if let Value::Object(responses) = operation.get_mut("responses").unwrap() {
-                        responses.insert(code.to_string(), resp_obj);
+                        operation["servers"] = json!([server]);
                     }
                 }
             } else if trimmed.starts_with("@security") {
-                let rest = trimmed.strip_prefix("@security").unwrap().trim();
-                let (scheme, scopes) = if let Some(paren_start) = rest.find('(') {
-                    let name = rest[..paren_start].trim();
-                    let inner = &rest[paren_start + 1..rest.len() - 1];
-                    let s: Vec<String> = inner
-                        .split(',')
-                        .map(|s| s.trim().trim_matches('"').to_string())
-                        .collect();
-                    (name, s)
-                } else {
-                    (rest, vec![])
-                };
-
                 if operation.get("security").is_none() {
                     operation["security"] = json!([]);
                 }
-
-                if let Value::Array(sec) = operation.get_mut("security").unwrap() {
-                    sec.push(json!({ scheme: scopes }));
+                if let Value::Array(sec) = &mut operation["security"] {
+                    sec.push(route_dsl::parse_security_line(trimmed));
                 }
             } else if !trimmed.starts_with('@') {
                 if summary.is_none() {
                     summary = Some(trimmed.to_string());
                 } else {
-                    description_buffer.push(trimmed);
+                    description_buffer.push(line);
+                }
+            }
+        }
+
+        // After the loop, so they may come before the @return they belong to
+        for (code, name, header) in response_headers {
+            route_dsl::response_mut(&mut operation, &code)["headers"][name] = header;
+        }
+        for (code, name, link) in links {
+            route_dsl::response_mut(&mut operation, &code)["links"][name] = link;
+        }
+        for (code, name, example) in examples {
+            let response = route_dsl::response_mut(&mut operation, &code);
+            if response.get("content").is_none() {
+                let mime = self
+                    .current_produces
+                    .as_deref()
+                    .unwrap_or(route_dsl::JSON_MIME);
+                response["content"] = json!({ mime: {} });
+            }
+            if let Some(Value::Object(content)) = response.get_mut("content") {
+                for media in content.values_mut() {
+                    media["examples"][&name] = example.clone();
                 }
             }
         }
 
+        operation["parameters"] = json!(params);
+        operation["tags"] = json!(tags);
         if let Some(s) = summary {
             operation["summary"] = json!(s);
         }
-        if !description_buffer.is_empty() {
-            operation["description"] = json!(description_buffer.join("\n"));
+        if let Some(description) = route_dsl::join_description(&description_buffer) {
+            operation["description"] = json!(description);
         }
 
-        // Validation
-        let validation_re = Regex::new(r"\{(\w+)\}").unwrap();
-        for cap in validation_re.captures_iter(&path) {
-            let var = cap.get(1).unwrap().as_str();
-            if !declared_path_params.contains(var) {
-                // Panic on validation error as per requirements
-                panic!(
-                    "Missing definition for path parameter '{}' in route '{}'",
-                    var, path
-                );
+        // Framework attribute: fills in a missing @route, must agree with an explicit one
+        if let Some((attr_method, attr_path)) = attr_route {
+            if method.is_empty() {
+                method = attr_method;
+                path = attr_path;
+            } else if method != attr_method || path != attr_path {
+                self.route_errors.push((
+                    i.span().start().line,
+                    format!(
+                        "@route {} {} disagrees with routing attribute {} {} on '{}'",
+                        method.to_uppercase(),
+                        path,
+                        attr_method.to_uppercase(),
+                        attr_path,
+                        i.sig.ident
+                    ),
+                ));
+                visit::visit_item_fn(self, i);
+                return;
             }
         }
-        // Check for unused path params is implicitly handled if we track them,
-        // to check strict unused we'd need to check declared_path_params vs matches in path.
-        // The declared_path_params set contains only those captured from inline or @path-param.
-        // We should check if any declared param is NOT in path?
-        // Inline params are by definition in path.
-        // @path-param defined variables might NOT be in path.
-        for declared in &declared_path_params {
-            if !path.contains(&format!("{{{}}}", declared)) {
-                panic!(
-                    "Declared path parameter '{}' is unused in route '{}'",
-                    declared, path
-                );
-            }
+
+        // Validation
+        if let Err(message) = route_dsl::check_path_params(&path, &declared_path_params) {
+            self.route_errors.push((i.span().start().line, message));
+            visit::visit_item_fn(self, i);
+            return;
         }
 
         if let Value::Object(map) = &mut operation {
             map.retain(|_, v| !v.is_null());
+            if let Some(Value::Array(tags)) = map.get_mut("tags") {
+                if tags.is_empty() {
+                    tags.extend(self.input_metadata.tags.iter().map(|t| json!(t)));
+                }
+            }
+            for (key, value) in &self.input_metadata.extensions {
+                map.entry(key.clone()).or_insert_with(|| value.clone());
+            }
         }
 
         if !method.is_empty() && !path.is_empty() {
             let mut method_map = serde_json::Map::new();
             method_map.insert(method, operation);
+            // Path item level; an operation's own @server overrides them
+            if !self.current_servers.is_empty() {
+                method_map.insert("servers".to_string(), json!(self.current_servers));
+            }
 
             let mut path_map = serde_json::Map::new();
             path_map.insert(path, Value::Object(method_map));
@@ -891,9 +2048,13 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             if let Ok(generated) = serde_yaml::to_string(&path_item) {
                 let trimmed = generated.trim_start_matches("---\n").to_string();
                 self.items.push(ExtractedItem::Schema {
+                    module: self.current_module(),
                     name: None,
                     content: trimmed,
                     line: i.span().start().line,
+                    first_line: None,
+                    raw: None,
+                    priority: 0,
                 });
             }
         }
@@ -903,7 +2064,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
 
     fn visit_item_type(&mut self, i: &'ast ItemType) {
         let ident = i.ident.to_string();
-        let (mut schema, _) = map_syn_type_to_openapi(&i.ty);
+        let (mut schema, _) = map_syn_type_to_openapi(&i.ty, &self.type_mappers);
 
         // Docs & Overrides
         let mut desc_lines = Vec::new();
@@ -918,7 +2079,9 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             let val = lit_str.value();
                             let trimmed = val.trim();
 
-                            if trimmed.starts_with("@openapi") {
+                            if trimmed.starts_with(ALIAS_DIRECTIVE) {
+                                // See push_aliases
+                            } else if trimmed.starts_with("@openapi") {
                                 collecting_openapi = true;
                                 let rest = trimmed.strip_prefix("@openapi").unwrap().trim();
                                 if !rest.is_empty() {
@@ -927,7 +2090,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             } else if collecting_openapi {
                                 openapi_lines.push(val.to_string());
                             } else {
-                                desc_lines.push(val.trim().to_string());
+                                desc_lines.push(val);
                             }
                         }
                     }
@@ -937,10 +2100,9 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             }
         }
 
-        if !desc_lines.is_empty() {
-            let desc_str = desc_lines.join(" ");
-            if let Value::Object(map) = &mut schema {
-                map.insert("description".to_string(), Value::String(desc_str));
+        if let Value::Object(map) = &mut schema {
+            if let Value::Object(doc) = doc_text(&desc_lines, self.doc_style) {
+                map.extend(doc);
             }
         }
 
@@ -955,114 +2117,36 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
 
         if let Ok(generated) = serde_yaml::to_string(&schema) {
             let trimmed = generated.trim_start_matches("---\n").to_string();
-            let wrapped = wrap_in_schema(&ident, &trimmed);
+            let name = self.schema_name(&ident);
+            let wrapped = wrap_in_schema(&name, &trimmed);
             self.items.push(ExtractedItem::Schema {
-                name: Some(ident),
+                module: self.current_module(),
+                name: Some(name.clone()),
                 content: wrapped,
-                line: i.span().start().line,
-            });
-        }
-
-        visit::visit_item_type(self, i);
-    }
-
-    fn visit_item_struct(&mut self, i: &'ast ItemStruct) {
-        let ident = i.ident.to_string();
-
-        let mut properties = serde_json::Map::new();
-        let mut required_fields = Vec::new();
-        let mut has_fields = false;
-
-        if let syn::Fields::Named(fields) = &i.fields {
-            for field in &fields.named {
-                has_fields = true;
-                let field_name = field.ident.as_ref().unwrap().to_string();
-
-                let (mut field_schema, is_required) = map_syn_type_to_openapi(&field.ty);
-
-                let mut field_desc = Vec::new();
-                for attr in &field.attrs {
-                    if attr.path().is_ident("doc") {
-                        if let syn::Meta::NameValue(meta) = &attr.meta {
-                            if let Expr::Lit(expr_lit) = &meta.value {
-                                if let syn::Lit::Str(lit_str) = &expr_lit.lit {
-                                    let val = lit_str.value().trim().to_string();
-                                    if val.starts_with("@openapi") {
-                                        break;
-                                    }
-                                    field_desc.push(val);
-                                }
-                            }
-                        }
-                    }
-                }
-                if !field_desc.is_empty() {
-                    let desc_str = field_desc.join(" ");
-                    if let Value::Object(map) = &mut field_schema {
-                        map.insert("description".to_string(), Value::String(desc_str));
-                    }
-                }
-
-                // Field Level Overrides
-                let mut openapi_lines = Vec::new();
-                let mut collecting_openapi = false;
-
-                for attr in &field.attrs {
-                    if attr.path().is_ident("doc") {
-                        if let syn::Meta::NameValue(meta) = &attr.meta {
-                            if let Expr::Lit(expr_lit) = &meta.value {
-                                if let syn::Lit::Str(lit_str) = &expr_lit.lit {
-                                    let val = lit_str.value();
-                                    let trimmed = val.trim();
-
-                                    if trimmed.starts_with("@openapi") {
-                                        collecting_openapi = true;
-                                        let rest = trimmed.strip_prefix("@openapi").unwrap().trim();
-                                        if !rest.is_empty() {
-                                            openapi_lines.push(rest.to_string());
-                                        }
-                                    } else if collecting_openapi {
-                                        openapi_lines.push(val.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        collecting_openapi = false;
-                    }
-                }
-
-                if !openapi_lines.is_empty() {
-                    let override_yaml = openapi_lines.join("\n");
-                    if let Ok(override_val) = serde_yaml::from_str::<Value>(&override_yaml) {
-                        if !override_val.is_null() {
-                            json_merge(&mut field_schema, override_val);
-                        }
-                    }
-                }
-
-                properties.insert(field_name.clone(), field_schema);
-                if is_required {
-                    required_fields.push(field_name);
-                }
-            }
+                line: i.span().start().line,
+                first_line: None,
+                raw: None,
+                priority: 0,
+            });
+            self.push_aliases(&i.attrs, &name, i.span().start().line);
         }
 
+        visit::visit_item_type(self, i);
+    }
+
+    fn visit_item_struct(&mut self, i: &'ast ItemStruct) {
+        self.warn_schemars_examples(&i.attrs, &i.fields);
+        let container_attrs = SchemaAttrs::from_attrs(&i.attrs);
+        let ident = container_attrs
+            .rename
+            .clone()
+            .unwrap_or_else(|| i.ident.to_string());
+
         // Struct Level Schema
-        let mut schema = if has_fields {
-            let mut s = json!({
-                "type": "object",
-                "properties": properties
-            });
-            if !required_fields.is_empty() {
-                if let Value::Object(map) = &mut s {
-                    map.insert("required".to_string(), json!(required_fields));
-                }
-            }
-            s
-        } else {
+        let mut schema = match &i.fields {
+            syn::Fields::Named(fields) => self.object_schema(fields),
             // Unit Struct default
-            json!({ "type": "object" })
+            _ => json!({ "type": "object" }),
         };
 
         // Struct Level Docs & Overrides
@@ -1078,7 +2162,12 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                         if let syn::Lit::Str(lit_str) = &expr_lit.lit {
                             let val = lit_str.value();
                             let trimmed = val.trim();
-                            if trimmed.starts_with("@openapi") {
+                            if trimmed.starts_with(ALIAS_DIRECTIVE)
+                                || trimmed == SPLIT_READONLY_DIRECTIVE
+                                || trimmed.starts_with(EXAMPLE_FROM_DIRECTIVE)
+                            {
+                                // Handled once the schema is built
+                            } else if trimmed.starts_with("@openapi") {
                                 collecting_openapi = true;
                                 let rest = trimmed.strip_prefix("@openapi").unwrap().trim();
                                 if !rest.is_empty() {
@@ -1086,14 +2175,9 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                                         // Blueprint detection
                                         if let Some(start) = rest.find('<') {
                                             if let Some(end) = rest.rfind('>') {
-                                                let params_str = &rest[start + 1..end];
-                                                blueprint_params = Some(
-                                                    params_str
-                                                        .split(',')
-                                                        .map(|p| p.trim().to_string())
-                                                        .filter(|p| !p.is_empty())
-                                                        .collect(),
-                                                );
+                                                blueprint_params = Some(parse_blueprint_params(
+                                                    &rest[start + 1..end],
+                                                ));
 
                                                 let after_gt = rest[end + 1..].trim();
                                                 if !after_gt.is_empty() {
@@ -1108,7 +2192,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             } else if collecting_openapi {
                                 openapi_lines.push(val.to_string());
                             } else {
-                                desc_lines.push(val.trim().to_string());
+                                desc_lines.push(val);
                             }
                         }
                     }
@@ -1118,11 +2202,13 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             }
         }
 
-        if !desc_lines.is_empty() {
-            let desc_str = desc_lines.join(" ");
-            json_merge(&mut schema, json!({ "description": desc_str }));
+        let doc = doc_text(&desc_lines, self.doc_style);
+        if !doc.is_null() {
+            json_merge(&mut schema, doc);
         }
 
+        container_attrs.apply(&mut schema);
+
         if !openapi_lines.is_empty() {
             let override_yaml = openapi_lines.join("\n");
             if let Ok(override_val) = serde_yaml::from_str::<Value>(&override_yaml) {
@@ -1132,6 +2218,48 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             }
         }
 
+        // Real generic type params make the reflected schema a blueprint ($ref: $T placeholders)
+        if blueprint_params.is_none() {
+            let type_params: Vec<String> = i
+                .generics
+                .type_params()
+                .map(|p| p.ident.to_string())
+                .collect();
+            if !type_params.is_empty() {
+                blueprint_params = Some(type_params);
+            }
+        }
+
+        let example_from = doc_strings(&i.attrs).find_map(|line| {
+            Some(
+                line.strip_prefix(EXAMPLE_FROM_DIRECTIVE)?
+                    .trim()
+                    .to_string(),
+            )
+        });
+        if let Some(source) = example_from {
+            let line = i.span().start().line;
+            if source != "default" {
+                self.warnings.push((
+                    line,
+                    format!("{}: unknown example source '{}'", ident, source),
+                ));
+            } else if !derives(&i.attrs, "Default") {
+                self.warnings.push((
+                    line,
+                    format!(
+                        "{}: @openapi-example-from default requires #[derive(Default)]",
+                        ident
+                    ),
+                ));
+            } else if schema.get("example").is_none() {
+                let mut skipped = Vec::new();
+                schema["example"] = default_example(&ident, &schema, &mut skipped);
+                self.warnings
+                    .extend(skipped.into_iter().map(|message| (line, message)));
+            }
+        }
+
         // Final Serialize
         if let Ok(generated) = serde_yaml::to_string(&schema) {
             let trimmed = generated.trim_start_matches("---\n").to_string();
@@ -1144,12 +2272,35 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                     line: i.span().start().line,
                 });
             } else {
-                let wrapped = wrap_in_schema(&ident, &trimmed);
+                let name = self.schema_name(&ident);
+                let wrapped = wrap_in_schema(&name, &trimmed);
                 self.items.push(ExtractedItem::Schema {
-                    name: Some(ident),
+                    module: self.current_module(),
+                    name: Some(name.clone()),
                     content: wrapped,
                     line: i.span().start().line,
+                    first_line: None,
+                    raw: None,
+                    priority: 0,
                 });
+                self.push_aliases(&i.attrs, &name, i.span().start().line);
+
+                if doc_strings(&i.attrs).any(|line| line == SPLIT_READONLY_DIRECTIVE) {
+                    let create = without_read_only(&schema);
+                    if let Ok(generated) = serde_yaml::to_string(&create) {
+                        let name = self.schema_name(&format!("{}Create", ident));
+                        let content = generated.trim_start_matches("---\n");
+                        self.items.push(ExtractedItem::Schema {
+                            module: self.current_module(),
+                            content: wrap_in_schema(&name, content),
+                            name: Some(name),
+                            line: i.span().start().line,
+                            first_line: None,
+                            raw: None,
+                            priority: 0,
+                        });
+                    }
+                }
             }
         }
 
@@ -1157,16 +2308,61 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
     }
 
     fn visit_item_enum(&mut self, i: &'ast ItemEnum) {
-        let ident = i.ident.to_string();
+        self.warn_schemars_examples(&i.attrs, &syn::Fields::Unit);
+        for v in &i.variants {
+            self.warn_schemars_examples(&v.attrs, &v.fields);
+        }
+        let container_attrs = SchemaAttrs::from_attrs(&i.attrs);
+        let ident = container_attrs
+            .rename
+            .clone()
+            .unwrap_or_else(|| i.ident.to_string());
 
         let mut variants = Vec::new();
+        let mut branches = Vec::new();
+        let mut nullable = false;
         for v in &i.variants {
-            if matches!(v.fields, syn::Fields::Unit) {
-                variants.push(v.ident.to_string());
+            if container_attrs.untagged || container_attrs.tag.is_some() {
+                if SchemaAttrs::from_attrs(&v.attrs).skip {
+                    continue;
+                }
+                let branch = match &container_attrs.tag {
+                    Some(tag) => Some(self.tagged_variant_schema(
+                        v,
+                        tag,
+                        container_attrs.content.as_deref(),
+                        container_attrs.rename_all.as_deref(),
+                    )),
+                    // Unit variants of untagged enums serialize as null
+                    None => self.variant_payload(v),
+                };
+                match branch {
+                    Some(mut branch) => {
+                        self.document_variant(v, &mut branch);
+                        branches.push(branch);
+                    }
+                    None => nullable = true,
+                }
+            } else if matches!(v.fields, syn::Fields::Unit) {
+                if SchemaAttrs::from_attrs(&v.attrs).skip {
+                    continue;
+                }
+                variants.push(variant_name(v, container_attrs.rename_all.as_deref()));
             }
         }
 
-        let mut schema = if !variants.is_empty() {
+        let mut schema = if let (Some(tag), false) = (&container_attrs.tag, branches.is_empty()) {
+            json!({
+                "oneOf": branches,
+                "discriminator": { "propertyName": tag }
+            })
+        } else if !branches.is_empty() {
+            let mut s = json!({ "anyOf": branches });
+            if nullable {
+                s["nullable"] = json!(true);
+            }
+            s
+        } else if !variants.is_empty() {
             json!({
                 "type": "string",
                 "enum": variants
@@ -1188,7 +2384,9 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                         if let syn::Lit::Str(lit_str) = &expr_lit.lit {
                             let val = lit_str.value();
                             let trimmed = val.trim();
-                            if trimmed.starts_with("@openapi") {
+                            if trimmed.starts_with(ALIAS_DIRECTIVE) {
+                                // See push_aliases
+                            } else if trimmed.starts_with("@openapi") {
                                 collecting_openapi = true;
                                 let rest = trimmed.strip_prefix("@openapi").unwrap().trim();
                                 if !rest.is_empty() {
@@ -1196,14 +2394,9 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                                         // Blueprint detection
                                         if let Some(start) = rest.find('<') {
                                             if let Some(end) = rest.rfind('>') {
-                                                let params_str = &rest[start + 1..end];
-                                                blueprint_params = Some(
-                                                    params_str
-                                                        .split(',')
-                                                        .map(|p| p.trim().to_string())
-                                                        .filter(|p| !p.is_empty())
-                                                        .collect(),
-                                                );
+                                                blueprint_params = Some(parse_blueprint_params(
+                                                    &rest[start + 1..end],
+                                                ));
 
                                                 let after_gt = rest[end + 1..].trim();
                                                 if !after_gt.is_empty() {
@@ -1218,7 +2411,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                             } else if collecting_openapi {
                                 openapi_lines.push(val.to_string());
                             } else {
-                                desc_lines.push(val.trim().to_string());
+                                desc_lines.push(val);
                             }
                         }
                     }
@@ -1228,11 +2421,13 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             }
         }
 
-        if !desc_lines.is_empty() {
-            let desc_str = desc_lines.join(" ");
-            json_merge(&mut schema, json!({ "description": desc_str }));
+        let doc = doc_text(&desc_lines, self.doc_style);
+        if !doc.is_null() {
+            json_merge(&mut schema, doc);
         }
 
+        container_attrs.apply(&mut schema);
+
         if !openapi_lines.is_empty() {
             let override_yaml = openapi_lines.join("\n");
             if let Ok(override_val) = serde_yaml::from_str::<Value>(&override_yaml) {
@@ -1243,7 +2438,7 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
         }
 
         // Only emit if we have variants OR overrides
-        if !variants.is_empty() || !openapi_lines.is_empty() {
+        if !variants.is_empty() || !branches.is_empty() || !openapi_lines.is_empty() {
             if let Ok(generated) = serde_yaml::to_string(&schema) {
                 let trimmed = generated.trim_start_matches("---\n").to_string();
 
@@ -1255,12 +2450,18 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
                         line: i.span().start().line,
                     });
                 } else {
-                    let wrapped = wrap_in_schema(&ident, &trimmed);
+                    let name = self.schema_name(&ident);
+                    let wrapped = wrap_in_schema(&name, &trimmed);
                     self.items.push(ExtractedItem::Schema {
-                        name: Some(ident),
+                        module: self.current_module(),
+                        name: Some(name.clone()),
                         content: wrapped,
                         line: i.span().start().line,
+                        first_line: None,
+                        raw: None,
+                        priority: 0,
                     });
+                    self.push_aliases(&i.attrs, &name, i.span().start().line);
                 }
             }
         }
@@ -1270,13 +2471,16 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
 
     fn visit_item_mod(&mut self, i: &'ast ItemMod) {
         let mut found_tags = Vec::new();
+        let mut replace_tags = false;
         for attr in &i.attrs {
             if attr.path().is_ident("doc") {
                 if let syn::Meta::NameValue(meta) = &attr.meta {
                     if let Expr::Lit(expr_lit) = &meta.value {
                         if let syn::Lit::Str(lit_str) = &expr_lit.lit {
                             let val = lit_str.value();
-                            if val.contains("tags:") {
+                            let replaces = val.trim_start().starts_with(TAGS_REPLACE_KEY);
+                            if replaces || val.contains("tags:") {
+                                replace_tags |= replaces;
                                 if let Some(start) = val.find('[') {
                                     if let Some(end) = val.find(']') {
                                         let content = &val[start + 1..end];
@@ -1292,13 +2496,25 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
             }
         }
 
-        let old_len = self.current_tags.len();
+        let old_tags = self.current_tags.clone();
+        if replace_tags {
+            self.current_tags.clear();
+        }
         self.current_tags.extend(found_tags);
+        let old_produces = self.current_produces.clone();
+        let old_consumes = self.current_consumes.clone();
+        let old_servers = self.current_servers.len();
+        self.apply_module_directives(&i.attrs);
 
         self.check_attributes(&i.attrs, None, i.span().start().line);
+        self.module_path.push(i.ident.to_string());
         visit::visit_item_mod(self, i);
+        self.module_path.pop();
 
-        self.current_tags.truncate(old_len);
+        self.current_tags = old_tags;
+        self.current_produces = old_produces;
+        self.current_consumes = old_consumes;
+        self.current_servers.truncate(old_servers);
     }
 
     fn visit_impl_item_fn(&mut self, i: &'ast ImplItemFn) {
@@ -1308,22 +2524,178 @@ impl<'ast> Visit<'ast> for OpenApiVisitor {
 }
 
 pub fn extract_from_file(path: std::path::PathBuf) -> crate::error::Result<Vec<ExtractedItem>> {
+    extract_from_module(
+        path,
+        Vec::new(),
+        SchemaNaming::Plain,
+        &TypeMappers::default(),
+    )
+}
+
+/// Like [`extract_from_file`], for a file that is the Rust module `module_path`.
+pub fn extract_from_module(
+    path: std::path::PathBuf,
+    module_path: Vec<String>,
+    schema_naming: SchemaNaming,
+    type_mappers: &TypeMappers,
+) -> crate::error::Result<Vec<ExtractedItem>> {
+    extract_from_input(
+        path,
+        module_path,
+        schema_naming,
+        type_mappers,
+        &InputMetadata::default(),
+    )
+}
+
+/// Like [`extract_from_module`], applying the metadata of the input directory the file is in.
+pub fn extract_from_input(
+    path: std::path::PathBuf,
+    module_path: Vec<String>,
+    schema_naming: SchemaNaming,
+    type_mappers: &TypeMappers,
+    input_metadata: &InputMetadata,
+) -> crate::error::Result<Vec<ExtractedItem>> {
     let content = std::fs::read_to_string(&path)?;
-    let parsed_file = syn::parse_file(&content).map_err(|e| crate::error::Error::Parse {
+    let (items, warnings) = extract_from_source(
+        path,
+        &content,
+        module_path,
+        schema_naming,
+        type_mappers,
+        input_metadata,
+        DocStyle::default(),
+    )?;
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    Ok(items)
+}
+
+/// Like [`extract_from_input`], for the already read `content` of the file at `path`; also
+/// returns the warnings found while visiting (ignored directives and attributes).
+pub fn extract_from_source(
+    path: std::path::PathBuf,
+    content: &str,
+    module_path: Vec<String>,
+    schema_naming: SchemaNaming,
+    type_mappers: &TypeMappers,
+    input_metadata: &InputMetadata,
+    doc_style: DocStyle,
+) -> crate::error::Result<(Vec<ExtractedItem>, Vec<Diagnostic>)> {
+    let parsed_file = syn::parse_file(content).map_err(|e| crate::error::Error::Parse {
         file: path.clone(),
         source: e,
     })?;
 
-    let mut visitor = OpenApiVisitor::default();
+    let mut visitor = OpenApiVisitor {
+        current_tags: input_metadata.tags.clone(),
+        module_path,
+        schema_naming,
+        doc_style,
+        type_mappers: type_mappers.clone(),
+        input_metadata: input_metadata.clone(),
+        ..Default::default()
+    };
     visitor.visit_file(&parsed_file);
 
-    Ok(visitor.items)
+    crate::error::Error::from_all(
+        visitor
+            .route_errors
+            .into_iter()
+            .map(|(line, message)| crate::error::Error::Route {
+                file: path.clone(),
+                line,
+                message,
+            })
+            .collect(),
+    )?;
+
+    let source_lines: Vec<&str> = content.lines().collect();
+    for item in &mut visitor.items {
+        if let ExtractedItem::Schema {
+            content,
+            first_line: Some(first),
+            raw,
+            ..
+        } = item
+        {
+            let start = first.saturating_sub(1).min(source_lines.len());
+            let end = (start + content.lines().count()).min(source_lines.len());
+            *raw = Some(source_lines[start..end].join("\n"));
+        }
+    }
+
+    let warnings = visitor
+        .warnings
+        .into_iter()
+        .map(|(line, message)| Diagnostic::warning(message, path.clone(), line))
+        .collect();
+    Ok((visitor.items, warnings))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_has_markers() {
+        assert!(has_markers("/// @openapi-type Id"));
+        assert!(has_markers("//! @produces application/xml"));
+        assert!(has_markers("/// @consumes text/plain"));
+        assert!(has_markers("    /// @readonly\n    id: u64,"));
+        assert!(has_markers("/// @writeonly"));
+        assert!(has_markers("/// @route GET /users"));
+        assert!(!has_markers("/// Plain documentation"));
+        for directive in ROUTE_DIRECTIVES.iter().chain(&MODULE_DIRECTIVES) {
+            assert!(
+                MARKERS.contains(directive),
+                "{directive} missing from MARKERS"
+            );
+        }
+    }
+
+    #[test]
+    fn test_header_priority() {
+        assert_eq!(header_priority("@openapi priority: 10"), Ok(10));
+        assert_eq!(header_priority("@openapi priority:-2"), Ok(-2));
+        assert_eq!(header_priority("@openapi"), Ok(0));
+        assert!(header_priority("@openapi priority: high").is_err());
+
+        let mut visitor = OpenApiVisitor::default();
+        visitor.parse_doc_block("@openapi priority: 3\ninfo: {version: '2'}", None, 1);
+        let ExtractedItem::Schema {
+            priority, content, ..
+        } = &visitor.items[0]
+        else {
+            panic!("expected schema");
+        };
+        assert_eq!(*priority, 3);
+        assert!(content.contains("version"));
+    }
+
+    #[test]
+    fn test_fragment_header_defaults() {
+        let mut visitor = OpenApiVisitor::default();
+        visitor.parse_doc_block(
+            "@openapi-fragment Err(code = \"Internal, Error\", hint)\ndescription: {{code}}",
+            None,
+            1,
+        );
+        let ExtractedItem::Fragment {
+            params, defaults, ..
+        } = &visitor.items[0]
+        else {
+            panic!("expected fragment");
+        };
+        assert_eq!(params, &vec!["code".to_string(), "hint".to_string()]);
+        assert_eq!(
+            defaults.get("code").map(String::as_str),
+            Some("Internal, Error")
+        );
+        assert!(!defaults.contains_key("hint"));
+    }
+
     #[test]
     fn test_struct_reflection() {
         let code = r#"
@@ -1364,41 +2736,687 @@ mod tests {
                 assert!(content.contains("tags"));
                 // meta should NOT be in required
             }
-            _ => panic!("Expected Schema"),
-        }
+            _ => panic!("Expected Schema"),
+        }
+    }
+
+    #[test]
+    fn test_byte_buffers() {
+        let code = r#"
+            struct Upload {
+                checksum: Vec<u8>,
+                payload: bytes::Bytes,
+                #[serde(with = "serde_bytes")]
+                signature: Option<serde_bytes::ByteBuf>,
+                /// @openapi format: binary
+                raw: Vec<u8>,
+                scores: Vec<u16>,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let props = &json["components"]["schemas"]["Upload"]["properties"];
+        let base64 = json!({ "type": "string", "format": "byte" });
+        assert_eq!(props["checksum"], base64);
+        assert_eq!(props["payload"], base64);
+        assert_eq!(props["signature"], base64);
+        assert_eq!(
+            props["raw"],
+            json!({ "type": "string", "format": "binary" })
+        );
+        assert_eq!(props["scores"]["type"], "array");
+    }
+
+    #[test]
+    fn test_collection_types() {
+        let code = r#"
+            struct Inventory {
+                tags: HashSet<String>,
+                owners: BTreeSet<accounts::User>,
+                queue: VecDeque<u32>,
+                stock: IndexMap<String, Item>,
+                totals: BTreeMap<String, u64>,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let props = &json["components"]["schemas"]["Inventory"]["properties"];
+        assert_eq!(
+            props["tags"],
+            json!({ "type": "array", "items": { "type": "string" }, "uniqueItems": true })
+        );
+        assert_eq!(props["owners"]["items"]["$ref"], "$accounts::User");
+        assert_eq!(props["owners"][SORTED_KEY], true);
+        assert_eq!(props["queue"]["items"]["format"], "int32");
+        assert!(props["queue"].get("uniqueItems").is_none());
+        assert_eq!(
+            props["stock"],
+            json!({ "type": "object", "additionalProperties": { "$ref": "$Item" } })
+        );
+        assert_eq!(props["totals"][SORTED_KEY], true);
+    }
+
+    #[test]
+    fn test_option_nesting() {
+        // (type, required, nullable, patch field)
+        let cases = [
+            ("String", true, false, false),
+            ("Option<String>", false, false, false),
+            ("Option<Option<String>>", false, true, true),
+            ("Option<Box<Option<u32>>>", false, true, true),
+            ("Box<Option<Vec<String>>>", false, false, false),
+            ("Arc<Option<Option<User>>>", false, true, true),
+            ("Option<Vec<String>>", false, false, false),
+            ("Rc<String>", true, false, false),
+        ];
+        let mappers = TypeMappers::default();
+        for (ty, required, nullable, patch) in cases {
+            let parsed: syn::Type = syn::parse_str(ty).unwrap();
+            let (schema, is_required) = map_syn_type_to_openapi(&parsed, &mappers);
+            assert_eq!(is_required, required, "{}", ty);
+            assert_eq!(schema.get("nullable").is_some(), nullable, "{}", ty);
+            assert_eq!(schema.get(PATCH_FIELD_KEY).is_some(), patch, "{}", ty);
+        }
+
+        let parsed: syn::Type = syn::parse_str("Vec<Option<String>>").unwrap();
+        let (schema, is_required) = map_syn_type_to_openapi(&parsed, &mappers);
+        assert!(is_required);
+        assert_eq!(
+            schema["items"],
+            json!({ "type": "string", "nullable": true })
+        );
+        let parsed: syn::Type = syn::parse_str("HashMap<String, Option<u64>>").unwrap();
+        let (schema, _) = map_syn_type_to_openapi(&parsed, &mappers);
+        assert_eq!(schema["additionalProperties"]["nullable"], true);
+
+        // Keys next to a $ref would be ignored in 3.0
+        let parsed: syn::Type = syn::parse_str("Arc<Option<Option<User>>>").unwrap();
+        let (schema, _) = map_syn_type_to_openapi(&parsed, &mappers);
+        assert_eq!(
+            schema,
+            json!({ "allOf": [{ "$ref": "$User" }], "nullable": true, PATCH_FIELD_KEY: true })
+        );
+        for ty in ["Vec<Option<User>>", "HashMap<String, Option<User>>"] {
+            let parsed: syn::Type = syn::parse_str(ty).unwrap();
+            let (schema, _) = map_syn_type_to_openapi(&parsed, &mappers);
+            let inner = schema.get("items").or(schema.get("additionalProperties"));
+            assert_eq!(
+                inner,
+                Some(&json!({ "allOf": [{ "$ref": "$User" }], "nullable": true })),
+                "{}",
+                ty
+            );
+        }
+    }
+
+    #[test]
+    fn test_type_mapper_hook() {
+        let code = r#"
+            /// @openapi
+            struct Feed {
+                pub page: MyWrapper<Post>,
+                pub first: Option<MyWrapper<Post>>,
+                pub count: u32,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).unwrap();
+
+        let mut visitor = OpenApiVisitor::default();
+        visitor.type_mappers.push(|ty: &syn::Type| {
+            let syn::Type::Path(p) = ty else { return None };
+            let seg = p.path.segments.last()?;
+            if seg.ident != "MyWrapper" {
+                return None;
+            }
+            let syn::PathArguments::AngleBracketed(args) = &seg.arguments else {
+                return None;
+            };
+            let Some(syn::GenericArgument::Type(syn::Type::Path(inner))) = args.args.first() else {
+                return None;
+            };
+            let inner = inner.path.segments.last()?.ident.to_string();
+            Some(json!({
+                "allOf": [
+                    { "$ref": format!("${}", inner) },
+                    { "properties": { "cursor": { "type": "string" } } }
+                ]
+            }))
+        });
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let doc: serde_yaml::Value = serde_yaml::from_str(content).unwrap();
+        let schema = &doc["components"]["schemas"]["Feed"];
+        let page = &schema["properties"]["page"];
+        assert_eq!(page["allOf"][0]["$ref"].as_str(), Some("$Post"));
+        assert_eq!(schema["properties"]["first"], *page);
+        assert_eq!(
+            schema["properties"]["count"]["type"].as_str(),
+            Some("integer")
+        );
+        let required: Vec<&str> = schema["required"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        assert_eq!(required, vec!["page", "count"]);
+    }
+
+    #[test]
+    fn test_module_tags() {
+        let code = r#"
+            /// @openapi
+            /// tags: [GroupA]
+            mod my_mod {
+                /// @openapi
+                /// paths:
+                ///   /test:
+                ///     get:
+                ///       description: op
+                fn my_fn() {}
+            }
+        "#;
+        let item_mod: ItemMod = syn::parse_str(code).expect("Failed to parse mod");
+
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_mod(&item_mod);
+
+        assert_eq!(visitor.items.len(), 2);
+        match &visitor.items[1] {
+            ExtractedItem::Schema { content, .. } => {
+                assert!(
+                    content.contains("tags:"),
+                    "Function should have tags injected"
+                );
+                assert!(content.contains("- GroupA"));
+                assert!(content.contains("/test:"));
+            }
+            _ => panic!("Expected Schema"),
+        }
+    }
+
+    #[test]
+    fn test_module_tags_per_operation() {
+        let code = r#"
+            /// @openapi
+            /// tags: [GroupA]
+            mod my_mod {
+                /// @openapi
+                /// paths:
+                ///   /a:
+                ///     get:
+                ///       tags: [Own]
+                ///     post:
+                ///       description: op
+                ///   /b:
+                ///     get:
+                ///       requestBody:
+                ///         content:
+                ///           application/json:
+                ///             schema:
+                ///               properties:
+                ///                 tags: {type: array}
+                /// components:
+                ///   schemas:
+                ///     Post:
+                ///       properties:
+                ///         tags: {type: array}
+                fn structured() {}
+
+                /// @openapi
+                /// paths:
+                ///   /c:
+                ///     get:
+                ///       tags: [Own]
+                ///       @insert Paged
+                ///     post:
+                ///       description: op
+                fn with_directives() {}
+            }
+        "#;
+        let item_mod: ItemMod = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_mod(&item_mod);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[1] else {
+            panic!("Expected Schema");
+        };
+        let doc: serde_yaml::Value = serde_yaml::from_str(content).unwrap();
+        let tags = |path: &str, method: &str| -> Vec<String> {
+            doc["paths"][path][method]["tags"]
+                .as_sequence()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t.as_str().map(str::to_string))
+                .collect()
+        };
+        assert_eq!(tags("/a", "get"), vec!["Own"]);
+        assert_eq!(tags("/a", "post"), vec!["GroupA"]);
+        assert_eq!(tags("/b", "get"), vec!["GroupA"]);
+        assert!(doc["components"]["schemas"]["Post"].get("tags").is_none());
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[2] else {
+            panic!("Expected Schema");
+        };
+        assert_eq!(content.matches("- GroupA").count(), 1, "{}", content);
+        let post = content.find("post:").unwrap();
+        assert!(content[post..].contains("- GroupA"), "{}", content);
+    }
+
+    #[test]
+    fn test_nested_module_tags() {
+        let code = r#"
+            /// @openapi
+            /// tags: [Users]
+            mod users {
+                /// @openapi
+                /// tags: [Admin]
+                mod admin {
+                    /// @openapi
+                    /// paths:
+                    ///   /inherit:
+                    ///     get: {}
+                    fn inherit() {}
+                }
+
+                /// @openapi
+                /// tags-replace: [Audit]
+                mod audit {
+                    /// @openapi
+                    /// paths:
+                    ///   /replace:
+                    ///     get: {}
+                    fn replace() {}
+                }
+
+                /// @openapi
+                /// tags: [Users, Search]
+                mod search {
+                    /// @openapi
+                    /// paths:
+                    ///   /dedupe:
+                    ///     get: {}
+                    fn dedupe() {}
+                }
+            }
+        "#;
+        let item_mod: ItemMod = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_mod(&item_mod);
+
+        let mut operations = std::collections::BTreeMap::new();
+        let mut module_tags = Vec::new();
+        for item in &visitor.items {
+            let ExtractedItem::Schema { content, .. } = item else {
+                continue;
+            };
+            let doc: serde_yaml::Value = serde_yaml::from_str(content).unwrap();
+            let names = |tags: &serde_yaml::Value| -> Vec<String> {
+                tags.as_sequence()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect()
+            };
+            match doc.get("paths").and_then(serde_yaml::Value::as_mapping) {
+                Some(paths) => {
+                    for (path, item) in paths {
+                        operations.insert(
+                            path.as_str().unwrap().to_string(),
+                            names(&item["get"]["tags"]),
+                        );
+                    }
+                }
+                None => module_tags.push(names(&doc["tags"])),
+            }
+        }
+        assert_eq!(operations["/inherit"], vec!["Users", "Admin"]);
+        assert_eq!(operations["/replace"], vec!["Audit"]);
+        assert_eq!(operations["/dedupe"], vec!["Users", "Search"]);
+        // The replacing list still declares its tags like `tags:` does
+        assert!(module_tags.contains(&vec!["Audit".to_string()]));
+    }
+
+    #[test]
+    fn test_module_default_mime_types() {
+        let code = r#"
+            //! @consumes application/protobuf
+
+            /// @produces application/protobuf
+            mod gateway {
+                /// @route POST /users
+                /// @body User
+                /// @return 201: User "Created"
+                fn create_user() {}
+
+                /// @produces application/xml
+                mod legacy {
+                    /// @route GET /legacy
+                    /// @return 200: User
+                    /// @return 400: Error "Bad" application/json
+                    fn legacy() {}
+                }
+
+                /// @route GET /users
+                /// @return 200: User
+                fn list_users() {}
+            }
+
+            /// @route GET /health
+            /// @return 200: Health
+            fn health() {}
+        "#;
+        let file: File = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_file(&file);
+
+        let mut paths = json!({});
+        for item in &visitor.items {
+            let ExtractedItem::Schema { content, .. } = item else {
+                continue;
+            };
+            let doc: Value = serde_yaml::from_str(content).unwrap();
+            if let Some(found) = doc.get("paths") {
+                json_merge(&mut paths, found.clone());
+            }
+        }
+        let media_types = |path: &str, method: &str, code: &str| -> Vec<String> {
+            paths[path][method]["responses"][code]["content"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect()
+        };
+
+        assert_eq!(
+            media_types("/users", "post", "201"),
+            ["application/protobuf"]
+        );
+        let body = paths["/users"]["post"]["requestBody"]["content"]
+            .as_object()
+            .unwrap();
+        assert!(body.contains_key("application/protobuf"));
+        assert_eq!(media_types("/legacy", "get", "200"), ["application/xml"]);
+        assert_eq!(media_types("/legacy", "get", "400"), ["application/json"]);
+        // The nested module's default ends with it
+        assert_eq!(
+            media_types("/users", "get", "200"),
+            ["application/protobuf"]
+        );
+        assert_eq!(media_types("/health", "get", "200"), ["application/json"]);
+    }
+
+    #[test]
+    fn test_description_directive_keeps_markdown() {
+        let code = r#"
+            /// Run a report
+            /// @route POST /reports
+            /// @return 202: "Accepted"
+            /// @description
+            /// Queues the report. Example body:
+            ///
+            /// ```json
+            /// {
+            ///   "kind": "daily"
+            /// }
+            /// ```
+            ///
+            /// | Field | Meaning |
+            /// |-------|---------|
+            /// | kind  | Report  |
+            fn run_report() {}
+        "#;
+        let item: ItemFn = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        assert!(content.contains("description: |"), "{}", content);
+        let doc: Value = serde_yaml::from_str(content).unwrap();
+        let operation = &doc["paths"]["/reports"]["post"];
+        assert_eq!(operation["summary"], "Run a report");
+        assert_eq!(
+            operation["description"],
+            "Queues the report. Example body:\n\n```json\n{\n  \"kind\": \"daily\"\n}\n```\n\n\
+             | Field | Meaning |\n|-------|---------|\n| kind  | Report  |"
+        );
+        assert_eq!(operation["responses"]["202"]["description"], "Accepted");
+    }
+
+    #[test]
+    fn test_implicit_description_keeps_blank_lines() {
+        let code = r#"
+            /// Fetch a user
+            ///
+            /// Looks the user up by id.
+            ///
+            ///     GET /users/1
+            /// @route GET /users/{id: u64}
+            fn get_user() {}
+        "#;
+        let item: ItemFn = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let doc: Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(
+            doc["paths"]["/users/{id}"]["get"]["description"],
+            "Looks the user up by id.\n\n    GET /users/1"
+        );
+    }
+
+    #[test]
+    fn test_doc_styles() {
+        let code = r#"
+            /// A billing account.
+            ///
+            /// Accounts own invoices:
+            ///
+            /// - one per month
+            ///   (or on demand)
+            struct Account {
+                /// Current balance.
+                ///
+                /// In cents.
+                balance: i64,
+                /// Owner name
+                owner: String,
+            }
+
+            /// Account state.
+            ///
+            /// Closed accounts are read only.
+            enum State { Open, Closed }
+
+            /// Identifier.
+            ///
+            /// Assigned by the server.
+            type AccountId = String;
+        "#;
+        let schemas = |doc_style| {
+            let file: File = syn::parse_str(code).unwrap();
+            let mut visitor = OpenApiVisitor {
+                doc_style,
+                ..Default::default()
+            };
+            visitor.visit_file(&file);
+            let mut schemas = json!({});
+            for item in &visitor.items {
+                if let ExtractedItem::Schema { content, .. } = item {
+                    let doc: Value = serde_yaml::from_str(content).unwrap();
+                    json_merge(&mut schemas, doc["components"]["schemas"].clone());
+                }
+            }
+            schemas
+        };
+
+        let joined = schemas(DocStyle::Joined);
+        assert_eq!(
+            joined["Account"]["description"],
+            "A billing account.  Accounts own invoices:  - one per month (or on demand)"
+        );
+        assert!(joined["Account"].get("title").is_none());
+        assert_eq!(
+            joined["Account"]["properties"]["balance"]["description"],
+            "Current balance.  In cents."
+        );
+
+        let paragraphs = schemas(DocStyle::Paragraphs);
+        let account = &paragraphs["Account"];
+        assert_eq!(account["title"], "A billing account.");
+        assert_eq!(
+            account["description"],
+            "Accounts own invoices:\n\n- one per month\n  (or on demand)"
+        );
+        let balance = &account["properties"]["balance"];
+        assert_eq!(balance["title"], "Current balance.");
+        assert_eq!(balance["description"], "In cents.");
+        // A single paragraph stays the description
+        assert_eq!(account["properties"]["owner"]["description"], "Owner name");
+        assert!(account["properties"]["owner"].get("title").is_none());
+        assert_eq!(paragraphs["State"]["title"], "Account state.");
+        assert_eq!(
+            paragraphs["State"]["description"],
+            "Closed accounts are read only."
+        );
+        assert_eq!(paragraphs["AccountId"]["title"], "Identifier.");
+        assert_eq!(
+            paragraphs["AccountId"]["description"],
+            "Assigned by the server."
+        );
     }
 
     #[test]
-    fn test_module_tags() {
+    fn test_doc_block_code_fences() {
         let code = r#"
+            /// Lists users.
+            ///
             /// @openapi
-            /// tags: [GroupA]
-            mod my_mod {
-                /// @openapi
-                /// paths:
-                ///   /test:
-                ///     get:
-                ///       description: op
-                fn my_fn() {}
-            }
+            /// paths:
+            ///   /users:
+            ///     get:
+            ///       description: |
+            ///         Returns users, e.g.
+            ///         ```json
+            ///         [{"name": "Ada"}]
+            ///         ```
+            ///
+            /// ```rust
+            /// let users = list_users();
+            /// assert!(users.is_empty());
+            /// ```
+            fn list_users() {}
         "#;
-        let item_mod: ItemMod = syn::parse_str(code).expect("Failed to parse mod");
+        let item: ItemFn = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item);
+        let contents: Vec<&String> = visitor
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                ExtractedItem::Schema { content, .. } if content.starts_with("paths:") => {
+                    Some(content)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(contents.len(), 1);
+        let doc: Value = serde_yaml::from_str(contents[0]).unwrap();
+        assert_eq!(
+            doc["paths"]["/users"]["get"]["description"],
+            "Returns users, e.g.\n```json\n[{\"name\": \"Ada\"}]\n```"
+        );
+        assert!(!contents[0].contains("assert!"));
 
+        let code = r#"
+            /// @openapi
+            /// ```yaml
+            /// paths:
+            ///   /health:
+            ///     get:
+            ///       description: Health
+            /// ```
+            ///
+            /// # Examples
+            ///
+            /// health();
+            fn health() {}
+        "#;
+        let item: ItemFn = syn::parse_str(code).unwrap();
         let mut visitor = OpenApiVisitor::default();
-        visitor.visit_item_mod(&item_mod);
+        visitor.visit_item_fn(&item);
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let doc: Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(doc["paths"]["/health"]["get"]["description"], "Health");
+        assert!(!content.contains("health();"));
+    }
 
-        assert_eq!(visitor.items.len(), 2);
-        match &visitor.items[1] {
-            ExtractedItem::Schema { content, .. } => {
-                assert!(
-                    content.contains("tags:"),
-                    "Function should have tags injected"
-                );
-                assert!(content.contains("- GroupA"));
-                assert!(content.contains("/test:"));
+    #[test]
+    fn test_module_servers() {
+        let code = r#"
+            /// @server https://billing.example.com "Billing"
+            mod billing {
+                /// @route GET /invoices
+                fn list_invoices() {}
+
+                /// @route POST /invoices
+                /// @server https://billing-eu.example.com
+                fn create_invoice() {}
             }
-            _ => panic!("Expected Schema"),
+
+            /// @route GET /health
+            fn health() {}
+        "#;
+        let file: File = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_file(&file);
+
+        let docs: Vec<Value> = visitor
+            .items
+            .iter()
+            .map(|item| match item {
+                ExtractedItem::Schema { content, .. } => serde_yaml::from_str(content).unwrap(),
+                _ => panic!("Expected Schema"),
+            })
+            .collect();
+        let module_servers =
+            json!([{ "url": "https://billing.example.com", "description": "Billing" }]);
+        for doc in &docs[..2] {
+            assert_eq!(doc["paths"]["/invoices"]["servers"], module_servers);
         }
+        assert!(
+            docs[0]["paths"]["/invoices"]["get"]
+                .get("servers")
+                .is_none()
+        );
+        assert_eq!(
+            docs[1]["paths"]["/invoices"]["post"]["servers"],
+            json!([{ "url": "https://billing-eu.example.com" }])
+        );
+        assert!(docs[2]["paths"]["/health"].get("servers").is_none());
     }
 
     #[test]
@@ -1464,14 +3482,17 @@ mod tests {
         let mut visitor = OpenApiVisitor::default();
         visitor.visit_item_struct(&item_struct);
         match &visitor.items[0] {
-            ExtractedItem::Schema { content, .. } => {
+            ExtractedItem::Blueprint {
+                params, content, ..
+            } => {
                 // FIX 3: Should contain $ref: $T, NOT #/components/schemas/T
+                assert_eq!(params, &vec!["T".to_string()]);
                 assert!(
                     content.contains("$ref: $T"),
                     "Should use Smart Ref for generics (expected $ref: $T)"
                 );
             }
-            _ => panic!("Expected Schema"),
+            _ => panic!("Expected Blueprint"),
         }
 
         // 2. Multi-line Field Docs Test
@@ -1572,6 +3593,379 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validator_constraints() {
+        let code = r#"
+            /// @openapi
+            struct CreateUser {
+                #[validate(length(min = 1, max = 64))]
+                pub name: String,
+                #[validate(email)]
+                pub email: String,
+                #[validate(url)]
+                pub homepage: Option<String>,
+                #[validate(range(min = 0, max = 150))]
+                pub age: u32,
+                #[validate(range(min = -1.5))]
+                pub offset: f64,
+                #[validate(length(min = 1), custom(function = "check_tags"))]
+                pub tags: Vec<String>,
+                #[validate(regex(path = *RE_SLUG))]
+                pub slug: String,
+                #[validate(regex = "RE_CODE")]
+                /// @openapi pattern: "^[A-Z]{3}$"
+                pub code: String,
+                #[validate(range(exclusive_min = 0, exclusive_max = 1.5))]
+                pub ratio: f64,
+                #[validate(length(max = 10))]
+                /// @openapi maxLength: 20
+                pub nick: String,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let props = &json["components"]["schemas"]["CreateUser"]["properties"];
+
+        assert_eq!(props["name"]["minLength"], 1);
+        assert_eq!(props["name"]["maxLength"], 64);
+        assert_eq!(props["email"]["format"], "email");
+        assert_eq!(props["homepage"]["format"], "uri");
+        assert_eq!(props["age"]["minimum"], 0);
+        assert_eq!(props["age"]["maximum"], 150);
+        assert_eq!(props["offset"]["minimum"], -1.5);
+        assert_eq!(props["tags"]["minItems"], 1);
+        assert!(props["tags"].get("minLength").is_none());
+        assert!(props["slug"].get("pattern").is_none());
+        assert_eq!(props["code"]["pattern"], "^[A-Z]{3}$");
+        assert_eq!(props["ratio"]["minimum"], 0);
+        assert_eq!(props["ratio"]["exclusiveMinimum"], true);
+        assert_eq!(props["ratio"]["maximum"], 1.5);
+        assert_eq!(props["ratio"]["exclusiveMaximum"], true);
+        // Explicit @openapi override wins
+        assert_eq!(props["nick"]["maxLength"], 20);
+    }
+
+    #[test]
+    fn test_schemars_and_serde_attributes() {
+        let code = r#"
+            /// @openapi
+            #[serde(rename = "Account")]
+            #[schemars(description = "An account")]
+            struct AccountDto {
+                #[serde(rename = "accountId")]
+                pub id: Uuid,
+                #[schemars(with = "String", example = "created_example")]
+                pub created: MyTimestamp,
+                #[serde(rename = "a")]
+                #[schemars(rename = "b")]
+                pub renamed: bool,
+                #[serde(skip)]
+                pub secret: String,
+                #[schemars(skip)]
+                pub internal: String,
+                #[schemars(description = "From schemars")]
+                /// @openapi
+                /// description: From openapi
+                pub note: String,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Schema { name, content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        assert_eq!(name.as_deref(), Some("Account"));
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let schema = &json["components"]["schemas"]["Account"];
+        assert_eq!(schema["description"], "An account");
+
+        let props = &schema["properties"];
+        assert_eq!(props["accountId"]["format"], "uuid");
+        // with = "String" replaces the reflected $ref
+        assert_eq!(props["created"]["type"], "string");
+        assert!(props["created"].get("$ref").is_none());
+        // schemars(example) names a function and is not taken as a literal
+        assert!(props["created"].get("example").is_none());
+        // schemars rename beats serde rename
+        assert!(props.get("b").is_some());
+        assert!(props.get("a").is_none());
+        assert!(props.get("secret").is_none());
+        assert!(props.get("internal").is_none());
+        // @openapi override beats schemars
+        assert_eq!(props["note"]["description"], "From openapi");
+    }
+
+    #[test]
+    fn test_enum_serde_variant_attributes() {
+        let code = r#"
+            /// @openapi
+            enum Status {
+                #[serde(rename = "active")]
+                Active,
+                #[schemars(skip)]
+                Hidden,
+                Closed,
+            }
+        "#;
+        let item_enum: ItemEnum = syn::parse_str(code).expect("Failed to parse enum");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_enum(&item_enum);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(
+            json["components"]["schemas"]["Status"]["enum"],
+            json!(["active", "Closed"])
+        );
+
+        let code = r#"
+            #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+            enum Level { Info, #[serde(rename = "warn")] Warning, HighRisk }
+        "#;
+        let item_enum: ItemEnum = syn::parse_str(code).expect("Failed to parse enum");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_enum(&item_enum);
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(
+            json["components"]["schemas"]["Level"]["enum"],
+            json!(["INFO", "warn", "HIGH_RISK"])
+        );
+    }
+
+    #[test]
+    fn test_untagged_enum_any_of() {
+        let code = r#"
+            /// A user reference.
+            #[serde(untagged)]
+            enum UserRef {
+                /// By id
+                Id(Uuid),
+                /// Inline user
+                Inline {
+                    name: String,
+                    email: Option<String>,
+                },
+            }
+        "#;
+        let item_enum: ItemEnum = syn::parse_str(code).expect("Failed to parse enum");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_enum(&item_enum);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let schema = &json["components"]["schemas"]["UserRef"];
+        assert!(schema.get("type").is_none());
+        assert_eq!(schema["description"], "A user reference.");
+        assert_eq!(
+            schema["anyOf"][0],
+            json!({ "type": "string", "format": "uuid", "description": "By id" })
+        );
+        let inline = &schema["anyOf"][1];
+        assert_eq!(inline["type"], "object");
+        assert_eq!(inline["description"], "Inline user");
+        assert_eq!(inline["properties"]["name"]["type"], "string");
+        assert_eq!(inline["required"], json!(["name"]));
+    }
+
+    #[test]
+    fn test_adjacently_tagged_enum() {
+        let code = r#"
+            #[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+            enum Event {
+                /// Account created
+                #[serde(rename = "created")]
+                Created { id: Uuid },
+                UserDeleted(Uuid),
+            }
+        "#;
+        let item_enum: ItemEnum = syn::parse_str(code).expect("Failed to parse enum");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_enum(&item_enum);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let expected = r#"components:
+  schemas:
+    Event:
+      discriminator:
+        propertyName: kind
+      oneOf:
+      - description: Account created
+        properties:
+          data:
+            properties:
+              id:
+                format: uuid
+                type: string
+            required:
+            - id
+            type: object
+          kind:
+            enum:
+            - created
+            type: string
+        required:
+        - kind
+        - data
+        type: object
+      - properties:
+          data:
+            format: uuid
+            type: string
+          kind:
+            enum:
+            - user_deleted
+            type: string
+        required:
+        - kind
+        - data
+        type: object
+"#;
+        assert_eq!(content.trim_end(), expected.trim_end());
+    }
+
+    #[test]
+    fn test_internally_tagged_enum() {
+        let code = r#"
+            #[serde(tag = "type", rename_all = "kebab-case")]
+            enum Shape {
+                Circle { radius: f64 },
+                EmptyShape,
+            }
+        "#;
+        let item_enum: ItemEnum = syn::parse_str(code).expect("Failed to parse enum");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_enum(&item_enum);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        let schema = &json["components"]["schemas"]["Shape"];
+        assert_eq!(schema["discriminator"]["propertyName"], "type");
+        let circle = &schema["oneOf"][0];
+        assert_eq!(circle["required"], json!(["type", "radius"]));
+        assert_eq!(circle["properties"]["type"]["enum"], json!(["circle"]));
+        assert_eq!(circle["properties"]["radius"]["type"], "number");
+        assert_eq!(schema["oneOf"][1]["required"], json!(["type"]));
+        assert_eq!(
+            schema["oneOf"][1]["properties"]["type"]["enum"],
+            json!(["empty-shape"])
+        );
+    }
+
+    #[test]
+    fn test_blueprint_skips_lifetimes_and_const_params() {
+        let code = r#"
+            /// @openapi<'a, const N: usize, T>
+            struct Buffer<'a, const N: usize, T> {
+                items: &'a [T; N],
+                name: &'a str,
+                tail: &'a mut [u8],
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        match &visitor.items[0] {
+            ExtractedItem::Blueprint {
+                name,
+                params,
+                content,
+                ..
+            } => {
+                assert_eq!(name, "Buffer");
+                assert_eq!(params, &vec!["T".to_string()]);
+                let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+                let props = &json["properties"];
+                assert_eq!(props["items"]["type"], "array");
+                assert_eq!(props["items"]["items"]["$ref"], "$T");
+                assert_eq!(props["name"]["type"], "string");
+                assert_eq!(props["tail"]["type"], "array");
+            }
+            _ => panic!("Expected Blueprint"),
+        }
+    }
+
+    #[test]
+    fn test_generic_struct_auto_blueprint() {
+        let code = r#"
+            /// A page of results
+            struct Page<T> {
+                pub items: Vec<T>,
+                pub total: u64,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        match &visitor.items[0] {
+            ExtractedItem::Blueprint {
+                name,
+                params,
+                content,
+                ..
+            } => {
+                assert_eq!(name, "Page");
+                assert_eq!(params, &vec!["T".to_string()]);
+                let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+                assert_eq!(json["properties"]["items"]["items"]["$ref"], "$T");
+                assert_eq!(json["properties"]["total"]["format"], "int64");
+                assert_eq!(json["description"], "A page of results");
+            }
+            _ => panic!("Expected Blueprint"),
+        }
+    }
+
+    #[test]
+    fn test_generic_struct_manual_blueprint_overrides() {
+        let code = r#"
+            /// @openapi<T>
+            /// properties:
+            ///   items:
+            ///     description: Manual
+            struct Page<T> {
+                pub items: Vec<T>,
+            }
+        "#;
+        let item_struct: ItemStruct = syn::parse_str(code).expect("Failed to parse struct");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item_struct);
+
+        let ExtractedItem::Blueprint { content, .. } = &visitor.items[0] else {
+            panic!("Expected Blueprint");
+        };
+        let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(json["properties"]["items"]["description"], "Manual");
+        assert_eq!(json["properties"]["items"]["type"], "array");
+    }
+
+    #[test]
+    fn test_parse_blueprint_params() {
+        assert_eq!(
+            parse_blueprint_params("'a, const N: usize, T: Clone, U = String"),
+            vec!["T".to_string(), "U".to_string()]
+        );
+    }
+
     #[test]
     fn test_type_alias_reflection() {
         let code = r#"
@@ -1597,6 +3991,158 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_schema_aliases() {
+        let code = r#"
+            /// An organization
+            /// @openapi-alias Account
+            /// @openapi-alias Team, Group
+            struct Organization { id: u64 }
+        "#;
+        let item: ItemStruct = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item);
+
+        let names: Vec<_> = visitor
+            .items
+            .iter()
+            .map(|item| match item {
+                ExtractedItem::Schema { name, .. } => name.clone().unwrap(),
+                _ => panic!("Expected Schema"),
+            })
+            .collect();
+        assert_eq!(names, ["Organization", "Account", "Team", "Group"]);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        assert!(content.contains("description: An organization"));
+        assert!(!content.contains("alias"));
+        let ExtractedItem::Schema { content, .. } = &visitor.items[1] else {
+            panic!("Expected Schema");
+        };
+        let doc: serde_json::Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(
+            doc["components"]["schemas"]["Account"],
+            json!({ "$ref": "#/components/schemas/Organization" })
+        );
+    }
+
+    #[test]
+    fn test_split_readonly_schemas() {
+        let code = r#"
+            /// @openapi-split-readonly
+            struct User {
+                /// Server assigned
+                /// @readonly
+                id: u64,
+                name: String,
+                /// @writeonly
+                password: String,
+                /// @openapi readOnly: true
+                created_at: String,
+                nickname: Option<String>,
+            }
+        "#;
+        let item: ItemStruct = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item);
+        assert_eq!(visitor.items.len(), 2);
+
+        let schema = |idx: usize, name: &str| {
+            let ExtractedItem::Schema {
+                name: item_name,
+                content,
+                ..
+            } = &visitor.items[idx]
+            else {
+                panic!("Expected Schema");
+            };
+            assert_eq!(item_name.as_deref(), Some(name));
+            let doc: Value = serde_yaml::from_str(content).unwrap();
+            doc["components"]["schemas"][name].clone()
+        };
+
+        let user = schema(0, "User");
+        assert_eq!(
+            user["required"],
+            json!(["id", "name", "password", "created_at"])
+        );
+        assert_eq!(user["properties"]["id"]["readOnly"], json!(true));
+        assert_eq!(user["properties"]["id"]["description"], "Server assigned");
+        assert_eq!(user["properties"]["password"]["writeOnly"], json!(true));
+        assert!(user.get("description").is_none());
+
+        let create = schema(1, "UserCreate");
+        assert_eq!(create["required"], json!(["name", "password"]));
+        let properties: Vec<&String> = create["properties"].as_object().unwrap().keys().collect();
+        assert_eq!(properties, ["name", "nickname", "password"]);
+    }
+
+    #[test]
+    fn test_example_from_default() {
+        let code = r#"
+            /// @openapi-example-from default
+            #[derive(Debug, Default, Serialize)]
+            struct Settings {
+                retries: u32,
+                ratio: f64,
+                name: String,
+                enabled: bool,
+                tags: Vec<String>,
+                limits: std::collections::HashMap<String, u32>,
+                /// @openapi example: 8080
+                port: u16,
+                /// @openapi example: dark
+                theme: Option<String>,
+                nickname: Option<String>,
+                owner: Account,
+            }
+        "#;
+        let item: ItemStruct = syn::parse_str(code).unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item);
+
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        let doc: Value = serde_yaml::from_str(content).unwrap();
+        assert_eq!(
+            doc["components"]["schemas"]["Settings"]["example"],
+            json!({
+                "retries": 0,
+                "ratio": 0,
+                "name": "",
+                "enabled": false,
+                "tags": [],
+                "limits": {},
+                "port": 8080,
+                "theme": "dark"
+            })
+        );
+
+        assert_eq!(
+            visitor.warnings,
+            vec![(
+                2,
+                "Settings: cannot derive an example value for field 'owner', skipping it"
+                    .to_string()
+            )]
+        );
+
+        // Without derive(Default) there is nothing to construct from
+        let item: ItemStruct =
+            syn::parse_str("/// @openapi-example-from default\nstruct Plain { retries: u32 }")
+                .unwrap();
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_struct(&item);
+        let ExtractedItem::Schema { content, .. } = &visitor.items[0] else {
+            panic!("Expected Schema");
+        };
+        assert!(!content.contains("example"));
+        assert_eq!(visitor.warnings.len(), 1);
+    }
+
     #[test]
     fn test_virtual_types_unit_struct() {
         let code = r#"
@@ -1907,7 +4453,84 @@ mod v0_7_0_tests {
     }
 
     #[test]
-    #[should_panic(expected = "Missing definition for path parameter 'id'")]
+    fn test_route_from_actix_attribute() {
+        let code = r#"
+            /// Get a user
+            /// @path-param id: u32 "User ID"
+            /// @return 200: $User "The user"
+            #[get("/users/{id}")]
+            async fn get_user() {}
+        "#;
+        let item_fn: ItemFn = syn::parse_str(code).expect("Failed to parse fn");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item_fn);
+
+        assert!(visitor.route_errors.is_empty());
+        if let ExtractedItem::Schema { content, .. } = &visitor.items[0] {
+            let json: serde_json::Value = serde_yaml::from_str(content).unwrap();
+            let op = &json["paths"]["/users/{id}"]["get"];
+            assert_eq!(op["summary"], "Get a user");
+            assert_eq!(op["parameters"][0]["name"], "id");
+            assert_eq!(op["parameters"][0]["in"], "path");
+            assert_eq!(
+                op["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+                "#/components/schemas/User"
+            );
+        } else {
+            panic!("Expected Schema");
+        }
+    }
+
+    #[test]
+    fn test_route_from_rocket_attribute() {
+        let code = r#"
+            /// @path-param id: u32
+            /// @return 204: "Deleted"
+            #[delete("/users/<id>?<force>")]
+            fn delete_user() {}
+        "#;
+        let item_fn: ItemFn = syn::parse_str(code).expect("Failed to parse fn");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item_fn);
+
+        if let ExtractedItem::Schema { content, .. } = &visitor.items[0] {
+            assert!(content.contains("/users/{id}:"));
+            assert!(content.contains("delete:"));
+        } else {
+            panic!("Expected Schema");
+        }
+    }
+
+    #[test]
+    fn test_route_attribute_mismatch() {
+        let code = r#"
+            /// @route GET /users/{id: u32}
+            #[post("/users/{id}")]
+            fn mismatched() {}
+        "#;
+        let item_fn: ItemFn = syn::parse_str(code).expect("Failed to parse fn");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item_fn);
+
+        assert!(visitor.items.is_empty());
+        assert_eq!(visitor.route_errors.len(), 1);
+        assert!(visitor.route_errors[0].1.contains("POST /users/{id}"));
+    }
+
+    #[test]
+    fn test_route_attribute_without_dsl_is_ignored() {
+        let code = r#"
+            /// Plain handler docs
+            #[get("/health")]
+            fn health() {}
+        "#;
+        let item_fn: ItemFn = syn::parse_str(code).expect("Failed to parse fn");
+        let mut visitor = OpenApiVisitor::default();
+        visitor.visit_item_fn(&item_fn);
+        assert!(visitor.items.is_empty());
+    }
+
+    #[test]
     fn test_route_dsl_validation_error() {
         let code = r#"
             /// @route GET /items/{id}
@@ -1916,5 +4539,12 @@ mod v0_7_0_tests {
         let item_fn: ItemFn = syn::parse_str(code).expect("Failed to parse fn");
         let mut visitor = OpenApiVisitor::default();
         visitor.visit_item_fn(&item_fn);
+        assert!(visitor.items.is_empty());
+        assert_eq!(visitor.route_errors.len(), 1);
+        assert!(
+            visitor.route_errors[0]
+                .1
+                .contains("Missing definition for path parameter 'id'")
+        );
     }
 }
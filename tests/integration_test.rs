@@ -1,114 +1,97 @@
-use oas_forge::scanner::scan_directories;
+use oas_forge::config::Config;
+use oas_forge::error::Error;
+use oas_forge::index::REGISTRY_FILE;
+use oas_forge::scanner::{
+    ScanOptions, iter_snippets_with_options, scan, scan_directories, scan_with_options,
+};
+use oas_forge::visitor::SchemaNaming;
+use oas_forge::{Format, Generator};
 use std::fs::File;
 use std::io::Write;
 use tempfile::tempdir;
 
 #[test]
-fn test_full_pipeline_v0_2_0() {
+fn test_multi_arg_generic_route() {
     let dir = tempdir().unwrap();
     let src_dir = dir.path().join("src");
     std::fs::create_dir(&src_dir).unwrap();
 
-    // 1. Define Fragment (//! comment)
-    let lib_rs = src_dir.join("lib.rs");
-    let mut f = File::create(&lib_rs).unwrap();
-    writeln!(
-        f,
-        "{}",
+    std::fs::write(
+        src_dir.join("models.rs"),
         r#"
-//! @openapi-fragment CommonError(code)
-//! description: Error {{code}}
-//! content:
-//!   application/json:
-//!     schema:
-//!       $ref: $ErrorModel
-    "#
-    )
-    .unwrap();
+/// @openapi
+struct ApiResult<T, E> {
+    ok: Option<T>,
+    err: Option<E>,
+}
 
-    // 2. Define Blueprint (/// @openapi<T>)
-    let models_rs = src_dir.join("models.rs");
-    let mut f = File::create(&models_rs).unwrap();
-    writeln!(
-        f,
-        r#"
-/// @openapi<T>
-/// type: object
-/// properties:
-///   data: 
-///     $ref: $T
-struct Wrapper<T>(T);
+/// @openapi
+struct Page<T> {
+    items: Vec<T>,
+}
 
 /// @openapi
-/// type: object
-struct ErrorModel;
+struct User { id: u64 }
 
 /// @openapi
-/// type: object
-struct User;
-    "#
+struct Error { message: String }
+
+/// Fetch users
+/// @route GET /users
+/// @return 200: $ApiResult<$Page<User>, Error> "Users or an error"
+fn list_users() {}
+"#,
     )
     .unwrap();
 
-    // 3. Define Output Schema using both
-    let main_rs = src_dir.join("main.rs");
-    let mut f = File::create(&main_rs).unwrap();
-    writeln!(
-        f,
-        "{}",
+    let results = scan_directories(&[src_dir], &[]).expect("Scan failed");
+    let merged = results
+        .iter()
+        .map(|s| s.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    assert!(merged.contains("$ref: \"#/components/schemas/ApiResult_Page_User_Error\""));
+    assert!(merged.contains("ApiResult_Page_User_Error:"));
+    assert!(merged.contains("Page_User:"));
+    assert!(merged.contains("$ref: \"#/components/schemas/Page_User\""));
+    assert!(merged.contains("$ref: \"#/components/schemas/Error\""));
+}
+
+#[test]
+fn test_parameterized_virtual_type() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+
+    std::fs::write(
+        src_dir.join("types.rs"),
         r#"
-/// @openapi
-/// paths:
-///   /test:
-///     get:
-///       responses:
-///         '200':
-///           content:
-///              application/json:
-///                schema:
-///                  $ref: $Wrapper<User>
-///         '400':
-///           @insert CommonError("Bad Request")
-///         '401':
-///           @insert CommonError
-///           
-///   /macro-test:
-///     parameters:
-///       @insert QueryParam
-///     get:
-///       @extend MergeBase
-///       responses:
-///         '200':
-///            description: OK override
-///            content:
-///              application/json:
-///                schema:
-///                  $ref: $Wrapper<User>
-fn main() {{}}
-    "#
+//! @openapi-type Paginated<T>
+//! type: object
+//! properties:
+//!   items:
+//!     type: array
+//!     items:
+//!       $ref: $T
+//!   total:
+//!     type: integer
+"#,
     )
     .unwrap();
-
-    // 4. Register MergeBase Fragment (Already done) / Register Param?
-    // User wants @insert Param to be a ref NO MATTER WHAT if not in registry?
-    // "QueryParam" is NOT in registry.
-    // So it should become - $ref: "#/components/parameters/QueryParam"
-
-    let merge_rs = src_dir.join("merge.rs");
-    let mut f = File::create(&merge_rs).unwrap();
-    writeln!(
-        f,
-        "{}",
+    std::fs::write(
+        src_dir.join("users.rs"),
         r#"
-    //! @openapi-fragment MergeBase
-    //! responses:
-    //!   '404':
-    //!     description: Not Found
-    "#
+/// @openapi
+struct User { id: u64 }
+
+/// @route GET /users
+/// @return 200: $Paginated<User> "Users"
+fn list_users() {}
+"#,
     )
     .unwrap();
 
-    // Execute
     let results = scan_directories(&[src_dir], &[]).expect("Scan failed");
     let merged = results
         .iter()
@@ -116,131 +99,1655 @@ fn main() {{}}
         .collect::<Vec<_>>()
         .join("\n");
 
-    // Assertions
+    assert!(merged.contains("$ref: \"#/components/schemas/Paginated_User\""));
+    assert!(merged.contains("Paginated_User:"));
+    assert!(!merged.contains("Paginated<T>"));
+    assert!(merged.contains("$ref: \"#/components/schemas/User\""));
+}
 
-    // 1. Fragment Insertion
-    assert!(merged.contains("description: Error Bad Request"));
+fn scan_strict(source: &str) -> oas_forge::error::Result<Vec<oas_forge::scanner::Snippet>> {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("lib.rs"), source).unwrap();
+    let options = ScanOptions {
+        strict: true,
+        ..Default::default()
+    };
+    scan_with_options(&[src_dir], &[], &options)
+}
 
-    // 2. Monomorphization (Wrapper_User)
-    // The main code uses $Wrapper<User> -> $Wrapper_User
-    // Passes 3 & 4 should resolve this.
-    // The final output should contain a reference to Wrapper_User:
-    assert!(merged.contains("$ref: \"#/components/schemas/Wrapper_User\""));
+#[test]
+fn test_strict_mode_errors() {
+    let missing_blueprint = r#"
+/// @openapi
+/// components:
+///   schemas:
+///     Holder:
+///       $ref: $Missing<User>
+fn a() {}
+"#;
+    assert!(matches!(
+        scan_strict(missing_blueprint),
+        Err(Error::BlueprintNotFound { ref name, .. }) if name == "Missing"
+    ));
 
-    // 3. Concrete Schema Generation
-    // We expect the concrete schema to be injected into the output
-    // components: schemas: Wrapper_User: ... data: $ref: "#/components/schemas/User"
-    assert!(merged.contains("Wrapper_User:"));
-    assert!(merged.contains("$ref: \"#/components/schemas/User\""));
+    let missing_fragment = r#"
+/// @openapi
+/// paths:
+///   /a:
+///     get:
+///       @extend Nowhere
+///       summary: A
+fn a() {}
+"#;
+    assert!(matches!(
+        scan_strict(missing_fragment),
+        Err(Error::FragmentNotFound { ref name, .. }) if name == "Nowhere"
+    ));
 
-    // 4. Optional Parens (@insert CommonError)
-    // Should produce "Error {{code}}" -> "Error {{code}}" if no args provided?
-    // Or did we default substitute?
-    // Our fragment expects {{code}}. If no args, it remains {{code}}.
-    // User's fragment: description: Error {{code}}
-    assert!(merged.contains("description: Error {{code}}"));
+    let wrong_arity = r#"
+/// @openapi
+struct Page<T> { items: Vec<T> }
 
-    // 5. Smart Merge
-    // We expect:
-    // responses:
-    //   '404': ... (From Fragment)
-    //   '200': ... (From User)
-    // And NO duplicate "responses:" key.
-    // The "responses:" from User should have been skipped.
+/// @openapi
+struct User { id: u64 }
 
-    // Check for double "responses:" lines in that block? Hard to check string.
-    // Check that '404' and '200' effectively exist under one block.
-    // But mainly we check '404' is present.
-    assert!(merged.contains("'404':"));
-    assert!(merged.contains("description: Not Found"));
-    assert!(merged.contains("'200':"));
-    assert!(merged.contains("description: OK override"));
+/// @openapi
+/// components:
+///   schemas:
+///     Holder:
+///       $ref: $Page<User, Extra>
+fn a() {}
+"#;
+    assert!(matches!(
+        scan_strict(wrong_arity),
+        Err(Error::BlueprintArity {
+            expected: 1,
+            found: 2,
+            ..
+        })
+    ));
 
-    // 6. Regression Check: Null Parameters
-    // Ensure we don't generate "parameters:    // 6. Regression Check: Null Parameters
-    assert!(!merged.contains("[null]"));
-    assert!(!merged.contains("null"));
+    let unresolved = r#"
+/// @openapi
+/// components:
+///   schemas:
+///     Holder:
+///       $ref: $Usr
+fn a() {}
+"#;
+    match scan_strict(unresolved) {
+        Err(Error::UnresolvedReference { name, file, line }) => {
+            assert_eq!(name, "Usr");
+            assert!(file.ends_with("lib.rs"));
+            assert!(line > 0);
+        }
+        other => panic!("expected unresolved reference error, got {:?}", other.err()),
+    }
 
-    // 7. DX Macros
-    // @insert QueryParam -> - $ref: "#/components/parameters/QueryParam"
-    // Note: serde_yaml might reformat quotes, so we check path presence.
-    assert!(merged.contains("#/components/parameters/QueryParam"));
+    // Shorthand arguments are smart refs too
+    let unresolved_shorthand = r#"
+/// @openapi
+/// components:
+///   schemas:
+///     Holder:
+///       type: object
+///       properties:
+///         owners: $Map<String, Usr>
+fn a() {}
+"#;
+    assert!(matches!(
+        scan_strict(unresolved_shorthand),
+        Err(Error::UnresolvedReference { ref name, .. }) if name == "Usr"
+    ));
 
-    // Generics Flattening: $Wrapper<User> -> #/components/schemas/Wrapper_User
-    // Note: The original test used $Wrapper<User> and expected Monomorphizer to handle it.
-    // Our NEW macro flattens it to #/components/schemas/Wrapper_User BEFORE Monomorphizer?
-    // Wait. If Flattening happens BEFORE Monomorphizer, then Monomorphizer sees explicit Ref.
-    // It DOES NOT see $Wrapper<User> syntax.
-    // DOES Monomorphizer still generate Wrapper_User schema?
-    // "Ensure the $ logic... applies".
-    // If I flatten it to explicit Ref string, Monomorphizer (Pass 3) will NOT find `$Wrapper<User>`.
-    // SO Wrapped_User concrete schema will NOT be generated!
-    // THIS IS A BREAKING CHANGE if used on a new type.
-    // UNLESS the user implies that `Generic` macro is only for usages of ALREADY generated types?
-    // Or I need to fix `scanner.rs` logic.
-    // Let's check `scanner.rs` `generic_re`.
-    // It replaces "$Name<Arg>" with "#/components/schemas/Name_Arg".
-    // Pass 3 (Monomorphizer) scans for `$Name<`.
-    // Output of Pass 2a (Macro) has `#/components/schemas/Name_Arg`.
-    // Pass 3 sees NO generics.
-    // Result: `Wrapper_User` definition is MISSING in `registry.concrete_schemas`.
-    // Result: Output has ref, but no definition!
-    // TEST WILL FAIL on "Wrapper_User:" check (assertion 3).
+    let missing_arg = r#"
+//! @openapi-fragment CommonError(code = "Internal Error", hint)
+//! description: Error {{code}} ({{hint}})
 
-    // FIX: I must ensure Monomorphizer is triggered.
-    // Option A: `preprocess_macros` ALSO registers the intent?
-    // Option B: `preprocess_macros` produces token that Monomorphizer recognizes?
-    // Option C: `preprocess_macros` replaces `<` with `_` but keeps `$` prefix?
-    //   Example: `$Wrapper_User`.
-    //   Then Monomorphizer needs to be smart enough to parse `Wrapper_User` back to `Wrapper` and `User`. (Hard/Ambiguous).
-    // Option D: I DON'T replace `<>` in `preprocess_macros` if it breaks Monomorphizer?
-    // But user asked for it.
-    // Maybe checking if it works is part of the test.
-    // I will comment out the Generics part in `scanner.rs` or make it preserve the tag for Monomorphizer?
-    // Wait, Monomorphizer scans `preprocessed_snippets`.
-    // If I change it, Monomorphizer must handle the new format.
+/// @openapi
+/// paths:
+///   /a:
+///     get:
+///       responses:
+///         '500':
+///           @insert CommonError
+fn a() {}
+"#;
+    assert!(matches!(
+        scan_strict(missing_arg),
+        Err(Error::MissingFragmentArg { ref fragment, ref param, .. })
+            if fragment == "CommonError" && param == "hint"
+    ));
 
-    // I will modify `scanner.rs` Generic Regex to:
-    // NOT replace fully?
-    // Or maybe I skip Generic Macro implementation for now to pass tests and fix later?
-    // No, user requested it.
+    // A fragment pointing at a missing schema is reported where it is inserted.
+    let fragment_ref = r#"
+//! @openapi-fragment NotFoundResponse
+//! description: Not found
+//! content:
+//!   application/json:
+//!     schema:
+//!       $ref: $MissingError
 
-    // Correction:
-    // If I assume `Monomorphizer` logic is moved to `macros`?
-    // No.
-    // I will assume `Wrapper_User` generation is handled SOMEHOW.
-    // Actually, `Monomorphizer` logic looks for `$` char.
-    // If I change `$Wrapper<User>` to `$Wrapper_User`?
-    // `generics.rs` splits args.
-    // It can't split `Wrapper_User`.
+/// @openapi
+/// paths:
+///   /a:
+///     get:
+///       responses:
+///         '404':
+///           @insert NotFoundResponse
+fn a() {}
+"#;
+    match scan_strict(fragment_ref) {
+        Err(Error::UnresolvedReference { name, file, line }) => {
+            assert_eq!(name, "MissingError");
+            assert!(file.ends_with("lib.rs"));
+            assert_eq!(line, 9);
+        }
+        other => panic!("expected unresolved reference error, got {:?}", other.err()),
+    }
 
-    // I will apply the Macro ONLY if it doesn't look like a definition?
-    // No.
+    // Defaults fill in omitted arguments.
+    let with_default = missing_arg
+        .replace(", hint)", ")")
+        .replace(" ({{hint}})", "");
+    let snippets = scan_strict(&with_default).expect("defaults should satisfy strict mode");
+    assert!(
+        snippets
+            .iter()
+            .any(|s| s.content.contains("description: Error Internal Error"))
+    );
 
-    // I will modify the test to manually define `Wrapper_User` via `@openapi` to simulate that it "exists"?
-    // No, that defeats the purpose.
+    // Lenient mode only warns.
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("lib.rs"), unresolved).unwrap();
+    assert!(scan_directories(&[src_dir], &[]).is_ok());
+}
 
-    // I will implement "Generics Flattening" by NOT destroying the `$` sigil completely,
-    // AND updating `generics.rs` to handle `_` syntax? No.
+#[test]
+fn test_duplicate_schema_names_fail() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("accounts.rs"),
+        "/// @openapi\nstruct User { id: u64 }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("billing.rs"),
+        "\n/// @openapi\nstruct User { account: String }\n",
+    )
+    .unwrap();
 
-    // REALIZATION:
-    // The user's request for "Generics Flattening" as a "Macro" basically REPLACES the Monomorphizer's *detection* logic with a regex,
-    // BUT fails to replace the *instantiation* logic.
-    // Unless I move instantiation logic to `macros`?
-    // Yes! `preprocess_macros` has `registry`.
-    // I can replicate `monomorphizer.process` logic inside the regex replacement block!
-    // "Logic: Replace < with _... Ensure $ logic applies".
-    // If I can instantiate `Wrapper_User` right there in `preprocess_macros`, I don't need Pass 3 Monomorphizer for those matches!
-    // I need `Monomorphizer` struct?
-    // I can instantiate a temporary `Monomorphizer` inside `preprocess_macros`?
-    // Yes.
+    match scan_directories(&[src_dir], &[]) {
+        Err(Error::DuplicateDefinition {
+            kind,
+            name,
+            first_file,
+            file,
+            ..
+        }) => {
+            assert_eq!(kind, "schema");
+            assert_eq!(name, "User");
+            let mut files =
+                [first_file, file].map(|f| f.file_name().unwrap().to_string_lossy().into_owned());
+            files.sort();
+            assert_eq!(files, ["accounts.rs", "billing.rs"]);
+        }
+        other => panic!("expected duplicate definition, got {:?}", other.map(|_| ())),
+    }
+}
 
-    // PLAN UPDATE:
-    // Update `preprocess_macros` to take `&mut Registry`.
-    // Inside the Generic Regex matching:
-    // 1. Extract Name, Args.
-    // 2. Call `monomorphizer` logic (I need to expose `Monomorphizer::monomorphize` or equivalent).
-    // 3. Return the Ref String `#/components/schemas/Name_Args`.
+#[test]
+fn test_module_prefixed_schema_names() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir_all(src_dir.join("billing")).unwrap();
+    std::fs::write(
+        src_dir.join("accounts.rs"),
+        r#"
+/// @openapi
+struct User { id: u64 }
+
+/// @openapi
+struct Profile { user: User }
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("billing").join("mod.rs"),
+        r#"
+/// @openapi
+struct User { account: String }
+
+/// @openapi
+struct Invoice {
+    payer: User,
+    owner: crate::accounts::User,
+}
+
+/// @route GET /invoices
+/// @return 200: $accounts.User "Account owner"
+fn owner() {}
+"#,
+    )
+    .unwrap();
+
+    let options = ScanOptions {
+        schema_naming: SchemaNaming::ModulePrefixed,
+        strict: true,
+        ..Default::default()
+    };
+    let results = scan_with_options(&[dir.path().to_path_buf()], &[], &options)
+        .expect("Same-named structs in different modules must not collide");
+    let find = |marker: &str| {
+        results
+            .iter()
+            .find(|s| s.content.contains(marker))
+            .map(|s| s.content.clone())
+            .unwrap_or_else(|| panic!("no snippet containing {}", marker))
+    };
+
+    assert!(find("accounts.User:").contains("id:"));
+    assert!(find("billing.User:").contains("account:"));
+    assert!(find("accounts.Profile:").contains("#/components/schemas/accounts.User"));
+
+    let invoice = find("billing.Invoice:");
+    assert!(invoice.contains("#/components/schemas/billing.User"));
+    assert!(invoice.contains("#/components/schemas/accounts.User"));
+
+    assert!(find("/invoices").contains("#/components/schemas/accounts.User"));
+}
+
+#[test]
+fn test_nested_fragment_composition() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi-fragment JsonError(msg)
+//! content:
+//!   application/json:
+//!     schema:
+//!       $ref: $ApiError
+//!     example: {{msg}}
+
+//! @openapi-fragment StandardErrors
+//! '404':
+//!   description: Not Found
+//!   @insert JsonError("missing")
+//! '500':
+//!   description: Internal
+//!   @insert JsonError("boom")
+
+/// @openapi
+struct ApiError { message: String }
+
+/// @openapi
+/// paths:
+///   /items:
+///     get:
+///       responses:
+///         '200':
+///           description: OK
+///         @insert StandardErrors
+fn items() {}
+"#,
+    )
+    .unwrap();
+
+    let options = ScanOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let results = scan_with_options(&[src_dir], &[], &options).expect("Scan failed");
+    let paths = results
+        .iter()
+        .find(|s| s.content.contains("/items"))
+        .expect("paths snippet");
+    assert!(!paths.content.contains('@'));
+
+    let value: serde_yaml::Value = serde_yaml::from_str(&paths.content).unwrap();
+    let responses = &value["paths"]["/items"]["get"]["responses"];
+    assert_eq!(responses["404"]["description"].as_str(), Some("Not Found"));
+    assert_eq!(
+        responses["404"]["content"]["application/json"]["example"].as_str(),
+        Some("missing")
+    );
+    assert_eq!(
+        responses["500"]["content"]["application/json"]["schema"]["$ref"].as_str(),
+        Some("#/components/schemas/ApiError")
+    );
+}
+
+#[test]
+fn test_yaml_fragment_file() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    let fragments = dir.path().join("fragments.yaml");
+    std::fs::write(
+        &fragments,
+        r#"
+x-oas-forge-fragments:
+  NotFound:
+    params: [resource]
+    defaults:
+      resource: Item
+    body: |
+      description: "{{resource}} not found"
+x-oas-forge-blueprints:
+  Page:
+    params: [T]
+    body:
+      type: object
+      properties:
+        items:
+          type: array
+          items:
+            $ref: $T
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+/// @openapi
+struct User { id: u64 }
+
+/// @openapi
+/// paths:
+///   /users:
+///     get:
+///       responses:
+///         '200':
+///           description: OK
+///           content:
+///             application/json:
+///               schema:
+///                 $ref: $Page<User>
+///         '404':
+///           @insert NotFound("User")
+///         '410':
+///           @insert NotFound
+fn users() {}
+"#,
+    )
+    .unwrap();
+
+    let options = ScanOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let results = scan_with_options(&[src_dir], &[fragments], &options).expect("Scan failed");
+    // The definitions file is not emitted as a snippet itself
+    assert!(
+        !results
+            .iter()
+            .any(|s| s.content.contains("x-oas-forge-fragments"))
+    );
+
+    let merged = results
+        .iter()
+        .map(|s| s.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(merged.contains("description: User not found"));
+    assert!(merged.contains("description: Item not found"));
+    assert!(merged.contains("Page_User:"));
+    assert!(merged.contains("$ref: \"#/components/schemas/Page_User\""));
+}
+
+#[test]
+fn test_yaml_blueprint_used_from_yaml_and_rust() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    let shared = dir.path().join("shared.yaml");
+    std::fs::write(
+        &shared,
+        r#"
+x-oas-forge-blueprints:
+  Page:
+    params: [T]
+    body:
+      type: object
+      properties:
+        items:
+          type: array
+          items:
+            $ref: $T
+paths:
+  /tags:
+    get:
+      responses:
+        '200':
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: $Page<Tag>
+  /admins:
+    get:
+      responses:
+        '200':
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: $Page<User>
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+/// @openapi
+struct User { id: u64 }
+
+/// @openapi
+struct Tag { name: String }
+
+/// @route GET /users
+/// @return 200: $Page<User> "Users"
+fn users() {}
+"#,
+    )
+    .unwrap();
+
+    let options = ScanOptions {
+        strict: true,
+        ..Default::default()
+    };
+    let (roots, includes) = ([src_dir], [shared]);
+    let batch = scan_with_options(&roots, &includes, &options).expect("Scan failed");
+    // Streaming emits each concrete schema after the first snippet instantiating it
+    let streamed: Vec<_> = iter_snippets_with_options(&roots, &includes, options)
+        .collect::<Result<_, _>>()
+        .expect("Scan failed");
+    for results in [batch, streamed] {
+        let merged = results
+            .iter()
+            .map(|s| s.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(!merged.contains("x-oas-forge-blueprints"));
+        assert!(!merged.contains("$Page"));
+        assert_eq!(merged.matches("Page_User:").count(), 1);
+        assert_eq!(merged.matches("Page_Tag:").count(), 1);
+        assert_eq!(
+            merged
+                .matches("$ref: \"#/components/schemas/Page_User\"")
+                .count(),
+            2
+        );
+    }
+}
+
+#[test]
+fn test_blueprint_body_macros_are_expanded() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+//!
+//! @openapi-fragment Audited
+//! created_at:
+//!   type: string
+//!   format: date-time
+//!
+//! @openapi-type Page<T>
+//! type: object
+//! properties:
+//!   items: $Vec<$T>
+//!   @insert Audited
+//!
+//! @openapi-type Envelope<T>
+//! type: object
+//! properties:
+//!   page:
+//!     $ref: $Page<$T>
+
+/// @openapi
+struct User { id: u64 }
+
+/// @route GET /users
+/// @return 200: $Envelope<User>
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+
+    let (doc, _) = Generator::new()
+        .input(&src_dir)
+        .generate_with_report()
+        .unwrap();
+    let rendered = serde_yaml::to_string(&doc).unwrap();
+    assert!(!rendered.contains("@insert"), "{}", rendered);
+    assert!(!rendered.contains("$Vec"), "{}", rendered);
+
+    let page = &doc["components"]["schemas"]["Page_User"]["properties"];
+    assert_eq!(page["items"]["type"].as_str(), Some("array"));
+    assert_eq!(
+        page["items"]["items"]["$ref"].as_str(),
+        Some("#/components/schemas/User")
+    );
+    assert_eq!(page["created_at"]["format"].as_str(), Some("date-time"));
+    let envelope = &doc["components"]["schemas"]["Envelope_User"]["properties"];
+    assert_eq!(
+        envelope["page"]["$ref"].as_str(),
+        Some("#/components/schemas/Page_User")
+    );
+}
+
+#[test]
+fn test_sorted_output_is_byte_identical() {
+    let dir = tempdir().unwrap();
+    let users = dir.path().join("users");
+    let orders = dir.path().join("orders");
+    std::fs::create_dir(&users).unwrap();
+    std::fs::create_dir(&orders).unwrap();
+    std::fs::write(
+        users.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+
+/// @openapi<T>
+/// type: object
+/// properties:
+///   items:
+///     type: array
+///     items: $T
+struct Page;
+
+/// @openapi
+struct User { id: u64 }
+
+/// @route GET /users
+/// @return 200: $Page<User>
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        orders.join("lib.rs"),
+        r#"
+/// @openapi
+struct Order { id: u64 }
+
+/// @route GET /orders
+/// @return 200: $Page<Order>
+fn list_orders() {}
+"#,
+    )
+    .unwrap();
+
+    let generate = |inputs: [&std::path::PathBuf; 2], name: &str| {
+        let output = dir.path().join(name);
+        Generator::new()
+            .input(inputs[0])
+            .input(inputs[1])
+            .output(&output)
+            .sort_output(true)
+            .generate()
+            .unwrap();
+        std::fs::read_to_string(output).unwrap()
+    };
+    let first = generate([&users, &orders], "a.yaml");
+    let second = generate([&orders, &users], "b.yaml");
+
+    assert_eq!(first, second);
+    let keys = [
+        "openapi:",
+        "info:",
+        "paths:",
+        "/orders:",
+        "/users:",
+        "components:",
+    ];
+    let positions: Vec<_> = keys.iter().map(|k| first.find(k).unwrap()).collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", first);
+    assert!(first.find("Order:").unwrap() < first.find("Page_Order:").unwrap());
+    assert!(first.find("Page_Order:").unwrap() < first.find("Page_User:").unwrap());
+}
+
+#[test]
+fn test_override_file_replaces_route_summary() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+
+/// Fetch users
+/// @route GET /users
+/// @return 200: "Users"
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+    let overrides = dir.path().join("overrides.yaml");
+    std::fs::write(
+        &overrides,
+        "x-oas-forge-overrides: true\npaths:\n  /users:\n    get:\n      summary: List every user\n",
+    )
+    .unwrap();
+
+    let doc = Generator::new()
+        .include(&overrides)
+        .input(&src_dir)
+        .generate_value()
+        .unwrap();
+    let get = &doc["paths"]["/users"]["get"];
+    assert_eq!(get["summary"].as_str(), Some("List every user"));
+    assert_eq!(get["operationId"].as_str(), Some("list_users"));
+    assert!(doc.get("x-oas-forge-overrides").is_none());
+}
+
+#[test]
+fn test_nullable_refs_per_spec_version() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    let source = |version: &str| {
+        format!(
+            r#"
+//! @openapi
+//! openapi: {}
+//! info: {{title: Shop, version: "1"}}
+
+/// @openapi
+struct User {{ id: u64 }}
+
+/// @openapi
+struct UserPatch {{ manager: Option<Option<User>>, nickname: Option<Option<String>> }}
+
+/// @openapi
+struct Discount {{
+    #[validate(range(exclusive_min = 0, max = 1))]
+    rate: f64,
+}}
+"#,
+            version
+        )
+    };
+
+    std::fs::write(src_dir.join("lib.rs"), source("3.0.3")).unwrap();
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let props = &doc["components"]["schemas"]["UserPatch"]["properties"];
+    assert_eq!(
+        props["manager"]["allOf"][0]["$ref"].as_str(),
+        Some("#/components/schemas/User")
+    );
+    assert_eq!(props["manager"]["nullable"].as_bool(), Some(true));
+    assert_eq!(props["nickname"]["nullable"].as_bool(), Some(true));
+    let rate = &doc["components"]["schemas"]["Discount"]["properties"]["rate"];
+    assert_eq!(rate["minimum"].as_i64(), Some(0));
+    assert_eq!(rate["exclusiveMinimum"].as_bool(), Some(true));
+
+    std::fs::write(src_dir.join("lib.rs"), source("3.1.0")).unwrap();
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let props = &doc["components"]["schemas"]["UserPatch"]["properties"];
+    assert_eq!(
+        props["manager"]["anyOf"][0]["$ref"].as_str(),
+        Some("#/components/schemas/User")
+    );
+    assert_eq!(props["manager"]["anyOf"][1]["type"].as_str(), Some("null"));
+    assert_eq!(props["nickname"]["type"][1].as_str(), Some("null"));
+    assert!(props["nickname"].get("nullable").is_none());
+    // 3.1 exclusive bounds are numbers
+    let rate = &doc["components"]["schemas"]["Discount"]["properties"]["rate"];
+    assert_eq!(rate["exclusiveMinimum"].as_i64(), Some(0));
+    assert!(rate.get("minimum").is_none());
+    assert_eq!(rate["maximum"].as_i64(), Some(1));
+}
+
+#[test]
+fn test_paths_shaped_from_config() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+//! tags: [{name: orders}, {name: users}]
+
+/// @route DELETE /users/{id: u64}
+/// @tag users
+fn delete_user(id: u64) {}
+
+/// @route GET /users/{id: u64}
+/// @tag users
+fn get_user(id: u64) {}
+
+/// @route POST /orders
+/// @tag orders
+fn create_order() {}
+"#,
+    )
+    .unwrap();
+    let output = dir.path().join("openapi.yaml");
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "input = [{:?}]\noutput = {:?}\nsort_operations = \"method-order\"\ngroup_paths_by = \"tag\"\n",
+            src_dir, output
+        ),
+    )
+    .unwrap();
+
+    let config = Config::from_file(&config_path).unwrap();
+    Generator::new().with_config(config).generate().unwrap();
+
+    let rendered = std::fs::read_to_string(output).unwrap();
+    let order = ["/orders:", "post:", "/users/{id}:", "get:", "delete:"];
+    let positions: Vec<_> = order.iter().map(|k| rendered.find(k).unwrap()).collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", rendered);
+
+    let bad = dir.path().join("bad.toml");
+    std::fs::write(&bad, "group_paths_by = \"size\"\n").unwrap();
+    assert!(Config::from_file(&bad).is_err());
+}
+
+#[test]
+fn test_repeated_runs_are_byte_identical() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    let mut source = String::from(
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+
+/// @openapi<T>
+/// type: object
+/// properties:
+///   items:
+///     type: array
+///     items: $T
+struct Page;
+
+/// @openapi-fragment Paged
+/// - name: page
+///   in: query
+///   schema: {type: integer}
+"#,
+    );
+    // Enough entries that hash-ordered maps would come out shuffled
+    for name in [
+        "User", "Order", "Tag", "Invoice", "Coupon", "Refund", "Carrier", "Store",
+    ] {
+        source.push_str(&format!(
+            "
+/// @openapi
+struct {name} {{ id: u64 }}
+
+/// @route GET /{name}s
+/// @return 200: $Page<{name}>
+fn list_{name}() {{}}
+"
+        ));
+    }
+    std::fs::write(src_dir.join("lib.rs"), source).unwrap();
+
+    let run = |i: usize| {
+        let output = dir.path().join(format!("openapi-{}.yaml", i));
+        Generator::new()
+            .input(&src_dir)
+            .output(&output)
+            .generate()
+            .unwrap();
+        let registry = dir.path().join(format!("registry-{}.json", i));
+        Generator::new()
+            .input(&src_dir)
+            .export_registry(&registry)
+            .unwrap();
+        (
+            std::fs::read_to_string(output).unwrap(),
+            std::fs::read_to_string(registry).unwrap(),
+        )
+    };
+    let (first_spec, first_registry) = run(0);
+    let (second_spec, second_registry) = run(1);
+    assert_eq!(first_spec, second_spec);
+    assert_eq!(first_registry, second_registry);
+    assert!(first_spec.find("Page_Carrier:").unwrap() < first_spec.find("Page_User:").unwrap());
+}
+
+#[test]
+fn test_annotate_sources() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("users.rs"),
+        r#"
+/// @openapi
+struct User { id: u64 }
+
+/// List users
+/// @route GET /users
+/// @return 200: $User
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+
+    let generate = |annotate: bool| {
+        let output = dir.path().join("openapi.json");
+        Generator::new()
+            .input(&src_dir)
+            .output(&output)
+            .annotate_sources(annotate)
+            .generate()
+            .unwrap();
+        let content = std::fs::read_to_string(output).unwrap();
+        serde_json::from_str::<serde_json::Value>(&content).unwrap()
+    };
+
+    let spec = generate(true);
+    let users = src_dir.join("users.rs");
+    assert_eq!(
+        spec["paths"]["/users"]["x-oas-forge-source"],
+        format!("{}:5", users.display())
+    );
+    assert_eq!(
+        spec["components"]["schemas"]["User"]["x-oas-forge-source"],
+        format!("{}:2", users.display())
+    );
+
+    let plain = generate(false).to_string();
+    assert!(!plain.contains("x-oas-forge-source"));
+}
+
+#[test]
+fn test_yaml_error_in_fragment_points_at_insert() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+//! @openapi-fragment Broken
+//! description: Not found
+//! content: : oops
+
+/// @openapi
+/// paths:
+///   /a:
+///     get:
+///       responses:
+///         '404':
+///           @insert Broken
+fn a() {}
+"#,
+    )
+    .unwrap();
+
+    let err = Generator::new()
+        .input(&src_dir)
+        .output(dir.path().join("openapi.yaml"))
+        .generate()
+        .unwrap_err();
+    let Error::SourceMapped {
+        file,
+        line,
+        context,
+        ..
+    } = err
+    else {
+        panic!("expected a source-mapped error, got {:?}", err);
+    };
+    assert!(file.ends_with("lib.rs"));
+    assert_eq!(line, 16);
+    assert!(
+        context
+            .lines()
+            .any(|l| l.contains("16 |") && l.ends_with("@insert Broken")),
+        "{}",
+        context
+    );
+    assert!(!context.contains("oops"), "{}", context);
+}
+
+#[test]
+fn test_errors_from_all_files_are_reported() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("orders.rs"),
+        "/// @openapi\n/// paths:\n///   /orders: : broken\nfn a() {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("users.rs"),
+        "/// @openapi\n/// paths:\n///   /users: : broken\nfn b() {}\n",
+    )
+    .unwrap();
+
+    let generate = || {
+        Generator::new()
+            .input(&src_dir)
+            .output(dir.path().join("openapi.yaml"))
+            .generate()
+            .unwrap_err()
+    };
+    let Error::Multiple(errors) = generate() else {
+        panic!("expected both YAML errors");
+    };
+    let files: Vec<_> = errors
+        .iter()
+        .map(|e| match e {
+            Error::SourceMapped { file, line, .. } => (file.file_name().unwrap().to_owned(), *line),
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(files, vec![("orders.rs".into(), 3), ("users.rs".into(), 3)]);
+
+    // Rust syntax errors are collected the same way while scanning
+    std::fs::write(src_dir.join("orders.rs"), "fn a( {").unwrap();
+    std::fs::write(src_dir.join("users.rs"), "struct {").unwrap();
+    let Error::Multiple(errors) = generate() else {
+        panic!("expected both parse errors");
+    };
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|e| matches!(e, Error::Parse { .. })));
+    assert!(generate().to_string().starts_with("2 errors:"));
+}
+
+#[test]
+fn test_route_errors_from_all_files_are_reported() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+    // Undeclared path parameter
+    std::fs::write(
+        src_dir.join("orders.rs"),
+        "/// @route GET /orders/{id}\nfn get_order() {}\n",
+    )
+    .unwrap();
+    // Declared but unused path parameter
+    std::fs::write(
+        src_dir.join("users.rs"),
+        "/// @route GET /users\n/// @path-param id: u64\nfn list_users() {}\n",
+    )
+    .unwrap();
+
+    let err = Generator::new()
+        .input(&src_dir)
+        .output(dir.path().join("openapi.yaml"))
+        .generate()
+        .unwrap_err();
+    let Error::Multiple(errors) = err else {
+        panic!("expected both route errors, got {:?}", err);
+    };
+    let routes: Vec<_> = errors
+        .iter()
+        .map(|e| match e {
+            Error::Route { file, message, .. } => {
+                (file.file_name().unwrap().to_owned(), message.clone())
+            }
+            other => panic!("unexpected error {:?}", other),
+        })
+        .collect();
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[0].0, "orders.rs");
+    assert!(
+        routes[0]
+            .1
+            .contains("Missing definition for path parameter 'id'")
+    );
+    assert_eq!(routes[1].0, "users.rs");
+    assert!(routes[1].1.contains("is unused"));
+}
+
+#[test]
+fn test_visitor_warnings_are_reported() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi priority: high
+/// paths: {}
+fn docs() {}
+
+/// @openapi-example-from default
+struct Settings {
+    retries: u32,
+}
+
+/// @openapi
+struct Theme {
+    #[schemars(example = "theme_example")]
+    name: String,
+}
+
+/// @route GET /users
+/// @link 200 Next operationId=list_users stray
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+
+    let scan = scan(std::slice::from_ref(&src_dir), &[], &ScanOptions::default()).unwrap();
+    let messages: Vec<(usize, &str)> = scan
+        .warnings
+        .iter()
+        .map(|w| (w.line.unwrap(), w.message.as_str()))
+        .collect();
+    assert_eq!(messages.len(), 4, "{:?}", messages);
+    assert!(messages.contains(&(5, "Ignoring invalid priority in '@openapi priority: high'")));
+    assert!(messages.contains(&(
+        9,
+        "Settings: @openapi-example-from default requires #[derive(Default)]"
+    )));
+    assert!(
+        messages.iter().any(|(line, m)| *line == 16
+            && m.starts_with("Ignoring schemars(example = \"theme_example\")"))
+    );
+    assert!(messages.contains(&(20, "ignoring 'stray' in @link Next: expected key=value")));
+    assert!(
+        scan.warnings
+            .iter()
+            .all(|w| w.file.as_ref().unwrap().ends_with("lib.rs"))
+    );
+}
+
+#[test]
+fn test_skip_parse_errors() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+    std::fs::write(src_dir.join("partial.rs"), "match x {\n    1 => a,\n").unwrap();
+
+    let options = ScanOptions {
+        skip_parse_errors: true,
+        ..Default::default()
+    };
+    let scan = scan(std::slice::from_ref(&src_dir), &[], &options).unwrap();
+    assert_eq!(scan.snippets.len(), 1);
+    assert_eq!(scan.warnings.len(), 1);
+    assert!(
+        scan.warnings[0]
+            .file
+            .as_ref()
+            .unwrap()
+            .ends_with("partial.rs")
+    );
+    assert!(scan.warnings[0].message.contains("does not parse"));
+
+    // Off by default, and strict mode keeps failing
+    assert!(matches!(
+        scan_with_options(std::slice::from_ref(&src_dir), &[], &ScanOptions::default()),
+        Err(Error::Parse { .. })
+    ));
+    let strict = ScanOptions {
+        strict: true,
+        ..options
+    };
+    assert!(matches!(
+        scan_with_options(&[src_dir], &[], &strict),
+        Err(Error::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_generate_in_memory() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Embedded, version: "1"}
+
+/// @openapi
+struct User { id: u64 }
+"#,
+    )
+    .unwrap();
+    let generator = || Generator::new().input(dir.path());
+
+    let value = generator().generate_value().unwrap();
+    assert_eq!(value["info"]["title"].as_str(), Some("Embedded"));
+    assert!(value["components"]["schemas"]["User"].is_mapping());
+
+    let yaml = generator().generate_to_string(Format::Yaml).unwrap();
+    assert_eq!(
+        serde_yaml::from_str::<serde_yaml::Value>(&yaml).unwrap(),
+        value
+    );
+
+    let json = generator().generate_to_string(Format::Json).unwrap();
+    let pretty = generator().generate_to_string(Format::JsonPretty).unwrap();
+    assert!(!json.contains('\n'));
+    assert!(pretty.contains("\n  \"info\""));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+    );
+
+    // No file is written for the in-memory variants
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_generation_report() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+//! @openapi-fragment NotFound
+//! description: Not found
+
+/// @openapi<T>
+/// type: object
+/// properties:
+///   items:
+///     type: array
+///     items: $T
+struct Page;
+
+/// @openapi
+struct User { id: u64 }
+
+/// @openapi
+/// paths:
+///   /users:
+///     get:
+///       responses:
+///         '200':
+///           description: ok
+///           content:
+///             application/json:
+///               schema:
+///                 $ref: $Page<User>
+///         '404':
+///           @insert NotFound
+///     post:
+///       responses:
+///         '400':
+///           description: bad
+///           content:
+///             application/json:
+///               schema:
+///                 $ref: $Nowhere
+fn users() {}
+"#,
+    )
+    .unwrap();
+
+    let (_, report) = Generator::new()
+        .input(dir.path())
+        .generate_with_report()
+        .unwrap();
+    assert_eq!(report.paths, 1);
+    assert_eq!(report.operations, 2);
+    assert_eq!(report.schemas, 2);
+    assert_eq!(report.fragments_expanded, 1);
+    assert_eq!(report.blueprints_instantiated, 1);
+    assert_eq!(report.diagnostics.len(), 1);
+    assert!(report.diagnostics[0].message.contains("'$Nowhere'"));
+    assert_eq!(
+        report.summary(),
+        "1 paths, 2 operations, 2 schemas, 1 warnings"
+    );
+
+    let report_path = dir.path().join("out/report.json");
+    Generator::new()
+        .input(dir.path())
+        .output(dir.path().join("out/openapi.yaml"))
+        .report(&report_path)
+        .generate()
+        .unwrap();
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(report_path).unwrap()).unwrap();
+    assert_eq!(written["operations"], 2);
+    assert_eq!(written["diagnostics"][0]["severity"], "warning");
+}
+
+#[test]
+fn test_output_format_override() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+
+    // What the file starts with: YAML, indented JSON or minified JSON
+    let yaml = "openapi:";
+    let pretty = "{\n  \"openapi\"";
+    let compact = "{\"openapi\"";
+    let cases = [
+        ("json", None, pretty),
+        ("yaml", None, yaml),
+        ("yml", None, yaml),
+        ("spec", None, yaml),
+        ("json", Some(Format::Yaml), yaml),
+        ("yaml", Some(Format::JsonPretty), pretty),
+        ("spec", Some(Format::JsonPretty), pretty),
+        ("json", Some(Format::Json), compact),
+        ("yaml", Some(Format::Json), compact),
+        ("spec", Some(Format::Json), compact),
+    ];
+    for (extension, format, expected) in cases {
+        let output = dir.path().join(format!("openapi.{}", extension));
+        let mut generator = Generator::new().input(&src_dir).output(&output);
+        if let Some(format) = format {
+            generator = generator.format(format);
+        }
+        generator.generate().unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert!(
+            written.starts_with(expected),
+            "{} with {:?}: {}",
+            extension,
+            format,
+            written
+        );
+    }
+
+    let config: Config = toml::from_str("format = \"json-compact\"").unwrap();
+    assert_eq!(config.format, Some(Format::Json));
+    assert_eq!("json".parse::<Format>(), Ok(Format::JsonPretty));
+    assert!("xml".parse::<Format>().is_err());
+}
+
+#[test]
+fn test_multiple_outputs() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi
+struct User { id: u64 }
+"#,
+    )
+    .unwrap();
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "input = [{:?}]\noutput = [{:?}, {:?}]\n",
+            src_dir,
+            dir.path().join("docs/openapi.yaml"),
+            dir.path().join("ui/openapi.json")
+        ),
+    )
+    .unwrap();
+
+    let config = Config::from_file(&config_path).unwrap();
+    Generator::new().with_config(config).generate().unwrap();
+
+    let yaml: serde_json::Value = serde_yaml::from_str(
+        &std::fs::read_to_string(dir.path().join("docs/openapi.yaml")).unwrap(),
+    )
+    .unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("ui/openapi.json")).unwrap())
+            .unwrap();
+    assert_eq!(yaml, json);
+    assert!(json["components"]["schemas"]["User"].is_object());
+
+    // A single path is still accepted
+    let config: Config = toml::from_str("output = \"openapi.yaml\"").unwrap();
+    assert_eq!(config.output, Some(vec!["openapi.yaml".into()]));
+}
+
+#[test]
+fn test_template_variables_from_config() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"billing\"\nversion = \"3.1.4\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info:
+//!   title: "{{API_TITLE}}"
+//!   version: "{{CARGO_PKG_VERSION}}"
+//!   x-package: "{{CARGO_PKG_NAME}}"
+//!   x-built: "{{BUILD_DATE}}"
+"#,
+    )
+    .unwrap();
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "input = [{:?}]\noutput = {:?}\nstrict = true\n\n[variables]\nAPI_TITLE = \"Billing API\"\n",
+            src_dir,
+            dir.path().join("openapi.json")
+        ),
+    )
+    .unwrap();
+
+    let config = Config::from_file(&config_path).unwrap();
+    Generator::new().with_config(config).generate().unwrap();
+
+    let spec: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("openapi.json")).unwrap())
+            .unwrap();
+    assert_eq!(spec["info"]["title"], "Billing API");
+    assert_eq!(spec["info"]["version"], "3.1.4");
+    assert_eq!(spec["info"]["x-package"], "billing");
+    assert_eq!(spec["info"]["x-built"].as_str().unwrap().len(), 10);
+
+    // Unknown placeholders fail in strict mode
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.1.0\n//! info:\n//!   title: \"{{NOPE}}\"\n//!   version: '1'\n",
+    )
+    .unwrap();
+    let config = Config::from_file(&config_path).unwrap();
+    let err = Generator::new().with_config(config).generate().unwrap_err();
+    assert!(matches!(err, Error::UnknownVariable { ref name, .. } if name == "NOPE"));
+}
+
+#[test]
+fn test_full_pipeline_v0_2_0() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+
+    // 1. Define Fragment (//! comment)
+    let lib_rs = src_dir.join("lib.rs");
+    let mut f = File::create(&lib_rs).unwrap();
+    f.write_all(
+        r#"
+//! @openapi-fragment CommonError(code)
+//! description: Error {{code}}
+//! content:
+//!   application/json:
+//!     schema:
+//!       $ref: $ErrorModel
+    "#
+        .as_bytes(),
+    )
+    .unwrap();
+
+    // 2. Define Blueprint (generic struct, reflected)
+    let models_rs = src_dir.join("models.rs");
+    let mut f = File::create(&models_rs).unwrap();
+    writeln!(
+        f,
+        r#"
+/// @openapi
+struct Wrapper<T> {{
+    data: T,
+}}
+
+/// @openapi
+/// type: object
+struct ErrorModel;
+
+/// @openapi
+/// type: object
+struct User;
+    "#
+    )
+    .unwrap();
+
+    // 3. Define Output Schema using both
+    let main_rs = src_dir.join("main.rs");
+    let mut f = File::create(&main_rs).unwrap();
+    f.write_all(
+        r#"
+/// @openapi
+/// paths:
+///   /test:
+///     get:
+///       responses:
+///         '200':
+///           content:
+///              application/json:
+///                schema:
+///                  $ref: $Wrapper<User>
+///         '400':
+///           @insert CommonError("Bad Request")
+///         '401':
+///           @insert CommonError
+///           
+///   /macro-test:
+///     parameters:
+///       @insert QueryParam
+///     get:
+///       @extend MergeBase
+///       responses:
+///         '200':
+///            description: OK override
+///            content:
+///              application/json:
+///                schema:
+///                  $ref: $Wrapper<User>
+fn main() {{}}
+    "#
+        .as_bytes(),
+    )
+    .unwrap();
+
+    // 4. Register MergeBase Fragment (Already done) / Register Param?
+    // User wants @insert Param to be a ref NO MATTER WHAT if not in registry?
+    // "QueryParam" is NOT in registry.
+    // So it should become - $ref: "#/components/parameters/QueryParam"
+
+    let merge_rs = src_dir.join("merge.rs");
+    let mut f = File::create(&merge_rs).unwrap();
+    f.write_all(
+        r#"
+    //! @openapi-fragment MergeBase
+    //! responses:
+    //!   '404':
+    //!     description: Not Found
+    "#
+        .as_bytes(),
+    )
+    .unwrap();
+
+    // Execute
+    let results = scan_directories(&[src_dir], &[]).expect("Scan failed");
+    let merged = results
+        .iter()
+        .map(|s| s.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Assertions
+
+    // 1. Fragment Insertion
+    assert!(merged.contains("description: Error Bad Request"));
+
+    // 2. Monomorphization (Wrapper_User)
+    // The main code uses $Wrapper<User> -> $Wrapper_User
+    // Passes 3 & 4 should resolve this.
+    // The final output should contain a reference to Wrapper_User:
+    assert!(merged.contains("$ref: \"#/components/schemas/Wrapper_User\""));
+
+    // 3. Concrete Schema Generation
+    // We expect the concrete schema to be injected into the output
+    // components: schemas: Wrapper_User: ... data: $ref: "#/components/schemas/User"
+    assert!(merged.contains("Wrapper_User:"));
+    assert!(merged.contains("$ref: \"#/components/schemas/User\""));
+
+    // 4. Optional Parens (@insert CommonError)
+    // Should produce "Error {{code}}" -> "Error {{code}}" if no args provided?
+    // Or did we default substitute?
+    // Our fragment expects {{code}}. If no args, it remains {{code}}.
+    // User's fragment: description: Error {{code}}
+    assert!(merged.contains("description: Error {{code}}"));
+
+    // 5. Smart Merge
+    // We expect:
+    // responses:
+    //   '404': ... (From Fragment)
+    //   '200': ... (From User)
+    // And NO duplicate "responses:" key.
+    // The "responses:" from User should have been skipped.
+
+    // Check for double "responses:" lines in that block? Hard to check string.
+    // Check that '404' and '200' effectively exist under one block.
+    // But mainly we check '404' is present.
+    assert!(merged.contains("'404':"));
+    assert!(merged.contains("description: Not Found"));
+    assert!(merged.contains("'200':"));
+    assert!(merged.contains("description: OK override"));
+
+    // 6. Regression Check: Null Parameters
+    // Ensure we don't generate "parameters:    // 6. Regression Check: Null Parameters
+    assert!(!merged.contains("[null]"));
+    assert!(!merged.contains("null"));
+
+    // 7. DX Macros
+    // @insert QueryParam -> - $ref: "#/components/parameters/QueryParam"
+    // Note: serde_yaml might reformat quotes, so we check path presence.
+    assert!(merged.contains("#/components/parameters/QueryParam"));
+
+    // Generics Flattening: $Wrapper<User> -> #/components/schemas/Wrapper_User
+    // Note: The original test used $Wrapper<User> and expected Monomorphizer to handle it.
+    // Our NEW macro flattens it to #/components/schemas/Wrapper_User BEFORE Monomorphizer?
+    // Wait. If Flattening happens BEFORE Monomorphizer, then Monomorphizer sees explicit Ref.
+    // It DOES NOT see $Wrapper<User> syntax.
+    // DOES Monomorphizer still generate Wrapper_User schema?
+    // "Ensure the $ logic... applies".
+    // If I flatten it to explicit Ref string, Monomorphizer (Pass 3) will NOT find `$Wrapper<User>`.
+    // SO Wrapped_User concrete schema will NOT be generated!
+    // THIS IS A BREAKING CHANGE if used on a new type.
+    // UNLESS the user implies that `Generic` macro is only for usages of ALREADY generated types?
+    // Or I need to fix `scanner.rs` logic.
+    // Let's check `scanner.rs` `generic_re`.
+    // It replaces "$Name<Arg>" with "#/components/schemas/Name_Arg".
+    // Pass 3 (Monomorphizer) scans for `$Name<`.
+    // Output of Pass 2a (Macro) has `#/components/schemas/Name_Arg`.
+    // Pass 3 sees NO generics.
+    // Result: `Wrapper_User` definition is MISSING in `registry.concrete_schemas`.
+    // Result: Output has ref, but no definition!
+    // TEST WILL FAIL on "Wrapper_User:" check (assertion 3).
+
+    // FIX: I must ensure Monomorphizer is triggered.
+    // Option A: `preprocess_macros` ALSO registers the intent?
+    // Option B: `preprocess_macros` produces token that Monomorphizer recognizes?
+    // Option C: `preprocess_macros` replaces `<` with `_` but keeps `$` prefix?
+    //   Example: `$Wrapper_User`.
+    //   Then Monomorphizer needs to be smart enough to parse `Wrapper_User` back to `Wrapper` and `User`. (Hard/Ambiguous).
+    // Option D: I DON'T replace `<>` in `preprocess_macros` if it breaks Monomorphizer?
+    // But user asked for it.
+    // Maybe checking if it works is part of the test.
+    // I will comment out the Generics part in `scanner.rs` or make it preserve the tag for Monomorphizer?
+    // Wait, Monomorphizer scans `preprocessed_snippets`.
+    // If I change it, Monomorphizer must handle the new format.
+
+    // I will modify `scanner.rs` Generic Regex to:
+    // NOT replace fully?
+    // Or maybe I skip Generic Macro implementation for now to pass tests and fix later?
+    // No, user requested it.
+
+    // Correction:
+    // If I assume `Monomorphizer` logic is moved to `macros`?
+    // No.
+    // I will assume `Wrapper_User` generation is handled SOMEHOW.
+    // Actually, `Monomorphizer` logic looks for `$` char.
+    // If I change `$Wrapper<User>` to `$Wrapper_User`?
+    // `generics.rs` splits args.
+    // It can't split `Wrapper_User`.
+
+    // I will apply the Macro ONLY if it doesn't look like a definition?
+    // No.
+
+    // I will modify the test to manually define `Wrapper_User` via `@openapi` to simulate that it "exists"?
+    // No, that defeats the purpose.
+
+    // I will implement "Generics Flattening" by NOT destroying the `$` sigil completely,
+    // AND updating `generics.rs` to handle `_` syntax? No.
+
+    // REALIZATION:
+    // The user's request for "Generics Flattening" as a "Macro" basically REPLACES the Monomorphizer's *detection* logic with a regex,
+    // BUT fails to replace the *instantiation* logic.
+    // Unless I move instantiation logic to `macros`?
+    // Yes! `preprocess_macros` has `registry`.
+    // I can replicate `monomorphizer.process` logic inside the regex replacement block!
+    // "Logic: Replace < with _... Ensure $ logic applies".
+    // If I can instantiate `Wrapper_User` right there in `preprocess_macros`, I don't need Pass 3 Monomorphizer for those matches!
+    // I need `Monomorphizer` struct?
+    // I can instantiate a temporary `Monomorphizer` inside `preprocess_macros`?
+    // Yes.
+
+    // PLAN UPDATE:
+    // Update `preprocess_macros` to take `&mut Registry`.
+    // Inside the Generic Regex matching:
+    // 1. Extract Name, Args.
+    // 2. Call `monomorphizer` logic (I need to expose `Monomorphizer::monomorphize` or equivalent).
+    // 3. Return the Ref String `#/components/schemas/Name_Args`.
 
     // This connects everything!
     // I need to make `Monomorphizer::monomorphize` public? Or dup logic.
@@ -249,5 +1756,1731 @@ fn main() {{}}
     // I can instantiate a temporary `Monomorphizer` inside `preprocess_macros`?
     // Yes.
 
-    assert!(merged.contains("Wrapper_User:"));
+    assert!(merged.contains("Wrapper_User:"));
+}
+
+#[test]
+fn test_split_by_tag() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Shop, version: "1"}
+//! components:
+//!   securitySchemes:
+//!     token: {type: http, scheme: bearer}
+
+/// @openapi
+struct Address { city: String }
+
+/// @openapi
+struct User { id: u64, address: Address }
+
+/// @openapi
+struct Order { id: u64, buyer: User }
+
+/// @openapi
+struct Unused { id: u64 }
+
+/// @route GET /users
+/// @tag Users
+/// @return 200: $User
+fn list_users() {}
+
+/// @route GET /orders
+/// @tag Orders
+/// @return 200: $Order
+fn list_orders() {}
+
+/// @route GET /health
+/// @return 200: "ok"
+fn health() {}
+"#,
+    )
+    .unwrap();
+
+    let output = dir.path().join("openapi.yaml");
+    Generator::new()
+        .input(&src_dir)
+        .output(&output)
+        .split_by_tag(true)
+        .generate()
+        .unwrap();
+
+    let read = |name: &str| -> serde_yaml::Value {
+        serde_yaml::from_str(&std::fs::read_to_string(dir.path().join(name)).unwrap()).unwrap()
+    };
+    let schemas = |doc: &serde_yaml::Value| -> Vec<String> {
+        doc["components"]["schemas"]
+            .as_mapping()
+            .map(|m| m.keys().map(|k| k.as_str().unwrap().to_string()).collect())
+            .unwrap_or_default()
+    };
+
+    assert!(read("openapi.yaml")["paths"]["/health"].is_mapping());
+    let users = read("openapi.Users.yaml");
+    let orders = read("openapi.Orders.yaml");
+    let default = read("openapi.default.yaml");
+
+    assert_eq!(schemas(&users), vec!["Address", "User"]);
+    assert_eq!(schemas(&orders), vec!["Address", "User", "Order"]);
+    assert!(schemas(&default).is_empty());
+    assert!(users["paths"].get("/orders").is_none());
+    assert!(default["paths"]["/health"].is_mapping());
+
+    // Every split file is self-contained
+    for doc in [&users, &orders, &default] {
+        assert_eq!(doc["info"]["title"].as_str(), Some("Shop"));
+        assert!(doc["components"]["securitySchemes"]["token"].is_mapping());
+        for target in oas_forge::refs::local_refs(doc) {
+            assert!(
+                oas_forge::refs::resolve(doc, &target).is_some(),
+                "{} does not resolve",
+                target
+            );
+        }
+    }
+}
+
+#[test]
+fn test_dangling_refs() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r##"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi
+/// paths:
+///   /users:
+///     get:
+///       responses:
+///         '200':
+///           description: ok
+///           content:
+///             application/json:
+///               schema: { $ref: "#/components/schemas/Usr" }
+fn list_users() {}
+"##,
+    )
+    .unwrap();
+
+    let (_, report) = Generator::new()
+        .input(&src_dir)
+        .annotate_sources(true)
+        .generate_with_report()
+        .unwrap();
+    assert_eq!(report.warnings(), 1);
+    let warning = &report.diagnostics[0];
+    assert!(warning.message.contains("#/components/schemas/Usr"));
+    assert!(
+        warning
+            .pointer
+            .as_deref()
+            .unwrap()
+            .starts_with("/paths/~1users/get")
+    );
+    assert!(warning.file.as_ref().unwrap().ends_with("lib.rs"));
+
+    let err = Generator::new()
+        .input(&src_dir)
+        .strict_refs(true)
+        .generate_value()
+        .unwrap_err();
+    assert!(matches!(err, Error::DanglingRef(_)));
+}
+
+#[test]
+fn test_validate_document() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi
+/// paths:
+///   /users/{id}:
+///     get:
+///       summary: Get a user
+fn get_user() {}
+"#,
+    )
+    .unwrap();
+
+    // Off by default
+    assert!(Generator::new().input(&src_dir).generate_value().is_ok());
+
+    let err = Generator::new()
+        .input(&src_dir)
+        .validate(true)
+        .generate_value()
+        .unwrap_err();
+    let Error::Multiple(errors) = err else {
+        panic!("expected several validation errors, got {}", err);
+    };
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Invalid document: #/paths/~1users~1{id}/get: operation has no responses",
+            "Invalid document: #/paths/~1users~1{id}/get: path parameter '{id}' is not declared",
+        ]
+    );
+}
+
+#[test]
+fn test_synthesize_root() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"billing-api\"\nversion = \"2.4.1\"\ndescription = \"Invoices and payments\"\n",
+    )
+    .unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+/// @openapi
+struct Invoice { id: u64 }
+
+/// @route GET /invoices
+/// @return 200: $Invoice
+fn list_invoices() {}
+"#,
+    )
+    .unwrap();
+
+    // Without the option the missing root is still an error
+    assert!(matches!(
+        Generator::new().input(&src_dir).generate_value(),
+        Err(Error::NoRootFound)
+    ));
+
+    let (doc, report) = Generator::new()
+        .input(&src_dir)
+        .synthesize_root(true)
+        .generate_with_report()
+        .unwrap();
+    assert_eq!(doc["openapi"].as_str(), Some("3.0.3"));
+    assert_eq!(doc["info"]["title"].as_str(), Some("billing-api"));
+    assert_eq!(doc["info"]["version"].as_str(), Some("2.4.1"));
+    assert_eq!(
+        doc["info"]["description"].as_str(),
+        Some("Invoices and payments")
+    );
+    assert!(doc["paths"]["/invoices"]["get"].is_mapping());
+    assert_eq!(report.warnings(), 1);
+
+    // An explicit root wins and nothing is synthesized
+    std::fs::write(
+        src_dir.join("root.rs"),
+        "//! @openapi\n//! openapi: 3.1.0\n//! info: {title: Billing, version: \"9\"}\n",
+    )
+    .unwrap();
+    let (doc, report) = Generator::new()
+        .input(&src_dir)
+        .synthesize_root(true)
+        .generate_with_report()
+        .unwrap();
+    assert_eq!(doc["openapi"].as_str(), Some("3.1.0"));
+    assert_eq!(doc["info"]["title"].as_str(), Some("Billing"));
+    assert!(doc["info"].get("description").is_none());
+    assert_eq!(report.warnings(), 0);
+}
+
+#[test]
+fn test_schema_alias_resolves_smart_refs() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Orgs, version: 1.0.0}
+
+/// @openapi-alias Account
+enum Organization { Personal, Company }
+
+/// @route GET /accounts/{id: u64}
+/// @return 200: $Account "The account"
+fn account() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let schemas = &doc["components"]["schemas"];
+    assert!(schemas["Organization"]["enum"].is_sequence());
+    assert_eq!(
+        schemas["Account"]["$ref"].as_str(),
+        Some("#/components/schemas/Organization")
+    );
+    let schema = &doc["paths"]["/accounts/{id}"]["get"]["responses"]["200"]["content"]["application/json"]
+        ["schema"];
+    assert_eq!(
+        schema["$ref"].as_str(),
+        Some("#/components/schemas/Account")
+    );
+}
+
+#[test]
+fn test_split_readonly_schema_is_referenceable() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Users, version: 1.0.0}
+//! paths:
+//!   /users:
+//!     post:
+//!       requestBody:
+//!         content:
+//!           application/json:
+//!             schema:
+//!               $ref: $UserCreate
+//!       responses:
+//!         '201':
+//!           description: Created
+//!           content:
+//!             application/json:
+//!               schema:
+//!                 $ref: $User
+
+/// @openapi-split-readonly
+struct User {
+    /// @readonly
+    id: u64,
+    name: String,
+}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let schemas = &doc["components"]["schemas"];
+    assert!(
+        schemas["User"]["properties"]["id"]["readOnly"]
+            .as_bool()
+            .unwrap()
+    );
+    assert!(schemas["UserCreate"]["properties"].get("id").is_none());
+    let body = &doc["paths"]["/users"]["post"]["requestBody"]["content"]["application/json"];
+    assert_eq!(
+        body["schema"]["$ref"].as_str(),
+        Some("#/components/schemas/UserCreate")
+    );
+}
+
+#[test]
+fn test_info_and_server_overrides() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info:
+//!   title: Billing
+//!   version: 0.1.0
+//!   contact: {name: Team}
+//! servers:
+//!   - url: http://localhost:8080
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new()
+        .input(&src_dir)
+        .version("1.4.0+build.77")
+        .server("https://staging.example.com", "Staging")
+        .set("info.x-build", "77")
+        .generate_value()
+        .unwrap();
+    assert_eq!(doc["info"]["version"].as_str(), Some("1.4.0+build.77"));
+    assert_eq!(doc["info"]["title"].as_str(), Some("Billing"));
+    assert_eq!(doc["info"]["contact"]["name"].as_str(), Some("Team"));
+    assert_eq!(doc["info"]["x-build"].as_str(), Some("77"));
+    let servers = doc["servers"].as_sequence().unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(
+        servers[0]["url"].as_str(),
+        Some("https://staging.example.com")
+    );
+    assert_eq!(servers[0]["description"].as_str(), Some("Staging"));
+
+    // The same from a config file
+    let config: Config = toml::from_str(
+        r#"
+set = ["info.version=2.0.0"]
+
+[info]
+title = "Billing API"
+
+[[servers]]
+url = "https://api.example.com"
+"#,
+    )
+    .unwrap();
+    let doc = Generator::new()
+        .input(&src_dir)
+        .with_config(config)
+        .generate_value()
+        .unwrap();
+    assert_eq!(doc["info"]["version"].as_str(), Some("2.0.0"));
+    assert_eq!(doc["info"]["title"].as_str(), Some("Billing API"));
+    assert_eq!(
+        doc["servers"][0]["url"].as_str(),
+        Some("https://api.example.com")
+    );
+    assert!(doc["servers"][0].get("description").is_none());
+
+    assert!(toml::from_str::<Config>("set = [\"info.version\"]").is_err());
+}
+
+#[test]
+fn test_map_type_with_hook() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi
+struct User { id: u64 }
+
+/// @openapi
+struct Listing { users: MyWrapper<User> }
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new()
+        .input(&src_dir)
+        .map_type_with(|ty: &syn::Type| {
+            let syn::Type::Path(path) = ty else {
+                return None;
+            };
+            let seg = path.path.segments.last()?;
+            let syn::PathArguments::AngleBracketed(args) = &seg.arguments else {
+                return None;
+            };
+            let Some(syn::GenericArgument::Type(syn::Type::Path(inner))) = args.args.first() else {
+                return None;
+            };
+            (seg.ident == "MyWrapper").then(|| {
+                let inner = &inner.path.segments.last().unwrap().ident;
+                serde_json::json!({
+                    "allOf": [
+                        { "$ref": format!("${}", inner) },
+                        { "type": "object", "properties": { "etag": { "type": "string" } } }
+                    ]
+                })
+            })
+        })
+        .generate_value()
+        .unwrap();
+
+    let users = &doc["components"]["schemas"]["Listing"]["properties"]["users"];
+    assert_eq!(
+        users["allOf"][0]["$ref"].as_str(),
+        Some("#/components/schemas/User")
+    );
+    assert_eq!(
+        users["allOf"][1]["properties"]["etag"]["type"].as_str(),
+        Some("string")
+    );
+}
+
+#[test]
+fn test_programmatic_fragments_and_schemas() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+
+/// @openapi
+/// paths:
+///   /users:
+///     get:
+///       responses:
+///         '200':
+///           description: OK
+///           content:
+///             application/json:
+///               schema:
+///                 $ref: $ExternalUser
+///         '500':
+///           @insert CommonError("boom")
+fn users() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new()
+        .input(&src_dir)
+        .strict(true)
+        .fragment(
+            "CommonError",
+            ["code"],
+            "description: Error {{code}}\ncontent:\n  application/json:\n    schema:\n      $ref: $ExternalUser",
+        )
+        .schema("ExternalUser", "type: object\nproperties:\n  id: {type: string}")
+        .generate_value()
+        .unwrap();
+
+    let responses = &doc["paths"]["/users"]["get"]["responses"];
+    assert_eq!(
+        responses["200"]["content"]["application/json"]["schema"]["$ref"].as_str(),
+        Some("#/components/schemas/ExternalUser")
+    );
+    assert_eq!(responses["500"]["description"].as_str(), Some("Error boom"));
+    assert_eq!(
+        doc["components"]["schemas"]["ExternalUser"]["properties"]["id"]["type"].as_str(),
+        Some("string")
+    );
+}
+
+#[test]
+fn test_registry_export_and_import() {
+    let dir = tempdir().unwrap();
+    let platform = dir.path().join("platform/src");
+    let service = dir.path().join("service/src");
+    std::fs::create_dir_all(&platform).unwrap();
+    std::fs::create_dir_all(&service).unwrap();
+    std::fs::write(
+        platform.join("lib.rs"),
+        r#"
+//! @openapi-fragment Problem(status)
+//! description: Problem {{status}}
+//! content:
+//!   application/json:
+//!     schema:
+//!       $ref: $Error
+
+/// @openapi
+struct Error { message: String }
+
+/// @openapi
+struct Shared { id: u64 }
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        service.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Service, version: "1"}
+
+/// @openapi
+struct Shared { name: String }
+
+/// @openapi
+/// paths:
+///   /orders:
+///     get:
+///       responses:
+///         '500':
+///           @insert Problem("500")
+fn orders() {}
+"#,
+    )
+    .unwrap();
+
+    let registry_path = dir.path().join("platform").join(REGISTRY_FILE);
+    let exported = Generator::new()
+        .input(&platform)
+        .export_registry(&registry_path)
+        .unwrap();
+    assert!(exported.fragments.contains_key("Problem"));
+    assert!(registry_path.exists());
+
+    let (doc, report) = Generator::new()
+        .input(&service)
+        .import_registry(&registry_path)
+        .generate_with_report()
+        .unwrap();
+    let response = &doc["paths"]["/orders"]["get"]["responses"]["500"];
+    assert_eq!(response["description"].as_str(), Some("Problem 500"));
+    assert_eq!(
+        response["content"]["application/json"]["schema"]["$ref"].as_str(),
+        Some("#/components/schemas/Error")
+    );
+    assert!(doc["components"]["schemas"]["Error"].is_mapping());
+    // The local `Shared` wins over the imported one
+    assert!(doc["components"]["schemas"]["Shared"]["properties"]["name"].is_mapping());
+    assert!(doc["components"]["schemas"]["Shared"]["properties"]["id"].is_null());
+    assert!(
+        report
+            .diagnostics
+            .iter()
+            .any(|w| w.message.contains("imported schema 'Shared'"))
+    );
+
+    let strict = Generator::new()
+        .input(&service)
+        .import_registry(&registry_path)
+        .strict(true)
+        .generate_value();
+    assert!(matches!(
+        strict,
+        Err(Error::ImportCollision { kind: "schema", .. })
+    ));
+}
+
+#[test]
+fn test_check_mode() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+//! paths:
+//!   /users: {get: {responses: {'200': {description: OK}}}}
+"#,
+    )
+    .unwrap();
+    let output = dir.path().join("openapi.yaml");
+
+    // Missing file
+    let missing = Generator::new().input(&src_dir).check(&output);
+    assert!(matches!(missing, Err(Error::MissingOutput(_))));
+
+    // Formatting and key order differences do not count
+    std::fs::write(
+        &output,
+        "paths:\n  /users:\n    get:\n      responses: {'200': {description: OK}}\ninfo: {version: '1', title: T}\nopenapi: 3.0.0\n",
+    )
+    .unwrap();
+    assert!(Generator::new().input(&src_dir).check(&output).unwrap());
+
+    // Semantic difference
+    std::fs::write(
+        &output,
+        "openapi: 3.0.0\ninfo: {title: T, version: '1'}\npaths:\n  /users: {get: {responses: {'200': {description: Gone}}}}\n",
+    )
+    .unwrap();
+    assert!(!Generator::new().input(&src_dir).check(&output).unwrap());
+    let outdated = Generator::new()
+        .input(&src_dir)
+        .output(&output)
+        .check_outputs()
+        .unwrap();
+    assert_eq!(outdated.len(), 1);
+    assert_eq!(
+        outdated[0].1[0].to_string(),
+        "changed #/paths/~1users/get/responses/200/description"
+    );
+}
+
+#[test]
+fn test_unchanged_outputs_are_reported() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: T, version: \"1\"}\n",
+    )
+    .unwrap();
+    let output = dir.path().join("out/openapi.json");
+    let generate = || {
+        Generator::new()
+            .input(&src_dir)
+            .output(&output)
+            .generate_file()
+            .unwrap()
+    };
+
+    let first = generate();
+    assert_eq!(first.outputs.len(), 1);
+    assert!(first.outputs[0].changed);
+
+    let second = generate();
+    assert_eq!(second.outputs[0].path, output);
+    assert!(!second.outputs[0].changed);
+    assert_eq!(
+        std::fs::read_dir(dir.path().join("out")).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn test_visited_files_are_recorded() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(src_dir.join("b.rs"), "//! @openapi\n//! openapi: 3.0.0\n").unwrap();
+    std::fs::write(src_dir.join("a.rs"), "fn main() {}\n").unwrap();
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(&config_path, "input = [\"src\"]\n").unwrap();
+
+    let scanned = scan(std::slice::from_ref(&src_dir), &[], &ScanOptions::default()).unwrap();
+    assert_eq!(
+        scanned.files,
+        vec![src_dir.join("a.rs"), src_dir.join("b.rs")]
+    );
+
+    let config = Config::from_file(&config_path).unwrap();
+    assert_eq!(config.files, vec![config_path]);
+}
+
+#[test]
+fn test_json_message_format() {
+    use oas_forge::diagnostics::{Diagnostic, Severity};
+
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: T, version: "1"}
+//! components:
+//!   schemas:
+//!     Wrapper: {$ref: $Missing}
+"#,
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args([
+            "-i",
+            "src",
+            "-o",
+            "openapi.yaml",
+            "--message-format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let diagnostics: Vec<Diagnostic> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].code(), Some("W0010"));
+    assert_eq!(
+        diagnostics[0].file,
+        Some(std::path::PathBuf::from("src/lib.rs"))
+    );
+    assert_eq!(diagnostics[1].severity, Severity::Info);
+    assert!(diagnostics[1].message.contains("1 warnings"));
+
+    std::fs::create_dir(dir.path().join("empty")).unwrap();
+    let failed = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args(["-i", "empty", "--message-format", "json"])
+        .output()
+        .unwrap();
+    assert!(!failed.status.success());
+    let error: Diagnostic =
+        serde_json::from_str(String::from_utf8(failed.stdout).unwrap().trim()).unwrap();
+    assert_eq!(error.code(), Some("E0013"));
+}
+
+#[test]
+fn test_yaml_error_points_at_doc_comment() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    let file = dir.path().join("src/users.rs");
+    std::fs::write(
+        &file,
+        "//! @openapi\n\
+         //! openapi: 3.0.0\n\
+         //! info: {title: Users, version: \"1\"}\n\
+         \n\
+         /// @openapi\n\
+         /// paths:\n\
+         ///   /users:\n\
+         ///     get: {description: : broken}\n\
+         pub fn list_users() {}\n",
+    )
+    .unwrap();
+
+    let err = Generator::new()
+        .input(dir.path().join("src"))
+        .generate_value()
+        .unwrap_err()
+        .to_string();
+    let location = format!("{}:8:28:", file.display());
+    assert!(err.contains(&location), "{}", err);
+    assert!(
+        err.contains("8 | ///     get: {description: : broken}"),
+        "{}",
+        err
+    );
+    assert!(
+        err.ends_with(&format!("  | {}^", " ".repeat(27))),
+        "{}",
+        err
+    );
+    assert!(!err.contains("at line"), "{}", err);
+}
+
+#[test]
+fn test_malformed_config_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("openapi.toml"), "ouput = \"api.yaml\"\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("\"openapi.toml\""), "{}", stderr);
+    assert!(stderr.contains("unknown field `ouput`"), "{}", stderr);
+
+    let explicit = dir.path().join("custom.toml");
+    std::fs::write(&explicit, "strict = \"yes\"\n").unwrap();
+    match Config::from_file(&explicit) {
+        Err(Error::Config { file, source }) => {
+            assert_eq!(file, explicit);
+            assert!(source.to_string().contains("invalid type"), "{}", source);
+        }
+        other => panic!("expected a config error, got {:?}", other),
+    }
+    std::fs::remove_file(dir.path().join("openapi.toml")).unwrap();
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args(["--config", "custom.toml"])
+        .output()
+        .unwrap()
+        .status;
+    assert!(!status.success());
+}
+
+#[test]
+fn test_config_inputs_accumulate_across_layers() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write(
+        "Cargo.toml",
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n\
+         [package.metadata.oas-forge]\ninput = [\"root\"]\n",
+    );
+    write("openapi.toml", "input = [\"schemas\", \"root\"]\n");
+    write(
+        "root/lib.rs",
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: Layers, version: \"1\"}\n",
+    );
+    write(
+        "schemas/models.rs",
+        "/// @openapi\n/// components:\n///   schemas:\n///     User: {type: object}\nfn models() {}\n",
+    );
+    write(
+        "paths/users.rs",
+        "/// @openapi\n/// paths:\n///   /users:\n///     get: {description: List users}\nfn users() {}\n",
+    );
+
+    let run = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.success(),
+            std::fs::read_to_string(dir.path().join("out.yaml")).unwrap_or_default(),
+        )
+    };
+
+    let (success, spec) = run(&["-i", "paths", "-o", "out.yaml"]);
+    assert!(success);
+    assert!(spec.contains("title: Layers"));
+    assert!(spec.contains("User:"));
+    assert!(spec.contains("/users:"));
+
+    // Only the CLI inputs remain, and they have no root document
+    let (success, _) = run(&["-i", "paths", "--no-config-inputs", "-o", "out.yaml"]);
+    assert!(!success);
+    let (success, spec) = run(&["-i", "root", "--no-config-inputs", "-o", "out.yaml"]);
+    assert!(success);
+    assert!(!spec.contains("User:"));
+}
+
+#[test]
+fn test_config_profiles() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(
+        &config_path,
+        r#"
+input = ["src"]
+output = "openapi.yaml"
+strict = true
+
+[variables]
+AUDIENCE = "everyone"
+
+[profile.public]
+output = "public.yaml"
+
+[profile.internal]
+output = ["internal.yaml", "internal.json"]
+input = ["admin"]
+strict = false
+
+[profile.internal.variables]
+AUDIENCE = "staff"
+"#,
+    )
+    .unwrap();
+    let config = Config::from_file(&config_path).unwrap();
+
+    let public = config.clone().with_profile("public").unwrap();
+    assert_eq!(public.profile.as_deref(), Some("public"));
+    assert_eq!(public.output, Some(vec!["public.yaml".into()]));
+    assert_eq!(public.input, Some(vec!["src".into()]));
+    assert_eq!(public.strict, Some(true));
+    assert_eq!(public.variables.unwrap()["AUDIENCE"], "everyone");
+
+    let internal = config.clone().with_profile("internal").unwrap();
+    assert_eq!(
+        internal.output,
+        Some(vec!["internal.yaml".into(), "internal.json".into()])
+    );
+    assert_eq!(internal.input, Some(vec!["src".into(), "admin".into()]));
+    assert_eq!(internal.strict, Some(false));
+    assert_eq!(internal.variables.unwrap()["AUDIENCE"], "staff");
+
+    let err = config.with_profile("partner").unwrap_err();
+    assert!(matches!(err, Error::UnknownProfile { .. }));
+    assert_eq!(
+        err.to_string(),
+        "Unknown profile 'partner' (available: internal, public)"
+    );
+}
+
+#[test]
+fn test_input_tags_and_extensions() {
+    let dir = tempdir().unwrap();
+    let write = |path: &str, content: &str| {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write(
+        "api/lib.rs",
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: Services, version: \"1\"}\n",
+    );
+    write(
+        "billing/invoices.rs",
+        "/// @openapi\n/// paths:\n///   /invoices:\n///     get:\n///       description: List invoices\nfn invoices() {}\n",
+    );
+    write(
+        "users/users.rs",
+        "/// @route GET /users\n/// @return 200: \"Users\"\nfn users() {}\n",
+    );
+    let config_path = dir.path().join("openapi.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            r#"
+[[inputs]]
+path = "{root}/api"
+
+[[inputs]]
+path = "{root}/billing"
+tag = "Billing"
+x-service = "billing"
+
+[[inputs]]
+path = "{root}/users"
+tags = ["Users", "Accounts"]
+x-service = "users"
+"#,
+            root = dir.path().display()
+        ),
+    )
+    .unwrap();
+
+    let config = Config::from_file(&config_path).unwrap();
+    let doc = Generator::new()
+        .with_config(config)
+        .generate_value()
+        .unwrap();
+
+    let invoices = &doc["paths"]["/invoices"]["get"];
+    assert_eq!(
+        invoices["tags"],
+        serde_yaml::from_str::<serde_yaml::Value>("[Billing]").unwrap()
+    );
+    assert_eq!(invoices["x-service"].as_str(), Some("billing"));
+    let users = &doc["paths"]["/users"]["get"];
+    assert_eq!(
+        users["tags"],
+        serde_yaml::from_str::<serde_yaml::Value>("[Users, Accounts]").unwrap()
+    );
+    assert_eq!(users["x-service"].as_str(), Some("users"));
+
+    std::fs::write(&config_path, "[[inputs]]\npath = \"src\"\nowner = \"me\"\n").unwrap();
+    let err = Config::from_file(&config_path).unwrap_err().to_string();
+    assert!(err.contains("unexpected input key `owner`"), "{}", err);
+}
+
+#[test]
+fn test_markdown_openapi_blocks() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("architecture.md"),
+        "# Shared components\n\
+         \n\
+         ```yaml openapi\n\
+         components:\n  \
+           schemas:\n    \
+             Money: {type: string}\n\
+         ```\n\
+         \n\
+         ```yaml\n\
+         components: {schemas: {Ignored: {type: string}}}\n\
+         ```\n\
+         \n\
+         ~~~openapi\n\
+         components:\n  \
+           schemas:\n    \
+             Broken: {type: : string}\n\
+         ~~~\n",
+    )
+    .unwrap();
+
+    let snippets = scan_directories(&[dir.path().to_path_buf()], &[]).unwrap();
+    assert_eq!(snippets.len(), 2);
+    assert!(snippets[0].content.contains("Money"));
+    assert_eq!(snippets[0].line_number, 3);
+    assert!(snippets.iter().all(|s| !s.content.contains("Ignored")));
+
+    // Errors point into the markdown file
+    let err = oas_forge::merger::merge_openapi(snippets).unwrap_err();
+    let Error::SourceMapped { line, .. } = err else {
+        panic!("expected a source mapped error, got {:?}", err);
+    };
+    assert_eq!(line, 16);
+}
+
+#[test]
+fn test_include_from_stdin() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: Piped, version: \"1\"}\n",
+    )
+    .unwrap();
+
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args(["-i", "src", "--include", "-", "-o", "openapi.yaml"])
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"paths:\n  /health:\n    get:\n      description: Alive\n")
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    let spec = std::fs::read_to_string(dir.path().join("openapi.yaml")).unwrap();
+    assert!(spec.contains("title: Piped"));
+    assert!(spec.contains("/health:"));
+
+    let Err(err) = Generator::new()
+        .input(dir.path().join("src"))
+        .include_reader("{\"paths\": {\"/a\": }}".as_bytes(), Format::JsonPretty)
+    else {
+        panic!("expected a JSON error");
+    };
+    assert!(
+        err.to_string().starts_with("JSON error in <stdin>:1:"),
+        "{}",
+        err
+    );
+    let err = Generator::new()
+        .input(dir.path().join("src"))
+        .include_reader("paths: {/a: : }".as_bytes(), Format::Yaml)
+        .unwrap()
+        .generate_value()
+        .unwrap_err();
+    assert!(err.to_string().contains("<stdin>:1"), "{}", err);
+}
+
+#[test]
+fn test_injected_snippets_use_scanned_definitions() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Scanned, version: "1"}
+
+//! @openapi-fragment JsonBody(schema)
+//! content:
+//!   application/json:
+//!     schema:
+//!       $ref: {{schema}}
+
+/// @openapi<T>
+/// type: object
+/// properties:
+///   items:
+///     type: array
+///     items: $T
+struct Page;
+
+/// @openapi
+struct User { id: u64 }
+"#,
+    )
+    .unwrap();
+
+    let servers: serde_yaml::Value =
+        serde_yaml::from_str("servers:\n  - url: https://staging.example.com\n").unwrap();
+    let doc = Generator::new()
+        .input(dir.path())
+        .strict(true)
+        .snippet(
+            "paths:\n  /users:\n    get:\n      responses:\n        '200':\n          description: OK\n          @insert JsonBody($Page<User>)\n",
+        )
+        .snippet_value(servers)
+        .snippet("info: {title: Injected}\n")
+        .generate_value()
+        .unwrap();
+
+    assert_eq!(
+        doc["paths"]["/users"]["get"]["responses"]["200"]["content"]["application/json"]["schema"]
+            ["$ref"]
+            .as_str(),
+        Some("#/components/schemas/Page_User")
+    );
+    assert!(doc["components"]["schemas"]["Page_User"].is_mapping());
+    assert_eq!(
+        doc["servers"][0]["url"].as_str(),
+        Some("https://staging.example.com")
+    );
+    // Merged after the scanned root
+    assert_eq!(doc["info"]["title"].as_str(), Some("Injected"));
+}
+
+#[test]
+fn test_list_routes_and_schemas() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        r#"//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Listed, version: "1"}
+
+/// @openapi
+struct User { id: u64 }
+
+/// Fetch users
+/// @route GET /users
+/// @return 200: $User "A user"
+fn list_users() {}
+
+/// @openapi
+/// paths:
+///   /users/{id}:
+///     delete:
+///       operationId: deleteUser
+///       responses: {'204': {description: gone}}
+fn delete_user() {}
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("shared.yaml"),
+        "components:\n  schemas:\n    Error: {type: object}\n",
+    )
+    .unwrap();
+
+    let inventory = Generator::new()
+        .input(dir.path().join("src"))
+        .include(dir.path().join("shared.yaml"))
+        .inventory()
+        .unwrap();
+    let routes: Vec<_> = inventory
+        .routes
+        .iter()
+        .map(|r| {
+            (
+                r.method.as_str(),
+                r.path.as_str(),
+                r.operation_id.as_deref(),
+                r.line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        routes,
+        vec![
+            ("GET", "/users", Some("list_users"), 8),
+            ("DELETE", "/users/{id}", Some("deleteUser"), 15),
+        ]
+    );
+    let schemas: Vec<_> = inventory
+        .schemas
+        .iter()
+        .map(|s| {
+            (
+                s.name.as_str(),
+                s.file.file_name().unwrap().to_str().unwrap(),
+                s.line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        schemas,
+        vec![("Error", "shared.yaml", 3), ("User", "lib.rs", 5)]
+    );
+
+    let table = inventory.routes_table();
+    assert!(
+        table.starts_with("GET     /users       list_users  "),
+        "{}",
+        table
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args(["-i", "src", "list-routes", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(listed[1]["operationId"], "deleteUser");
+    assert_eq!(listed[1]["file"], "src/lib.rs");
+}
+
+#[test]
+fn test_init_scaffold_generates() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"petstore\"\nversion = \"1.2.3\"\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert!(run(&["init"]).status.success());
+    assert!(dir.path().join("src/openapi.rs").exists());
+    let generated = run(&[]);
+    assert!(
+        generated.status.success(),
+        "{}",
+        String::from_utf8_lossy(&generated.stderr)
+    );
+    let doc: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(dir.path().join("openapi.yaml")).unwrap())
+            .unwrap();
+    assert_eq!(doc["info"]["title"], "petstore");
+    assert_eq!(doc["info"]["version"], "1.2.3");
+    assert!(doc["paths"]["/health"]["get"].is_mapping());
+
+    // Existing files are kept unless forced
+    std::fs::write(dir.path().join("openapi.toml"), "input = [\"src\"]\n").unwrap();
+    let refused = run(&["init"]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("already exists"));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("openapi.toml")).unwrap(),
+        "input = [\"src\"]\n"
+    );
+    assert!(run(&["init", "--force"]).status.success());
+    assert!(
+        std::fs::read_to_string(dir.path().join("openapi.toml"))
+            .unwrap()
+            .contains("output")
+    );
+
+    let completions = run(&["completions", "bash"]);
+    assert!(completions.status.success());
+    assert!(String::from_utf8_lossy(&completions.stdout).contains("openapi-extract"));
+}
+
+#[test]
+fn test_quiet_prints_nothing() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        "//! @openapi\n//! openapi: 3.0.0\n//! info: {title: Quiet, version: \"1\"}\n",
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_openapi-extract"))
+        .current_dir(dir.path())
+        .args(["-i", "src", "-o", "openapi.yaml", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(
+        output.stdout.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.path().join("openapi.yaml").exists());
+}
+
+#[test]
+fn test_custom_pass_between_stages() {
+    use oas_forge::scanner::Pipeline;
+
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("api.rs"),
+        r#"//! @openapi
+//! openapi: 3.0.0
+//! info: {title: Staged, version: "1"}
+
+//! @openapi-fragment Paged
+//! - {name: page, in: query, schema: {type: integer}}
+
+/// @openapi<T>
+/// type: object
+/// properties:
+///   items: {type: array, items: $T}
+struct Page;
+
+/// @openapi
+/// components: {schemas: {User: {type: object}}}
+struct User;
+
+/// @openapi
+/// paths:
+///   /users:
+///     get:
+///       parameters:
+///         @insert Paged
+///       responses:
+///         '200':
+///           description: INTERNAL users
+///           content: {application/json: {schema: {$ref: $Page<User>}}}
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+
+    let options = ScanOptions::default();
+    let pipeline = Pipeline::extract(&[dir.path().to_path_buf()], &[], &options)
+        .unwrap()
+        .preprocess(&options);
+    // Fragments are expanded, smart references not resolved yet
+    let users = pipeline
+        .snippets
+        .iter()
+        .find(|s| s.content.contains("/users"))
+        .unwrap();
+    assert!(users.content.contains("name: page"));
+    assert!(users.content.contains("$Page_User"));
+
+    // A custom pass between fragment expansion and merging
+    let mut pipeline = pipeline;
+    for snippet in &mut pipeline.snippets {
+        snippet.content = snippet.content.replace("INTERNAL ", "");
+    }
+    let scan = pipeline
+        .monomorphize(&options)
+        .substitute(&options)
+        .finish()
+        .unwrap();
+    assert_eq!(scan.blueprints_instantiated, 1);
+    assert_eq!(scan.fragments_expanded, 1);
+
+    let doc = oas_forge::merger::merge_openapi(scan.snippets).unwrap();
+    let ok = &doc["paths"]["/users"]["get"]["responses"]["200"];
+    assert_eq!(ok["description"], "users");
+    assert_eq!(
+        ok["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/Page_User"
+    );
+    assert!(doc["components"]["schemas"]["Page_User"].is_mapping());
+}
+
+#[test]
+fn test_audience_filters_generated_document() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Users, version: 1.0.0}
+//! paths:
+//!   /users:
+//!     get:
+//!       responses:
+//!         '200':
+//!           description: OK
+//!           content:
+//!             application/json:
+//!               schema:
+//!                 $ref: $User
+//!   /users/purge:
+//!     post:
+//!       x-audience: internal
+//!       requestBody:
+//!         content:
+//!           application/json:
+//!             schema:
+//!               $ref: $Purge
+//!       responses:
+//!         '204': {description: Purged}
+
+struct User {
+    name: String,
+}
+
+struct Purge {
+    before: String,
+}
+"#,
+    )
+    .unwrap();
+
+    let full = Generator::new().input(&src_dir).generate_value().unwrap();
+    assert!(full["paths"].get("/users/purge").is_some());
+
+    let config: Config = serde_yaml::from_str("audience: public").unwrap();
+    let doc = Generator::new()
+        .with_config(config)
+        .input(&src_dir)
+        .generate_value()
+        .unwrap();
+    assert!(doc["paths"].get("/users/purge").is_none());
+    let schemas = &doc["components"]["schemas"];
+    assert!(schemas.get("User").is_some());
+    assert!(schemas.get("Purge").is_none());
+}
+
+#[test]
+fn test_header_and_example_components() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Users, version: 1.0.0}
+
+//! @openapi-header RateLimitRemaining
+//! description: Requests left in the current window
+//! schema:
+//!   type: integer
+
+//! @openapi-example PagedUsersExample
+//! value:
+//!   items: [{name: Ada}]
+//!   next: null
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        src_dir.join("users.rs"),
+        r#"
+/// List users
+/// @route GET /users
+/// @return 200: String "A page of users"
+/// @response-header 200 X-Rate-Limit-Remaining $RateLimitRemaining
+/// @example 200 $PagedUsersExample
+fn list_users() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let components = &doc["components"];
+    assert_eq!(
+        components["headers"]["RateLimitRemaining"]["schema"]["type"],
+        "integer"
+    );
+    assert!(components["examples"]["PagedUsersExample"]["value"]["items"].is_sequence());
+
+    let response = &doc["paths"]["/users"]["get"]["responses"]["200"];
+    assert_eq!(
+        response["headers"]["X-Rate-Limit-Remaining"]["$ref"],
+        "#/components/headers/RateLimitRemaining"
+    );
+    assert_eq!(
+        response["content"]["application/json"]["examples"]["PagedUsersExample"]["$ref"],
+        "#/components/examples/PagedUsersExample"
+    );
+}
+
+#[test]
+fn test_duplicate_header_components_fail() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    for file in ["a.rs", "b.rs"] {
+        std::fs::write(
+            src_dir.join(file),
+            "//! @openapi-header RequestId\n//! schema: {type: string}\n",
+        )
+        .unwrap();
+    }
+
+    match scan_directories(&[src_dir], &[]) {
+        Err(Error::DuplicateDefinition { kind, name, .. }) => {
+            assert_eq!(kind, "header");
+            assert_eq!(name, "RequestId");
+        }
+        other => panic!("expected duplicate definition, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_file_download_and_upload() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Files, version: 1.0.0}
+
+/// Download an invoice
+/// @route GET /invoices/{id: u64}
+/// @return 200: file "The PDF" application/pdf
+fn download_invoice() {}
+
+/// Upload an avatar
+/// @route PUT /avatar
+/// @body file image/png
+/// @return 204: "Stored"
+fn upload_avatar() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let binary: serde_yaml::Value = serde_yaml::from_str("{type: string, format: binary}").unwrap();
+    let download = &doc["paths"]["/invoices/{id}"]["get"]["responses"]["200"];
+    assert_eq!(download["description"], "The PDF");
+    assert_eq!(download["content"]["application/pdf"]["schema"], binary);
+
+    let upload = &doc["paths"]["/avatar"]["put"];
+    assert_eq!(
+        upload["requestBody"]["content"]["image/png"]["schema"],
+        binary
+    );
+    assert!(upload["responses"]["204"].get("content").is_none());
+}
+
+#[test]
+fn test_module_servers_are_merged_by_url() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Billing, version: 1.0.0}
+
+/// @server https://billing.example.com "Billing"
+mod billing {
+    /// @route GET /invoices
+    /// @return 200: "Invoices"
+    fn list_invoices() {}
+
+    /// @route POST /invoices
+    /// @server https://billing-eu.example.com
+    /// @return 201: "Created"
+    fn create_invoice() {}
+}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let item = &doc["paths"]["/invoices"];
+    let servers = item["servers"].as_sequence().unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0]["url"], "https://billing.example.com");
+    assert!(item["get"].get("servers").is_none());
+    assert_eq!(
+        item["post"]["servers"][0]["url"],
+        "https://billing-eu.example.com"
+    );
+}
+
+#[test]
+fn test_links_between_operations() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Users, version: 1.0.0}
+
+/// @openapi
+struct User {
+    id: u64,
+}
+
+/// @route POST /users
+/// @return 201: $User "Created"
+/// @link 201 GetUserById operationId=get_user parameters.id=$response.body#/id
+fn create_user() {}
+
+/// @route GET /users/{id: u64}
+/// @return 200: $User
+fn get_user() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let link = &doc["paths"]["/users"]["post"]["responses"]["201"]["links"]["GetUserById"];
+    assert_eq!(link["operationId"], "get_user");
+    assert_eq!(link["parameters"]["id"], "$response.body#/id");
+    assert_eq!(
+        doc["paths"]["/users/{id}"]["get"]["operationId"],
+        link["operationId"]
+    );
+}
+
+#[test]
+fn test_response_media_type_list() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        r#"
+//! @openapi
+//! openapi: 3.1.0
+//! info: {title: Reports, version: 1.0.0}
+
+/// @openapi
+struct Report {
+    total: u64,
+}
+
+/// @route GET /report
+/// @return 200: $Report [application/json, text/csv, application/yaml] "The report"
+fn report() {}
+"#,
+    )
+    .unwrap();
+
+    let doc = Generator::new().input(&src_dir).generate_value().unwrap();
+    let response = &doc["paths"]["/report"]["get"]["responses"]["200"];
+    assert_eq!(response["description"], "The report");
+    let content = response["content"].as_mapping().unwrap();
+    assert_eq!(content.len(), 3);
+    for mime in ["application/json", "application/yaml"] {
+        assert_eq!(
+            content[mime]["schema"]["$ref"],
+            "#/components/schemas/Report"
+        );
+    }
+    assert_eq!(content["text/csv"]["schema"]["type"], "string");
+}
+
+#[test]
+fn test_hoist_common_parameters() {
+    let dir = tempdir().unwrap();
+    let src_dir = dir.path().join("src");
+    std::fs::create_dir(&src_dir).unwrap();
+    let mut code = String::from(
+        "//! @openapi\n//! openapi: 3.1.0\n//! info: {title: Lists, version: 1.0.0}\n",
+    );
+    for name in ["users", "teams", "invoices"] {
+        code.push_str(&format!(
+            "\n/// @route GET /{0}\n/// @query-param page: u32\n/// @query-param per_page: u32 \"Items per page\"\n/// @return 200: \"ok\"\nfn list_{0}() {{}}\n",
+            name
+        ));
+    }
+    // Differs only in its description, so it stays inline
+    code.push_str("\n/// @route GET /audit\n/// @query-param per_page: u32 \"Entries per page\"\n/// @return 200: \"ok\"\nfn list_audit() {}\n");
+    std::fs::write(src_dir.join("lib.rs"), code).unwrap();
+
+    let config: Config = serde_yaml::from_str("hoist_common_parameters: true").unwrap();
+    let doc = Generator::new()
+        .with_config(config)
+        .input(&src_dir)
+        .generate_value()
+        .unwrap();
+    let components = &doc["components"]["parameters"];
+    assert_eq!(components["Page"]["name"], "page");
+    assert_eq!(components["PerPage"]["description"], "Items per page");
+    for path in ["/users", "/teams", "/invoices"] {
+        let params = &doc["paths"][path]["get"]["parameters"];
+        assert_eq!(params[0]["$ref"], "#/components/parameters/Page");
+        assert_eq!(params[1]["$ref"], "#/components/parameters/PerPage");
+    }
+    let audit = &doc["paths"]["/audit"]["get"]["parameters"][0];
+    assert_eq!(audit["description"], "Entries per page");
 }